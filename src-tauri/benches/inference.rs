@@ -0,0 +1,163 @@
+//! Throughput of sequential vs batched inference on CPU.
+//! Needs the models downloaded first (e.g. `cargo run --example verify_ml`).
+//! Run with: MINDSCRIBE_BENCH_MODELS=./test_models cargo bench --bench inference
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use mindscribe_lib::ml::embeddings::{chunk_text, EmbeddingModel};
+use mindscribe_lib::ml::models::{
+    is_model_downloaded, Precision, EMBEDDING_MODEL, MINILM_EMBEDDING, SENTIMENT_MODEL,
+};
+use mindscribe_lib::ml::sentiment::{Aggregation, SentimentModel};
+
+/// Roughly the chunks of a long journal entry.
+fn sample_texts(count: usize) -> Vec<String> {
+    let entry = "Woke up early and went for a run along the river. ".repeat(8)
+        + &"Work was stressful, the deadline moved again and I felt anxious. ".repeat(8)
+        + &"In the evening I called my sister and we laughed for an hour. ".repeat(8);
+    chunk_text(&entry, 500, 100)
+        .into_iter()
+        .cycle()
+        .take(count)
+        .collect()
+}
+
+/// Whole journal entries of varying length, as the reindex backfill sees them.
+fn sample_entries(count: usize) -> Vec<String> {
+    let sentences = [
+        "Woke up early and went for a run along the river. ",
+        "Work was stressful, the deadline moved again and I felt anxious. ",
+        "In the evening I called my sister and we laughed for an hour. ",
+    ];
+    (0..count)
+        .map(|i| sentences[i % sentences.len()].repeat(1 + i % 12))
+        .collect()
+}
+
+fn models_dir() -> PathBuf {
+    std::env::var("MINDSCRIBE_BENCH_MODELS")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("./test_models"))
+}
+
+fn bench_embeddings(c: &mut Criterion) {
+    let models_dir = models_dir();
+    if !is_model_downloaded(&models_dir, EMBEDDING_MODEL) {
+        eprintln!(
+            "Skipping embedding benchmarks: model not found in {}",
+            models_dir.display()
+        );
+        return;
+    }
+    let model = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
+
+    let mut group = c.benchmark_group("embed");
+    for count in [8, 32] {
+        let texts = sample_texts(count);
+        let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("sequential", count), &refs, |b, refs| {
+            b.iter(|| {
+                refs.iter()
+                    .map(|text| model.embed(text).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batched", count), &refs, |b, refs| {
+            b.iter(|| model.embed_batch(refs).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_emotions(c: &mut Criterion) {
+    let models_dir = models_dir();
+    if !is_model_downloaded(&models_dir, SENTIMENT_MODEL) {
+        eprintln!(
+            "Skipping emotion benchmarks: model not found in {}",
+            models_dir.display()
+        );
+        return;
+    }
+    let model = SentimentModel::load(&models_dir, Precision::F32).unwrap();
+
+    let mut group = c.benchmark_group("emotions");
+    for count in [8, 32] {
+        let texts = sample_texts(count);
+        let refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        group.throughput(Throughput::Elements(count as u64));
+
+        group.bench_with_input(BenchmarkId::new("sequential", count), &refs, |b, refs| {
+            b.iter(|| {
+                refs.iter()
+                    .map(|text| model.predict(text, 0.1, 5).unwrap())
+                    .collect::<Vec<_>>()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("batched", count), &refs, |b, refs| {
+            b.iter(|| model.predict_batch(refs, 0.1, 5).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// One reindex batch: each entry on its own vs the whole batch in shared forward passes.
+fn bench_backfill(c: &mut Criterion) {
+    let models_dir = models_dir();
+    if !is_model_downloaded(&models_dir, EMBEDDING_MODEL)
+        || !is_model_downloaded(&models_dir, SENTIMENT_MODEL)
+    {
+        eprintln!(
+            "Skipping backfill benchmarks: models not found in {}",
+            models_dir.display()
+        );
+        return;
+    }
+    let embedder = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
+    let classifier = SentimentModel::load(&models_dir, Precision::F32).unwrap();
+
+    // Matches the reindexer's batch size
+    let entries = sample_entries(20);
+    let refs: Vec<&str> = entries.iter().map(String::as_str).collect();
+
+    let mut group = c.benchmark_group("backfill");
+    group.throughput(Throughput::Elements(refs.len() as u64));
+    group.bench_function("embed/per_entry", |b| {
+        b.iter(|| {
+            refs.iter()
+                .map(|text| embedder.embed(text).unwrap())
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("embed/batched", |b| {
+        b.iter(|| embedder.embed_batch(&refs).unwrap())
+    });
+    group.bench_function("emotions/per_entry", |b| {
+        b.iter(|| {
+            refs.iter()
+                .map(|text| {
+                    classifier
+                        .predict_chunked(text, 0.1, 5, Aggregation::Max)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+    group.bench_function("emotions/batched", |b| {
+        b.iter(|| {
+            classifier
+                .predict_chunked_batch(&refs, 0.1, 5, Aggregation::Max)
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_embeddings, bench_emotions, bench_backfill
+}
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+//! Prints `pinned_sha256` entries for every bundled model definition.
+//! Downloads each model into a temporary directory (verifying LFS files against the
+//! hashes the Hub reports) and prints the recorded checksums as Rust source, ready to
+//! paste into `src/ml/models.rs`.
+//! Run with: cargo run --example pin_model_hashes
+
+use mindscribe_lib::ml::download::{download_model, read_manifest, FileProgress};
+use mindscribe_lib::ml::models::{
+    ModelInfo, BGE_SMALL_EMBEDDING, EMBEDDING_MODEL, MULTILINGUAL_MINILM_EMBEDDING,
+    MULTILINGUAL_SENTIMENT_MODEL, NER_MODEL, RERANKER_MODEL, SENTIMENT_MODEL,
+};
+
+fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let models: Vec<(&str, ModelInfo)> = vec![
+        ("EMBEDDING_MODEL", EMBEDDING_MODEL),
+        (
+            "BGE_SMALL_EMBEDDING",
+            BGE_SMALL_EMBEDDING.local_info().unwrap(),
+        ),
+        (
+            "MULTILINGUAL_MINILM_EMBEDDING",
+            MULTILINGUAL_MINILM_EMBEDDING.local_info().unwrap(),
+        ),
+        ("SENTIMENT_MODEL", SENTIMENT_MODEL),
+        ("MULTILINGUAL_SENTIMENT_MODEL", MULTILINGUAL_SENTIMENT_MODEL),
+        ("NER_MODEL", NER_MODEL),
+        ("RERANKER_MODEL", RERANKER_MODEL),
+    ];
+
+    let models_dir = tempfile::tempdir().expect("Failed to create temp dir");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let report = |_: FileProgress| {};
+
+    for (name, model) in models {
+        rt.block_on(download_model(models_dir.path(), model, &report))
+            .unwrap_or_else(|e| panic!("Failed to download {}: {}", model.repo_id, e));
+        let manifest = read_manifest(models_dir.path(), &model).expect("No checksums recorded");
+
+        println!("// {} ({})", name, model.repo_id);
+        println!("pinned_sha256: &[");
+        for file in model.files() {
+            println!("    (\"{}\", \"{}\"),", file, manifest[file].sha256);
+        }
+        println!("],");
+    }
+}
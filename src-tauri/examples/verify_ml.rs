@@ -21,8 +21,16 @@ fn main() {
 }
 
 async fn verify_models(models_dir: &PathBuf) {
-    use mindscribe_lib::ml::models::{
-        download_model, is_model_downloaded, EMBEDDING_MODEL, SENTIMENT_MODEL,
+    use mindscribe_lib::ml::download::{download_model, FileProgress};
+    use mindscribe_lib::ml::models::{is_model_downloaded, EMBEDDING_MODEL, SENTIMENT_MODEL};
+
+    let report = |progress: FileProgress| {
+        if let Some(total) = progress.total_bytes {
+            println!(
+                "  {}: {} / {} bytes",
+                progress.file, progress.downloaded_bytes, total
+            );
+        }
     };
 
     // Check/download embedding model
@@ -31,7 +39,7 @@ async fn verify_models(models_dir: &PathBuf) {
         println!("✓ Already downloaded");
     } else {
         println!("Downloading...");
-        match download_model(models_dir, EMBEDDING_MODEL).await {
+        match download_model(models_dir, EMBEDDING_MODEL, &report).await {
             Ok(_) => println!("✓ Download complete"),
             Err(e) => {
                 println!("✗ Download failed: {}", e);
@@ -46,7 +54,7 @@ async fn verify_models(models_dir: &PathBuf) {
         println!("✓ Already downloaded");
     } else {
         println!("Downloading...");
-        match download_model(models_dir, SENTIMENT_MODEL).await {
+        match download_model(models_dir, SENTIMENT_MODEL, &report).await {
             Ok(_) => println!("✓ Download complete"),
             Err(e) => {
                 println!("✗ Download failed: {}", e);
@@ -57,7 +65,10 @@ async fn verify_models(models_dir: &PathBuf) {
 
     // Test loading and inference
     println!("\n--- Testing Sentiment Inference ---");
-    match mindscribe_lib::ml::sentiment::SentimentModel::load(models_dir) {
+    match mindscribe_lib::ml::sentiment::SentimentModel::load(
+        models_dir,
+        mindscribe_lib::ml::models::Precision::F32,
+    ) {
         Ok(model) => {
             println!("✓ Model loaded successfully");
 
@@ -97,7 +108,11 @@ async fn verify_models(models_dir: &PathBuf) {
     }
 
     println!("\n--- Testing Embedding Inference ---");
-    match mindscribe_lib::ml::embeddings::EmbeddingModel::load(models_dir) {
+    match mindscribe_lib::ml::embeddings::EmbeddingModel::load(
+        models_dir,
+        &mindscribe_lib::ml::models::MINILM_EMBEDDING,
+        mindscribe_lib::ml::models::Precision::F32,
+    ) {
         Ok(model) => {
             println!("✓ Model loaded successfully");
             match model.embed("Hello world") {
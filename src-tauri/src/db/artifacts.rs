@@ -0,0 +1,239 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+/// Data derived from an entry's content that must be recomputed when the content changes.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Artifact {
+    Embedding,
+    Chunks,
+    Emotions,
+    SentenceEmotions,
+    Entities,
+}
+
+impl Artifact {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Artifact::Embedding => "embedding",
+            Artifact::Chunks => "chunks",
+            Artifact::Emotions => "emotions",
+            Artifact::SentenceEmotions => "sentence_emotions",
+            Artifact::Entities => "entities",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "embedding" => Some(Artifact::Embedding),
+            "chunks" => Some(Artifact::Chunks),
+            "emotions" => Some(Artifact::Emotions),
+            "sentence_emotions" => Some(Artifact::SentenceEmotions),
+            "entities" => Some(Artifact::Entities),
+            _ => None,
+        }
+    }
+}
+
+/// Hash entry content for freshness tracking (hex-encoded SHA-256).
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+/// Record that an artifact was computed from the given content hash.
+pub fn record(
+    conn: &Connection,
+    journal_id: &str,
+    artifact: Artifact,
+    content_hash: &str,
+    model_version: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO derived_artifacts (journal_id, artifact, content_hash, model_version, is_stale, updated_at)
+         VALUES (?1, ?2, ?3, ?4, 0, datetime('now'))",
+        params![journal_id, artifact.as_str(), content_hash, model_version],
+    )?;
+    Ok(())
+}
+
+/// Check whether an artifact exists and was computed from the given content by the given model.
+pub fn is_fresh(
+    conn: &Connection,
+    journal_id: &str,
+    artifact: Artifact,
+    content_hash: &str,
+    model_version: &str,
+) -> Result<bool, AppError> {
+    let stored: Option<(String, bool)> = conn
+        .query_row(
+            "SELECT content_hash, is_stale FROM derived_artifacts
+             WHERE journal_id = ?1 AND artifact = ?2 AND model_version = ?3",
+            params![journal_id, artifact.as_str(), model_version],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(matches!(stored, Some((hash, false)) if hash == content_hash))
+}
+
+/// List entries whose artifact is missing, stale, or computed by a different model version.
+/// Newest entries come first so recent writing is indexed before old backlog.
+pub fn list_outdated(
+    conn: &Connection,
+    artifact: Artifact,
+    model_version: &str,
+) -> Result<Vec<String>, AppError> {
+    conn.prepare(
+        "SELECT j.id FROM journals j
+         WHERE NOT EXISTS (
+             SELECT 1 FROM derived_artifacts a
+             WHERE a.journal_id = j.id AND a.artifact = ?1
+               AND a.model_version = ?2 AND a.is_stale = 0
+         )
+         ORDER BY j.created_at DESC",
+    )?
+    .query_map(params![artifact.as_str(), model_version], |row| row.get(0))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(AppError::from)
+}
+
+/// Mark an entry's artifacts stale if they were computed from different content.
+/// Returns the artifacts that became stale.
+pub fn mark_stale(
+    conn: &Connection,
+    journal_id: &str,
+    content_hash: &str,
+) -> Result<Vec<Artifact>, AppError> {
+    let changed: Vec<String> = conn
+        .prepare(
+            "SELECT artifact FROM derived_artifacts
+             WHERE journal_id = ?1 AND content_hash != ?2 AND is_stale = 0",
+        )?
+        .query_map(params![journal_id, content_hash], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    conn.execute(
+        "UPDATE derived_artifacts SET is_stale = 1
+         WHERE journal_id = ?1 AND content_hash != ?2",
+        params![journal_id, content_hash],
+    )?;
+
+    Ok(changed.iter().filter_map(|a| Artifact::parse(a)).collect())
+}
+
+/// Remove freshness tracking for an artifact that no longer exists.
+pub fn clear(
+    conn: &Connection,
+    journal_id: &str,
+    artifact: Artifact,
+    model_version: &str,
+) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM derived_artifacts WHERE journal_id = ?1 AND artifact = ?2 AND model_version = ?3",
+        params![journal_id, artifact.as_str(), model_version],
+    )?;
+    Ok(())
+}
+
+/// Remove freshness tracking for every entry's artifacts from one model.
+pub fn clear_model(conn: &Connection, model_version: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM derived_artifacts WHERE model_version = ?1",
+        params![model_version],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+
+    fn setup_test_db() -> Connection {
+        #[allow(clippy::missing_transmute_annotations)]
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO journals (id, content) VALUES ('entry-1', 'Original content')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash("hello"), content_hash("hello"));
+        assert_ne!(content_hash("hello"), content_hash("hello!"));
+        assert_eq!(content_hash("").len(), 64);
+    }
+
+    #[test]
+    fn test_fresh_until_content_changes() {
+        let conn = setup_test_db();
+        let original = content_hash("Original content");
+        let edited = content_hash("Edited content");
+
+        assert!(!is_fresh(&conn, "entry-1", Artifact::Emotions, &original, "v1").unwrap());
+
+        record(&conn, "entry-1", Artifact::Emotions, &original, "v1").unwrap();
+        record(&conn, "entry-1", Artifact::Embedding, &original, "v1").unwrap();
+        assert!(is_fresh(&conn, "entry-1", Artifact::Emotions, &original, "v1").unwrap());
+
+        // Marking with the same content is a no-op
+        assert!(mark_stale(&conn, "entry-1", &original).unwrap().is_empty());
+
+        let stale = mark_stale(&conn, "entry-1", &edited).unwrap();
+        assert_eq!(stale.len(), 2);
+        assert!(!is_fresh(&conn, "entry-1", Artifact::Emotions, &original, "v1").unwrap());
+        assert!(!is_fresh(&conn, "entry-1", Artifact::Emotions, &edited, "v1").unwrap());
+
+        record(&conn, "entry-1", Artifact::Emotions, &edited, "v1").unwrap();
+        assert!(is_fresh(&conn, "entry-1", Artifact::Emotions, &edited, "v1").unwrap());
+        assert!(!is_fresh(&conn, "entry-1", Artifact::Emotions, &edited, "v2").unwrap());
+
+        // Artifacts from different model versions are tracked side by side
+        record(&conn, "entry-1", Artifact::Emotions, &edited, "v2").unwrap();
+        assert!(is_fresh(&conn, "entry-1", Artifact::Emotions, &edited, "v1").unwrap());
+        assert!(is_fresh(&conn, "entry-1", Artifact::Emotions, &edited, "v2").unwrap());
+    }
+
+    #[test]
+    fn test_list_outdated() {
+        let conn = setup_test_db();
+        conn.execute(
+            "INSERT INTO journals (id, content) VALUES ('entry-2', 'Second entry')",
+            [],
+        )
+        .unwrap();
+        let hash = content_hash("Original content");
+
+        assert_eq!(
+            list_outdated(&conn, Artifact::Embedding, "v1")
+                .unwrap()
+                .len(),
+            2
+        );
+
+        record(&conn, "entry-1", Artifact::Embedding, &hash, "v1").unwrap();
+        assert_eq!(
+            list_outdated(&conn, Artifact::Embedding, "v1").unwrap(),
+            vec!["entry-2".to_string()]
+        );
+        // A model upgrade makes every entry outdated again
+        assert_eq!(
+            list_outdated(&conn, Artifact::Embedding, "v2")
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::journals::{self, Journal};
+use crate::db::search::{cosine, SearchFilter};
+use crate::db::{emotions, vectors};
+use crate::error::AppError;
+use crate::ml::dedup::{self, Overlap};
+use crate::ml::models::EmbeddingModelSpec;
+
+/// Nearest neighbours of each entry checked for duplicates.
+const NEIGHBORS: usize = 5;
+
+/// Two entries that are (nearly) the same text.
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePair {
+    /// The older entry, suggested as the one to keep
+    pub first: Journal,
+    pub second: Journal,
+    /// Cosine similarity of the entries' embeddings
+    pub similarity: f32,
+    #[serde(flatten)]
+    pub overlap: Overlap,
+}
+
+/// Find pairs of non-archived near-duplicate entries: embedding neighbours that are
+/// at least `dedup::MIN_COSINE` similar and share enough word shingles. Only entries
+/// embedded by `model` are considered. Pairs with the most overlap come first.
+pub fn find_duplicates(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    limit: usize,
+) -> Result<Vec<DuplicatePair>, AppError> {
+    let active: HashSet<String> = conn
+        .prepare("SELECT id FROM journals WHERE is_archived = 0")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    let embeddings: BTreeMap<String, Vec<f32>> = vectors::all_embeddings(conn, model)?
+        .into_iter()
+        .filter(|(id, _)| active.contains(id))
+        .collect();
+
+    let mut entries: HashMap<String, Journal> = HashMap::new();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut pairs = Vec::new();
+
+    for (id, vector) in &embeddings {
+        for (other, _) in
+            vectors::search_similar(conn, model, vector, NEIGHBORS + 1, &SearchFilter::default())?
+        {
+            let Some(other_vector) = embeddings.get(&other) else {
+                continue;
+            };
+            let key = if *id < other {
+                (id.clone(), other.clone())
+            } else {
+                (other.clone(), id.clone())
+            };
+            if other == *id || !seen.insert(key) {
+                continue;
+            }
+            let similarity = cosine(vector, other_vector);
+            if similarity < dedup::MIN_COSINE {
+                continue;
+            }
+
+            for entry_id in [id, &other] {
+                if !entries.contains_key(entry_id) {
+                    entries.insert(entry_id.clone(), journals::get(conn, entry_id)?);
+                }
+            }
+            let (a, b) = (&entries[id], &entries[&other]);
+            let overlap = dedup::overlap(&a.content, &b.content);
+            if !overlap.is_duplicate() {
+                continue;
+            }
+            let (first, second) = if (a.created_at, &a.id) <= (b.created_at, &b.id) {
+                (a.clone(), b.clone())
+            } else {
+                (b.clone(), a.clone())
+            };
+            pairs.push(DuplicatePair {
+                first,
+                second,
+                similarity,
+                overlap,
+            });
+        }
+    }
+
+    pairs.sort_by(|a, b| {
+        b.overlap
+            .jaccard
+            .total_cmp(&a.overlap.jaccard)
+            .then(b.similarity.total_cmp(&a.similarity))
+            .then_with(|| a.first.id.cmp(&b.first.id))
+    });
+    pairs.truncate(limit);
+    Ok(pairs)
+}
+
+/// Merge `merge_id` into `keep_id`. The kept entry gets the text of both (unless one
+/// already contains the other), the earlier creation date, a title if it had none,
+/// and the merged entry's images, chat messages, emotions and emotion corrections.
+/// The merged entry is archived and recorded in `entry_merges` rather than deleted.
+/// Derived data of the kept entry goes stale if its content changed.
+pub fn merge(conn: &Connection, keep_id: &str, merge_id: &str) -> Result<Journal, AppError> {
+    if keep_id == merge_id {
+        return Err(AppError::InvalidInput(
+            "Cannot merge an entry into itself".to_string(),
+        ));
+    }
+    let keep = journals::get(conn, keep_id)?;
+    let other = journals::get(conn, merge_id)?;
+    let already_merged: bool = conn
+        .prepare("SELECT 1 FROM entry_merges WHERE merged_id IN (?1, ?2)")?
+        .exists(params![keep_id, merge_id])?;
+    if already_merged {
+        return Err(AppError::InvalidInput(
+            "Entry was already merged into another".to_string(),
+        ));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+
+    let content = merged_content(&keep.content, &other.content);
+    let created_at = (other.created_at < keep.created_at).then(|| other.created_at.to_rfc3339());
+    let title = match (&keep.title, &other.title) {
+        (None, Some(title)) => Some(title.as_str()),
+        _ => None,
+    };
+    journals::update(
+        &tx,
+        keep_id,
+        (content != keep.content).then_some(content.as_str()),
+        title,
+        None,
+        created_at.as_deref(),
+    )?;
+
+    tx.execute(
+        "UPDATE entry_images SET entry_id = ?1 WHERE entry_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "UPDATE chat_messages SET journal_id = ?1 WHERE journal_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+
+    // Strongest score per label across both entries; the merged entry keeps its own
+    let mut scores: BTreeMap<String, f32> = BTreeMap::new();
+    for (label, score) in emotions::get(&tx, keep_id)?
+        .into_iter()
+        .chain(emotions::get(&tx, merge_id)?)
+    {
+        let best = scores.entry(label).or_insert(score);
+        *best = best.max(score);
+    }
+    tx.execute(
+        "DELETE FROM journal_emotions WHERE journal_id = ?1",
+        params![keep_id],
+    )?;
+    for (label, score) in &scores {
+        emotions::store(&tx, keep_id, label, *score)?;
+    }
+    // The kept entry's own corrections win
+    tx.execute(
+        "INSERT OR IGNORE INTO emotion_corrections (journal_id, emotion_label, action, created_at)
+         SELECT ?1, emotion_label, action, created_at FROM emotion_corrections
+         WHERE journal_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+
+    journals::archive(&tx, merge_id)?;
+    tx.execute(
+        "INSERT INTO entry_merges (merged_id, into_id) VALUES (?1, ?2)",
+        params![merge_id, keep_id],
+    )?;
+    tx.commit()?;
+
+    log::info!("Entry merged: {} into {}", merge_id, keep_id);
+    journals::get(conn, keep_id)
+}
+
+/// Text of two entries combined: the longer one if it already contains the other,
+/// otherwise both, separated by a blank line.
+fn merged_content(keep: &str, other: &str) -> String {
+    let (keep_trimmed, other_trimmed) = (keep.trim(), other.trim());
+    if keep_trimmed.contains(other_trimmed) {
+        keep.to_string()
+    } else if other_trimmed.contains(keep_trimmed) {
+        other.to_string()
+    } else {
+        format!("{}\n\n{}", keep_trimmed, other_trimmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{self, CreateMessageParams};
+    use crate::db::schema::run_migrations;
+    use crate::ml::calibration::CorrectionAction;
+    use crate::ml::models::MINILM_EMBEDDING;
+
+    const MODEL: &EmbeddingModelSpec = &MINILM_EMBEDDING;
+
+    const WALK: &str = "Woke up early and walked the dog along the canal. The fog was still \
+        sitting on the water and everything felt quiet.";
+
+    fn setup_test_db() -> Connection {
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn vector(axis: usize, tilt: f32) -> Vec<f32> {
+        let mut v = vec![0.0; MODEL.dimension];
+        v[axis] = 1.0;
+        v[0] += tilt;
+        crate::ml::backend::l2_normalized(v)
+    }
+
+    fn add_entry(conn: &Connection, id: &str, content: &str, created_at: &str, vector: &[f32]) {
+        conn.execute(
+            "INSERT INTO journals (id, content, created_at) VALUES (?1, ?2, ?3)",
+            params![id, content, created_at],
+        )
+        .unwrap();
+        vectors::store_embedding(conn, MODEL, id, vector).unwrap();
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let conn = setup_test_db();
+        add_entry(
+            &conn,
+            "original",
+            WALK,
+            "2024-03-01T08:00:00Z",
+            &vector(1, 0.0),
+        );
+        add_entry(
+            &conn,
+            "import",
+            &WALK.to_uppercase(),
+            "2024-03-02T08:00:00Z",
+            &vector(1, 0.05),
+        );
+        // Close in meaning but written differently
+        add_entry(
+            &conn,
+            "similar",
+            "Another foggy walk with the dog by the canal, very peaceful morning.",
+            "2024-03-03T08:00:00Z",
+            &vector(1, 0.1),
+        );
+        add_entry(
+            &conn,
+            "unrelated",
+            "Taxes are due next week.",
+            "2024-03-04T08:00:00Z",
+            &vector(2, 0.0),
+        );
+
+        let pairs = find_duplicates(&conn, MODEL, 10).unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].first.id, "original");
+        assert_eq!(pairs[0].second.id, "import");
+        assert_eq!(pairs[0].overlap.jaccard, 1.0);
+
+        journals::archive(&conn, "import").unwrap();
+        assert!(find_duplicates(&conn, MODEL, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_moves_data_and_archives() {
+        let conn = setup_test_db();
+        add_entry(&conn, "keep", WALK, "2024-03-02T08:00:00Z", &vector(1, 0.0));
+        add_entry(
+            &conn,
+            "dupe",
+            "Slept badly before the walk.",
+            "2024-03-01T08:00:00Z",
+            &vector(1, 0.05),
+        );
+        conn.execute("UPDATE journals SET title = 'Canal' WHERE id = 'dupe'", [])
+            .unwrap();
+        chat::create(
+            &conn,
+            CreateMessageParams {
+                journal_id: "dupe".to_string(),
+                role: "user".to_string(),
+                content: "Why do I sleep badly?".to_string(),
+                metadata: None,
+            },
+        )
+        .unwrap();
+        emotions::store(&conn, "keep", "joy", 0.4).unwrap();
+        emotions::store(&conn, "dupe", "joy", 0.7).unwrap();
+        emotions::store(&conn, "dupe", "nervousness", 0.3).unwrap();
+        emotions::set_correction(&conn, "dupe", "nervousness", CorrectionAction::Confirm).unwrap();
+
+        let merged = merge(&conn, "keep", "dupe").unwrap();
+        assert!(merged.content.starts_with(WALK));
+        assert!(merged.content.ends_with("Slept badly before the walk."));
+        assert_eq!(merged.title.as_deref(), Some("Canal"));
+        assert_eq!(merged.created_at.to_rfc3339(), "2024-03-01T08:00:00+00:00");
+        assert_eq!(chat::list_for_entry(&conn, "keep").unwrap().len(), 1);
+        assert_eq!(
+            emotions::get(&conn, "keep").unwrap(),
+            vec![("joy".to_string(), 0.7), ("nervousness".to_string(), 0.3)]
+        );
+        assert_eq!(emotions::get_corrections(&conn, "keep").unwrap().len(), 1);
+
+        // The merged entry is moved aside, not deleted
+        let dupe = journals::get(&conn, "dupe").unwrap();
+        assert!(dupe.is_archived);
+        assert_eq!(dupe.content, "Slept badly before the walk.");
+
+        assert!(merge(&conn, "keep", "dupe").is_err());
+        assert!(merge(&conn, "keep", "keep").is_err());
+    }
+
+    #[test]
+    fn test_merged_content() {
+        assert_eq!(
+            merged_content("A long entry.", "long entry"),
+            "A long entry."
+        );
+        assert_eq!(
+            merged_content("entry", "The whole entry."),
+            "The whole entry."
+        );
+        assert_eq!(merged_content("First. ", " Second."), "First.\n\nSecond.");
+    }
+}
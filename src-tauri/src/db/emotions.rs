@@ -1,6 +1,59 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
 use rusqlite::{params, Connection};
+use serde::Serialize;
 
+use crate::db::artifacts::Artifact;
 use crate::error::AppError;
+use crate::ml::affect::{self, Affect};
+use crate::ml::anomaly::{self, MoodInsight};
+use crate::ml::calibration::{Calibration, CorrectionAction, CorrectionCounts};
+use crate::ml::language::{self, ENGLISH};
+use crate::ml::sentiment::{ChunkEmotions, EmotionPrediction, SentenceEmotions};
+use crate::ml::series::{EntryScores, Granularity, ScoreAggregation, Smoothing};
+
+/// Current emotion model version for tracking
+pub const EMOTION_MODEL_VERSION: &str = "distilbert-go-emotions";
+
+/// Emotion model version for entries that aren't in English
+pub const MULTILINGUAL_EMOTION_MODEL_VERSION: &str = "xlm-emo-t";
+
+/// Emotion model version used for an entry in the given language.
+pub fn model_version_for(language: Option<&str>) -> &'static str {
+    if language::is_english(language) {
+        EMOTION_MODEL_VERSION
+    } else {
+        MULTILINGUAL_EMOTION_MODEL_VERSION
+    }
+}
+
+/// Entries without fresh emotions from the model matching their language, newest first.
+pub fn list_outdated(conn: &Connection) -> Result<Vec<String>, AppError> {
+    conn.prepare(
+        "SELECT j.id FROM journals j
+         WHERE NOT EXISTS (
+             SELECT 1 FROM derived_artifacts a
+             WHERE a.journal_id = j.id AND a.artifact = ?1 AND a.is_stale = 0
+               AND a.model_version = CASE
+                   WHEN j.language IS NULL OR j.language = ?2 THEN ?3
+                   ELSE ?4
+               END
+         )
+         ORDER BY j.created_at DESC",
+    )?
+    .query_map(
+        params![
+            Artifact::Emotions.as_str(),
+            ENGLISH,
+            EMOTION_MODEL_VERSION,
+            MULTILINGUAL_EMOTION_MODEL_VERSION
+        ],
+        |row| row.get(0),
+    )?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(AppError::from)
+}
 
 /// Get emotions for a journal entry as (label, score) pairs.
 pub fn get(conn: &Connection, journal_id: &str) -> Result<Vec<(String, f32)>, AppError> {
@@ -18,6 +71,112 @@ pub fn get(conn: &Connection, journal_id: &str) -> Result<Vec<(String, f32)>, Ap
     Ok(emotions)
 }
 
+/// Get emotions for a journal entry with the user's corrections applied.
+pub fn get_effective(conn: &Connection, journal_id: &str) -> Result<Vec<(String, f32)>, AppError> {
+    conn.prepare(
+        "SELECT emotion_label, confidence_score FROM effective_emotions
+         WHERE journal_id = ?1 ORDER BY confidence_score DESC",
+    )?
+    .query_map(params![journal_id], |row| {
+        Ok((row.get(0)?, row.get::<_, f64>(1)? as f32))
+    })?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(AppError::from)
+}
+
+/// A user's correction to one emotion label of an entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmotionCorrection {
+    pub label: String,
+    pub action: CorrectionAction,
+    pub created_at: String,
+}
+
+/// Record a correction, replacing any earlier one for the same label.
+pub fn set_correction(
+    conn: &Connection,
+    journal_id: &str,
+    label: &str,
+    action: CorrectionAction,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO emotion_corrections (journal_id, emotion_label, action, created_at)
+         VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)",
+        params![journal_id, label, action.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Remove the correction for a label, falling back to the model's output.
+pub fn clear_correction(conn: &Connection, journal_id: &str, label: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM emotion_corrections WHERE journal_id = ?1 AND emotion_label = ?2",
+        params![journal_id, label],
+    )?;
+    Ok(())
+}
+
+/// Corrections recorded for an entry, oldest first.
+pub fn get_corrections(
+    conn: &Connection,
+    journal_id: &str,
+) -> Result<Vec<EmotionCorrection>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT emotion_label, action, created_at FROM emotion_corrections
+         WHERE journal_id = ?1 ORDER BY created_at, emotion_label",
+    )?;
+    let rows = stmt.query_map(params![journal_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+
+    let mut corrections = Vec::new();
+    for row in rows {
+        let (label, action, created_at) = row?;
+        match CorrectionAction::parse(&action) {
+            Some(action) => corrections.push(EmotionCorrection {
+                label,
+                action,
+                created_at,
+            }),
+            None => log::warn!(
+                "Skipping emotion correction with unknown action '{}'",
+                action
+            ),
+        }
+    }
+    Ok(corrections)
+}
+
+/// Per-label calibration learned from every correction so far.
+pub fn get_calibration(conn: &Connection) -> Result<Calibration, AppError> {
+    let counts: Vec<(String, CorrectionCounts)> = conn
+        .prepare(
+            "SELECT emotion_label,
+                    SUM(action IN ('confirm', 'add')),
+                    SUM(action = 'remove')
+             FROM emotion_corrections
+             GROUP BY emotion_label",
+        )?
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                CorrectionCounts {
+                    agreed: row.get(1)?,
+                    removed: row.get(2)?,
+                },
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(Calibration::from_counts(
+        counts.iter().map(|(label, c)| (label.as_str(), *c)),
+    ))
+}
+
 /// Store a single emotion for a journal entry.
 pub fn store(conn: &Connection, journal_id: &str, label: &str, score: f32) -> Result<(), AppError> {
     conn.execute(
@@ -27,8 +186,175 @@ pub fn store(conn: &Connection, journal_id: &str, label: &str, score: f32) -> Re
     Ok(())
 }
 
-/// Get the dominant emotion for entries on each date within a date range.
-/// Returns a list of (date, dominant_emotion, entry_count) tuples.
+/// Replace all stored emotions for a journal entry with a fresh set of predictions.
+pub fn replace(
+    conn: &Connection,
+    journal_id: &str,
+    predictions: &[EmotionPrediction],
+) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM journal_emotions WHERE journal_id = ?1",
+        params![journal_id],
+    )?;
+    for pred in predictions {
+        store(&tx, journal_id, &pred.label, pred.score)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Replace the stored per-window emotions for a journal entry.
+pub fn replace_chunks(
+    conn: &Connection,
+    journal_id: &str,
+    chunks: &[ChunkEmotions],
+) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM emotion_chunks WHERE journal_id = ?1",
+        params![journal_id],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO emotion_chunks (journal_id, chunk_index, start_offset, end_offset, emotion_label, confidence_score)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for chunk in chunks {
+            for pred in &chunk.predictions {
+                stmt.execute(params![
+                    journal_id,
+                    chunk.chunk_index as i64,
+                    chunk.start_offset as i64,
+                    chunk.end_offset as i64,
+                    pred.label,
+                    pred.score as f64
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Get the per-window emotions for a journal entry, in entry order.
+pub fn get_chunks(conn: &Connection, journal_id: &str) -> Result<Vec<ChunkEmotions>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT chunk_index, start_offset, end_offset, emotion_label, confidence_score
+         FROM emotion_chunks
+         WHERE journal_id = ?1
+         ORDER BY chunk_index, confidence_score DESC",
+    )?;
+
+    let rows = stmt
+        .query_map(params![journal_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, i64>(1)? as usize,
+                row.get::<_, i64>(2)? as usize,
+                EmotionPrediction {
+                    label: row.get(3)?,
+                    score: row.get::<_, f64>(4)? as f32,
+                },
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut chunks: Vec<ChunkEmotions> = Vec::new();
+    for (chunk_index, start_offset, end_offset, prediction) in rows {
+        match chunks.last_mut() {
+            Some(chunk) if chunk.chunk_index == chunk_index => chunk.predictions.push(prediction),
+            _ => chunks.push(ChunkEmotions {
+                chunk_index,
+                start_offset,
+                end_offset,
+                predictions: vec![prediction],
+            }),
+        }
+    }
+
+    Ok(chunks)
+}
+
+/// Replace the cached per-sentence emotions for a journal entry.
+pub fn replace_sentences(
+    conn: &Connection,
+    journal_id: &str,
+    sentences: &[SentenceEmotions],
+) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM sentence_emotions WHERE journal_id = ?1",
+        params![journal_id],
+    )?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO sentence_emotions (journal_id, sentence_index, start_offset, end_offset, emotion_label, confidence_score)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for sentence in sentences {
+            for pred in &sentence.predictions {
+                stmt.execute(params![
+                    journal_id,
+                    sentence.sentence_index as i64,
+                    sentence.start_offset as i64,
+                    sentence.end_offset as i64,
+                    pred.label,
+                    pred.score as f64
+                ])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Get the cached per-sentence emotions for a journal entry, in entry order.
+pub fn get_sentences(
+    conn: &Connection,
+    journal_id: &str,
+) -> Result<Vec<SentenceEmotions>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT sentence_index, start_offset, end_offset, emotion_label, confidence_score
+         FROM sentence_emotions
+         WHERE journal_id = ?1
+         ORDER BY sentence_index, confidence_score DESC",
+    )?;
+
+    let rows = stmt
+        .query_map(params![journal_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)? as usize,
+                row.get::<_, i64>(1)? as usize,
+                row.get::<_, i64>(2)? as usize,
+                EmotionPrediction {
+                    label: row.get(3)?,
+                    score: row.get::<_, f64>(4)? as f32,
+                },
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut sentences: Vec<SentenceEmotions> = Vec::new();
+    for (sentence_index, start_offset, end_offset, prediction) in rows {
+        match sentences.last_mut() {
+            Some(sentence) if sentence.sentence_index == sentence_index => {
+                sentence.predictions.push(prediction)
+            }
+            _ => sentences.push(SentenceEmotions {
+                sentence_index,
+                start_offset,
+                end_offset,
+                predictions: vec![prediction],
+            }),
+        }
+    }
+
+    Ok(sentences)
+}
+
+/// Get the dominant emotion for entries on each date within a date range, preferring
+/// user-corrected labels. Returns a list of (date, dominant_emotion, entry_count) tuples.
 pub fn get_daily_emotions(
     conn: &Connection,
     start_date: &str,
@@ -74,7 +400,7 @@ pub fn get_daily_emotions(
         let placeholders: String = entry_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
         let sql = format!(
             "SELECT emotion_label, SUM(confidence_score) as total_score
-             FROM journal_emotions
+             FROM effective_emotions
              WHERE journal_id IN ({})
              GROUP BY emotion_label
              ORDER BY total_score DESC
@@ -98,6 +424,189 @@ pub fn get_daily_emotions(
     Ok(results)
 }
 
+/// Mood for one day or week: valence/arousal and Ekman scores averaged over its entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct AffectPoint {
+    /// First day of the period (YYYY-MM-DD)
+    pub period_start: String,
+    /// Entries in the period that have emotions
+    pub entry_count: u32,
+    #[serde(flatten)]
+    pub affect: Affect,
+}
+
+/// Emotions of every analyzed, non-archived entry created within a date range, with
+/// the user's corrections applied.
+pub fn entry_emotions_in_range(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<EntryScores>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT j.id, date(j.created_at), e.emotion_label, e.confidence_score
+         FROM journals j
+         JOIN effective_emotions e ON e.journal_id = j.id
+         WHERE j.is_archived = 0
+         AND date(j.created_at) >= ?1
+         AND date(j.created_at) <= ?2",
+    )?;
+
+    let mut entries: BTreeMap<String, EntryScores> = BTreeMap::new();
+    let rows = stmt.query_map(params![start_date, end_date], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, f64>(3)? as f32,
+        ))
+    })?;
+    for row in rows {
+        let (id, date, label, score) = row?;
+        entries
+            .entry(id.clone())
+            .or_insert_with(|| EntryScores {
+                journal_id: id,
+                date,
+                emotions: Vec::new(),
+            })
+            .emotions
+            .push((label, score));
+    }
+    Ok(entries.into_values().collect())
+}
+
+/// Valence/arousal and Ekman series over a date range, one point per day or week
+/// that has analyzed entries. Archived entries are excluded.
+pub fn get_affect_series(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    granularity: Granularity,
+) -> Result<Vec<AffectPoint>, AppError> {
+    let mut periods: BTreeMap<String, Vec<Affect>> = BTreeMap::new();
+    for entry in entry_emotions_in_range(conn, start_date, end_date)? {
+        if let Some(affect) = affect::project(&entry.emotions) {
+            periods
+                .entry(granularity.period_start(&entry.date))
+                .or_default()
+                .push(affect);
+        }
+    }
+
+    Ok(periods
+        .into_iter()
+        .filter_map(|(period_start, affects)| {
+            Some(AffectPoint {
+                period_start,
+                entry_count: affects.len() as u32,
+                affect: affect::mean(&affects)?,
+            })
+        })
+        .collect())
+}
+
+/// Mood patterns (elevated distress, sustained decline) in the two weeks up to
+/// `as_of`, measured against the user's own history before that.
+pub fn get_mood_insights(
+    conn: &Connection,
+    as_of: NaiveDate,
+) -> Result<Vec<MoodInsight>, AppError> {
+    let (start_date, end_date) = anomaly::date_range(as_of);
+    let entries = entry_emotions_in_range(conn, &start_date, &end_date)?;
+    Ok(anomaly::detect(&entries, as_of))
+}
+
+/// Per-label emotion scores over every period of a date range.
+#[derive(Debug, Clone, Serialize)]
+pub struct EmotionSeries {
+    /// First day of each period (YYYY-MM-DD), covering the whole range
+    pub periods: Vec<String>,
+    /// Analyzed entries in each period
+    pub entry_counts: Vec<u32>,
+    pub labels: Vec<LabelSeries>,
+}
+
+/// One label's aggregated score per period.
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelSeries {
+    pub label: String,
+    /// Null for periods without analyzed entries
+    pub values: Vec<Option<f32>>,
+}
+
+/// Emotion scores per day or week for the given labels (or, if none are given,
+/// every label that occurs in the range, strongest first).
+/// An entry that wasn't assigned a label scores 0 for it; a period without analyzed
+/// entries is a gap, and stays one after smoothing.
+pub fn get_emotion_series(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    labels: &[String],
+    granularity: Granularity,
+    aggregation: ScoreAggregation,
+    smoothing: Option<Smoothing>,
+) -> Result<EmotionSeries, AppError> {
+    let periods = granularity.periods(start_date, end_date)?;
+    let index: HashMap<&str, usize> = periods
+        .iter()
+        .enumerate()
+        .map(|(i, period)| (period.as_str(), i))
+        .collect();
+
+    // Entries' emotions grouped by period
+    let mut buckets: Vec<Vec<HashMap<String, f32>>> = vec![Vec::new(); periods.len()];
+    for entry in entry_emotions_in_range(conn, start_date, end_date)? {
+        if let Some(&i) = index.get(granularity.period_start(&entry.date).as_str()) {
+            buckets[i].push(entry.emotions.into_iter().collect());
+        }
+    }
+
+    let labels: Vec<String> = if labels.is_empty() {
+        let mut totals: HashMap<&str, f32> = HashMap::new();
+        for entry in buckets.iter().flatten() {
+            for (label, score) in entry {
+                *totals.entry(label).or_default() += score;
+            }
+        }
+        let mut totals: Vec<(&str, f32)> = totals.into_iter().collect();
+        totals.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(b.0)));
+        totals
+            .into_iter()
+            .map(|(label, _)| label.to_string())
+            .collect()
+    } else {
+        labels.to_vec()
+    };
+
+    let labels = labels
+        .into_iter()
+        .map(|label| {
+            let values: Vec<Option<f32>> = buckets
+                .iter()
+                .map(|entries| {
+                    let scores: Vec<f32> = entries
+                        .iter()
+                        .map(|entry| entry.get(&label).copied().unwrap_or(0.0))
+                        .collect();
+                    aggregation.apply(&scores)
+                })
+                .collect();
+            let values = match smoothing {
+                Some(smoothing) => smoothing.apply(&values),
+                None => values,
+            };
+            LabelSeries { label, values }
+        })
+        .collect();
+
+    Ok(EmotionSeries {
+        periods,
+        entry_counts: buckets.iter().map(|entries| entries.len() as u32).collect(),
+        labels,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +644,325 @@ mod tests {
         assert_eq!(retrieved[0].0, "Joy");
         assert!((retrieved[0].1 - 0.85).abs() < 0.01);
     }
+
+    #[test]
+    fn test_replace_emotions() {
+        let conn = setup_test_db();
+
+        conn.execute(
+            "INSERT INTO journals (id, content) VALUES ('test-id', 'Test content')",
+            [],
+        )
+        .unwrap();
+
+        store(&conn, "test-id", "joy", 0.85).unwrap();
+        store(&conn, "test-id", "gratitude", 0.72).unwrap();
+
+        let predictions = vec![EmotionPrediction {
+            label: "sadness".to_string(),
+            score: 0.6,
+        }];
+        replace(&conn, "test-id", &predictions).unwrap();
+
+        let retrieved = get(&conn, "test-id").unwrap();
+        assert_eq!(retrieved.len(), 1);
+        assert_eq!(retrieved[0].0, "sadness");
+    }
+
+    #[test]
+    fn test_list_outdated_uses_model_for_language() {
+        let conn = setup_test_db();
+
+        conn.execute_batch(
+            "INSERT INTO journals (id, content, language) VALUES ('en', 'English', 'eng');
+             INSERT INTO journals (id, content, language) VALUES ('es', 'Spanish', 'spa');",
+        )
+        .unwrap();
+
+        // Each entry was analyzed by the wrong model for its language
+        crate::db::artifacts::record(
+            &conn,
+            "en",
+            Artifact::Emotions,
+            "h",
+            MULTILINGUAL_EMOTION_MODEL_VERSION,
+        )
+        .unwrap();
+        crate::db::artifacts::record(&conn, "es", Artifact::Emotions, "h", EMOTION_MODEL_VERSION)
+            .unwrap();
+        let mut outdated = list_outdated(&conn).unwrap();
+        outdated.sort();
+        assert_eq!(outdated, vec!["en", "es"]);
+
+        crate::db::artifacts::record(
+            &conn,
+            "es",
+            Artifact::Emotions,
+            "h",
+            model_version_for(Some("spa")),
+        )
+        .unwrap();
+        assert_eq!(list_outdated(&conn).unwrap(), vec!["en"]);
+    }
+
+    #[test]
+    fn test_replace_and_get_chunks() {
+        let conn = setup_test_db();
+
+        conn.execute(
+            "INSERT INTO journals (id, content) VALUES ('test-id', 'Test content')",
+            [],
+        )
+        .unwrap();
+
+        let prediction = |label: &str, score: f32| EmotionPrediction {
+            label: label.to_string(),
+            score,
+        };
+        let chunks = vec![
+            ChunkEmotions {
+                chunk_index: 0,
+                start_offset: 0,
+                end_offset: 2000,
+                predictions: vec![prediction("neutral", 0.7)],
+            },
+            ChunkEmotions {
+                chunk_index: 1,
+                start_offset: 1800,
+                end_offset: 3500,
+                predictions: vec![prediction("grief", 0.4), prediction("sadness", 0.9)],
+            },
+        ];
+        replace_chunks(&conn, "test-id", &chunks).unwrap();
+        replace_chunks(&conn, "test-id", &chunks).unwrap();
+
+        let stored = get_chunks(&conn, "test-id").unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[1].start_offset, 1800);
+        assert_eq!(stored[1].predictions.len(), 2);
+        assert_eq!(stored[1].predictions[0].label, "sadness");
+    }
+
+    #[test]
+    fn test_replace_and_get_sentences() {
+        let conn = setup_test_db();
+
+        conn.execute(
+            "INSERT INTO journals (id, content) VALUES ('test-id', 'Test content')",
+            [],
+        )
+        .unwrap();
+
+        let sentences = vec![
+            SentenceEmotions {
+                sentence_index: 0,
+                start_offset: 0,
+                end_offset: 12,
+                predictions: vec![EmotionPrediction {
+                    label: "joy".to_string(),
+                    score: 0.8,
+                }],
+            },
+            SentenceEmotions {
+                sentence_index: 1,
+                start_offset: 13,
+                end_offset: 30,
+                predictions: vec![EmotionPrediction {
+                    label: "fear".to_string(),
+                    score: 0.5,
+                }],
+            },
+        ];
+        replace_sentences(&conn, "test-id", &sentences).unwrap();
+        replace_sentences(&conn, "test-id", &sentences[1..]).unwrap();
+
+        let stored = get_sentences(&conn, "test-id").unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].sentence_index, 1);
+        assert_eq!(stored[0].predictions[0].label, "fear");
+    }
+
+    #[test]
+    fn test_affect_series_by_day_and_week() {
+        let conn = setup_test_db();
+
+        // 2024-01-01 is a Monday; the 8th starts the next ISO week
+        conn.execute_batch(
+            "INSERT INTO journals (id, content, created_at) VALUES ('a', 'A', '2024-01-01 09:00:00');
+             INSERT INTO journals (id, content, created_at) VALUES ('b', 'B', '2024-01-03 09:00:00');
+             INSERT INTO journals (id, content, created_at) VALUES ('c', 'C', '2024-01-08 09:00:00');
+             INSERT INTO journals (id, content, created_at) VALUES ('d', 'D', '2024-01-09 09:00:00');",
+        )
+        .unwrap();
+        store(&conn, "a", "joy", 0.9).unwrap();
+        store(&conn, "b", "sadness", 0.8).unwrap();
+        store(&conn, "c", "anger", 0.7).unwrap();
+        store(&conn, "c", "annoyance", 0.5).unwrap();
+        // 'd' has no emotions yet and is left out
+
+        let daily = get_affect_series(&conn, "2024-01-01", "2024-01-31", Granularity::Day).unwrap();
+        let days: Vec<&str> = daily.iter().map(|p| p.period_start.as_str()).collect();
+        assert_eq!(days, vec!["2024-01-01", "2024-01-03", "2024-01-08"]);
+        assert!(daily[0].affect.valence > 0.0);
+        assert!(daily[1].affect.valence < 0.0);
+        assert_eq!(daily[2].affect.ekman.anger, 1.0);
+
+        let weekly =
+            get_affect_series(&conn, "2024-01-01", "2024-01-31", Granularity::Week).unwrap();
+        assert_eq!(weekly.len(), 2);
+        assert_eq!(weekly[0].period_start, "2024-01-01");
+        assert_eq!(weekly[0].entry_count, 2);
+        assert!((weekly[0].affect.ekman.joy - 0.45).abs() < 1e-6);
+        assert!((weekly[0].affect.ekman.sadness - 0.4).abs() < 1e-6);
+        assert_eq!(weekly[1].period_start, "2024-01-08");
+
+        let none = get_affect_series(&conn, "2024-02-01", "2024-02-28", Granularity::Day).unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_emotion_series_fills_gaps() {
+        let conn = setup_test_db();
+
+        conn.execute_batch(
+            "INSERT INTO journals (id, content, created_at) VALUES ('a', 'A', '2024-01-01 09:00:00');
+             INSERT INTO journals (id, content, created_at) VALUES ('b', 'B', '2024-01-01 18:00:00');
+             INSERT INTO journals (id, content, created_at) VALUES ('c', 'C', '2024-01-03 09:00:00');",
+        )
+        .unwrap();
+        store(&conn, "a", "joy", 0.8).unwrap();
+        store(&conn, "a", "gratitude", 0.4).unwrap();
+        store(&conn, "b", "sadness", 0.6).unwrap();
+        store(&conn, "c", "joy", 0.2).unwrap();
+
+        let labels = vec!["joy".to_string(), "sadness".to_string()];
+        let series = get_emotion_series(
+            &conn,
+            "2024-01-01",
+            "2024-01-04",
+            &labels,
+            Granularity::Day,
+            ScoreAggregation::Mean,
+            None,
+        )
+        .unwrap();
+        assert_eq!(series.periods.len(), 4);
+        assert_eq!(series.entry_counts, vec![2, 0, 1, 0]);
+        assert_eq!(series.labels[0].label, "joy");
+        // Entry 'b' has no joy, so it counts as 0 in the mean
+        assert_eq!(
+            series.labels[0].values,
+            vec![Some(0.4), None, Some(0.2), None]
+        );
+        assert_eq!(
+            series.labels[1].values,
+            vec![Some(0.3), None, Some(0.0), None]
+        );
+
+        let max = get_emotion_series(
+            &conn,
+            "2024-01-01",
+            "2024-01-04",
+            &labels,
+            Granularity::Day,
+            ScoreAggregation::Max,
+            Some(Smoothing::Rolling { window: 3 }),
+        )
+        .unwrap();
+        assert_eq!(max.labels[0].values, vec![Some(0.8), None, Some(0.5), None]);
+
+        // Without labels, every label in the range is returned, strongest first
+        let all = get_emotion_series(
+            &conn,
+            "2024-01-01",
+            "2024-01-04",
+            &[],
+            Granularity::Week,
+            ScoreAggregation::Sum,
+            None,
+        )
+        .unwrap();
+        let names: Vec<&str> = all.labels.iter().map(|l| l.label.as_str()).collect();
+        assert_eq!(names, vec!["joy", "sadness", "gratitude"]);
+        assert_eq!(all.periods, vec!["2024-01-01"]);
+    }
+
+    #[test]
+    fn test_mood_insights_from_stored_emotions() {
+        let conn = setup_test_db();
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+
+        for offset in 0..40 {
+            let id = format!("e{}", offset);
+            let day = as_of - chrono::Duration::days(offset);
+            conn.execute(
+                "INSERT INTO journals (id, content, created_at) VALUES (?1, 'Entry', ?2)",
+                params![id, format!("{} 12:00:00", day.format("%Y-%m-%d"))],
+            )
+            .unwrap();
+            if offset < 7 {
+                store(&conn, &id, "sadness", 0.8).unwrap();
+            } else {
+                store(&conn, &id, "joy", 0.6).unwrap();
+                if offset % 3 == 0 {
+                    store(&conn, &id, "nervousness", 0.3).unwrap();
+                }
+            }
+        }
+        // Archived entries don't count
+        conn.execute("UPDATE journals SET is_archived = 1 WHERE id = 'e0'", [])
+            .unwrap();
+
+        let insights = get_mood_insights(&conn, as_of).unwrap();
+        let elevated = insights
+            .iter()
+            .find(|i| i.kind == anomaly::InsightKind::ElevatedNegative)
+            .unwrap();
+        assert!(elevated.evidence.iter().all(|e| e.journal_id != "e0"));
+        assert_eq!(elevated.evidence[0].labels, vec!["sadness"]);
+
+        // Two weeks earlier nothing stood out
+        let earlier = as_of - chrono::Duration::days(14);
+        assert!(get_mood_insights(&conn, earlier).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_corrections_override_model_output() {
+        let conn = setup_test_db();
+        conn.execute(
+            "INSERT INTO journals (id, content, created_at) VALUES ('a', 'Great, another Monday', '2024-01-01 09:00:00')",
+            [],
+        )
+        .unwrap();
+        store(&conn, "a", "amusement", 0.7).unwrap();
+        store(&conn, "a", "joy", 0.3).unwrap();
+
+        set_correction(&conn, "a", "amusement", CorrectionAction::Remove).unwrap();
+        set_correction(&conn, "a", "annoyance", CorrectionAction::Add).unwrap();
+        set_correction(&conn, "a", "joy", CorrectionAction::Confirm).unwrap();
+
+        // Model output is untouched
+        assert_eq!(get(&conn, "a").unwrap().len(), 2);
+        assert_eq!(
+            get_effective(&conn, "a").unwrap(),
+            vec![("annoyance".to_string(), 1.0), ("joy".to_string(), 0.3)]
+        );
+        let entries = entry_emotions_in_range(&conn, "2024-01-01", "2024-01-01").unwrap();
+        assert_eq!(entries[0].emotions.len(), 2);
+        assert!(entries[0]
+            .emotions
+            .iter()
+            .all(|(label, _)| label != "amusement"));
+
+        let calibration = get_calibration(&conn).unwrap();
+        assert!(calibration.bias("amusement") < 0.0);
+        assert!(calibration.bias("annoyance") > 0.0);
+
+        // A later correction replaces the earlier one; clearing restores the model's label
+        set_correction(&conn, "a", "amusement", CorrectionAction::Confirm).unwrap();
+        assert!(get_calibration(&conn).unwrap().bias("amusement") > 0.0);
+        clear_correction(&conn, "a", "amusement").unwrap();
+        assert_eq!(get_corrections(&conn, "a").unwrap().len(), 2);
+        assert_eq!(get_effective(&conn, "a").unwrap()[1].0, "amusement");
+    }
 }
@@ -0,0 +1,626 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use rusqlite::{params, Connection, OptionalExtension, ToSql};
+use serde::Serialize;
+
+use crate::db::artifacts::{self, Artifact};
+use crate::error::AppError;
+use crate::llm::ollama::CHAT_MODEL;
+use crate::ml::language::{self, ENGLISH};
+use crate::ml::models::NER_MODEL;
+use crate::ml::ner::{self, EntityKind, EntityMention};
+
+/// Entity extractor versions whose results are current for an entry in the given
+/// language. English entries use the NER model, but the local LLM handles them on
+/// machines where it can't be loaded; other languages always use the LLM.
+pub fn model_versions_for(language: Option<&str>) -> &'static [&'static str] {
+    if language::is_english(language) {
+        &[NER_MODEL.local_dir, CHAT_MODEL]
+    } else {
+        &[CHAT_MODEL]
+    }
+}
+
+/// Whether an entry's entities were extracted from this content by an extractor
+/// accepted for its language.
+pub fn is_fresh(
+    conn: &Connection,
+    journal_id: &str,
+    content_hash: &str,
+    language: Option<&str>,
+) -> Result<bool, AppError> {
+    for version in model_versions_for(language) {
+        if artifacts::is_fresh(conn, journal_id, Artifact::Entities, content_hash, version)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Entries without fresh entities from an extractor accepted for their language,
+/// newest first.
+pub fn list_outdated(conn: &Connection) -> Result<Vec<String>, AppError> {
+    conn.prepare(
+        "SELECT j.id FROM journals j
+         WHERE NOT EXISTS (
+             SELECT 1 FROM derived_artifacts a
+             WHERE a.journal_id = j.id AND a.artifact = ?1 AND a.is_stale = 0
+               AND (a.model_version = ?4
+                    OR (a.model_version = ?3 AND (j.language IS NULL OR j.language = ?2)))
+         )
+         ORDER BY j.created_at DESC",
+    )?
+    .query_map(
+        params![
+            Artifact::Entities.as_str(),
+            ENGLISH,
+            NER_MODEL.local_dir,
+            CHAT_MODEL
+        ],
+        |row| row.get(0),
+    )?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(AppError::from)
+}
+
+/// Whether entities were ever extracted from an entry (even if it mentions none).
+pub fn is_extracted(conn: &Connection, journal_id: &str) -> Result<bool, AppError> {
+    conn.prepare("SELECT 1 FROM derived_artifacts WHERE journal_id = ?1 AND artifact = ?2")?
+        .exists(params![journal_id, Artifact::Entities.as_str()])
+        .map_err(AppError::from)
+}
+
+/// A person or place with how often it comes up in non-archived entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntitySummary {
+    pub id: i64,
+    pub kind: EntityKind,
+    pub name: String,
+    /// Other names it was mentioned by (e.g. a first name or nickname)
+    pub aliases: Vec<String>,
+    pub mention_count: u32,
+    pub entry_count: u32,
+    pub first_mentioned: Option<String>,
+    pub last_mentioned: Option<String>,
+}
+
+/// Everything known about one person or place.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityDetail {
+    #[serde(flatten)]
+    pub summary: EntitySummary,
+    /// Entries mentioning it, newest first
+    pub entries: Vec<EntityEntry>,
+    /// Mentions per month, oldest first
+    pub timeline: Vec<MentionMonth>,
+    /// Emotions of the entries mentioning it, strongest first
+    pub emotions: Vec<EntityEmotion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityEntry {
+    pub journal_id: String,
+    pub title: Option<String>,
+    pub created_at: String,
+    pub mention_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MentionMonth {
+    /// YYYY-MM
+    pub month: String,
+    pub entries: u32,
+    pub mentions: u32,
+}
+
+/// How an emotion shows up in entries mentioning an entity compared to all entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityEmotion {
+    pub label: String,
+    /// Mean score over analyzed entries mentioning the entity (0 where the label is absent)
+    pub score: f32,
+    /// Mean score over all analyzed entries
+    pub baseline: f32,
+    /// `score - baseline`; positive when the entity comes with more of this emotion
+    pub lift: f32,
+}
+
+/// Replace the people and places recorded for an entry. Each mention is resolved
+/// to an entity through its aliases; see `resolve` for how new names are merged.
+pub fn replace_for_entry(
+    conn: &Connection,
+    journal_id: &str,
+    mentions: &[EntityMention],
+) -> Result<(), AppError> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM entry_entities WHERE journal_id = ?1",
+        params![journal_id],
+    )?;
+
+    // Full names first, so "Sam" can be matched to the "Sam Rivera" of the same entry
+    let mut ordered: Vec<&EntityMention> = mentions.iter().collect();
+    ordered.sort_by_key(|m| Reverse(m.name.split_whitespace().count()));
+
+    let mut counts: BTreeMap<i64, u32> = BTreeMap::new();
+    for mention in ordered {
+        let id = resolve(&tx, mention.kind, &mention.name)?;
+        *counts.entry(id).or_default() += 1;
+    }
+    for (entity_id, count) in &counts {
+        tx.execute(
+            "INSERT INTO entry_entities (journal_id, entity_id, mention_count) VALUES (?1, ?2, ?3)",
+            params![journal_id, entity_id, count],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Find or create the entity a name refers to. Known aliases win; otherwise a
+/// lone first name joins the one person whose full name starts with it, and a full
+/// name takes over the one person known only by its first name. Ambiguous names
+/// get an entity of their own. New names are recorded as aliases.
+fn resolve(conn: &Connection, kind: EntityKind, name: &str) -> Result<i64, AppError> {
+    let key = ner::alias_key(name);
+    let known: Option<i64> = conn
+        .query_row(
+            "SELECT entity_id FROM entity_aliases WHERE kind = ?1 AND alias_key = ?2",
+            params![kind.as_str(), key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(id) = known {
+        return Ok(id);
+    }
+
+    let id = match person_match(conn, kind, name, &key)? {
+        Some(id) => id,
+        None => {
+            conn.execute(
+                "INSERT INTO entities (kind, name) VALUES (?1, ?2)",
+                params![kind.as_str(), name],
+            )?;
+            conn.last_insert_rowid()
+        }
+    };
+    conn.execute(
+        "INSERT OR IGNORE INTO entity_aliases (kind, alias_key, alias, entity_id)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![kind.as_str(), key, name, id],
+    )?;
+    Ok(id)
+}
+
+/// The existing person a first name or full name unambiguously belongs to.
+/// A full name replaces the display name of a person known only by its first name.
+fn person_match(
+    conn: &Connection,
+    kind: EntityKind,
+    name: &str,
+    key: &str,
+) -> Result<Option<i64>, AppError> {
+    if kind != EntityKind::Person {
+        return Ok(None);
+    }
+    let Some(first) = key.split_whitespace().next() else {
+        return Ok(None);
+    };
+    // Person entities sharing the first name, split by whether that's all they're called
+    let namesakes: Vec<(i64, String)> = conn
+        .prepare("SELECT id, name FROM entities WHERE kind = ?1")?
+        .query_map(params![kind.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<(i64, String)>, _>>()?
+        .into_iter()
+        .map(|(id, name)| (id, ner::alias_key(&name)))
+        .filter(|(_, other)| other.split_whitespace().next() == Some(first))
+        .collect();
+    let ids = |first_only: bool| -> Vec<i64> {
+        namesakes
+            .iter()
+            .filter(|(_, other)| (other == first) == first_only)
+            .map(|(id, _)| *id)
+            .collect()
+    };
+    let (first_only, full) = (ids(true), ids(false));
+
+    let is_first_name = key == first;
+    match (first_only.as_slice(), full.as_slice()) {
+        (_, [id]) if is_first_name => Ok(Some(*id)),
+        ([id], []) if !is_first_name => {
+            conn.execute(
+                "UPDATE entities SET name = ?1 WHERE id = ?2",
+                params![name, id],
+            )?;
+            Ok(Some(*id))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Entity summaries matching `filter` (a condition on `e`), most mentioned first.
+/// With `mentioned_only`, entities not mentioned in any non-archived entry are left out.
+fn summaries(
+    conn: &Connection,
+    filter: &str,
+    filter_params: &[&dyn ToSql],
+    mentioned_only: bool,
+    limit: i64,
+) -> Result<Vec<EntitySummary>, AppError> {
+    let sql = format!(
+        "SELECT e.id, e.kind, e.name,
+                COALESCE(SUM(CASE WHEN j.id IS NOT NULL THEN ee.mention_count END), 0),
+                COUNT(j.id), MIN(j.created_at), MAX(j.created_at)
+         FROM entities e
+         LEFT JOIN entry_entities ee ON ee.entity_id = e.id
+         LEFT JOIN journals j ON j.id = ee.journal_id AND j.is_archived = 0
+         WHERE {}
+         GROUP BY e.id
+         {}
+         ORDER BY 4 DESC, e.name
+         LIMIT {}",
+        filter,
+        if mentioned_only {
+            "HAVING COUNT(j.id) > 0"
+        } else {
+            ""
+        },
+        limit
+    );
+    // Rows with an unknown kind are skipped
+    let mut found: Vec<EntitySummary> = conn
+        .prepare(&sql)?
+        .query_map(filter_params, |row| {
+            let Some(kind) = EntityKind::parse(&row.get::<_, String>(1)?) else {
+                return Ok(None);
+            };
+            Ok(Some(EntitySummary {
+                id: row.get(0)?,
+                kind,
+                name: row.get(2)?,
+                aliases: Vec::new(),
+                mention_count: row.get(3)?,
+                entry_count: row.get(4)?,
+                first_mentioned: row.get(5)?,
+                last_mentioned: row.get(6)?,
+            }))
+        })?
+        .filter_map(Result::transpose)
+        .collect::<Result<_, _>>()?;
+
+    let mut aliases_stmt = conn.prepare(
+        "SELECT alias FROM entity_aliases WHERE entity_id = ?1 AND alias != ?2 ORDER BY alias",
+    )?;
+    for entity in &mut found {
+        entity.aliases = aliases_stmt
+            .query_map(params![entity.id, entity.name], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+    }
+    Ok(found)
+}
+
+/// People or places mentioned in non-archived entries, most mentioned first.
+pub fn list(
+    conn: &Connection,
+    kind: EntityKind,
+    limit: usize,
+) -> Result<Vec<EntitySummary>, AppError> {
+    summaries(conn, "e.kind = ?1", &[&kind.as_str()], true, limit as i64)
+}
+
+/// An entity with the entries mentioning it, mentions per month and the emotions
+/// of those entries.
+pub fn get(conn: &Connection, id: i64) -> Result<EntityDetail, AppError> {
+    let summary = summaries(conn, "e.id = ?1", &[&id], false, 1)?
+        .pop()
+        .ok_or_else(|| AppError::NotFound(format!("entity {}", id)))?;
+
+    let entries = conn
+        .prepare(
+            "SELECT j.id, j.title, j.created_at, ee.mention_count
+             FROM entry_entities ee JOIN journals j ON j.id = ee.journal_id
+             WHERE ee.entity_id = ?1 AND j.is_archived = 0
+             ORDER BY j.created_at DESC",
+        )?
+        .query_map(params![id], |row| {
+            Ok(EntityEntry {
+                journal_id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                mention_count: row.get(3)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    let timeline = conn
+        .prepare(
+            "SELECT strftime('%Y-%m', j.created_at) AS month, COUNT(*), SUM(ee.mention_count)
+             FROM entry_entities ee JOIN journals j ON j.id = ee.journal_id
+             WHERE ee.entity_id = ?1 AND j.is_archived = 0
+             GROUP BY month
+             ORDER BY month",
+        )?
+        .query_map(params![id], |row| {
+            Ok(MentionMonth {
+                month: row.get(0)?,
+                entries: row.get(1)?,
+                mentions: row.get(2)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+
+    Ok(EntityDetail {
+        summary,
+        entries,
+        timeline,
+        emotions: emotion_associations(conn, id)?,
+    })
+}
+
+/// Mean emotion scores of the analyzed entries mentioning an entity, next to the
+/// means over all analyzed entries.
+fn emotion_associations(conn: &Connection, id: i64) -> Result<Vec<EntityEmotion>, AppError> {
+    let (mentioned, analyzed): (u32, u32) = conn.query_row(
+        "WITH analyzed AS (
+             SELECT DISTINCT f.journal_id FROM effective_emotions f
+             JOIN journals j ON j.id = f.journal_id
+             WHERE j.is_archived = 0
+         )
+         SELECT
+             (SELECT COUNT(*) FROM analyzed a
+              JOIN entry_entities ee ON ee.journal_id = a.journal_id
+              WHERE ee.entity_id = ?1),
+             (SELECT COUNT(*) FROM analyzed)",
+        params![id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    if mentioned == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut emotions: Vec<EntityEmotion> = conn
+        .prepare(
+            "SELECT f.emotion_label,
+                    SUM(CASE WHEN ee.entity_id IS NOT NULL THEN f.confidence_score ELSE 0 END),
+                    SUM(f.confidence_score)
+             FROM effective_emotions f
+             JOIN journals j ON j.id = f.journal_id AND j.is_archived = 0
+             LEFT JOIN entry_entities ee ON ee.journal_id = f.journal_id AND ee.entity_id = ?1
+             GROUP BY f.emotion_label",
+        )?
+        .query_map(params![id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, f64>(1)?,
+                row.get::<_, f64>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter(|(_, with_entity, _)| *with_entity > 0.0)
+        .map(|(label, with_entity, total)| {
+            let score = (with_entity / mentioned as f64) as f32;
+            let baseline = (total / analyzed as f64) as f32;
+            EntityEmotion {
+                label,
+                score,
+                baseline,
+                lift: score - baseline,
+            }
+        })
+        .collect();
+    emotions.sort_by(|a, b| b.score.total_cmp(&a.score));
+    Ok(emotions)
+}
+
+/// Merge `merge_id` into `keep_id` (e.g. a nickname into a full name): its aliases
+/// and mentions move over, so future mentions by those names count for `keep_id`.
+pub fn merge(conn: &Connection, keep_id: i64, merge_id: i64) -> Result<EntityDetail, AppError> {
+    if keep_id == merge_id {
+        return Err(AppError::InvalidInput(
+            "Cannot merge an entity into itself".to_string(),
+        ));
+    }
+    let kind_of = |id: i64| -> Result<String, AppError> {
+        conn.query_row(
+            "SELECT kind FROM entities WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("entity {}", id)))
+    };
+    let (keep_kind, merge_kind) = (kind_of(keep_id)?, kind_of(merge_id)?);
+    if keep_kind != merge_kind {
+        return Err(AppError::InvalidInput(format!(
+            "Cannot merge a {} into a {}",
+            merge_kind, keep_kind
+        )));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE entity_aliases SET entity_id = ?1 WHERE entity_id = ?2",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "INSERT INTO entry_entities (journal_id, entity_id, mention_count)
+         SELECT journal_id, ?1, mention_count FROM entry_entities WHERE entity_id = ?2
+         ON CONFLICT(journal_id, entity_id)
+         DO UPDATE SET mention_count = mention_count + excluded.mention_count",
+        params![keep_id, merge_id],
+    )?;
+    tx.execute(
+        "DELETE FROM entry_entities WHERE entity_id = ?1",
+        params![merge_id],
+    )?;
+    tx.execute("DELETE FROM entities WHERE id = ?1", params![merge_id])?;
+    tx.commit()?;
+
+    log::info!("Entity merged: {} into {}", merge_id, keep_id);
+    get(conn, keep_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+    use crate::db::{emotions, journals};
+
+    fn setup_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn add_entry(conn: &Connection, id: &str, created_at: &str) {
+        conn.execute(
+            "INSERT INTO journals (id, content, created_at) VALUES (?1, 'entry', ?2)",
+            params![id, created_at],
+        )
+        .unwrap();
+    }
+
+    fn mention(kind: EntityKind, name: &str) -> EntityMention {
+        EntityMention {
+            kind,
+            name: name.to_string(),
+            start_offset: 0,
+            end_offset: name.chars().count(),
+            score: 0.9,
+        }
+    }
+
+    fn person(name: &str) -> EntityMention {
+        mention(EntityKind::Person, name)
+    }
+
+    fn names(conn: &Connection, kind: EntityKind) -> Vec<(String, u32)> {
+        list(conn, kind, 10)
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.name, e.mention_count))
+            .collect()
+    }
+
+    #[test]
+    fn test_alias_merging() {
+        let conn = setup_test_db();
+        for (id, date) in [
+            ("a", "2024-01-05"),
+            ("b", "2024-02-01"),
+            ("c", "2024-02-03"),
+        ] {
+            add_entry(&conn, id, date);
+        }
+
+        // A first name joins the full name mentioned alongside it
+        replace_for_entry(
+            &conn,
+            "a",
+            &[
+                person("Sam"),
+                person("Sam Rivera"),
+                mention(EntityKind::Place, "Lisbon"),
+            ],
+        )
+        .unwrap();
+        // A person known by first name only takes on the full name once it shows up
+        replace_for_entry(&conn, "b", &[person("Alex"), person("sam")]).unwrap();
+        replace_for_entry(&conn, "c", &[person("Alex Kim"), person("Jo Park")]).unwrap();
+
+        assert_eq!(
+            names(&conn, EntityKind::Person),
+            vec![
+                ("Sam Rivera".to_string(), 3),
+                ("Alex Kim".to_string(), 2),
+                ("Jo Park".to_string(), 1),
+            ]
+        );
+        let sam = &list(&conn, EntityKind::Person, 10).unwrap()[0];
+        assert_eq!(sam.aliases, vec!["Sam".to_string()]);
+        assert_eq!(sam.entry_count, 2);
+        assert_eq!(
+            names(&conn, EntityKind::Place),
+            vec![("Lisbon".to_string(), 1)]
+        );
+
+        // With two Jos around, a lone "Jo" can't be placed
+        replace_for_entry(&conn, "b", &[person("Jo Lee"), person("Jo")]).unwrap();
+        let people = names(&conn, EntityKind::Person);
+        assert!(people.contains(&("Jo".to_string(), 1)));
+        // Re-extracting an entry replaces its mentions
+        assert!(people.contains(&("Sam Rivera".to_string(), 2)));
+        assert!(people.contains(&("Alex Kim".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_entity_detail() {
+        let conn = setup_test_db();
+        add_entry(&conn, "jan", "2024-01-10T09:00:00Z");
+        add_entry(&conn, "feb", "2024-02-10T09:00:00Z");
+        add_entry(&conn, "feb2", "2024-02-20T09:00:00Z");
+        add_entry(&conn, "other", "2024-02-21T09:00:00Z");
+        replace_for_entry(&conn, "jan", &[person("Sam"), person("Sam")]).unwrap();
+        replace_for_entry(&conn, "feb", &[person("Sam")]).unwrap();
+        replace_for_entry(&conn, "feb2", &[person("Sam")]).unwrap();
+        emotions::store(&conn, "jan", "joy", 0.8).unwrap();
+        emotions::store(&conn, "feb", "joy", 0.4).unwrap();
+        emotions::store(&conn, "other", "sadness", 0.9).unwrap();
+        journals::archive(&conn, "feb2").unwrap();
+
+        let id = list(&conn, EntityKind::Person, 10).unwrap()[0].id;
+        let detail = get(&conn, id).unwrap();
+        assert_eq!(detail.summary.mention_count, 3);
+        assert_eq!(detail.summary.entry_count, 2);
+        assert_eq!(
+            detail.summary.first_mentioned.as_deref(),
+            Some("2024-01-10T09:00:00Z")
+        );
+        assert_eq!(detail.entries[0].journal_id, "feb");
+        let timeline: Vec<(&str, u32, u32)> = detail
+            .timeline
+            .iter()
+            .map(|m| (m.month.as_str(), m.entries, m.mentions))
+            .collect();
+        assert_eq!(timeline, vec![("2024-01", 1, 2), ("2024-02", 1, 1)]);
+
+        // Joy averages 0.6 across Sam's entries against 0.4 across all three analyzed
+        assert_eq!(detail.emotions.len(), 1);
+        assert_eq!(detail.emotions[0].label, "joy");
+        assert!((detail.emotions[0].score - 0.6).abs() < 1e-5);
+        assert!((detail.emotions[0].baseline - 0.4).abs() < 1e-5);
+        assert!(detail.emotions[0].lift > 0.0);
+
+        assert!(matches!(get(&conn, 999), Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_merge_entities() {
+        let conn = setup_test_db();
+        add_entry(&conn, "a", "2024-01-05");
+        add_entry(&conn, "b", "2024-01-06");
+        replace_for_entry(&conn, "a", &[person("Samuel"), person("Sammy")]).unwrap();
+        replace_for_entry(&conn, "b", &[person("Sammy")]).unwrap();
+        let people = list(&conn, EntityKind::Person, 10).unwrap();
+        let (sammy, samuel) = (people[0].id, people[1].id);
+
+        let merged = merge(&conn, samuel, sammy).unwrap();
+        assert_eq!(merged.summary.name, "Samuel");
+        assert_eq!(merged.summary.aliases, vec!["Sammy".to_string()]);
+        assert_eq!(merged.summary.mention_count, 3);
+        assert_eq!(merged.entries.len(), 2);
+        assert_eq!(list(&conn, EntityKind::Person, 10).unwrap().len(), 1);
+
+        // Later mentions of the nickname count for the kept entity
+        replace_for_entry(&conn, "b", &[person("Sammy"), person("Sammy")]).unwrap();
+        assert_eq!(get(&conn, samuel).unwrap().summary.mention_count, 4);
+
+        replace_for_entry(&conn, "a", &[mention(EntityKind::Place, "Lisbon")]).unwrap();
+        let lisbon = list(&conn, EntityKind::Place, 10).unwrap()[0].id;
+        assert!(merge(&conn, samuel, lisbon).is_err());
+        assert!(merge(&conn, samuel, samuel).is_err());
+    }
+}
@@ -0,0 +1,559 @@
+use std::str::FromStr;
+
+use chrono::{Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// Default priority for user-triggered work.
+pub const PRIORITY_NORMAL: i64 = 0;
+/// Priority for work the user is actively waiting on.
+pub const PRIORITY_HIGH: i64 = 10;
+/// Priority for background maintenance that can wait behind everything else.
+pub const PRIORITY_LOW: i64 = -10;
+
+/// Number of attempts before a job is marked as permanently failed.
+const DEFAULT_MAX_ATTEMPTS: i64 = 5;
+/// Base delay for exponential retry backoff.
+const BACKOFF_BASE_SECS: i64 = 5;
+/// Upper bound for retry backoff.
+const BACKOFF_MAX_SECS: i64 = 600;
+
+/// Kinds of background work handled by the job worker.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum JobType {
+    Embedding,
+    Emotions,
+    Title,
+    Summary,
+    Entities,
+}
+
+impl JobType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobType::Embedding => "embedding",
+            JobType::Emotions => "emotions",
+            JobType::Title => "title",
+            JobType::Summary => "summary",
+            JobType::Entities => "entities",
+        }
+    }
+}
+
+impl FromStr for JobType {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "embedding" => Ok(JobType::Embedding),
+            "emotions" => Ok(JobType::Emotions),
+            "title" => Ok(JobType::Title),
+            "summary" => Ok(JobType::Summary),
+            "entities" => Ok(JobType::Entities),
+            _ => Err(AppError::InvalidInput(format!("Unknown job type: {}", s))),
+        }
+    }
+}
+
+/// Lifecycle state of a job.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Pending => "pending",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(JobStatus::Pending),
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            _ => Err(AppError::InvalidInput(format!("Unknown job status: {}", s))),
+        }
+    }
+}
+
+/// A persisted background job.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub job_type: JobType,
+    pub entry_id: Option<String>,
+    pub payload: Option<String>,
+    pub status: JobStatus,
+    pub priority: i64,
+    pub attempts: i64,
+    pub max_attempts: i64,
+    pub last_error: Option<String>,
+    pub result: Option<String>,
+    pub run_after: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Parameters for enqueuing a new job.
+#[derive(Debug)]
+pub struct EnqueueParams<'a> {
+    pub job_type: JobType,
+    pub entry_id: Option<&'a str>,
+    pub payload: Option<&'a str>,
+    pub priority: i64,
+}
+
+/// Outcome of a failed attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureOutcome {
+    /// The job was rescheduled and will run again after the given time.
+    Retrying { run_after: String },
+    /// The job exhausted its attempts and was marked failed.
+    Failed,
+}
+
+const JOB_COLUMNS: &str = "id, job_type, entry_id, payload, status, priority, attempts, max_attempts, last_error, result, run_after, created_at, updated_at";
+
+/// Build the deduplication key for a job.
+/// Entry-scoped jobs dedupe per entry; global jobs dedupe on their payload.
+fn dedupe_key(params: &EnqueueParams) -> String {
+    match (params.entry_id, params.payload) {
+        (Some(entry_id), _) => entry_id.to_string(),
+        (None, Some(payload)) => payload.to_string(),
+        (None, None) => String::new(),
+    }
+}
+
+/// Enqueue a job, deduplicating against an identical pending job.
+/// If one already exists, its priority is raised to the higher of the two and it is returned.
+pub fn enqueue(conn: &Connection, params: EnqueueParams) -> Result<Job, AppError> {
+    let key = dedupe_key(&params);
+    let now = Utc::now().to_rfc3339();
+
+    let existing: Option<String> = conn
+        .query_row(
+            "SELECT id FROM jobs WHERE job_type = ?1 AND dedupe_key = ?2 AND status = 'pending'",
+            params![params.job_type.as_str(), key],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    if let Some(id) = existing {
+        conn.execute(
+            "UPDATE jobs SET priority = MAX(priority, ?1), updated_at = ?2 WHERE id = ?3",
+            params![params.priority, now, id],
+        )?;
+        return get(conn, &id);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO jobs (id, job_type, entry_id, payload, dedupe_key, status, priority, attempts, max_attempts, run_after, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, 0, ?7, ?8, ?8, ?8)",
+        params![
+            id,
+            params.job_type.as_str(),
+            params.entry_id,
+            params.payload,
+            key,
+            params.priority,
+            DEFAULT_MAX_ATTEMPTS,
+            now,
+        ],
+    )?;
+
+    log::info!("Job enqueued: id={}, type={}", id, params.job_type.as_str());
+
+    get(conn, &id)
+}
+
+/// Get a job by ID.
+pub fn get(conn: &Connection, id: &str) -> Result<Job, AppError> {
+    conn.query_row(
+        &format!("SELECT {} FROM jobs WHERE id = ?1", JOB_COLUMNS),
+        params![id],
+        map_job,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Job not found: {}", id)))
+}
+
+/// List jobs, most recently updated first, optionally filtered by status.
+pub fn list(
+    conn: &Connection,
+    status: Option<JobStatus>,
+    limit: Option<i64>,
+) -> Result<Vec<Job>, AppError> {
+    let limit = limit.unwrap_or(50).min(500);
+
+    let jobs = if let Some(status) = status {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM jobs WHERE status = ?1 ORDER BY updated_at DESC LIMIT ?2",
+            JOB_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![status.as_str(), limit], map_job)?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    } else {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM jobs ORDER BY updated_at DESC LIMIT ?1",
+            JOB_COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![limit], map_job)?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    Ok(jobs)
+}
+
+/// Claim the next runnable job of one of the given types.
+/// Picks the highest priority job whose backoff has elapsed and marks it running, in
+/// one statement so a concurrent cancel or claim can't be overwritten.
+pub fn claim_next(conn: &Connection, job_types: &[JobType]) -> Result<Option<Job>, AppError> {
+    if job_types.is_empty() {
+        return Ok(None);
+    }
+
+    let now = Utc::now().to_rfc3339();
+    let placeholders: String = job_types
+        .iter()
+        .enumerate()
+        .map(|(i, _)| format!("?{}", i + 2))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let sql = format!(
+        "UPDATE jobs SET status = 'running', attempts = attempts + 1, updated_at = ?1
+         WHERE status = 'pending' AND id = (
+             SELECT id FROM jobs
+             WHERE status = 'pending' AND run_after <= ?1 AND job_type IN ({})
+             ORDER BY priority DESC, created_at ASC
+             LIMIT 1
+         )
+         RETURNING {}",
+        placeholders, JOB_COLUMNS
+    );
+
+    let mut query_params: Vec<&dyn rusqlite::ToSql> = vec![&now];
+    let type_strs: Vec<&str> = job_types.iter().map(|t| t.as_str()).collect();
+    for t in &type_strs {
+        query_params.push(t);
+    }
+
+    conn.query_row(&sql, query_params.as_slice(), map_job)
+        .optional()
+        .map_err(AppError::from)
+}
+
+/// Mark a running job as completed with an optional result payload.
+/// Jobs cancelled while running keep their cancelled status.
+pub fn complete(conn: &Connection, id: &str, result: Option<&str>) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE jobs SET status = 'completed', result = ?1, last_error = NULL, updated_at = ?2
+         WHERE id = ?3 AND status = 'running'",
+        params![result, Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// Record a failed attempt, rescheduling with exponential backoff until attempts run out.
+pub fn fail(conn: &Connection, id: &str, error: &str) -> Result<FailureOutcome, AppError> {
+    let job = get(conn, id)?;
+    let now = Utc::now();
+
+    if job.status != JobStatus::Running {
+        return Ok(FailureOutcome::Failed);
+    }
+
+    if job.attempts >= job.max_attempts {
+        conn.execute(
+            "UPDATE jobs SET status = 'failed', last_error = ?1, updated_at = ?2 WHERE id = ?3",
+            params![error, now.to_rfc3339(), id],
+        )?;
+        return Ok(FailureOutcome::Failed);
+    }
+
+    // A newer pending job for the same work supersedes this retry
+    let superseded: bool = conn
+        .prepare(
+            "SELECT 1 FROM jobs j
+             JOIN jobs o ON o.job_type = j.job_type AND o.dedupe_key = j.dedupe_key
+             WHERE j.id = ?1 AND o.status = 'pending'",
+        )?
+        .exists(params![id])?;
+    if superseded {
+        conn.execute(
+            "UPDATE jobs SET status = 'cancelled', last_error = ?1, updated_at = ?2 WHERE id = ?3",
+            params![error, now.to_rfc3339(), id],
+        )?;
+        return Ok(FailureOutcome::Failed);
+    }
+
+    let run_after = (now + backoff_delay(job.attempts)).to_rfc3339();
+    conn.execute(
+        "UPDATE jobs SET status = 'pending', last_error = ?1, run_after = ?2, updated_at = ?3 WHERE id = ?4",
+        params![error, run_after, now.to_rfc3339(), id],
+    )?;
+
+    Ok(FailureOutcome::Retrying { run_after })
+}
+
+/// Delay before the next attempt, doubling with each failure.
+fn backoff_delay(attempts: i64) -> Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let secs = BACKOFF_BASE_SECS
+        .saturating_mul(2i64.saturating_pow(exponent))
+        .min(BACKOFF_MAX_SECS);
+    Duration::seconds(secs)
+}
+
+/// Cancel a pending or running job.
+/// Returns true if the job was still active.
+pub fn cancel(conn: &Connection, id: &str) -> Result<bool, AppError> {
+    let rows = conn.execute(
+        "UPDATE jobs SET status = 'cancelled', updated_at = ?1
+         WHERE id = ?2 AND status IN ('pending', 'running')",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+
+    if rows == 0 {
+        // Distinguish "already finished" from "does not exist"
+        get(conn, id)?;
+    }
+
+    Ok(rows > 0)
+}
+
+/// Cancel all active jobs for an entry (e.g., when the entry is deleted).
+/// Returns the IDs of the cancelled jobs.
+pub fn cancel_for_entry(conn: &Connection, entry_id: &str) -> Result<Vec<String>, AppError> {
+    let ids: Vec<String> = conn
+        .prepare("SELECT id FROM jobs WHERE entry_id = ?1 AND status IN ('pending', 'running')")?
+        .query_map(params![entry_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    conn.execute(
+        "UPDATE jobs SET status = 'cancelled', updated_at = ?1
+         WHERE entry_id = ?2 AND status IN ('pending', 'running')",
+        params![Utc::now().to_rfc3339(), entry_id],
+    )?;
+
+    Ok(ids)
+}
+
+/// Return jobs left running by a previous session to the pending state.
+/// Called at startup so interrupted work is picked up again.
+pub fn requeue_interrupted(conn: &Connection) -> Result<usize, AppError> {
+    let now = Utc::now().to_rfc3339();
+
+    // Interrupted jobs that were re-enqueued in the meantime are superseded
+    conn.execute(
+        "UPDATE jobs SET status = 'cancelled', updated_at = ?1
+         WHERE status = 'running' AND EXISTS (
+             SELECT 1 FROM jobs o
+             WHERE o.job_type = jobs.job_type AND o.dedupe_key = jobs.dedupe_key AND o.status = 'pending'
+         )",
+        params![now],
+    )?;
+
+    let rows = conn.execute(
+        "UPDATE jobs SET status = 'pending', attempts = MAX(attempts - 1, 0), updated_at = ?1
+         WHERE status = 'running'",
+        params![now],
+    )?;
+
+    if rows > 0 {
+        log::info!("Requeued {} interrupted jobs", rows);
+    }
+
+    Ok(rows)
+}
+
+/// Delete finished jobs older than the given number of days.
+pub fn prune_finished(conn: &Connection, older_than_days: i64) -> Result<usize, AppError> {
+    let cutoff = (Utc::now() - Duration::days(older_than_days)).to_rfc3339();
+    let rows = conn.execute(
+        "DELETE FROM jobs WHERE status IN ('completed', 'cancelled', 'failed') AND updated_at < ?1",
+        params![cutoff],
+    )?;
+    Ok(rows)
+}
+
+fn map_job(row: &rusqlite::Row) -> rusqlite::Result<Job> {
+    let job_type: String = row.get(1)?;
+    let status: String = row.get(4)?;
+    Ok(Job {
+        id: row.get(0)?,
+        job_type: job_type.parse().unwrap_or(JobType::Embedding),
+        entry_id: row.get(2)?,
+        payload: row.get(3)?,
+        status: status.parse().unwrap_or(JobStatus::Failed),
+        priority: row.get(5)?,
+        attempts: row.get(6)?,
+        max_attempts: row.get(7)?,
+        last_error: row.get(8)?,
+        result: row.get(9)?,
+        run_after: row.get(10)?,
+        created_at: row.get(11)?,
+        updated_at: row.get(12)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+
+    fn setup_test_db() -> Connection {
+        #[allow(clippy::missing_transmute_annotations)]
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn enqueue_for(conn: &Connection, job_type: JobType, entry_id: &str, priority: i64) -> Job {
+        enqueue(
+            conn,
+            EnqueueParams {
+                job_type,
+                entry_id: Some(entry_id),
+                payload: None,
+                priority,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_enqueue_deduplicates_pending_jobs() {
+        let conn = setup_test_db();
+
+        let first = enqueue_for(&conn, JobType::Embedding, "entry-1", PRIORITY_NORMAL);
+        let second = enqueue_for(&conn, JobType::Embedding, "entry-1", PRIORITY_HIGH);
+
+        assert_eq!(first.id, second.id);
+        assert_eq!(second.priority, PRIORITY_HIGH);
+
+        // Different type for the same entry is a separate job
+        let other = enqueue_for(&conn, JobType::Emotions, "entry-1", PRIORITY_NORMAL);
+        assert_ne!(first.id, other.id);
+    }
+
+    #[test]
+    fn test_claim_respects_priority_and_type() {
+        let conn = setup_test_db();
+
+        enqueue_for(&conn, JobType::Embedding, "low", PRIORITY_NORMAL);
+        enqueue_for(&conn, JobType::Embedding, "high", PRIORITY_HIGH);
+        enqueue_for(&conn, JobType::Title, "title", PRIORITY_HIGH);
+
+        let job = claim_next(&conn, &[JobType::Embedding]).unwrap().unwrap();
+        assert_eq!(job.entry_id.as_deref(), Some("high"));
+        assert_eq!(job.status, JobStatus::Running);
+        assert_eq!(job.attempts, 1);
+
+        let job = claim_next(&conn, &[JobType::Embedding]).unwrap().unwrap();
+        assert_eq!(job.entry_id.as_deref(), Some("low"));
+
+        assert!(claim_next(&conn, &[JobType::Embedding]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_claim_skips_cancelled_jobs() {
+        let conn = setup_test_db();
+        let job = enqueue_for(&conn, JobType::Embedding, "entry-1", PRIORITY_NORMAL);
+        cancel(&conn, &job.id).unwrap();
+
+        assert!(claim_next(&conn, &[JobType::Embedding]).unwrap().is_none());
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert_eq!(job.attempts, 0);
+    }
+
+    #[test]
+    fn test_fail_retries_with_backoff_then_fails() {
+        let conn = setup_test_db();
+        let job = enqueue_for(&conn, JobType::Emotions, "entry-1", PRIORITY_NORMAL);
+
+        claim_next(&conn, &[JobType::Emotions]).unwrap().unwrap();
+        let outcome = fail(&conn, &job.id, "model not ready").unwrap();
+        assert!(matches!(outcome, FailureOutcome::Retrying { .. }));
+
+        // Backoff delays the retry, so it can't be claimed immediately
+        assert!(claim_next(&conn, &[JobType::Emotions]).unwrap().is_none());
+
+        // Exhaust remaining attempts
+        for _ in 1..DEFAULT_MAX_ATTEMPTS {
+            conn.execute(
+                "UPDATE jobs SET run_after = '1970-01-01T00:00:00+00:00' WHERE id = ?1",
+                [&job.id],
+            )
+            .unwrap();
+            claim_next(&conn, &[JobType::Emotions]).unwrap().unwrap();
+            fail(&conn, &job.id, "still broken").unwrap();
+        }
+
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Failed);
+        assert_eq!(job.last_error.as_deref(), Some("still broken"));
+    }
+
+    #[test]
+    fn test_backoff_grows_and_caps() {
+        assert_eq!(backoff_delay(1), Duration::seconds(5));
+        assert_eq!(backoff_delay(2), Duration::seconds(10));
+        assert_eq!(backoff_delay(3), Duration::seconds(20));
+        assert_eq!(backoff_delay(30), Duration::seconds(BACKOFF_MAX_SECS));
+    }
+
+    #[test]
+    fn test_cancelled_running_job_is_not_completed() {
+        let conn = setup_test_db();
+        let job = enqueue_for(&conn, JobType::Title, "entry-1", PRIORITY_NORMAL);
+
+        claim_next(&conn, &[JobType::Title]).unwrap().unwrap();
+        assert!(cancel(&conn, &job.id).unwrap());
+        complete(&conn, &job.id, Some("A Title")).unwrap();
+
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Cancelled);
+        assert!(!cancel(&conn, &job.id).unwrap());
+    }
+
+    #[test]
+    fn test_requeue_interrupted() {
+        let conn = setup_test_db();
+        let job = enqueue_for(&conn, JobType::Embedding, "entry-1", PRIORITY_NORMAL);
+        claim_next(&conn, &[JobType::Embedding]).unwrap().unwrap();
+
+        assert_eq!(requeue_interrupted(&conn).unwrap(), 1);
+
+        let job = get(&conn, &job.id).unwrap();
+        assert_eq!(job.status, JobStatus::Pending);
+        assert_eq!(job.attempts, 0);
+    }
+}
@@ -4,6 +4,7 @@ use chrono::{DateTime, Datelike, Local, NaiveDate, Utc};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 
+use crate::db::{artifacts, vectors};
 use crate::error::AppError;
 
 /// Entry types for different journaling modes.
@@ -50,6 +51,8 @@ pub struct Journal {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub is_archived: bool,
+    /// ISO 639-3 code of the detected language, if known
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -80,9 +83,11 @@ pub fn create(
     let now = Utc::now();
     let entry_type_str = entry_type.unwrap_or("reflection");
 
+    let language = crate::ml::language::detect(content);
+
     conn.execute(
-        "INSERT INTO journals (id, content, title, entry_type, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![id, content, title, entry_type_str, now.to_rfc3339(), now.to_rfc3339()],
+        "INSERT INTO journals (id, content, title, entry_type, created_at, updated_at, language) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![id, content, title, entry_type_str, now.to_rfc3339(), now.to_rfc3339(), language],
     )?;
 
     log::info!("Entry created: id={}", id);
@@ -97,7 +102,7 @@ pub fn create(
 pub fn get(conn: &Connection, id: &str) -> Result<Journal, AppError> {
     let journal = conn
         .query_row(
-            "SELECT id, content, title, entry_type, created_at, updated_at, is_archived FROM journals WHERE id = ?1",
+            "SELECT id, content, title, entry_type, created_at, updated_at, is_archived, language FROM journals WHERE id = ?1",
             params![id],
             |row| {
                 let entry_type_str: Option<String> = row.get(3)?;
@@ -109,6 +114,7 @@ pub fn get(conn: &Connection, id: &str) -> Result<Journal, AppError> {
                     created_at: parse_datetime(row.get::<_, String>(4)?),
                     updated_at: parse_datetime(row.get::<_, String>(5)?),
                     is_archived: row.get(6)?,
+                    language: row.get(7)?,
                 })
             },
         )
@@ -118,28 +124,44 @@ pub fn get(conn: &Connection, id: &str) -> Result<Journal, AppError> {
     Ok(journal)
 }
 
-/// List journal entries with pagination.
+/// List journal entries with pagination, optionally filtered by archive state and language.
 pub fn list(
     conn: &Connection,
     limit: Option<i64>,
     offset: Option<i64>,
     archived: Option<bool>,
+    language: Option<&str>,
 ) -> Result<Vec<Journal>, AppError> {
     let limit = limit.unwrap_or(50).min(100);
     let offset = offset.unwrap_or(0);
 
-    let (sql, use_archived_param) = if archived.is_some() {
-        (
-            "SELECT id, content, title, entry_type, created_at, updated_at, is_archived FROM journals WHERE is_archived = ?1 ORDER BY created_at DESC LIMIT ?2 OFFSET ?3".to_string(),
-            true,
-        )
+    let mut filters = Vec::new();
+    let mut params_vec: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(a) = archived {
+        filters.push(format!("is_archived = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(a));
+    }
+    if let Some(l) = language {
+        filters.push(format!("language = ?{}", params_vec.len() + 1));
+        params_vec.push(Box::new(l.to_string()));
+    }
+
+    let where_clause = if filters.is_empty() {
+        String::new()
     } else {
-        (
-            "SELECT id, content, title, entry_type, created_at, updated_at, is_archived FROM journals ORDER BY created_at DESC LIMIT ?1 OFFSET ?2".to_string(),
-            false,
-        )
+        format!("WHERE {}", filters.join(" AND "))
     };
 
+    let sql = format!(
+        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived, language FROM journals {} ORDER BY created_at DESC LIMIT ?{} OFFSET ?{}",
+        where_clause,
+        params_vec.len() + 1,
+        params_vec.len() + 2
+    );
+    params_vec.push(Box::new(limit));
+    params_vec.push(Box::new(offset));
+
     let mut stmt = conn.prepare(&sql)?;
 
     let row_mapper = |row: &rusqlite::Row| {
@@ -156,20 +178,18 @@ pub fn list(
             created_at: parse_datetime(row.get::<_, String>(4)?),
             updated_at: parse_datetime(row.get::<_, String>(5)?),
             is_archived: row.get(6)?,
+            language: row.get(7)?,
         })
     };
 
-    let journals: Vec<Journal> = if use_archived_param {
-        let archived_val: i32 = if archived.unwrap_or(false) { 1 } else { 0 };
-        stmt.query_map(params![archived_val, limit, offset], row_mapper)?
-    } else {
-        stmt.query_map(params![limit, offset], row_mapper)?
-    }
-    .filter_map(|r| {
-        r.map_err(|e| log::error!("Failed to parse journal row: {}", e))
-            .ok()
-    })
-    .collect();
+    let params_refs: Vec<&dyn rusqlite::ToSql> = params_vec.iter().map(|p| p.as_ref()).collect();
+    let journals: Vec<Journal> = stmt
+        .query_map(params_refs.as_slice(), row_mapper)?
+        .filter_map(|r| {
+            r.map_err(|e| log::error!("Failed to parse journal row: {}", e))
+                .ok()
+        })
+        .collect();
 
     Ok(journals)
 }
@@ -211,6 +231,8 @@ pub fn update(
     if let Some(c) = content {
         updates.push("content = ?".to_string());
         params_vec.push(Box::new(c.to_string()));
+        updates.push("language = ?".to_string());
+        params_vec.push(Box::new(crate::ml::language::detect(c)));
     }
     if let Some(t) = title {
         updates.push("title = ?".to_string());
@@ -259,6 +281,18 @@ pub fn update(
         )));
     }
 
+    // Embeddings, chunks and emotions computed from the old text are now out of date
+    if let Some(c) = content {
+        let stale = artifacts::mark_stale(conn, id, &artifacts::content_hash(c))?;
+        if !stale.is_empty() {
+            log::info!(
+                "Marked {} derived artifact(s) stale for entry {}",
+                stale.len(),
+                id
+            );
+        }
+    }
+
     log::info!("Entry updated: id={}", id);
 
     get(conn, id)
@@ -266,6 +300,9 @@ pub fn update(
 
 /// Delete a journal entry.
 pub fn delete(conn: &Connection, id: &str) -> Result<DeleteResponse, AppError> {
+    // vec0 tables don't support foreign keys, so clean up vectors while chunk ids still exist
+    vectors::delete_for_entry(conn, id)?;
+
     let rows_affected = conn.execute("DELETE FROM journals WHERE id = ?1", params![id])?;
 
     if rows_affected == 0 {
@@ -322,11 +359,12 @@ pub fn unarchive(conn: &Connection, id: &str) -> Result<Journal, AppError> {
     get(conn, id)
 }
 
-/// Search journal entries using FTS5.
+/// Search journal entries using FTS5, optionally restricted to one language.
 pub fn search(
     conn: &Connection,
     query: &str,
     include_archived: bool,
+    language: Option<&str>,
 ) -> Result<Vec<Journal>, AppError> {
     if query.trim().is_empty() {
         return Ok(vec![]);
@@ -342,19 +380,21 @@ pub fn search(
 
     let sql = if include_archived {
         r#"
-            SELECT j.id, j.content, j.title, j.entry_type, j.created_at, j.updated_at, j.is_archived
+            SELECT j.id, j.content, j.title, j.entry_type, j.created_at, j.updated_at, j.is_archived, j.language
             FROM journals j
             JOIN journals_fts fts ON j.rowid = fts.rowid
             WHERE journals_fts MATCH ?1
+            AND (?2 IS NULL OR j.language = ?2)
             ORDER BY rank
             LIMIT 50
         "#
     } else {
         r#"
-            SELECT j.id, j.content, j.title, j.entry_type, j.created_at, j.updated_at, j.is_archived
+            SELECT j.id, j.content, j.title, j.entry_type, j.created_at, j.updated_at, j.is_archived, j.language
             FROM journals j
             JOIN journals_fts fts ON j.rowid = fts.rowid
             WHERE journals_fts MATCH ?1 AND j.is_archived = 0
+            AND (?2 IS NULL OR j.language = ?2)
             ORDER BY rank
             LIMIT 50
         "#
@@ -362,7 +402,7 @@ pub fn search(
 
     let mut stmt = conn.prepare(sql)?;
     let journals: Vec<Journal> = stmt
-        .query_map(params![escaped_query], |row| {
+        .query_map(params![escaped_query, language], |row| {
             let entry_type_str: Option<String> = row.get(3)?;
             Ok(Journal {
                 id: row.get(0)?,
@@ -376,6 +416,7 @@ pub fn search(
                 created_at: parse_datetime(row.get::<_, String>(4)?),
                 updated_at: parse_datetime(row.get::<_, String>(5)?),
                 is_archived: row.get(6)?,
+                language: row.get(7)?,
             })
         })?
         .filter_map(|r| {
@@ -395,7 +436,7 @@ pub fn list_without_titles(
     let limit = limit.unwrap_or(50).min(100);
 
     let mut stmt = conn.prepare(
-        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived
+        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived, language
          FROM journals
          WHERE title IS NULL AND content != ''
          ORDER BY created_at DESC
@@ -417,6 +458,7 @@ pub fn list_without_titles(
                 created_at: parse_datetime(row.get::<_, String>(4)?),
                 updated_at: parse_datetime(row.get::<_, String>(5)?),
                 is_archived: row.get(6)?,
+                language: row.get(7)?,
             })
         })?
         .filter_map(|r| r.ok())
@@ -458,6 +500,16 @@ pub struct JournalStats {
     pub entries_this_month: i64,
 }
 
+/// Number of entries written in a language.
+#[derive(Debug, Serialize)]
+pub struct LanguageCount {
+    /// ISO 639-3 code
+    pub language: String,
+    /// English name of the language, if known
+    pub name: Option<String>,
+    pub entry_count: i64,
+}
+
 /// Extended streak information for the dashboard.
 #[derive(Debug, Serialize)]
 pub struct StreakInfo {
@@ -476,11 +528,14 @@ pub struct DayEmotions {
 }
 
 /// Get journal statistics for the dashboard.
-pub fn get_stats(conn: &Connection) -> Result<JournalStats, AppError> {
+/// Entry counts can be restricted to one language; the streak always covers all entries.
+pub fn get_stats(conn: &Connection, language: Option<&str>) -> Result<JournalStats, AppError> {
     // Total count (excluding archived)
     let total_entries: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM journals WHERE is_archived = 0",
-        [],
+        "SELECT COUNT(*) FROM journals
+         WHERE is_archived = 0
+         AND (?1 IS NULL OR language = ?1)",
+        params![language],
         |row| row.get(0),
     )?;
 
@@ -488,8 +543,9 @@ pub fn get_stats(conn: &Connection) -> Result<JournalStats, AppError> {
     let entries_this_week: i64 = conn.query_row(
         "SELECT COUNT(*) FROM journals
          WHERE is_archived = 0
+         AND (?1 IS NULL OR language = ?1)
          AND created_at >= date('now', 'weekday 0', '-7 days')",
-        [],
+        params![language],
         |row| row.get(0),
     )?;
 
@@ -497,8 +553,9 @@ pub fn get_stats(conn: &Connection) -> Result<JournalStats, AppError> {
     let entries_this_month: i64 = conn.query_row(
         "SELECT COUNT(*) FROM journals
          WHERE is_archived = 0
+         AND (?1 IS NULL OR language = ?1)
          AND created_at >= date('now', 'start of month')",
-        [],
+        params![language],
         |row| row.get(0),
     )?;
 
@@ -513,6 +570,30 @@ pub fn get_stats(conn: &Connection) -> Result<JournalStats, AppError> {
     })
 }
 
+/// Languages detected across non-archived entries, most used first.
+pub fn list_languages(conn: &Connection) -> Result<Vec<LanguageCount>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT language, COUNT(*) AS entry_count
+         FROM journals
+         WHERE is_archived = 0 AND language IS NOT NULL
+         GROUP BY language
+         ORDER BY entry_count DESC, language",
+    )?;
+
+    let languages = stmt
+        .query_map([], |row| {
+            let language: String = row.get(0)?;
+            Ok(LanguageCount {
+                name: crate::ml::language::display_name(&language).map(str::to_string),
+                language,
+                entry_count: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(languages)
+}
+
 /// Calculate the current journaling streak.
 /// Streak is the number of consecutive days with at least one entry,
 /// ending today or yesterday.
@@ -664,7 +745,7 @@ pub fn get_on_this_day(conn: &Connection) -> Result<Vec<Journal>, AppError> {
     let month_day = today.format("%m-%d").to_string();
 
     let mut stmt = conn.prepare(
-        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived
+        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived, language
          FROM journals
          WHERE is_archived = 0
          AND strftime('%m-%d', created_at) = ?1
@@ -688,6 +769,7 @@ pub fn get_on_this_day(conn: &Connection) -> Result<Vec<Journal>, AppError> {
                 created_at: parse_datetime(row.get::<_, String>(4)?),
                 updated_at: parse_datetime(row.get::<_, String>(5)?),
                 is_archived: row.get(6)?,
+                language: row.get(7)?,
             })
         })?
         .filter_map(|r| r.ok())
@@ -728,7 +810,7 @@ pub fn get_entries_in_range(
     end_date: &str,
 ) -> Result<Vec<Journal>, AppError> {
     let mut stmt = conn.prepare(
-        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived
+        "SELECT id, content, title, entry_type, created_at, updated_at, is_archived, language
          FROM journals
          WHERE is_archived = 0
          AND date(created_at) >= ?1
@@ -751,6 +833,7 @@ pub fn get_entries_in_range(
                 created_at: parse_datetime(row.get::<_, String>(4)?),
                 updated_at: parse_datetime(row.get::<_, String>(5)?),
                 is_archived: row.get(6)?,
+                language: row.get(7)?,
             })
         })?
         .filter_map(|r| r.ok())
@@ -822,7 +905,7 @@ mod tests {
         create(&conn, "Entry 2", None, None).unwrap();
         create(&conn, "Entry 3", None, None).unwrap();
 
-        let entries = list(&conn, Some(10), None, None).unwrap();
+        let entries = list(&conn, Some(10), None, None, None).unwrap();
         assert_eq!(entries.len(), 3);
     }
 
@@ -836,6 +919,32 @@ mod tests {
         assert_eq!(updated.content, "Updated content");
     }
 
+    #[test]
+    fn test_update_content_marks_artifacts_stale() {
+        let conn = setup_test_db();
+
+        let entry = create(&conn, "Original content", None, None).unwrap();
+        let hash = artifacts::content_hash("Original content");
+        artifacts::record(&conn, &entry.id, artifacts::Artifact::Emotions, &hash, "v1").unwrap();
+
+        // Title-only edits leave derived data fresh
+        update(&conn, &entry.id, None, Some("Title"), None, None).unwrap();
+        assert!(
+            artifacts::is_fresh(&conn, &entry.id, artifacts::Artifact::Emotions, &hash, "v1")
+                .unwrap()
+        );
+
+        let updated = update(&conn, &entry.id, Some("Rewritten"), None, None, None).unwrap();
+        assert!(!artifacts::is_fresh(
+            &conn,
+            &entry.id,
+            artifacts::Artifact::Emotions,
+            &artifacts::content_hash(&updated.content),
+            "v1"
+        )
+        .unwrap());
+    }
+
     #[test]
     fn test_update_title_only() {
         let conn = setup_test_db();
@@ -889,7 +998,7 @@ mod tests {
         create(&conn, "Feeling anxious about tomorrow", None, None).unwrap();
         create(&conn, "Good morning sunshine", None, None).unwrap();
 
-        let results = search(&conn, "good", false).unwrap();
+        let results = search(&conn, "good", false, None).unwrap();
         assert_eq!(results.len(), 2);
     }
 
@@ -902,12 +1011,12 @@ mod tests {
         archive(&conn, &entry1.id).unwrap();
 
         // Without archived
-        let results = search(&conn, "good", false).unwrap();
+        let results = search(&conn, "good", false, None).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].content, "Good morning sunshine");
 
         // With archived
-        let results_with_archived = search(&conn, "good", true).unwrap();
+        let results_with_archived = search(&conn, "good", true, None).unwrap();
         assert_eq!(results_with_archived.len(), 2);
     }
 
@@ -919,10 +1028,57 @@ mod tests {
         create(&conn, "Entry 2", None, None).unwrap();
         archive(&conn, &entry1.id).unwrap();
 
-        let archived = list(&conn, None, None, Some(true)).unwrap();
+        let archived = list(&conn, None, None, Some(true), None).unwrap();
         assert_eq!(archived.len(), 1);
 
-        let not_archived = list(&conn, None, None, Some(false)).unwrap();
+        let not_archived = list(&conn, None, None, Some(false), None).unwrap();
         assert_eq!(not_archived.len(), 1);
     }
+
+    #[test]
+    fn test_language_detected_and_filterable() {
+        let conn = setup_test_db();
+
+        create(
+            &conn,
+            "Today I felt tired after work but happy to see my family.",
+            None,
+            None,
+        )
+        .unwrap();
+        let entry = create(
+            &conn,
+            "Heute war ein anstrengender Tag, aber ich bin froh, dass ich meine Freunde getroffen habe.",
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            get(&conn, &entry.id).unwrap().language.as_deref(),
+            Some("deu")
+        );
+
+        let german = list(&conn, None, None, None, Some("deu")).unwrap();
+        assert_eq!(german.len(), 1);
+        assert_eq!(get_stats(&conn, Some("eng")).unwrap().total_entries, 1);
+        assert_eq!(search(&conn, "tired", false, Some("deu")).unwrap().len(), 0);
+
+        let languages = list_languages(&conn).unwrap();
+        assert_eq!(languages.len(), 2);
+
+        // Editing the content re-detects the language
+        update(
+            &conn,
+            &entry.id,
+            Some("Hoy me siento muy cansado después del trabajo, pero feliz de ver a mi familia."),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            get(&conn, &entry.id).unwrap().language.as_deref(),
+            Some("spa")
+        );
+    }
 }
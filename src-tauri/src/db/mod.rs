@@ -1,10 +1,17 @@
+pub mod artifacts;
 pub mod chat;
+pub mod duplicates;
 pub mod emotions;
+pub mod entities;
 pub mod images;
+pub mod jobs;
 pub mod journals;
+pub mod projection;
 pub mod schema;
 pub mod search;
+pub mod settings;
 pub mod templates;
+pub mod themes;
 pub mod vectors;
 
 use rusqlite::Connection;
@@ -0,0 +1,411 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::artifacts::content_hash;
+use crate::db::vectors;
+use crate::error::AppError;
+use crate::ml::models::EmbeddingModelSpec;
+use crate::ml::projection;
+
+/// Share of mapped entries that may have been placed next to their neighbours
+/// (rather than by a full layout) before the map is laid out from scratch.
+const REBUILD_FRACTION: f32 = 0.25;
+
+/// An entry's position on the 2D map.
+#[derive(Debug, Clone, Serialize)]
+pub struct MapPoint {
+    pub journal_id: String,
+    pub title: Option<String>,
+    pub created_at: String,
+    /// Coordinates in [-1, 1]; nearby entries are similar in meaning
+    pub x: f32,
+    pub y: f32,
+    /// Strongest emotion of the entry, after corrections
+    pub dominant_emotion: Option<String>,
+    /// Theme the entry belongs to, if themes are available
+    pub theme_id: Option<i64>,
+    pub theme_label: Option<String>,
+}
+
+/// What a refresh did to the cached map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionRefresh {
+    /// Nothing changed since the last refresh
+    Unchanged,
+    /// New and edited entries were placed next to their nearest mapped neighbours
+    Incremental,
+    /// All entries were laid out from scratch
+    Rebuilt,
+}
+
+/// An embedded, non-archived entry.
+pub struct MapInput {
+    journal_id: String,
+    hash: String,
+    vector: Vec<f32>,
+}
+
+/// Work needed to bring the map up to date, decided by [`plan`]. Computing it
+/// doesn't touch the database, so the layout can run without holding the connection.
+pub enum ProjectionPlan {
+    Unchanged,
+    Rebuild {
+        inputs: Vec<MapInput>,
+    },
+    Incremental {
+        /// Unchanged mapped entries with their positions
+        anchors: Vec<(Vec<f32>, [f32; 2])>,
+        pending: Vec<MapInput>,
+        removed: Vec<String>,
+    },
+}
+
+/// Positions computed from a [`ProjectionPlan`], ready to [`save`].
+pub struct ProjectionUpdate {
+    refresh: ProjectionRefresh,
+    positions: Vec<(String, String, [f32; 2])>,
+    removed: Vec<String>,
+}
+
+/// Decide how to bring the cached map up to date with the entries embedded by
+/// `model`. New and edited entries are placed among their neighbours; a different
+/// model, `force`, or too many placed entries lay out everything again.
+pub fn plan(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    force: bool,
+) -> Result<ProjectionPlan, AppError> {
+    let inputs = load_inputs(conn, model)?;
+
+    // Cached positions, only if they were laid out from this model's vectors
+    let mut cached: HashMap<String, ([f32; 2], String, bool)> = conn
+        .prepare(
+            "SELECT journal_id, x, y, content_hash, placed FROM entry_projection
+             WHERE model_version = ?1",
+        )?
+        .query_map(params![model.id], |row| {
+            Ok((
+                row.get(0)?,
+                ([row.get(1)?, row.get(2)?], row.get(3)?, row.get(4)?),
+            ))
+        })?
+        .collect::<Result<_, _>>()?;
+    let stale_rows: bool = conn
+        .prepare("SELECT 1 FROM entry_projection WHERE model_version != ?1")?
+        .exists(params![model.id])?;
+
+    let current: HashSet<&str> = inputs.iter().map(|i| i.journal_id.as_str()).collect();
+    let removed: Vec<String> = cached
+        .keys()
+        .filter(|id| !current.contains(id.as_str()))
+        .cloned()
+        .collect();
+    let (unchanged, pending): (Vec<MapInput>, Vec<MapInput>) =
+        inputs.into_iter().partition(|input| {
+            cached
+                .get(&input.journal_id)
+                .is_some_and(|(_, hash, _)| *hash == input.hash)
+        });
+
+    if !force && !stale_rows && pending.is_empty() && removed.is_empty() {
+        return Ok(ProjectionPlan::Unchanged);
+    }
+
+    let placed = unchanged
+        .iter()
+        .filter(|input| cached[&input.journal_id].2)
+        .count()
+        + pending.len();
+    let total = unchanged.len() + pending.len();
+    if force || unchanged.is_empty() || placed as f32 > REBUILD_FRACTION * total as f32 {
+        let mut inputs: Vec<MapInput> = unchanged.into_iter().chain(pending).collect();
+        inputs.sort_by(|a, b| a.journal_id.cmp(&b.journal_id));
+        return Ok(ProjectionPlan::Rebuild { inputs });
+    }
+
+    let anchors = unchanged
+        .into_iter()
+        .map(|input| {
+            let (position, _, _) = cached.remove(&input.journal_id).unwrap_or_default();
+            (input.vector, position)
+        })
+        .collect();
+    Ok(ProjectionPlan::Incremental {
+        anchors,
+        pending,
+        removed,
+    })
+}
+
+impl ProjectionPlan {
+    /// Run the layout or placement. Slow for full layouts of large journals.
+    pub fn compute(self) -> ProjectionUpdate {
+        match self {
+            ProjectionPlan::Unchanged => ProjectionUpdate {
+                refresh: ProjectionRefresh::Unchanged,
+                positions: Vec::new(),
+                removed: Vec::new(),
+            },
+            ProjectionPlan::Rebuild { inputs } => {
+                let vectors: Vec<Vec<f32>> = inputs.iter().map(|i| i.vector.clone()).collect();
+                let positions = projection::layout(&vectors);
+                ProjectionUpdate {
+                    refresh: ProjectionRefresh::Rebuilt,
+                    positions: inputs
+                        .into_iter()
+                        .zip(positions)
+                        .map(|(input, position)| (input.journal_id, input.hash, position))
+                        .collect(),
+                    removed: Vec::new(),
+                }
+            }
+            ProjectionPlan::Incremental {
+                anchors,
+                pending,
+                removed,
+            } => {
+                let anchors: Vec<(&[f32], [f32; 2])> = anchors
+                    .iter()
+                    .map(|(vector, position)| (vector.as_slice(), *position))
+                    .collect();
+                ProjectionUpdate {
+                    refresh: ProjectionRefresh::Incremental,
+                    positions: pending
+                        .into_iter()
+                        .map(|input| {
+                            let position = projection::place(&input.vector, &anchors);
+                            (input.journal_id, input.hash, position)
+                        })
+                        .collect(),
+                    removed,
+                }
+            }
+        }
+    }
+}
+
+/// Write the positions of a computed update. A rebuild replaces the whole map.
+pub fn save(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    update: &ProjectionUpdate,
+) -> Result<ProjectionRefresh, AppError> {
+    let tx = conn.unchecked_transaction()?;
+    match update.refresh {
+        ProjectionRefresh::Unchanged => return Ok(ProjectionRefresh::Unchanged),
+        ProjectionRefresh::Rebuilt => {
+            tx.execute("DELETE FROM entry_projection", [])?;
+        }
+        ProjectionRefresh::Incremental => {
+            for id in &update.removed {
+                tx.execute(
+                    "DELETE FROM entry_projection WHERE journal_id = ?1",
+                    params![id],
+                )?;
+            }
+        }
+    }
+    {
+        let placed = update.refresh == ProjectionRefresh::Incremental;
+        let mut upsert = tx.prepare(
+            "INSERT OR REPLACE INTO entry_projection
+                 (journal_id, model_version, x, y, content_hash, placed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for (journal_id, hash, [x, y]) in &update.positions {
+            upsert.execute(params![journal_id, model.id, x, y, hash, placed])?;
+        }
+    }
+    tx.commit()?;
+
+    log::info!(
+        "Entry map {}: {} entries positioned",
+        if update.refresh == ProjectionRefresh::Rebuilt {
+            "rebuilt"
+        } else {
+            "updated"
+        },
+        update.positions.len()
+    );
+    Ok(update.refresh)
+}
+
+/// Mapped non-archived entries for `model`, oldest first, with their dominant
+/// emotion and cached theme.
+pub fn points(conn: &Connection, model: &EmbeddingModelSpec) -> Result<Vec<MapPoint>, AppError> {
+    let points = conn
+        .prepare(
+            "SELECT p.journal_id, j.title, j.created_at, p.x, p.y,
+                 (SELECT e.emotion_label FROM effective_emotions e
+                  WHERE e.journal_id = p.journal_id
+                  ORDER BY e.confidence_score DESC, e.emotion_label LIMIT 1),
+                 t.id, t.label
+             FROM entry_projection p
+             JOIN journals j ON j.id = p.journal_id AND j.is_archived = 0
+             LEFT JOIN theme_members m ON m.journal_id = p.journal_id
+             LEFT JOIN themes t ON t.id = m.theme_id AND t.model_version = ?1
+             WHERE p.model_version = ?1
+             ORDER BY j.created_at, p.journal_id",
+        )?
+        .query_map(params![model.id], |row| {
+            Ok(MapPoint {
+                journal_id: row.get(0)?,
+                title: row.get(1)?,
+                created_at: row.get(2)?,
+                x: row.get(3)?,
+                y: row.get(4)?,
+                dominant_emotion: row.get(5)?,
+                theme_id: row.get(6)?,
+                theme_label: row.get(7)?,
+            })
+        })?
+        .collect::<Result<_, _>>()?;
+    Ok(points)
+}
+
+fn load_inputs(conn: &Connection, model: &EmbeddingModelSpec) -> Result<Vec<MapInput>, AppError> {
+    let hashes: HashMap<String, String> = conn
+        .prepare("SELECT id, content FROM journals WHERE is_archived = 0")?
+        .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+        .map(|row| row.map(|(id, content)| (id, content_hash(&content))))
+        .collect::<Result<_, _>>()?;
+
+    Ok(vectors::all_embeddings(conn, model)?
+        .into_iter()
+        .filter_map(|(journal_id, vector)| {
+            Some(MapInput {
+                hash: hashes.get(&journal_id)?.clone(),
+                journal_id,
+                vector,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{emotions, journals, themes};
+    use crate::ml::models::MINILM_EMBEDDING;
+
+    const MODEL: &EmbeddingModelSpec = &MINILM_EMBEDDING;
+
+    fn refresh(conn: &Connection, model: &EmbeddingModelSpec, force: bool) -> ProjectionRefresh {
+        let update = plan(conn, model, force).unwrap().compute();
+        save(conn, model, &update).unwrap()
+    }
+
+    fn setup_test_db() -> Connection {
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    fn vector(axis: usize, tilt: f32) -> Vec<f32> {
+        let mut v = vec![0.0; MODEL.dimension];
+        v[axis] = 1.0;
+        v[10 + (tilt * 100.0) as usize % 10] = tilt;
+        crate::ml::backend::l2_normalized(v)
+    }
+
+    fn add_entry(conn: &Connection, id: &str, content: &str, vector: &[f32]) {
+        conn.execute(
+            "INSERT INTO journals (id, content, created_at)
+             VALUES (?1, ?2, datetime('2024-03-01', (SELECT COUNT(*) FROM journals) || ' days'))",
+            params![id, content],
+        )
+        .unwrap();
+        vectors::store_embedding(conn, MODEL, id, vector).unwrap();
+    }
+
+    fn position(conn: &Connection, id: &str) -> (f32, f32, bool) {
+        conn.query_row(
+            "SELECT x, y, placed FROM entry_projection WHERE journal_id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    fn seed(conn: &Connection) {
+        for i in 0..6 {
+            let tilt = 0.1 + 0.03 * i as f32;
+            add_entry(
+                conn,
+                &format!("walk{}", i),
+                &format!("Walk {}", i),
+                &vector(1, tilt),
+            );
+            add_entry(
+                conn,
+                &format!("work{}", i),
+                &format!("Work {}", i),
+                &vector(2, tilt),
+            );
+        }
+    }
+
+    #[test]
+    fn test_refresh_lays_out_and_places_new_entries() {
+        let conn = setup_test_db();
+        seed(&conn);
+
+        assert_eq!(refresh(&conn, MODEL, false), ProjectionRefresh::Rebuilt);
+        assert_eq!(refresh(&conn, MODEL, false), ProjectionRefresh::Unchanged);
+        let walk = position(&conn, "walk0");
+        assert!(!walk.2);
+
+        add_entry(&conn, "walk-new", "Walk again", &vector(1, 0.12));
+        assert_eq!(refresh(&conn, MODEL, false), ProjectionRefresh::Incremental);
+        // Existing positions are kept; the new entry lands among the walks
+        assert_eq!(position(&conn, "walk0"), walk);
+        let placed = position(&conn, "walk-new");
+        assert!(placed.2);
+        let near = |id: &str| {
+            let p = position(&conn, id);
+            ((p.0 - placed.0).powi(2) + (p.1 - placed.1).powi(2)).sqrt()
+        };
+        assert!(near("walk1") < near("work1"));
+
+        // Archived entries drop off the map
+        journals::archive(&conn, "work0").unwrap();
+        assert_eq!(refresh(&conn, MODEL, false), ProjectionRefresh::Incremental);
+        assert_eq!(points(&conn, MODEL).unwrap().len(), 12);
+
+        // Too many placed entries: lay out again
+        for i in 0..4 {
+            add_entry(&conn, &format!("new{}", i), "More work", &vector(2, 0.2));
+        }
+        assert_eq!(refresh(&conn, MODEL, false), ProjectionRefresh::Rebuilt);
+        assert!(!position(&conn, "walk-new").2);
+        assert_eq!(refresh(&conn, MODEL, true), ProjectionRefresh::Rebuilt);
+    }
+
+    #[test]
+    fn test_points_include_emotion_and_theme() {
+        let conn = setup_test_db();
+        seed(&conn);
+        emotions::store(&conn, "walk0", "joy", 0.8).unwrap();
+        emotions::store(&conn, "walk0", "calm", 0.3).unwrap();
+        themes::refresh(&conn, MODEL, false).unwrap();
+        refresh(&conn, MODEL, false);
+
+        let points = points(&conn, MODEL).unwrap();
+        assert_eq!(points.len(), 12);
+        let walk = points.iter().find(|p| p.journal_id == "walk0").unwrap();
+        assert_eq!(walk.dominant_emotion.as_deref(), Some("joy"));
+        assert!(walk.theme_id.is_some());
+        assert!(walk.theme_label.is_some());
+        assert!(points
+            .iter()
+            .all(|p| (-1.0..=1.0).contains(&p.x) && (-1.0..=1.0).contains(&p.y)));
+    }
+}
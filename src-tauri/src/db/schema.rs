@@ -7,6 +7,9 @@ use crate::error::AppError;
 pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
     log::info!("Running database migrations");
 
+    // Must run before the batch below recreates the table
+    drop_outdated_derived_artifacts(conn)?;
+
     conn.execute_batch(
         r#"
         -- Core journal entries table
@@ -29,10 +32,65 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
             FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE
         );
 
+        -- Per-window emotion predictions for long entries (where in the entry emotions peak)
+        CREATE TABLE IF NOT EXISTS emotion_chunks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            journal_id TEXT NOT NULL,
+            chunk_index INTEGER NOT NULL,
+            start_offset INTEGER NOT NULL,
+            end_offset INTEGER NOT NULL,
+            emotion_label TEXT NOT NULL,
+            confidence_score REAL NOT NULL,
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- Per-sentence emotion predictions, cached for editor highlighting
+        CREATE TABLE IF NOT EXISTS sentence_emotions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            journal_id TEXT NOT NULL,
+            sentence_index INTEGER NOT NULL,
+            start_offset INTEGER NOT NULL,
+            end_offset INTEGER NOT NULL,
+            emotion_label TEXT NOT NULL,
+            confidence_score REAL NOT NULL,
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- User corrections to detected emotions, kept apart from model output
+        CREATE TABLE IF NOT EXISTS emotion_corrections (
+            journal_id TEXT NOT NULL,
+            emotion_label TEXT NOT NULL,
+            action TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (journal_id, emotion_label),
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- Emotions with corrections applied: removed labels dropped, labels the user
+        -- added (or confirmed) but the model missed scored 1
+        CREATE VIEW IF NOT EXISTS effective_emotions AS
+            SELECT e.journal_id, e.emotion_label, e.confidence_score
+            FROM journal_emotions e
+            WHERE NOT EXISTS (
+                SELECT 1 FROM emotion_corrections c
+                WHERE c.journal_id = e.journal_id AND c.emotion_label = e.emotion_label
+                  AND c.action = 'remove'
+            )
+            UNION ALL
+            SELECT c.journal_id, c.emotion_label, 1.0
+            FROM emotion_corrections c
+            WHERE c.action IN ('add', 'confirm')
+              AND NOT EXISTS (
+                  SELECT 1 FROM journal_emotions e
+                  WHERE e.journal_id = c.journal_id AND e.emotion_label = c.emotion_label
+              );
+
         -- Index for archived queries
         CREATE INDEX IF NOT EXISTS idx_journals_archived ON journals(is_archived);
         CREATE INDEX IF NOT EXISTS idx_journals_created ON journals(created_at DESC);
         CREATE INDEX IF NOT EXISTS idx_journal_emotions_journal_id ON journal_emotions(journal_id);
+        CREATE INDEX IF NOT EXISTS idx_emotion_chunks_journal_id ON emotion_chunks(journal_id, chunk_index);
+        CREATE INDEX IF NOT EXISTS idx_sentence_emotions_journal_id ON sentence_emotions(journal_id, sentence_index);
 
         -- Full-text search for hybrid retrieval
         CREATE VIRTUAL TABLE IF NOT EXISTS journals_fts USING fts5(
@@ -41,13 +99,7 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
             content_rowid='rowid'
         );
 
-        -- Vector embeddings for semantic search (384-dim all-MiniLM-L6-v2)
-        CREATE VIRTUAL TABLE IF NOT EXISTS journal_embeddings USING vec0(
-            journal_id TEXT PRIMARY KEY,
-            embedding FLOAT[384]
-        );
-
-        -- Embedding metadata for version tracking (vec0 tables don't support extra columns)
+        -- Model version of each embedding in the default vector table
         CREATE TABLE IF NOT EXISTS embedding_metadata (
             journal_id TEXT PRIMARY KEY,
             model_version TEXT NOT NULL,
@@ -62,16 +114,11 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
             chunk_index INTEGER NOT NULL,
             chunk_text TEXT NOT NULL,
             created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            model_version TEXT NOT NULL DEFAULT 'all-MiniLM-L6-v2',
             FOREIGN KEY (journal_id) REFERENCES journals(id) ON DELETE CASCADE
         );
         CREATE INDEX IF NOT EXISTS idx_chunks_journal ON embedding_chunks(journal_id);
 
-        -- Vector embeddings for chunks (384-dim all-MiniLM-L6-v2)
-        CREATE VIRTUAL TABLE IF NOT EXISTS chunk_embeddings USING vec0(
-            chunk_id TEXT PRIMARY KEY,
-            embedding FLOAT[384]
-        );
-
         -- Journal templates table
         CREATE TABLE IF NOT EXISTS journal_templates (
             id TEXT PRIMARY KEY,
@@ -114,6 +161,117 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
         );
         CREATE INDEX IF NOT EXISTS idx_chat_messages_journal ON chat_messages(journal_id);
         CREATE INDEX IF NOT EXISTS idx_chat_messages_created ON chat_messages(created_at);
+
+        -- Freshness tracking for data derived from entry content (embeddings, chunks, emotions)
+        CREATE TABLE IF NOT EXISTS derived_artifacts (
+            journal_id TEXT NOT NULL,
+            artifact TEXT NOT NULL,
+            content_hash TEXT NOT NULL,
+            model_version TEXT NOT NULL,
+            is_stale BOOLEAN NOT NULL DEFAULT 0,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (journal_id, artifact, model_version),
+            FOREIGN KEY (journal_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- Persistent background job queue (embeddings, emotions, titles, summaries)
+        CREATE TABLE IF NOT EXISTS jobs (
+            id TEXT PRIMARY KEY,
+            job_type TEXT NOT NULL,
+            entry_id TEXT,
+            payload TEXT,
+            dedupe_key TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            priority INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            max_attempts INTEGER NOT NULL DEFAULT 5,
+            last_error TEXT,
+            result TEXT,
+            run_after TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status, priority DESC, created_at);
+        CREATE INDEX IF NOT EXISTS idx_jobs_entry ON jobs(entry_id);
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_jobs_pending_dedupe
+            ON jobs(job_type, dedupe_key) WHERE status = 'pending';
+
+        -- Entries folded into another by a merge; the merged entry is archived, not deleted
+        CREATE TABLE IF NOT EXISTS entry_merges (
+            merged_id TEXT PRIMARY KEY,
+            into_id TEXT NOT NULL,
+            merged_at TEXT DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY(merged_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- Recurring themes from clustering entry embeddings (see ml::themes)
+        CREATE TABLE IF NOT EXISTS themes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            model_version TEXT NOT NULL,
+            label TEXT NOT NULL,
+            label_refined BOOLEAN NOT NULL DEFAULT 0,
+            terms TEXT NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Theme of each clustered entry, with the content hash it was assigned from
+        CREATE TABLE IF NOT EXISTS theme_members (
+            journal_id TEXT PRIMARY KEY,
+            theme_id INTEGER NOT NULL,
+            content_hash TEXT NOT NULL,
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE,
+            FOREIGN KEY(theme_id) REFERENCES themes(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_theme_members_theme ON theme_members(theme_id);
+
+        -- Position of each entry on the 2D map (see ml::projection), with the content
+        -- hash it was laid out from; `placed` rows were added next to their neighbours
+        -- after the last full layout
+        CREATE TABLE IF NOT EXISTS entry_projection (
+            journal_id TEXT PRIMARY KEY,
+            model_version TEXT NOT NULL,
+            x REAL NOT NULL,
+            y REAL NOT NULL,
+            content_hash TEXT NOT NULL,
+            placed BOOLEAN NOT NULL DEFAULT 0,
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE
+        );
+
+        -- People and places mentioned in entries (see ml::ner)
+        CREATE TABLE IF NOT EXISTS entities (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            name TEXT NOT NULL,
+            created_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
+
+        -- Names an entity is mentioned by, matched on the lowercased name
+        CREATE TABLE IF NOT EXISTS entity_aliases (
+            kind TEXT NOT NULL,
+            alias_key TEXT NOT NULL,
+            alias TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            PRIMARY KEY (kind, alias_key),
+            FOREIGN KEY(entity_id) REFERENCES entities(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_entity_aliases_entity ON entity_aliases(entity_id);
+
+        CREATE TABLE IF NOT EXISTS entry_entities (
+            journal_id TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            mention_count INTEGER NOT NULL,
+            PRIMARY KEY (journal_id, entity_id),
+            FOREIGN KEY(journal_id) REFERENCES journals(id) ON DELETE CASCADE,
+            FOREIGN KEY(entity_id) REFERENCES entities(id) ON DELETE CASCADE
+        );
+        CREATE INDEX IF NOT EXISTS idx_entry_entities_entity ON entry_entities(entity_id);
+
+        -- App-wide settings (e.g. the active embedding model)
+        CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+        );
         "#,
     )?;
 
@@ -122,6 +280,11 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
 
     // Add new columns to existing tables (for upgrades from older schema)
     add_journal_columns_if_missing(conn)?;
+    add_chunk_columns_if_missing(conn)?;
+
+    // Vector tables for the default embedding model (journal_embeddings, chunk_embeddings),
+    // with metadata columns for filtered KNN search
+    crate::db::vectors::ensure_tables(conn, &crate::ml::models::MINILM_EMBEDDING)?;
 
     // Seed default templates
     seed_default_templates(conn)?;
@@ -130,7 +293,7 @@ pub fn run_migrations(conn: &Connection) -> Result<(), AppError> {
     Ok(())
 }
 
-/// Add title and entry_type columns to journals table if they don't exist.
+/// Add title, entry_type and language columns to journals table if they don't exist.
 /// This handles upgrading from older database schemas.
 fn add_journal_columns_if_missing(conn: &Connection) -> Result<(), AppError> {
     // Check if columns exist by querying table info
@@ -155,6 +318,77 @@ fn add_journal_columns_if_missing(conn: &Connection) -> Result<(), AppError> {
         )?;
     }
 
+    // Add language column if missing, detecting it for existing entries
+    if !columns.contains(&"language".to_string()) {
+        log::info!("Adding 'language' column to journals table");
+        conn.execute("ALTER TABLE journals ADD COLUMN language TEXT", [])?;
+        backfill_languages(conn)?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_journals_language ON journals(language)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+/// Detect and store the language of every existing entry.
+fn backfill_languages(conn: &Connection) -> Result<(), AppError> {
+    let entries: Vec<(String, String)> = conn
+        .prepare("SELECT id, content FROM journals")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut stmt = conn.prepare("UPDATE journals SET language = ?1 WHERE id = ?2")?;
+    for (id, content) in &entries {
+        if let Some(language) = crate::ml::language::detect(content) {
+            stmt.execute(rusqlite::params![language, id])?;
+        }
+    }
+
+    log::info!("Detected languages for {} existing entries", entries.len());
+    Ok(())
+}
+
+/// Add model_version column to embedding_chunks if it doesn't exist.
+/// Chunks created before the embedding model registry all came from MiniLM.
+fn add_chunk_columns_if_missing(conn: &Connection) -> Result<(), AppError> {
+    let columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(embedding_chunks)")?
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    if !columns.contains(&"model_version".to_string()) {
+        log::info!("Adding 'model_version' column to embedding_chunks table");
+        conn.execute(
+            "ALTER TABLE embedding_chunks ADD COLUMN model_version TEXT NOT NULL DEFAULT 'all-MiniLM-L6-v2'",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Drop derived_artifacts if it predates per-model tracking (model_version not in the key).
+/// It only caches freshness, so dropping it just causes a one-time recompute.
+fn drop_outdated_derived_artifacts(conn: &Connection) -> Result<(), AppError> {
+    // PRAGMA table_info columns: cid, name, type, notnull, dflt_value, pk
+    let key_columns: Vec<String> = conn
+        .prepare("PRAGMA table_info(derived_artifacts)")?
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, i64>(5)?))
+        })?
+        .filter_map(|r| r.ok())
+        .filter(|(_, pk)| *pk > 0)
+        .map(|(name, _)| name)
+        .collect();
+
+    if !key_columns.is_empty() && !key_columns.contains(&"model_version".to_string()) {
+        log::info!("Rebuilding derived_artifacts table with per-model keys");
+        conn.execute("DROP TABLE derived_artifacts", [])?;
+    }
+
     Ok(())
 }
 
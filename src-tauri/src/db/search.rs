@@ -1,10 +1,12 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use chrono::NaiveDate;
 use rusqlite::Connection;
 
-use crate::db::journals::Journal;
+use crate::db::journals::{EntryType, Journal};
 use crate::db::vectors;
 use crate::error::AppError;
+use crate::ml::models::EmbeddingModelSpec;
 
 /// RRF constant for rank fusion (standard value)
 const RRF_K: f64 = 60.0;
@@ -19,23 +21,77 @@ pub struct HybridSearchResult {
     pub score: f64,
     pub fts_rank: Option<usize>,
     pub vec_rank: Option<usize>,
+    /// Cross-encoder relevance, for results that were reranked
+    pub rerank_score: Option<f32>,
+}
+
+/// Which entries a search may return. The default excludes archived entries only.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilter {
+    pub include_archived: bool,
+    /// Only entries detected as this language
+    pub language: Option<String>,
+    pub entry_type: Option<EntryType>,
+    /// Only entries created on or after this day (UTC)
+    pub start_date: Option<NaiveDate>,
+    /// Only entries created on or before this day (UTC)
+    pub end_date: Option<NaiveDate>,
+}
+
+impl SearchFilter {
+    /// `AND ...` conditions for the filter and their parameters, in order. `column`
+    /// maps a journals column name (is_archived, language, entry_type, created_at) to
+    /// the SQL expression to compare; the vec0 tables store the same columns as metadata.
+    pub(crate) fn to_sql(
+        &self,
+        column: impl Fn(&str) -> String,
+    ) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+        let mut conditions = String::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if !self.include_archived {
+            conditions.push_str(&format!(" AND {} = ?", column("is_archived")));
+            params.push(Box::new(false));
+        }
+        if let Some(language) = &self.language {
+            conditions.push_str(&format!(" AND {} = ?", column("language")));
+            params.push(Box::new(language.clone()));
+        }
+        if let Some(entry_type) = &self.entry_type {
+            conditions.push_str(&format!(" AND {} = ?", column("entry_type")));
+            params.push(Box::new(entry_type.as_str()));
+        }
+        // created_at is RFC3339, so whole days compare as string prefixes
+        if let Some(start) = self.start_date {
+            conditions.push_str(&format!(" AND {} >= ?", column("created_at")));
+            params.push(Box::new(start.format("%Y-%m-%d").to_string()));
+        }
+        if let Some(end) = self.end_date.and_then(|end| end.succ_opt()) {
+            conditions.push_str(&format!(" AND {} < ?", column("created_at")));
+            params.push(Box::new(end.format("%Y-%m-%d").to_string()));
+        }
+
+        (conditions, params)
+    }
 }
 
 /// Perform hybrid search combining FTS5 and vector similarity.
 /// Uses Reciprocal Rank Fusion (RRF) to combine rankings.
+/// The query embedding is searched against the index of the model that produced it.
+/// Only entries matching `filter` are returned.
 pub fn hybrid_search(
     conn: &Connection,
     query: &str,
-    query_embedding: Option<&[f32]>,
+    query_embedding: Option<(&EmbeddingModelSpec, &[f32])>,
     limit: usize,
-    include_archived: bool,
+    filter: &SearchFilter,
 ) -> Result<Vec<HybridSearchResult>, AppError> {
     // Get FTS5 results
-    let fts_results = fts_search(conn, query, limit * 2, include_archived)?;
+    let fts_results = fts_search(conn, query, limit * 2, filter)?;
 
     // Get vector search results if embedding provided
-    let vec_results = if let Some(embedding) = query_embedding {
-        vector_search(conn, embedding, limit * 2, include_archived)?
+    let vec_results = if let Some((model, embedding)) = query_embedding {
+        vector_search(conn, model, embedding, limit * 2, filter)?
     } else {
         Vec::new()
     };
@@ -46,24 +102,133 @@ pub fn hybrid_search(
     // Fetch full journal entries for results
     let mut results = Vec::with_capacity(combined.len());
     for (id, score, fts_rank, vec_rank) in combined {
-        let journal = crate::db::journals::get(conn, &id)?;
+        let journal = match crate::db::journals::get(conn, &id) {
+            Ok(journal) => journal,
+            Err(AppError::NotFound(_)) => {
+                log::warn!(
+                    "Orphaned embedding found: journal '{}' no longer exists",
+                    id
+                );
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
         results.push(HybridSearchResult {
             journal,
             score,
             fts_rank,
             vec_rank,
+            rerank_score: None,
         });
     }
 
     Ok(results)
 }
 
+/// An entry similar to another one.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RelatedEntry {
+    pub journal: Journal,
+    /// Cosine similarity of the two entries, or of their closest passages if that is higher
+    pub similarity: f32,
+    /// Chunk of the related entry closest to the source entry, explaining the match
+    pub matched_chunk: Option<String>,
+}
+
+/// Entries most similar to `entry_id` ("more like this"), from the vectors already
+/// stored by `model`. Candidates come from the entry index and from each of the
+/// entry's chunks; the entry itself and archived entries are excluded. Returns no
+/// results if the entry hasn't been embedded yet.
+pub fn related_entries(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    entry_id: &str,
+    limit: usize,
+) -> Result<Vec<RelatedEntry>, AppError> {
+    let Some(source) = vectors::get_embedding(conn, model, entry_id)? else {
+        return Ok(Vec::new());
+    };
+    let source_chunks: Vec<Vec<f32>> = vectors::get_chunk_embeddings(conn, model, entry_id)?
+        .into_iter()
+        .map(|(_, vector)| vector)
+        .collect();
+
+    // Oversample: the entry itself is dropped afterwards, and chunks repeat entries
+    let k = limit * 3 + 1;
+    let filter = SearchFilter::default();
+    let mut candidates: HashSet<String> =
+        vectors::search_similar(conn, model, &source, k, &filter)?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+    for chunk in &source_chunks {
+        for result in vectors::search_similar_chunks(conn, model, chunk, k, &filter)? {
+            candidates.insert(result.journal_id);
+        }
+    }
+    candidates.remove(entry_id);
+
+    let mut related = Vec::new();
+    for id in candidates {
+        let journal = match crate::db::journals::get(conn, &id) {
+            Ok(journal) if !journal.is_archived => journal,
+            Ok(_) | Err(AppError::NotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        let Some(vector) = vectors::get_embedding(conn, model, &id)? else {
+            continue;
+        };
+
+        // Best passage: the candidate chunk closest to the source entry or any of its chunks
+        let mut similarity = cosine(&source, &vector);
+        let mut matched_chunk = None;
+        let mut best_chunk = f32::MIN;
+        for (text, chunk) in vectors::get_chunk_embeddings(conn, model, &id)? {
+            let score = std::iter::once(&source)
+                .chain(&source_chunks)
+                .map(|s| cosine(s, &chunk))
+                .fold(f32::MIN, f32::max);
+            if score > best_chunk {
+                best_chunk = score;
+                matched_chunk = Some(text);
+            }
+        }
+        similarity = similarity.max(best_chunk);
+
+        related.push(RelatedEntry {
+            journal,
+            similarity,
+            matched_chunk,
+        });
+    }
+
+    related.sort_by(|a, b| {
+        b.similarity
+            .total_cmp(&a.similarity)
+            .then_with(|| a.journal.id.cmp(&b.journal.id))
+    });
+    related.truncate(limit);
+    Ok(related)
+}
+
+/// Cosine similarity of two vectors (0 if either is all zeros).
+pub(crate) fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator > 0.0 {
+        dot / denominator
+    } else {
+        0.0
+    }
+}
+
 /// Perform FTS5 full-text search.
 fn fts_search(
     conn: &Connection,
     query: &str,
     limit: usize,
-    include_archived: bool,
+    filter: &SearchFilter,
 ) -> Result<Vec<(String, f64)>, AppError> {
     let escaped_query = query
         .replace('"', "\"\"")
@@ -72,28 +237,30 @@ fn fts_search(
         .collect::<Vec<_>>()
         .join(" ");
 
-    let archived_filter = if include_archived {
-        ""
-    } else {
-        "AND j.is_archived = 0"
-    };
+    let (conditions, filter_params) = filter.to_sql(|column| match column {
+        "entry_type" => vectors::normalized_entry_type("j.entry_type"),
+        column => format!("j.{}", column),
+    });
 
     let sql = format!(
         r#"
         SELECT j.id, bm25(journals_fts) as rank
         FROM journals_fts fts
         JOIN journals j ON j.rowid = fts.rowid
-        WHERE journals_fts MATCH ?
-        {}
+        WHERE journals_fts MATCH ? {}
         ORDER BY rank
         LIMIT ?
         "#,
-        archived_filter
+        conditions
     );
 
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(escaped_query)];
+    params.extend(filter_params);
+    params.push(Box::new(limit as i64));
+
     let mut stmt = conn.prepare(&sql)?;
     let results = stmt
-        .query_map(rusqlite::params![escaped_query, limit as i64], |row| {
+        .query_map(rusqlite::params_from_iter(params.iter()), |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
         })?
         .collect::<Result<Vec<_>, _>>()?;
@@ -102,18 +269,21 @@ fn fts_search(
 }
 
 /// Perform vector similarity search using both entry embeddings and chunks.
-/// Chunks provide better precision for long entries.
+/// Chunks provide better precision for long entries. The filter is applied by
+/// the vec0 KNN queries themselves.
 fn vector_search(
     conn: &Connection,
+    model: &EmbeddingModelSpec,
     query_embedding: &[f32],
     limit: usize,
-    include_archived: bool,
+    filter: &SearchFilter,
 ) -> Result<Vec<(String, f64)>, AppError> {
     // Get results from entry-level embeddings
-    let entry_results = vectors::search_similar(conn, query_embedding, limit * 2)?;
+    let entry_results = vectors::search_similar(conn, model, query_embedding, limit, filter)?;
 
     // Get results from chunk embeddings (may return multiple chunks per entry)
-    let chunk_results = vectors::search_similar_chunks(conn, query_embedding, limit * 3)?;
+    let chunk_results =
+        vectors::search_similar_chunks(conn, model, query_embedding, limit * 3, filter)?;
 
     // Combine: use best score per journal_id from either source
     let mut best_scores: HashMap<String, f64> = HashMap::new();
@@ -144,33 +314,8 @@ fn vector_search(
     let mut combined: Vec<(String, f64)> = best_scores.into_iter().collect();
     combined.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
 
-    if !include_archived {
-        // Filter out archived entries
-        let mut stmt = conn.prepare("SELECT is_archived FROM journals WHERE id = ?")?;
-        let mut filtered = Vec::with_capacity(combined.len());
-        for (id, distance) in combined {
-            match stmt.query_row([&id], |row| row.get::<_, bool>(0)) {
-                Ok(is_archived) => {
-                    if !is_archived {
-                        filtered.push((id, distance));
-                    }
-                }
-                Err(rusqlite::Error::QueryReturnedNoRows) => {
-                    log::warn!(
-                        "Orphaned embedding found: journal '{}' no longer exists",
-                        id
-                    );
-                }
-                Err(e) => return Err(e.into()),
-            }
-            if filtered.len() >= limit {
-                break;
-            }
-        }
-        Ok(filtered)
-    } else {
-        Ok(combined.into_iter().take(limit).collect())
-    }
+    combined.truncate(limit);
+    Ok(combined)
 }
 
 /// Combine two ranked lists using Reciprocal Rank Fusion.
@@ -210,14 +355,33 @@ fn reciprocal_rank_fusion(
     Ok(results)
 }
 
+/// Reorder the leading results by reranker `scores` (one per result, in order).
+/// Results beyond the scored prefix keep their fused order after the reranked ones.
+pub fn apply_rerank(
+    mut results: Vec<HybridSearchResult>,
+    scores: &[f32],
+) -> Vec<HybridSearchResult> {
+    let scored = scores.len().min(results.len());
+    for (result, score) in results.iter_mut().zip(scores) {
+        result.rerank_score = Some(*score);
+    }
+    // Stable, so equal scores keep their fused order
+    results[..scored].sort_by(|a, b| {
+        b.rerank_score
+            .unwrap_or(f32::MIN)
+            .total_cmp(&a.rerank_score.unwrap_or(f32::MIN))
+    });
+    results
+}
+
 /// Perform FTS-only search (for when embeddings aren't available).
 pub fn fts_only_search(
     conn: &Connection,
     query: &str,
     limit: usize,
-    include_archived: bool,
+    filter: &SearchFilter,
 ) -> Result<Vec<HybridSearchResult>, AppError> {
-    let fts_results = fts_search(conn, query, limit, include_archived)?;
+    let fts_results = fts_search(conn, query, limit, filter)?;
 
     let mut results = Vec::with_capacity(fts_results.len());
     for (rank, (id, _)) in fts_results.iter().enumerate() {
@@ -227,6 +391,7 @@ pub fn fts_only_search(
             score: 1.0 / (RRF_K + (rank + 1) as f64),
             fts_rank: Some(rank + 1),
             vec_rank: None,
+            rerank_score: None,
         });
     }
 
@@ -236,6 +401,7 @@ pub fn fts_only_search(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ml::models::MINILM_EMBEDDING;
 
     #[test]
     fn test_rrf_calculation() {
@@ -278,6 +444,31 @@ mod tests {
         assert!((combined[0].1 - expected).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_apply_rerank() {
+        let conn = setup_test_db();
+        for id in ["a", "b", "c", "d"] {
+            add_entry(&conn, id, false, &vector(1, 0.0));
+        }
+        let results: Vec<HybridSearchResult> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|id| HybridSearchResult {
+                journal: crate::db::journals::get(&conn, id).unwrap(),
+                score: 0.0,
+                fts_rank: None,
+                vec_rank: None,
+                rerank_score: None,
+            })
+            .collect();
+
+        // Only the first three were scored in time; "d" stays last, unscored
+        let reranked = apply_rerank(results, &[0.1, 2.0, 0.1]);
+        let ids: Vec<&str> = reranked.iter().map(|r| r.journal.id.as_str()).collect();
+        assert_eq!(ids, vec!["b", "a", "c", "d"]);
+        assert_eq!(reranked[0].rerank_score, Some(2.0));
+        assert_eq!(reranked[3].rerank_score, None);
+    }
+
     #[test]
     fn test_empty_results() {
         let fts: Vec<(String, f64)> = vec![];
@@ -286,4 +477,149 @@ mod tests {
         let combined = reciprocal_rank_fusion(&fts, &vec, 10).unwrap();
         assert!(combined.is_empty());
     }
+
+    fn setup_test_db() -> Connection {
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::run_migrations(&conn).unwrap();
+        conn
+    }
+
+    /// Unit vector mostly along `axis`, tilted towards axis 0 by `tilt`.
+    fn vector(axis: usize, tilt: f32) -> Vec<f32> {
+        let mut v = vec![0.0; MINILM_EMBEDDING.dimension];
+        v[axis] = 1.0;
+        v[0] += tilt;
+        crate::ml::backend::l2_normalized(v)
+    }
+
+    fn add_entry(conn: &Connection, id: &str, archived: bool, embedding: &[f32]) {
+        conn.execute(
+            "INSERT INTO journals (id, content, is_archived) VALUES (?1, 'Entry', ?2)",
+            rusqlite::params![id, archived],
+        )
+        .unwrap();
+        vectors::store_embedding(conn, &MINILM_EMBEDDING, id, embedding).unwrap();
+    }
+
+    #[test]
+    fn test_related_entries() {
+        let conn = setup_test_db();
+        let model = &MINILM_EMBEDDING;
+        add_entry(&conn, "source", false, &vector(0, 0.0));
+        add_entry(&conn, "close", false, &vector(1, 3.0));
+        add_entry(&conn, "archived", false, &vector(2, 5.0));
+        add_entry(&conn, "far", false, &vector(3, 0.0));
+        conn.execute(
+            "UPDATE journals SET is_archived = 1 WHERE id = 'archived'",
+            [],
+        )
+        .unwrap();
+
+        // "far" shares one passage with the source, which lifts it above its entry score
+        vectors::store_chunk_embeddings(
+            &conn,
+            model,
+            "source",
+            &[vectors::ChunkData {
+                chunk_index: 0,
+                chunk_text: "The lake at dawn".to_string(),
+                embedding: vector(5, 0.0),
+            }],
+        )
+        .unwrap();
+        vectors::store_chunk_embeddings(
+            &conn,
+            model,
+            "far",
+            &[
+                vectors::ChunkData {
+                    chunk_index: 0,
+                    chunk_text: "Taxes again".to_string(),
+                    embedding: vector(3, 0.0),
+                },
+                vectors::ChunkData {
+                    chunk_index: 1,
+                    chunk_text: "Back at the lake at dawn".to_string(),
+                    embedding: vector(5, 0.1),
+                },
+            ],
+        )
+        .unwrap();
+
+        let related = related_entries(&conn, model, "source", 5).unwrap();
+        let ids: Vec<&str> = related.iter().map(|r| r.journal.id.as_str()).collect();
+        assert_eq!(ids, vec!["far", "close"]);
+        assert_eq!(
+            related[0].matched_chunk.as_deref(),
+            Some("Back at the lake at dawn")
+        );
+        assert!(related[0].similarity > 0.99);
+        assert!(related[1].matched_chunk.is_none());
+        assert!((related[1].similarity - 3.0 / 10f32.sqrt()).abs() < 1e-4);
+
+        assert_eq!(related_entries(&conn, model, "source", 1).unwrap().len(), 1);
+        assert!(related_entries(&conn, model, "missing", 5)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_hybrid_search_filters() {
+        let conn = setup_test_db();
+        let model = &MINILM_EMBEDDING;
+        for (id, entry_type, created_at, axis) in [
+            ("march-walk", "morning", "2024-03-05T08:00:00+00:00", 1),
+            ("april-walk", "morning", "2024-04-05T08:00:00+00:00", 2),
+            ("march-evening", "evening", "2024-03-31T22:00:00+00:00", 3),
+        ] {
+            conn.execute(
+                "INSERT INTO journals (id, content, entry_type, created_at) VALUES (?1, 'A walk by the river', ?2, ?3)",
+                [id, entry_type, created_at],
+            )
+            .unwrap();
+            vectors::store_embedding(&conn, model, id, &vector(axis, 1.0)).unwrap();
+        }
+        let query_embedding = vector(0, 0.0);
+        let search = |filter: &SearchFilter| -> Vec<String> {
+            let mut ids: Vec<String> =
+                hybrid_search(&conn, "river", Some((model, &query_embedding)), 10, filter)
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.journal.id)
+                    .collect();
+            ids.sort();
+            ids
+        };
+
+        let march = SearchFilter {
+            start_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+            end_date: NaiveDate::from_ymd_opt(2024, 3, 31),
+            ..Default::default()
+        };
+        assert_eq!(search(&march), vec!["march-evening", "march-walk"]);
+
+        let march_mornings = SearchFilter {
+            entry_type: Some(EntryType::Morning),
+            ..march.clone()
+        };
+        assert_eq!(search(&march_mornings), vec!["march-walk"]);
+        assert_eq!(
+            fts_only_search(&conn, "river", 10, &march_mornings)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        conn.execute(
+            "UPDATE journals SET is_archived = 1 WHERE id = 'march-walk'",
+            [],
+        )
+        .unwrap();
+        assert!(search(&march_mornings).is_empty());
+    }
 }
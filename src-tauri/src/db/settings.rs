@@ -0,0 +1,82 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::AppError;
+
+/// Setting key for the active embedding model ID.
+pub const ACTIVE_EMBEDDING_MODEL: &str = "embedding_model";
+/// Setting key for the embedding model being migrated to.
+pub const PENDING_EMBEDDING_MODEL: &str = "pending_embedding_model";
+/// Setting key for how per-window emotion scores are combined ("max" or "mean").
+pub const EMOTION_AGGREGATION: &str = "emotion_aggregation";
+/// Setting key for the precision models are loaded in ("f32", "f16" or "bf16").
+pub const MODEL_PRECISION: &str = "model_precision";
+/// Setting key for how long a model may sit idle before it is unloaded, in
+/// seconds ("0" keeps models loaded).
+pub const MODEL_IDLE_TIMEOUT_SECS: &str = "model_idle_timeout_secs";
+/// Setting key for whether search results are reranked by the cross-encoder ("true"/"false").
+pub const RERANK_ENABLED: &str = "rerank_enabled";
+/// Setting key for how long reranking may add to a search, in milliseconds.
+pub const RERANK_BUDGET_MS: &str = "rerank_budget_ms";
+/// Setting key for how embedding vectors are stored ("f32", "int8" or "bit").
+pub const VECTOR_STORAGE: &str = "vector_storage";
+
+/// Get a setting value.
+pub fn get(conn: &Connection, key: &str) -> Result<Option<String>, AppError> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(AppError::from)
+}
+
+/// Set a setting value, replacing any existing value.
+pub fn set(conn: &Connection, key: &str, value: &str) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))",
+        params![key, value],
+    )?;
+    Ok(())
+}
+
+/// Remove a setting.
+pub fn delete(conn: &Connection, key: &str) -> Result<(), AppError> {
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", params![key])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+
+    fn setup_test_db() -> Connection {
+        #[allow(clippy::missing_transmute_annotations)]
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_get_set_delete() {
+        let conn = setup_test_db();
+
+        assert_eq!(get(&conn, ACTIVE_EMBEDDING_MODEL).unwrap(), None);
+
+        set(&conn, ACTIVE_EMBEDDING_MODEL, "model-a").unwrap();
+        set(&conn, ACTIVE_EMBEDDING_MODEL, "model-b").unwrap();
+        assert_eq!(
+            get(&conn, ACTIVE_EMBEDDING_MODEL).unwrap(),
+            Some("model-b".to_string())
+        );
+
+        delete(&conn, ACTIVE_EMBEDDING_MODEL).unwrap();
+        assert_eq!(get(&conn, ACTIVE_EMBEDDING_MODEL).unwrap(), None);
+    }
+}
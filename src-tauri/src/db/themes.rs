@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::db::artifacts::content_hash;
+use crate::db::vectors;
+use crate::error::AppError;
+use crate::ml::models::EmbeddingModelSpec;
+use crate::ml::themes;
+
+/// Share of cached entries that may be added, edited or removed before the themes
+/// are re-clustered from scratch instead of updated in place.
+const REBUILD_FRACTION: f32 = 0.25;
+
+/// A recurring theme with the entries it covers.
+#[derive(Debug, Clone, Serialize)]
+pub struct Theme {
+    pub id: i64,
+    pub label: String,
+    /// Whether `label` was written by the local LLM rather than taken from `terms`
+    pub label_refined: bool,
+    /// Most distinctive terms, strongest first
+    pub terms: Vec<String>,
+    /// Member entries, newest first
+    pub entries: Vec<ThemeEntry>,
+    /// Member entries per month, oldest first
+    pub frequency: Vec<ThemeFrequency>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeEntry {
+    pub journal_id: String,
+    pub title: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThemeFrequency {
+    /// YYYY-MM
+    pub month: String,
+    pub count: u32,
+}
+
+/// What a refresh did to the cached themes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeRefresh {
+    /// Nothing changed since the last refresh
+    Unchanged,
+    /// New and edited entries were assigned to the nearest existing theme
+    Incremental,
+    /// Themes were clustered from scratch
+    Rebuilt,
+    /// Not enough embedded entries for themes; the cache was cleared
+    TooFewEntries,
+}
+
+/// An embedded, non-archived entry.
+struct ThemeInput {
+    journal_id: String,
+    content: String,
+    hash: String,
+    vector: Vec<f32>,
+}
+
+/// Bring the cached themes up to date with the entries embedded by `model`.
+/// Small changes are folded into the existing themes (keeping their ids and any
+/// refined labels); larger ones, a different model or `force` re-cluster everything.
+pub fn refresh(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    force: bool,
+) -> Result<ThemeRefresh, AppError> {
+    let inputs = load_inputs(conn, model)?;
+    if inputs.len() < themes::MIN_ENTRIES {
+        clear(conn)?;
+        return Ok(ThemeRefresh::TooFewEntries);
+    }
+
+    // Cached memberships, only if they were built from this model's vectors
+    let cached: HashMap<String, (i64, String)> = conn
+        .prepare(
+            "SELECT m.journal_id, m.theme_id, m.content_hash
+             FROM theme_members m JOIN themes t ON t.id = m.theme_id
+             WHERE t.model_version = ?1",
+        )?
+        .query_map(params![model.id], |row| {
+            Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    let changed: Vec<usize> = (0..inputs.len())
+        .filter(|&i| {
+            cached
+                .get(&inputs[i].journal_id)
+                .is_none_or(|(_, hash)| *hash != inputs[i].hash)
+        })
+        .collect();
+    let current: HashSet<&str> = inputs.iter().map(|i| i.journal_id.as_str()).collect();
+    let removed = cached
+        .keys()
+        .filter(|id| !current.contains(id.as_str()))
+        .count();
+
+    if !force && !cached.is_empty() && changed.is_empty() && removed == 0 {
+        return Ok(ThemeRefresh::Unchanged);
+    }
+
+    let rebuild = force
+        || cached.is_empty()
+        || (changed.len() + removed) as f32 > REBUILD_FRACTION * cached.len() as f32;
+
+    let groups = if rebuild {
+        let vectors: Vec<Vec<f32>> = inputs.iter().map(|i| i.vector.clone()).collect();
+        let Some(clustering) = themes::cluster(&vectors) else {
+            clear(conn)?;
+            return Ok(ThemeRefresh::TooFewEntries);
+        };
+        let mut groups: Vec<(Option<i64>, Vec<usize>)> =
+            vec![(None, Vec::new()); clustering.centroids.len()];
+        for (i, &cluster) in clustering.assignments.iter().enumerate() {
+            groups[cluster].1.push(i);
+        }
+        groups
+    } else {
+        // Existing themes keep their unchanged members
+        let mut by_theme: HashMap<i64, Vec<usize>> = HashMap::new();
+        for (i, input) in inputs.iter().enumerate() {
+            if let Some((theme_id, hash)) = cached.get(&input.journal_id) {
+                if *hash == input.hash {
+                    by_theme.entry(*theme_id).or_default().push(i);
+                }
+            }
+        }
+        let mut groups: Vec<(Option<i64>, Vec<usize>)> = by_theme
+            .into_iter()
+            .map(|(id, members)| (Some(id), members))
+            .collect();
+        groups.sort_by_key(|(id, _)| *id);
+
+        let centroids: Vec<Vec<f32>> = groups
+            .iter()
+            .filter_map(|(_, members)| {
+                themes::centroid(members.iter().map(|&i| inputs[i].vector.as_slice()))
+            })
+            .collect();
+        for &i in &changed {
+            let nearest = themes::nearest(&centroids, &inputs[i].vector);
+            groups[nearest].1.push(i);
+        }
+        groups
+    };
+
+    save(conn, model, &inputs, &groups)?;
+    log::info!(
+        "Themes {}: {} themes over {} entries",
+        if rebuild { "rebuilt" } else { "updated" },
+        groups.len(),
+        inputs.len()
+    );
+
+    Ok(if rebuild {
+        ThemeRefresh::Rebuilt
+    } else {
+        ThemeRefresh::Incremental
+    })
+}
+
+/// Cached themes for `model`, largest first.
+pub fn list(conn: &Connection, model: &EmbeddingModelSpec) -> Result<Vec<Theme>, AppError> {
+    let rows: Vec<(i64, String, bool, String)> = conn
+        .prepare(
+            "SELECT t.id, t.label, t.label_refined, t.terms
+             FROM themes t
+             WHERE t.model_version = ?1
+             ORDER BY (SELECT COUNT(*) FROM theme_members m WHERE m.theme_id = t.id) DESC, t.id",
+        )?
+        .query_map(params![model.id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?
+        .collect::<Result<_, _>>()?;
+
+    let mut entries_stmt = conn.prepare(
+        "SELECT j.id, j.title, j.created_at
+         FROM theme_members m JOIN journals j ON j.id = m.journal_id
+         WHERE m.theme_id = ?1 AND j.is_archived = 0
+         ORDER BY j.created_at DESC",
+    )?;
+    let mut frequency_stmt = conn.prepare(
+        "SELECT strftime('%Y-%m', j.created_at) AS month, COUNT(*)
+         FROM theme_members m JOIN journals j ON j.id = m.journal_id
+         WHERE m.theme_id = ?1 AND j.is_archived = 0
+         GROUP BY month
+         ORDER BY month",
+    )?;
+
+    rows.into_iter()
+        .map(|(id, label, label_refined, terms)| {
+            let entries = entries_stmt
+                .query_map(params![id], |row| {
+                    Ok(ThemeEntry {
+                        journal_id: row.get(0)?,
+                        title: row.get(1)?,
+                        created_at: row.get(2)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+            let frequency = frequency_stmt
+                .query_map(params![id], |row| {
+                    Ok(ThemeFrequency {
+                        month: row.get(0)?,
+                        count: row.get(1)?,
+                    })
+                })?
+                .collect::<Result<_, _>>()?;
+            Ok(Theme {
+                id,
+                label,
+                label_refined,
+                terms: serde_json::from_str(&terms).unwrap_or_default(),
+                entries,
+                frequency,
+            })
+        })
+        .collect()
+}
+
+/// Replace a theme's term-based label (e.g. with one written by the local LLM).
+/// Refined labels survive incremental refreshes.
+pub fn set_label(conn: &Connection, theme_id: i64, label: &str) -> Result<(), AppError> {
+    let updated = conn.execute(
+        "UPDATE themes SET label = ?1, label_refined = 1, updated_at = CURRENT_TIMESTAMP
+         WHERE id = ?2",
+        params![label, theme_id],
+    )?;
+    if updated == 0 {
+        return Err(AppError::NotFound(format!("theme {}", theme_id)));
+    }
+    Ok(())
+}
+
+/// Drop all cached themes.
+pub fn clear(conn: &Connection) -> Result<(), AppError> {
+    conn.execute_batch("DELETE FROM theme_members; DELETE FROM themes;")?;
+    Ok(())
+}
+
+fn load_inputs(conn: &Connection, model: &EmbeddingModelSpec) -> Result<Vec<ThemeInput>, AppError> {
+    let mut contents: HashMap<String, String> = conn
+        .prepare("SELECT id, content FROM journals WHERE is_archived = 0")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut inputs: Vec<ThemeInput> = vectors::all_embeddings(conn, model)?
+        .into_iter()
+        .filter_map(|(journal_id, vector)| {
+            let content = contents.remove(&journal_id)?;
+            Some(ThemeInput {
+                hash: content_hash(&content),
+                journal_id,
+                content,
+                vector,
+            })
+        })
+        .collect();
+    // Stable order, so clustering doesn't depend on table layout
+    inputs.sort_by(|a, b| a.journal_id.cmp(&b.journal_id));
+    Ok(inputs)
+}
+
+/// Write themes and memberships. Groups with an id update that theme in place;
+/// the others become new themes. Themes not in `groups` are deleted.
+fn save(
+    conn: &Connection,
+    model: &EmbeddingModelSpec,
+    inputs: &[ThemeInput],
+    groups: &[(Option<i64>, Vec<usize>)],
+) -> Result<(), AppError> {
+    let mut texts = Vec::new();
+    let mut assignments = Vec::new();
+    for (group, (_, members)) in groups.iter().enumerate() {
+        for &i in members {
+            texts.push(inputs[i].content.as_str());
+            assignments.push(group);
+        }
+    }
+    let terms = themes::distinctive_terms(&texts, &assignments, groups.len());
+
+    let tx = conn.unchecked_transaction()?;
+    let kept: Vec<i64> = groups.iter().filter_map(|(id, _)| *id).collect();
+    tx.execute("DELETE FROM theme_members", [])?;
+    tx.execute(
+        &format!(
+            "DELETE FROM themes WHERE id NOT IN ({})",
+            kept.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        [],
+    )?;
+
+    {
+        let mut insert_member = tx.prepare(
+            "INSERT INTO theme_members (journal_id, theme_id, content_hash) VALUES (?1, ?2, ?3)",
+        )?;
+        for ((id, members), terms) in groups.iter().zip(&terms) {
+            if members.is_empty() {
+                continue;
+            }
+            let terms_json =
+                serde_json::to_string(terms).map_err(|e| AppError::Storage(e.to_string()))?;
+            let label = themes::label(terms);
+            let theme_id = match id {
+                Some(id) => {
+                    tx.execute(
+                        "UPDATE themes SET terms = ?1,
+                             label = CASE WHEN label_refined THEN label ELSE ?2 END,
+                             updated_at = CURRENT_TIMESTAMP
+                         WHERE id = ?3",
+                        params![terms_json, label, id],
+                    )?;
+                    *id
+                }
+                None => {
+                    tx.execute(
+                        "INSERT INTO themes (model_version, label, terms) VALUES (?1, ?2, ?3)",
+                        params![model.id, label, terms_json],
+                    )?;
+                    tx.last_insert_rowid()
+                }
+            };
+            for &i in members {
+                insert_member.execute(params![inputs[i].journal_id, theme_id, inputs[i].hash])?;
+            }
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+    use crate::ml::models::MINILM_EMBEDDING;
+
+    const MODEL: &EmbeddingModelSpec = &MINILM_EMBEDDING;
+
+    fn setup_test_db() -> Connection {
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn
+    }
+
+    /// Unit vector along `axis`, nudged by `jitter` so entries aren't identical.
+    fn vector(axis: usize, jitter: usize) -> Vec<f32> {
+        let mut v = vec![0.0; MODEL.dimension];
+        v[axis] = 1.0;
+        v[100 + jitter] = 0.1;
+        crate::ml::backend::l2_normalized(v)
+    }
+
+    fn add_entry(conn: &Connection, id: &str, content: &str, created_at: &str, vector: &[f32]) {
+        conn.execute(
+            "INSERT INTO journals (id, content, created_at) VALUES (?1, ?2, ?3)",
+            params![id, content, created_at],
+        )
+        .unwrap();
+        vectors::store_embedding(conn, MODEL, id, vector).unwrap();
+    }
+
+    fn seed(conn: &Connection) {
+        for i in 0..4 {
+            add_entry(
+                conn,
+                &format!("work-{}", i),
+                "Another deadline, the manager wants the report",
+                &format!("2024-0{}-10 09:00:00", i + 1),
+                &vector(0, i),
+            );
+            add_entry(
+                conn,
+                &format!("run-{}", i),
+                "Running along the river before breakfast",
+                "2024-01-15 07:00:00",
+                &vector(1, i),
+            );
+        }
+    }
+
+    #[test]
+    fn test_refresh_builds_themes() {
+        let conn = setup_test_db();
+        seed(&conn);
+
+        assert_eq!(refresh(&conn, MODEL, false).unwrap(), ThemeRefresh::Rebuilt);
+        let themes = list(&conn, MODEL).unwrap();
+        assert_eq!(themes.len(), 2);
+        let work = themes
+            .iter()
+            .find(|t| t.terms.contains(&"deadline".to_string()))
+            .unwrap();
+        assert_eq!(work.entries.len(), 4);
+        assert!(work
+            .entries
+            .iter()
+            .all(|e| e.journal_id.starts_with("work")));
+        assert_eq!(work.frequency.len(), 4);
+        assert_eq!(work.frequency[0].month, "2024-01");
+
+        assert_eq!(
+            refresh(&conn, MODEL, false).unwrap(),
+            ThemeRefresh::Unchanged
+        );
+        assert_eq!(refresh(&conn, MODEL, true).unwrap(), ThemeRefresh::Rebuilt);
+    }
+
+    #[test]
+    fn test_incremental_refresh_keeps_refined_labels() {
+        let conn = setup_test_db();
+        seed(&conn);
+        refresh(&conn, MODEL, false).unwrap();
+
+        let run = list(&conn, MODEL)
+            .unwrap()
+            .into_iter()
+            .find(|t| t.entries.iter().any(|e| e.journal_id == "run-0"))
+            .unwrap();
+        set_label(&conn, run.id, "Morning runs").unwrap();
+
+        // One new entry is folded into the nearest theme
+        add_entry(
+            &conn,
+            "run-new",
+            "Running by the river again",
+            "2024-02-01 07:00:00",
+            &vector(1, 9),
+        );
+        assert_eq!(
+            refresh(&conn, MODEL, false).unwrap(),
+            ThemeRefresh::Incremental
+        );
+
+        let themes = list(&conn, MODEL).unwrap();
+        let run = themes.iter().find(|t| t.id == run.id).unwrap();
+        assert_eq!(run.label, "Morning runs");
+        assert!(run.label_refined);
+        assert_eq!(run.entries.len(), 5);
+        assert_eq!(run.entries[0].journal_id, "run-new");
+
+        assert!(set_label(&conn, 9999, "Missing").is_err());
+    }
+
+    #[test]
+    fn test_too_few_entries_clears_cache() {
+        let conn = setup_test_db();
+        seed(&conn);
+        refresh(&conn, MODEL, false).unwrap();
+
+        conn.execute(
+            "UPDATE journals SET is_archived = 1 WHERE id LIKE 'work-%'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(
+            refresh(&conn, MODEL, false).unwrap(),
+            ThemeRefresh::TooFewEntries
+        );
+        assert!(list(&conn, MODEL).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,866 @@
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use rusqlite::Connection;
+
+use crate::db::artifacts::{self, Artifact};
+use crate::db::jobs::JobType;
+use crate::db::journals::Journal;
+use crate::db::{self, DbPool};
+use crate::error::AppError;
+use crate::llm::LlmState;
+use crate::ml::backend::{EntityExtractor, OllamaEntityExtractor};
+use crate::ml::calibration::Calibration;
+use crate::ml::ner::EntityMention;
+use crate::ml::sentiment::{
+    Aggregation, ChunkEmotions, ChunkedEmotions, EmotionPrediction, SentenceEmotions,
+};
+use crate::ml::{self, Embedder, EmbeddingModelSpec, EmotionClassifier, MlState};
+
+use super::JobContext;
+
+/// Minimum character count to trigger chunking (roughly 100+ words)
+const CHUNK_THRESHOLD_CHARS: usize = 500;
+/// Target chunk size in characters (roughly 100-125 words)
+const CHUNK_SIZE_CHARS: usize = 500;
+/// Overlap between chunks for context continuity
+const CHUNK_OVERLAP_CHARS: usize = 100;
+/// Chunks embedded per forward pass
+const CHUNK_EMBED_BATCH: usize = 16;
+
+/// Confidence threshold for storing an emotion label.
+pub const EMOTION_THRESHOLD: f32 = 0.1;
+/// Maximum number of emotion labels stored per entry.
+pub const EMOTION_MAX_LABELS: usize = 5;
+/// Maximum number of emotion labels stored per sentence.
+const SENTENCE_MAX_LABELS: usize = 3;
+
+/// Entries shorter than this are not worth titling.
+const MIN_TITLE_CONTENT_CHARS: usize = 20;
+
+/// Payload for summary jobs.
+#[derive(Debug, Deserialize)]
+struct SummaryPayload {
+    period: String,
+}
+
+/// Dispatch a job to its handler.
+/// Returns an optional JSON result that is stored with the job and sent to the frontend.
+pub async fn run(
+    ctx: &JobContext,
+    pool: &DbPool,
+    ml: &MlState,
+    llm: &LlmState,
+) -> Result<Option<String>, AppError> {
+    let job = &ctx.job;
+    log::info!("Running job: id={}, type={}", job.id, job.job_type.as_str());
+
+    match job.job_type {
+        JobType::Embedding => {
+            let entry_id = require_entry(ctx)?;
+            ctx.progress(0.0, "Generating embedding");
+            generate_embedding(pool, ml, entry_id, Some(ctx)).await?;
+            Ok(None)
+        }
+        JobType::Emotions => {
+            let entry_id = require_entry(ctx)?;
+            ctx.progress(0.0, "Analyzing emotions");
+            let predictions = analyze_emotions(pool, ml, entry_id).await?;
+            Ok(serde_json::to_string(&predictions).ok())
+        }
+        JobType::Entities => {
+            let entry_id = require_entry(ctx)?;
+            ctx.progress(0.0, "Finding people and places");
+            let mentions = extract_entities(pool, ml, llm, entry_id).await?;
+            Ok(serde_json::to_string(&mentions).ok())
+        }
+        JobType::Title => {
+            let entry_id = require_entry(ctx)?;
+            ctx.progress(0.0, "Generating title");
+            let title = generate_title(pool, llm, entry_id).await?;
+            Ok(title.map(|t| serde_json::Value::String(t).to_string()))
+        }
+        JobType::Summary => {
+            let payload: SummaryPayload = job
+                .payload
+                .as_deref()
+                .map(serde_json::from_str)
+                .transpose()
+                .map_err(|e| AppError::InvalidInput(format!("Invalid summary payload: {}", e)))?
+                .unwrap_or(SummaryPayload {
+                    period: "weekly".to_string(),
+                });
+            ctx.progress(0.0, "Generating summary");
+            let summary = crate::llm::summary::generate(pool, &llm.ollama, &payload.period).await?;
+            Ok(serde_json::to_string(&summary).ok())
+        }
+    }
+}
+
+fn require_entry(ctx: &JobContext) -> Result<&str, AppError> {
+    ctx.job.entry_id.as_deref().ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "{} job {} has no entry",
+            ctx.job.job_type.as_str(),
+            ctx.job.id
+        ))
+    })
+}
+
+/// Generate entry-level and chunk embeddings for a journal entry with the active model.
+/// Skips entries whose embedding was already computed from the current content.
+pub async fn generate_embedding(
+    pool: &DbPool,
+    ml: &MlState,
+    id: &str,
+    ctx: Option<&JobContext>,
+) -> Result<(), AppError> {
+    let model = ml.get_embedding_model().await?;
+    generate_embedding_with(pool, model.as_ref(), id, ctx).await
+}
+
+/// Generate entry-level and chunk embeddings for a journal entry with a specific model.
+pub async fn generate_embedding_with(
+    pool: &DbPool,
+    model: &dyn Embedder,
+    id: &str,
+    ctx: Option<&JobContext>,
+) -> Result<(), AppError> {
+    let Some((content, hash)) = stale_embedding(pool, model.spec(), id)? else {
+        return Ok(());
+    };
+
+    // Generate full-entry embedding
+    let embedding = model.embed(&content).await?;
+
+    if ctx.is_some_and(|c| c.is_cancelled()) {
+        return Ok(());
+    }
+
+    store_embeddings(pool, model, id, &content, &hash, &embedding, ctx).await
+}
+
+/// Generate embeddings for several entries, embedding their full texts in one batched
+/// forward pass. Returns each entry's outcome in input order: whether an embedding was
+/// generated (false when it was already up to date).
+pub async fn generate_embeddings_with(
+    pool: &DbPool,
+    model: &dyn Embedder,
+    ids: &[&str],
+) -> Result<Vec<Result<bool, AppError>>, AppError> {
+    let mut outcomes = Vec::with_capacity(ids.len());
+    let mut stale = Vec::new();
+    for (index, id) in ids.iter().enumerate() {
+        match stale_embedding(pool, model.spec(), id) {
+            Ok(Some((content, hash))) => {
+                stale.push((index, content, hash));
+                outcomes.push(Ok(true));
+            }
+            Ok(None) => outcomes.push(Ok(false)),
+            Err(e) => outcomes.push(Err(e)),
+        }
+    }
+    if stale.is_empty() {
+        return Ok(outcomes);
+    }
+
+    let texts: Vec<&str> = stale
+        .iter()
+        .map(|(_, content, _)| content.as_str())
+        .collect();
+    let embeddings = model.embed_batch(&texts).await?;
+
+    for ((index, content, hash), embedding) in stale.iter().zip(&embeddings) {
+        if let Err(e) =
+            store_embeddings(pool, model, ids[*index], content, hash, embedding, None).await
+        {
+            outcomes[*index] = Err(e);
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Content and content hash of an entry whose embedding is missing or outdated,
+/// or `None` when it is up to date.
+fn stale_embedding(
+    pool: &DbPool,
+    spec: &EmbeddingModelSpec,
+    id: &str,
+) -> Result<Option<(String, String)>, AppError> {
+    let conn = pool.get()?;
+    let content = db::journals::get(&conn, id)?.content;
+    let hash = artifacts::content_hash(&content);
+    if db::vectors::has_embedding(&conn, spec, id)?
+        && artifacts::is_fresh(&conn, id, Artifact::Embedding, &hash, spec.id)?
+    {
+        return Ok(None);
+    }
+    Ok(Some((content, hash)))
+}
+
+/// Store an entry's embedding, embed and store its chunks when it is long enough,
+/// then mark the embedding fresh for `hash`.
+async fn store_embeddings(
+    pool: &DbPool,
+    model: &dyn Embedder,
+    id: &str,
+    content: &str,
+    hash: &str,
+    embedding: &[f32],
+    ctx: Option<&JobContext>,
+) -> Result<(), AppError> {
+    let spec = model.spec();
+
+    // Store entry-level embedding
+    {
+        let conn = pool.get()?;
+        db::vectors::store_embedding(&conn, spec, id, embedding)?;
+    }
+
+    let mut stored_chunks = false;
+
+    // For longer entries, also generate chunk embeddings for better RAG precision
+    if content.len() > CHUNK_THRESHOLD_CHARS {
+        let chunks = ml::embeddings::chunk_text(content, CHUNK_SIZE_CHARS, CHUNK_OVERLAP_CHARS);
+
+        if chunks.len() > 1 {
+            let total = chunks.len();
+            let mut chunk_data = Vec::with_capacity(total);
+
+            // Embed in batches so progress and cancellation stay responsive
+            for (batch_index, batch) in chunks.chunks(CHUNK_EMBED_BATCH).enumerate() {
+                if let Some(ctx) = ctx {
+                    if ctx.is_cancelled() {
+                        return Ok(());
+                    }
+                }
+
+                let texts: Vec<&str> = batch.iter().map(String::as_str).collect();
+                match model.embed_batch(&texts).await {
+                    Ok(embeddings) => {
+                        let first_index = batch_index * CHUNK_EMBED_BATCH;
+                        for (offset, (chunk_text, chunk_embedding)) in
+                            batch.iter().zip(embeddings).enumerate()
+                        {
+                            chunk_data.push(db::vectors::ChunkData {
+                                chunk_index: first_index + offset,
+                                chunk_text: chunk_text.clone(),
+                                embedding: chunk_embedding,
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to embed chunk batch {} for entry {}: {}",
+                            batch_index,
+                            id,
+                            e
+                        );
+                    }
+                }
+
+                if let Some(ctx) = ctx {
+                    let done = ((batch_index + 1) * CHUNK_EMBED_BATCH).min(total);
+                    ctx.progress((done + 1) as f32 / (total + 1) as f32, "Embedding chunks");
+                }
+            }
+
+            if !chunk_data.is_empty() {
+                let conn = pool.get()?;
+                db::vectors::store_chunk_embeddings(&conn, spec, id, &chunk_data)?;
+                artifacts::record(&conn, id, Artifact::Chunks, hash, spec.id)?;
+                log::info!(
+                    "Generated {} chunk embeddings for entry {}",
+                    chunk_data.len(),
+                    id
+                );
+                stored_chunks = true;
+            }
+        }
+    }
+
+    {
+        let conn = pool.get()?;
+        // Entry is too short to chunk (or was shortened by an edit): drop old chunks
+        if !stored_chunks {
+            db::vectors::delete_chunks(&conn, spec, id)?;
+            artifacts::clear(&conn, id, Artifact::Chunks, spec.id)?;
+        }
+        // Recorded last so an interrupted run is redone rather than left half-fresh
+        artifacts::record(&conn, id, Artifact::Embedding, hash, spec.id)?;
+    }
+
+    log::info!("Generated {} embedding for entry {}", spec.id, id);
+    Ok(())
+}
+
+/// Run emotion analysis for an entry and replace any stored emotions.
+/// Long English entries are analyzed in windows whose per-window results are stored too;
+/// entries not written in English go through the multilingual model. Scores are adjusted
+/// by the calibration learned from the user's corrections.
+pub async fn analyze_emotions(
+    pool: &DbPool,
+    ml: &MlState,
+    id: &str,
+) -> Result<Vec<EmotionPrediction>, AppError> {
+    let (aggregation, calibration) = emotion_settings(pool)?;
+    let entry = {
+        let conn = pool.get()?;
+        db::journals::get(&conn, id)?
+    };
+
+    let model = emotion_model_for(ml, entry.language.as_deref()).await?;
+    let result = model.predict_chunked(
+        &entry.content,
+        EMOTION_THRESHOLD,
+        EMOTION_MAX_LABELS,
+        aggregation,
+    )?;
+    store_emotions(pool, &entry, result, &calibration)
+}
+
+/// `analyze_emotions` for several entries. Entries are grouped by the model their
+/// language needs, and each group runs through its model in shared batched forward
+/// passes. Returns each entry's outcome in input order.
+pub async fn analyze_emotions_batch(
+    pool: &DbPool,
+    ml: &MlState,
+    ids: &[&str],
+) -> Result<Vec<Result<Vec<EmotionPrediction>, AppError>>, AppError> {
+    let (aggregation, calibration) = emotion_settings(pool)?;
+
+    let mut outcomes = Vec::with_capacity(ids.len());
+    let mut english = Vec::new();
+    let mut other = Vec::new();
+    {
+        let conn = pool.get()?;
+        for (index, id) in ids.iter().enumerate() {
+            match db::journals::get(&conn, id) {
+                Ok(entry) => {
+                    if ml::language::is_english(entry.language.as_deref()) {
+                        english.push((index, entry));
+                    } else {
+                        other.push((index, entry));
+                    }
+                    outcomes.push(Ok(Vec::new()));
+                }
+                Err(e) => outcomes.push(Err(e)),
+            }
+        }
+    }
+
+    for group in [english, other] {
+        let Some((_, first)) = group.first() else {
+            continue;
+        };
+        let texts: Vec<&str> = group.iter().map(|(_, e)| e.content.as_str()).collect();
+        let results = match emotion_model_for(ml, first.language.as_deref()).await {
+            Ok(model) => model.predict_chunked_batch(
+                &texts,
+                EMOTION_THRESHOLD,
+                EMOTION_MAX_LABELS,
+                aggregation,
+            ),
+            Err(e) => Err(e),
+        };
+
+        match results {
+            Ok(results) => {
+                for ((index, entry), result) in group.iter().zip(results) {
+                    outcomes[*index] = store_emotions(pool, entry, result, &calibration);
+                }
+            }
+            Err(e) => {
+                let message = e.to_string();
+                for (index, _) in &group {
+                    outcomes[*index] = Err(AppError::Ml(message.clone()));
+                }
+            }
+        }
+    }
+    Ok(outcomes)
+}
+
+/// How window scores are aggregated, and the calibration from the user's corrections.
+fn emotion_settings(pool: &DbPool) -> Result<(Aggregation, Calibration), AppError> {
+    let conn = pool.get()?;
+    let aggregation = db::settings::get(&conn, db::settings::EMOTION_AGGREGATION)?
+        .as_deref()
+        .and_then(Aggregation::parse)
+        .unwrap_or_default();
+    Ok((aggregation, db::emotions::get_calibration(&conn)?))
+}
+
+/// The emotion model for entries written in `language`.
+async fn emotion_model_for(
+    ml: &MlState,
+    language: Option<&str>,
+) -> Result<Arc<dyn EmotionClassifier>, AppError> {
+    if ml::language::is_english(language) {
+        ml.get_sentiment_model().await
+    } else {
+        ml.get_multilingual_emotion_model().await
+    }
+}
+
+/// Calibrate a model's predictions for an entry and replace its stored emotions.
+fn store_emotions(
+    pool: &DbPool,
+    entry: &Journal,
+    result: ChunkedEmotions,
+    calibration: &Calibration,
+) -> Result<Vec<EmotionPrediction>, AppError> {
+    let predictions = calibration.apply(result.predictions, EMOTION_THRESHOLD);
+    let chunks: Vec<ChunkEmotions> = result
+        .chunks
+        .into_iter()
+        .map(|chunk| ChunkEmotions {
+            predictions: calibration.apply(chunk.predictions, EMOTION_THRESHOLD),
+            ..chunk
+        })
+        .collect();
+
+    let conn = pool.get()?;
+    db::emotions::replace(&conn, &entry.id, &predictions)?;
+    // A single window adds nothing over the entry-level result
+    let chunks = if chunks.len() > 1 { chunks } else { Vec::new() };
+    db::emotions::replace_chunks(&conn, &entry.id, &chunks)?;
+    artifacts::record(
+        &conn,
+        &entry.id,
+        Artifact::Emotions,
+        &artifacts::content_hash(&entry.content),
+        db::emotions::model_version_for(entry.language.as_deref()),
+    )?;
+
+    Ok(predictions)
+}
+
+/// Run emotion analysis on each sentence of an entry and cache the results.
+/// Offsets are character offsets into the entry, for highlighting in the editor.
+pub async fn analyze_sentence_emotions(
+    pool: &DbPool,
+    ml: &MlState,
+    id: &str,
+) -> Result<Vec<SentenceEmotions>, AppError> {
+    let (entry, calibration) = {
+        let conn = pool.get()?;
+        (
+            db::journals::get(&conn, id)?,
+            db::emotions::get_calibration(&conn)?,
+        )
+    };
+
+    let content = &entry.content;
+    let spans = ml::embeddings::sentence_spans(content);
+    let sentences: Vec<&str> = spans
+        .iter()
+        .map(|&(start, end)| &content[start..end])
+        .collect();
+
+    let language = entry.language.as_deref();
+    let model = if ml::language::is_english(language) {
+        ml.get_sentiment_model().await?
+    } else {
+        ml.get_multilingual_emotion_model().await?
+    };
+    let predictions = model.predict_batch(&sentences, EMOTION_THRESHOLD, SENTENCE_MAX_LABELS)?;
+
+    let results: Vec<SentenceEmotions> = spans
+        .iter()
+        .zip(predictions)
+        .enumerate()
+        .map(
+            |(sentence_index, (&(start, end), predictions))| SentenceEmotions {
+                sentence_index,
+                start_offset: content[..start].chars().count(),
+                end_offset: content[..end].chars().count(),
+                predictions: calibration.apply(predictions, EMOTION_THRESHOLD),
+            },
+        )
+        .collect();
+
+    {
+        let conn = pool.get()?;
+        db::emotions::replace_sentences(&conn, id, &results)?;
+        artifacts::record(
+            &conn,
+            id,
+            Artifact::SentenceEmotions,
+            &artifacts::content_hash(content),
+            db::emotions::model_version_for(language),
+        )?;
+    }
+
+    Ok(results)
+}
+
+/// Find the people and places an entry mentions and replace its recorded entities.
+/// English entries use the NER model; other languages, or machines where the model
+/// can't be loaded, fall back to the local LLM.
+pub async fn extract_entities(
+    pool: &DbPool,
+    ml: &MlState,
+    llm: &LlmState,
+    id: &str,
+) -> Result<Vec<EntityMention>, AppError> {
+    let fallback = Arc::new(OllamaEntityExtractor::new(llm.ollama.clone()));
+    extract_entities_with(pool, ml, fallback, id).await
+}
+
+/// Find an entry's people and places with a specific extractor standing in for
+/// the NER model where it doesn't apply.
+pub async fn extract_entities_with(
+    pool: &DbPool,
+    ml: &MlState,
+    fallback: Arc<dyn EntityExtractor>,
+    id: &str,
+) -> Result<Vec<EntityMention>, AppError> {
+    let entry = {
+        let conn = pool.get()?;
+        db::journals::get(&conn, id)?
+    };
+
+    let extractor = if ml::language::is_english(entry.language.as_deref()) {
+        match ml.get_ner_model().await {
+            Ok(model) => model,
+            Err(e) => {
+                log::warn!("NER model unavailable, using the local LLM: {}", e);
+                fallback
+            }
+        }
+    } else {
+        fallback
+    };
+    let mentions = extractor.extract(&entry.content).await?;
+
+    {
+        let conn = pool.get()?;
+        db::entities::replace_for_entry(&conn, id, &mentions)?;
+        artifacts::record(
+            &conn,
+            id,
+            Artifact::Entities,
+            &artifacts::content_hash(&entry.content),
+            extractor.model_version(),
+        )?;
+    }
+
+    Ok(mentions)
+}
+
+/// Jobs needed to bring an entry's existing derived data up to date with its content.
+/// Only artifacts that were computed before are refreshed; missing ones stay lazy.
+pub fn refresh_jobs(
+    conn: &Connection,
+    embedding_model: &EmbeddingModelSpec,
+    id: &str,
+) -> Result<Vec<JobType>, AppError> {
+    let entry = db::journals::get(conn, id)?;
+    let hash = artifacts::content_hash(&entry.content);
+    let mut jobs = Vec::new();
+
+    if db::vectors::has_embedding(conn, embedding_model, id)?
+        && !artifacts::is_fresh(conn, id, Artifact::Embedding, &hash, embedding_model.id)?
+    {
+        jobs.push(JobType::Embedding);
+    }
+    if !db::emotions::get(conn, id)?.is_empty()
+        && !artifacts::is_fresh(
+            conn,
+            id,
+            Artifact::Emotions,
+            &hash,
+            db::emotions::model_version_for(entry.language.as_deref()),
+        )?
+    {
+        jobs.push(JobType::Emotions);
+    }
+    if db::entities::is_extracted(conn, id)?
+        && !db::entities::is_fresh(conn, id, &hash, entry.language.as_deref())?
+    {
+        jobs.push(JobType::Entities);
+    }
+
+    Ok(jobs)
+}
+
+/// Generate and store a title for an entry that doesn't have one yet.
+/// Returns the generated title, or None if the entry was skipped.
+async fn generate_title(
+    pool: &DbPool,
+    llm: &LlmState,
+    id: &str,
+) -> Result<Option<String>, AppError> {
+    let entry = {
+        let conn = pool.get()?;
+        db::journals::get(&conn, id)?
+    };
+
+    if entry.title.is_some() || entry.content.trim().len() < MIN_TITLE_CONTENT_CHARS {
+        return Ok(None);
+    }
+
+    let title = llm.ollama.generate_title(&entry.content).await?;
+    if title.is_empty() {
+        return Err(AppError::Llm(format!(
+            "Empty title generated for entry {}",
+            id
+        )));
+    }
+
+    let conn = pool.get()?;
+    db::journals::update_title(&conn, id, &title)?;
+    log::info!("Generated title for entry {}: {}", id, title);
+
+    Ok(Some(title))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml::calibration::CorrectionAction;
+    use crate::ml::fake::{FakeEmbedder, FakeEmotionClassifier, FakeEntityExtractor};
+    use crate::ml::ner::EntityKind;
+
+    /// A database plus an `MlState` whose models directory is a plain file, so any
+    /// model that wasn't inserted fails to load (or download) straight away.
+    fn setup() -> (tempfile::TempDir, DbPool, MlState) {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = db::init(&dir.path().join("test.db")).unwrap();
+        let models_dir = dir.path().join("models");
+        std::fs::write(&models_dir, b"").unwrap();
+        (dir, pool, MlState::new(models_dir))
+    }
+
+    fn add_entry(pool: &DbPool, content: &str, language: &str) -> String {
+        let conn = pool.get().unwrap();
+        let id = db::journals::create(&conn, content, None, None).unwrap().id;
+        conn.execute(
+            "UPDATE journals SET language = ?1 WHERE id = ?2",
+            rusqlite::params![language, id],
+        )
+        .unwrap();
+        id
+    }
+
+    fn recorded_version(pool: &DbPool, id: &str, artifact: Artifact) -> String {
+        pool.get()
+            .unwrap()
+            .query_row(
+                "SELECT model_version FROM derived_artifacts WHERE journal_id = ?1 AND artifact = ?2",
+                rusqlite::params![id, artifact.as_str()],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_analyze_emotions_routes_by_language_and_calibrates() {
+        let (_dir, pool, ml) = setup();
+        ml.insert_sentiment_model(Arc::new(FakeEmotionClassifier))
+            .await;
+
+        let english = add_entry(
+            &pool,
+            "So happy, really happy and grateful",
+            ml::language::ENGLISH,
+        );
+        let predictions = analyze_emotions(&pool, &ml, &english).await.unwrap();
+        let labels: Vec<&str> = predictions.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["joy", "gratitude"]);
+        assert_eq!(
+            recorded_version(&pool, &english, Artifact::Emotions),
+            db::emotions::model_version_for(Some(ml::language::ENGLISH))
+        );
+
+        // Non-English entries never reach the English classifier
+        let german = add_entry(&pool, "Heute war ich so happy", "deu");
+        assert!(analyze_emotions(&pool, &ml, &german).await.is_err());
+        ml.insert_multilingual_emotion_model(Arc::new(FakeEmotionClassifier))
+            .await;
+        analyze_emotions(&pool, &ml, &german).await.unwrap();
+        assert_eq!(
+            recorded_version(&pool, &german, Artifact::Emotions),
+            db::emotions::model_version_for(Some("deu"))
+        );
+
+        // Corrections on other entries lower "joy" and raise "gratitude" past it
+        for i in 0..12 {
+            let other = add_entry(&pool, &format!("Entry {}", i), ml::language::ENGLISH);
+            let conn = pool.get().unwrap();
+            db::emotions::set_correction(&conn, &other, "joy", CorrectionAction::Remove).unwrap();
+            db::emotions::set_correction(&conn, &other, "gratitude", CorrectionAction::Add)
+                .unwrap();
+        }
+        let predictions = analyze_emotions(&pool, &ml, &english).await.unwrap();
+        assert_eq!(predictions[0].label, "gratitude");
+        assert_eq!(predictions[1].label, "joy");
+        assert!(predictions[1].score < predictions[0].score);
+
+        let stored = db::emotions::get(&pool.get().unwrap(), &english).unwrap();
+        assert_eq!(stored[0].0, "gratitude");
+    }
+
+    #[tokio::test]
+    async fn test_extract_entities_uses_ner_then_llm_fallback() {
+        let (_dir, pool, ml) = setup();
+        let text = "Met Sam Rivera in Lisbon. Later Sam called.";
+        let english = add_entry(&pool, text, ml::language::ENGLISH);
+        let german = add_entry(&pool, text, "deu");
+        let fallback: Arc<dyn EntityExtractor> = Arc::new(FakeEntityExtractor::llm());
+
+        // Without a loadable NER model, English entries use the fallback too
+        extract_entities_with(&pool, &ml, fallback.clone(), &english)
+            .await
+            .unwrap();
+        assert_eq!(
+            recorded_version(&pool, &english, Artifact::Entities),
+            crate::llm::ollama::CHAT_MODEL
+        );
+
+        ml.insert_ner_model(Arc::new(FakeEntityExtractor::ner()))
+            .await;
+        let mentions = extract_entities_with(&pool, &ml, fallback.clone(), &english)
+            .await
+            .unwrap();
+        let found: Vec<(EntityKind, &str)> =
+            mentions.iter().map(|m| (m.kind, m.name.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (EntityKind::Person, "Sam Rivera"),
+                (EntityKind::Place, "Lisbon"),
+                (EntityKind::Person, "Sam"),
+            ]
+        );
+        assert_eq!(
+            recorded_version(&pool, &english, Artifact::Entities),
+            crate::ml::models::NER_MODEL.local_dir
+        );
+
+        // Other languages always go to the fallback
+        extract_entities_with(&pool, &ml, fallback, &german)
+            .await
+            .unwrap();
+        assert_eq!(
+            recorded_version(&pool, &german, Artifact::Entities),
+            crate::llm::ollama::CHAT_MODEL
+        );
+
+        let conn = pool.get().unwrap();
+        let people = db::entities::list(&conn, EntityKind::Person, 10).unwrap();
+        assert_eq!(people[0].name, "Sam Rivera");
+        assert_eq!(people[0].entry_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_entities_are_not_refreshed_again() {
+        let (_dir, pool, ml) = setup();
+        let english = add_entry(&pool, "Dinner with Sam in Paris.", ml::language::ENGLISH);
+        let fallback: Arc<dyn EntityExtractor> = Arc::new(FakeEntityExtractor::llm());
+        assert!(db::entities::list_outdated(&pool.get().unwrap())
+            .unwrap()
+            .contains(&english));
+
+        // The NER model can't be loaded, so the fallback handles the English entry
+        extract_entities_with(&pool, &ml, fallback, &english)
+            .await
+            .unwrap();
+        {
+            let conn = pool.get().unwrap();
+            let spec = &crate::ml::models::MINILM_EMBEDDING;
+            assert!(!refresh_jobs(&conn, spec, &english)
+                .unwrap()
+                .contains(&JobType::Entities));
+            assert!(!db::entities::list_outdated(&conn)
+                .unwrap()
+                .contains(&english));
+
+            // Edited content is extracted again
+            conn.execute(
+                "UPDATE journals SET content = 'Lunch with Sam in Berlin.' WHERE id = ?1",
+                rusqlite::params![english],
+            )
+            .unwrap();
+            assert!(refresh_jobs(&conn, spec, &english)
+                .unwrap()
+                .contains(&JobType::Entities));
+        }
+    }
+
+    /// Counts forward passes made through a fake embedder.
+    struct CountingEmbedder {
+        inner: FakeEmbedder,
+        passes: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl Embedder for CountingEmbedder {
+        fn spec(&self) -> &'static EmbeddingModelSpec {
+            self.inner.spec()
+        }
+
+        async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError> {
+            self.passes
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.inner.embed_batch(texts).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generate_embeddings_batches_entries() {
+        let (_dir, pool, _ml) = setup();
+        let spec = &crate::ml::models::MINILM_EMBEDDING;
+        let model = CountingEmbedder {
+            inner: FakeEmbedder::new(spec),
+            passes: Default::default(),
+        };
+        let first = add_entry(&pool, "A walk along the river", ml::language::ENGLISH);
+        let second = add_entry(&pool, "A quiet evening at home", ml::language::ENGLISH);
+        generate_embedding_with(&pool, &model, &first, None)
+            .await
+            .unwrap();
+        model.passes.store(0, std::sync::atomic::Ordering::SeqCst);
+
+        let third = add_entry(&pool, "Dinner with friends", ml::language::ENGLISH);
+        let ids = [first.as_str(), second.as_str(), "missing", third.as_str()];
+        let outcomes = generate_embeddings_with(&pool, &model, &ids).await.unwrap();
+        assert!(matches!(outcomes[0], Ok(false)));
+        assert!(matches!(outcomes[1], Ok(true)));
+        assert!(matches!(outcomes[2], Err(AppError::NotFound(_))));
+        assert!(matches!(outcomes[3], Ok(true)));
+        assert_eq!(model.passes.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let expected = model.inner.embed("A quiet evening at home").await.unwrap();
+        let conn = pool.get().unwrap();
+        let stored = db::vectors::get_embedding(&conn, spec, &second)
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored, expected);
+        assert!(db::vectors::has_embedding(&conn, spec, &third).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_analyze_emotions_batch_reports_each_entry() {
+        let (_dir, pool, ml) = setup();
+        ml.insert_sentiment_model(Arc::new(FakeEmotionClassifier))
+            .await;
+        let happy = add_entry(&pool, "So happy today", ml::language::ENGLISH);
+        let german = add_entry(&pool, "Heute war ich so happy", "deu");
+        let sad = add_entry(&pool, "Felt sad and lonely", ml::language::ENGLISH);
+
+        // The multilingual model can't be loaded, which fails only the German entry
+        let ids = [happy.as_str(), german.as_str(), "missing", sad.as_str()];
+        let outcomes = analyze_emotions_batch(&pool, &ml, &ids).await.unwrap();
+        assert_eq!(outcomes[0].as_ref().unwrap()[0].label, "joy");
+        assert!(outcomes[1].is_err());
+        assert!(matches!(outcomes[2], Err(AppError::NotFound(_))));
+        assert_eq!(outcomes[3].as_ref().unwrap()[0].label, "sadness");
+
+        let conn = pool.get().unwrap();
+        assert_eq!(db::emotions::get(&conn, &sad).unwrap()[0].0, "sadness");
+        assert!(db::emotions::get(&conn, &german).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+pub mod handlers;
+pub mod reindex;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Notify, Semaphore};
+
+use crate::db::jobs::{self, FailureOutcome, Job, JobType};
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::llm::LlmState;
+use crate::ml::MlState;
+
+/// How often the worker re-checks the queue for jobs whose backoff has elapsed.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Finished jobs are pruned after this many days.
+const PRUNE_AFTER_DAYS: i64 = 7;
+
+/// Job types that run local ML inference (CPU bound).
+const ML_JOB_TYPES: &[JobType] = &[JobType::Embedding, JobType::Emotions, JobType::Entities];
+/// Job types that call the local LLM (one request at a time is plenty for Ollama).
+const LLM_JOB_TYPES: &[JobType] = &[JobType::Title, JobType::Summary];
+
+/// Concurrency limits for the job worker.
+#[derive(Debug, Clone, Copy)]
+pub struct JobLimits {
+    pub max_ml_jobs: usize,
+    pub max_llm_jobs: usize,
+}
+
+impl Default for JobLimits {
+    fn default() -> Self {
+        Self {
+            max_ml_jobs: 1,
+            max_llm_jobs: 1,
+        }
+    }
+}
+
+/// Handle to the background job queue.
+/// The queue itself lives in the `jobs` table; this tracks in-flight work and wakes the worker.
+#[derive(Clone)]
+pub struct JobQueue {
+    notify: Arc<Notify>,
+    running: Arc<Mutex<HashMap<String, Arc<AtomicBool>>>>,
+    ml_slots: Arc<Semaphore>,
+    llm_slots: Arc<Semaphore>,
+}
+
+impl JobQueue {
+    pub fn new(limits: JobLimits) -> Self {
+        Self {
+            notify: Arc::new(Notify::new()),
+            running: Arc::new(Mutex::new(HashMap::new())),
+            ml_slots: Arc::new(Semaphore::new(limits.max_ml_jobs.max(1))),
+            llm_slots: Arc::new(Semaphore::new(limits.max_llm_jobs.max(1))),
+        }
+    }
+
+    /// Enqueue a job and wake the worker.
+    pub fn enqueue(
+        &self,
+        pool: &DbPool,
+        job_type: JobType,
+        entry_id: Option<&str>,
+        payload: Option<&str>,
+        priority: i64,
+    ) -> Result<Job, AppError> {
+        let job = {
+            let conn = pool.get()?;
+            jobs::enqueue(
+                &conn,
+                jobs::EnqueueParams {
+                    job_type,
+                    entry_id,
+                    payload,
+                    priority,
+                },
+            )?
+        };
+        self.notify.notify_one();
+        Ok(job)
+    }
+
+    /// Cancel a job. Running jobs are signalled and stop at their next checkpoint.
+    pub fn cancel(&self, pool: &DbPool, id: &str) -> Result<Job, AppError> {
+        let conn = pool.get()?;
+        jobs::cancel(&conn, id)?;
+        self.signal_cancel(id);
+        jobs::get(&conn, id)
+    }
+
+    /// Cancel all active jobs for an entry.
+    pub fn cancel_for_entry(&self, pool: &DbPool, entry_id: &str) -> Result<(), AppError> {
+        let ids = {
+            let conn = pool.get()?;
+            jobs::cancel_for_entry(&conn, entry_id)?
+        };
+        for id in ids {
+            self.signal_cancel(&id);
+        }
+        Ok(())
+    }
+
+    /// Run ML work outside the queue (e.g. bulk reindexing) while holding an ML slot,
+    /// so it never runs concurrently with queued ML jobs.
+    pub async fn run_ml<T>(&self, work: impl Future<Output = T>) -> T {
+        let result = {
+            let _permit = self.ml_slots.acquire().await;
+            work.await
+        };
+        // Give the worker a chance to claim queued jobs before the caller takes the slot again
+        self.notify.notify_one();
+        tokio::task::yield_now().await;
+        result
+    }
+
+    fn signal_cancel(&self, id: &str) {
+        if let Ok(running) = self.running.lock() {
+            if let Some(flag) = running.get(id) {
+                flag.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Requeue interrupted jobs and start the worker loop.
+    /// Called once at startup.
+    pub fn start(&self, app: AppHandle, pool: DbPool, ml: MlState, llm: LlmState) {
+        match pool.get() {
+            Ok(conn) => {
+                if let Err(e) = jobs::requeue_interrupted(&conn) {
+                    log::error!("Failed to requeue interrupted jobs: {}", e);
+                }
+                if let Err(e) = jobs::prune_finished(&conn, PRUNE_AFTER_DAYS) {
+                    log::warn!("Failed to prune finished jobs: {}", e);
+                }
+            }
+            Err(e) => log::error!("Job worker could not access database: {}", e),
+        }
+
+        let queue = self.clone();
+        tauri::async_runtime::spawn(async move {
+            queue.run(app, pool, ml, llm).await;
+        });
+    }
+
+    async fn run(&self, app: AppHandle, pool: DbPool, ml: MlState, llm: LlmState) {
+        log::info!("Job worker started");
+
+        loop {
+            let mut claimed = false;
+
+            for (slots, job_types) in [
+                (&self.ml_slots, ML_JOB_TYPES),
+                (&self.llm_slots, LLM_JOB_TYPES),
+            ] {
+                let Ok(permit) = Arc::clone(slots).try_acquire_owned() else {
+                    continue;
+                };
+
+                let job = match pool
+                    .get()
+                    .and_then(|conn| jobs::claim_next(&conn, job_types))
+                {
+                    Ok(Some(job)) => job,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        log::error!("Failed to claim job: {}", e);
+                        continue;
+                    }
+                };
+
+                claimed = true;
+                let ctx = Arc::new(self.context_for(&job, app.clone()));
+                let queue = self.clone();
+                let (pool, ml, llm) = (pool.clone(), ml.clone(), llm.clone());
+
+                tauri::async_runtime::spawn(async move {
+                    let outcome = {
+                        let (ctx, pool) = (Arc::clone(&ctx), pool.clone());
+                        run_isolated(async move { handlers::run(&ctx, &pool, &ml, &llm).await })
+                            .await
+                    };
+                    queue.finish(&ctx, &pool, outcome);
+                    drop(permit);
+                    queue.notify.notify_one();
+                });
+            }
+
+            if !claimed {
+                tokio::select! {
+                    _ = self.notify.notified() => {}
+                    _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                }
+            }
+        }
+    }
+
+    fn context_for(&self, job: &Job, app: AppHandle) -> JobContext {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut running) = self.running.lock() {
+            running.insert(job.id.clone(), Arc::clone(&cancelled));
+        }
+        JobContext {
+            job: job.clone(),
+            cancelled,
+            app,
+        }
+    }
+
+    /// Record the outcome of a job run and notify the frontend.
+    fn finish(&self, ctx: &JobContext, pool: &DbPool, outcome: Result<Option<String>, AppError>) {
+        if let Ok(mut running) = self.running.lock() {
+            running.remove(&ctx.job.id);
+        }
+
+        let job = &ctx.job;
+        let conn = match pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                log::error!("Failed to record outcome of job {}: {}", job.id, e);
+                return;
+            }
+        };
+
+        if ctx.is_cancelled() {
+            log::info!(
+                "Job cancelled: id={}, type={}",
+                job.id,
+                job.job_type.as_str()
+            );
+            let _ = ctx.app.emit("job-cancelled", JobEvent::from_job(job));
+            return;
+        }
+
+        match outcome {
+            Ok(result) => {
+                if let Err(e) = jobs::complete(&conn, &job.id, result.as_deref()) {
+                    log::error!("Failed to mark job {} completed: {}", job.id, e);
+                }
+                let _ = ctx.app.emit(
+                    "job-completed",
+                    JobCompletedEvent {
+                        job: JobEvent::from_job(job),
+                        result,
+                    },
+                );
+            }
+            Err(error) => {
+                let message = error.to_string();
+                let will_retry = match jobs::fail(&conn, &job.id, &message) {
+                    Ok(FailureOutcome::Retrying { run_after }) => {
+                        log::warn!(
+                            "Job {} ({}) failed, retrying after {}: {}",
+                            job.id,
+                            job.job_type.as_str(),
+                            run_after,
+                            message
+                        );
+                        true
+                    }
+                    Ok(FailureOutcome::Failed) => {
+                        log::error!(
+                            "Job {} ({}) failed permanently: {}",
+                            job.id,
+                            job.job_type.as_str(),
+                            message
+                        );
+                        false
+                    }
+                    Err(e) => {
+                        log::error!("Failed to record failure of job {}: {}", job.id, e);
+                        false
+                    }
+                };
+                let _ = ctx.app.emit(
+                    "job-failed",
+                    JobFailedEvent {
+                        job: JobEvent::from_job(job),
+                        error: message,
+                        will_retry,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Run a job handler on its own task, so a panic (e.g. a tensor shape error) fails
+/// the job like any other error instead of taking down the task that records it.
+async fn run_isolated(
+    handler: impl Future<Output = Result<Option<String>, AppError>> + Send + 'static,
+) -> Result<Option<String>, AppError> {
+    tokio::spawn(handler).await.unwrap_or_else(|e| {
+        Err(AppError::Io(std::io::Error::other(format!(
+            "Job handler panicked: {}",
+            e
+        ))))
+    })
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new(JobLimits::default())
+    }
+}
+
+/// Per-run context handed to job handlers for progress reporting and cancellation.
+pub struct JobContext {
+    pub job: Job,
+    cancelled: Arc<AtomicBool>,
+    app: AppHandle,
+}
+
+impl JobContext {
+    /// Whether the job was cancelled while running.
+    /// Handlers should check this between expensive steps.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Emit a progress event for this job (progress in 0.0..=1.0).
+    pub fn progress(&self, progress: f32, message: &str) {
+        let _ = self.app.emit(
+            "job-progress",
+            JobProgressEvent {
+                job: JobEvent::from_job(&self.job),
+                progress: progress.clamp(0.0, 1.0),
+                message: message.to_string(),
+            },
+        );
+    }
+}
+
+/// Identifying fields of a job included in every job event.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobEvent {
+    pub job_id: String,
+    pub job_type: JobType,
+    pub entry_id: Option<String>,
+}
+
+impl JobEvent {
+    fn from_job(job: &Job) -> Self {
+        Self {
+            job_id: job.id.clone(),
+            job_type: job.job_type,
+            entry_id: job.entry_id.clone(),
+        }
+    }
+}
+
+/// Event payload for job progress updates.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgressEvent {
+    #[serde(flatten)]
+    pub job: JobEvent,
+    pub progress: f32,
+    pub message: String,
+}
+
+/// Event payload for completed jobs.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobCompletedEvent {
+    #[serde(flatten)]
+    pub job: JobEvent,
+    pub result: Option<String>,
+}
+
+/// Event payload for failed job attempts.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobFailedEvent {
+    #[serde(flatten)]
+    pub job: JobEvent,
+    pub error: String,
+    pub will_retry: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_isolated_turns_panics_into_errors() {
+        let outcome = run_isolated(async { Ok(Some("done".to_string())) }).await;
+        assert_eq!(outcome.unwrap(), Some("done".to_string()));
+
+        let outcome = run_isolated(async {
+            let content = "é".repeat(2);
+            Ok(Some(content[..1].to_string()))
+        })
+        .await;
+        assert!(outcome.unwrap_err().to_string().contains("panicked"));
+    }
+}
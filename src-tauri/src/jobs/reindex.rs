@@ -0,0 +1,672 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+
+use crate::db::artifacts::{self, Artifact};
+use crate::db::jobs::{JobType, PRIORITY_LOW};
+use crate::db::{self, settings, DbPool};
+use crate::error::AppError;
+use crate::ml::{EmbeddingModelSpec, MlState};
+
+use super::{handlers, JobQueue};
+
+/// Entries embedded and analyzed together (one forward pass each), between progress events.
+const BATCH_SIZE: usize = 20;
+
+/// Derived data an entry is missing (or has from an older model version).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReindexItem {
+    pub entry_id: String,
+    pub embedding: bool,
+    pub emotions: bool,
+}
+
+/// Find entries with missing or outdated embeddings (for the given model) or emotions,
+/// newest first.
+pub fn plan(
+    conn: &Connection,
+    embedding_model: &EmbeddingModelSpec,
+) -> Result<Vec<ReindexItem>, AppError> {
+    let mut items: Vec<ReindexItem> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    let mut embedding_ids =
+        artifacts::list_outdated(conn, Artifact::Embedding, embedding_model.id)?;
+    if embedding_model.uses_default_tables() {
+        embedding_ids.extend(db::vectors::get_outdated_embeddings(conn)?);
+    }
+
+    for id in embedding_ids {
+        if !index.contains_key(&id) {
+            index.insert(id.clone(), items.len());
+            items.push(ReindexItem {
+                entry_id: id,
+                embedding: true,
+                emotions: false,
+            });
+        }
+    }
+
+    for id in db::emotions::list_outdated(conn)? {
+        match index.get(&id) {
+            Some(&i) => items[i].emotions = true,
+            None => {
+                index.insert(id.clone(), items.len());
+                items.push(ReindexItem {
+                    entry_id: id,
+                    embedding: false,
+                    emotions: true,
+                });
+            }
+        }
+    }
+
+    Ok(items)
+}
+
+/// Lifecycle of a reindex run.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReindexState {
+    #[default]
+    Idle,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+}
+
+/// Progress of the current (or last) reindex run.
+/// Emitted as the "reindex-progress" event.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReindexProgress {
+    pub state: ReindexState,
+    pub processed: usize,
+    pub total: usize,
+    pub failed: usize,
+    pub embeddings_generated: usize,
+    pub emotions_analyzed: usize,
+    /// Estimated seconds remaining, based on throughput while running.
+    pub eta_seconds: Option<u64>,
+    /// Embedding model being switched to, if this run builds its index
+    pub migrating_to: Option<&'static str>,
+    pub error: Option<String>,
+}
+
+/// Counts of entries that still need indexing, for the dashboard.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStatus {
+    pub total_entries: usize,
+    pub unindexed_entries: usize,
+    pub missing_embeddings: usize,
+    pub missing_emotions: usize,
+    pub reindex: ReindexProgress,
+}
+
+/// Handle to the bulk reindexer. At most one run is active at a time.
+#[derive(Clone, Default)]
+pub struct Reindexer {
+    progress: Arc<Mutex<ReindexProgress>>,
+    paused: Arc<AtomicBool>,
+    resume: Arc<Notify>,
+}
+
+impl Reindexer {
+    /// Snapshot of the current run's progress.
+    pub fn progress(&self) -> ReindexProgress {
+        self.progress.lock().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    /// Current index counts (for the active embedding model) plus the progress of any run.
+    pub fn status(
+        &self,
+        conn: &Connection,
+        embedding_model: &EmbeddingModelSpec,
+    ) -> Result<IndexStatus, AppError> {
+        let items = plan(conn, embedding_model)?;
+        let total_entries: i64 =
+            conn.query_row("SELECT COUNT(*) FROM journals", [], |row| row.get(0))?;
+
+        Ok(IndexStatus {
+            total_entries: total_entries as usize,
+            unindexed_entries: items.len(),
+            missing_embeddings: items.iter().filter(|i| i.embedding).count(),
+            missing_emotions: items.iter().filter(|i| i.emotions).count(),
+            reindex: self.progress(),
+        })
+    }
+
+    /// Whether no run is in progress (running or paused).
+    pub fn is_idle(&self) -> bool {
+        !matches!(
+            self.progress().state,
+            ReindexState::Running | ReindexState::Paused
+        )
+    }
+
+    /// Start a reindex run, or resume a paused one.
+    /// While an embedding model switch is pending, the run builds the new model's index
+    /// and switches over once every entry is embedded.
+    pub async fn start(
+        &self,
+        app: AppHandle,
+        pool: DbPool,
+        ml: MlState,
+        queue: JobQueue,
+    ) -> Result<ReindexProgress, AppError> {
+        match self.progress().state {
+            ReindexState::Running => return Ok(self.progress()),
+            ReindexState::Paused => return Ok(self.resume(&app)),
+            ReindexState::Idle | ReindexState::Completed | ReindexState::Failed => {}
+        }
+
+        if let Some(target) = ml.pending_embedding() {
+            let items = {
+                let conn = pool.get()?;
+                db::vectors::ensure_tables(&conn, target)?;
+                plan(&conn, target)?
+            };
+            let items: Vec<ReindexItem> = items
+                .into_iter()
+                .filter(|i| i.embedding)
+                .map(|i| ReindexItem {
+                    emotions: false,
+                    ..i
+                })
+                .collect();
+
+            log::info!("Building {} index for {} entries", target.id, items.len());
+            return Ok(self.spawn(app, pool, ml, queue, items, Some(target)));
+        }
+
+        let status = ml.models_ready().await;
+        if !status.embedding_downloaded && !status.sentiment_downloaded {
+            return Err(AppError::ModelNotReady(
+                "Download ML models before reindexing".to_string(),
+            ));
+        }
+
+        let mut items = {
+            let conn = pool.get()?;
+            plan(&conn, ml.active_embedding())?
+        };
+        // Only do the work the downloaded models can do
+        for item in &mut items {
+            item.embedding &= status.embedding_downloaded;
+            item.emotions &= status.sentiment_downloaded;
+        }
+        items.retain(|i| i.embedding || i.emotions);
+
+        log::info!("Starting reindex of {} entries", items.len());
+        Ok(self.spawn(app, pool, ml, queue, items, None))
+    }
+
+    fn spawn(
+        &self,
+        app: AppHandle,
+        pool: DbPool,
+        ml: MlState,
+        queue: JobQueue,
+        items: Vec<ReindexItem>,
+        migrating_to: Option<&'static EmbeddingModelSpec>,
+    ) -> ReindexProgress {
+        self.paused.store(false, Ordering::SeqCst);
+        let progress = self.update(&app, |p| {
+            *p = ReindexProgress {
+                state: ReindexState::Running,
+                total: items.len(),
+                migrating_to: migrating_to.map(|spec| spec.id),
+                ..Default::default()
+            };
+        });
+
+        let reindexer = self.clone();
+        tauri::async_runtime::spawn(async move {
+            reindexer
+                .run(app, pool, ml, queue, items, migrating_to)
+                .await;
+        });
+
+        progress
+    }
+
+    /// Switch to a different embedding model.
+    /// Search keeps using the current model's index until the new one is complete.
+    pub async fn switch_embedding_model(
+        &self,
+        app: AppHandle,
+        pool: DbPool,
+        ml: MlState,
+        queue: JobQueue,
+        target: &'static EmbeddingModelSpec,
+    ) -> Result<ReindexProgress, AppError> {
+        if !self.is_idle() {
+            return Err(AppError::InvalidInput(
+                "Wait for the current reindex to finish before switching models".to_string(),
+            ));
+        }
+
+        let abandoned = ml.pending_embedding();
+        {
+            let conn = pool.get()?;
+            // Discard a partially built index from an earlier switch
+            if let Some(previous) = abandoned.filter(|p| p.id != target.id) {
+                db::vectors::drop_model(&conn, previous)?;
+                artifacts::clear_model(&conn, previous.id)?;
+            }
+
+            if target.id == ml.active_embedding().id {
+                settings::delete(&conn, settings::PENDING_EMBEDDING_MODEL)?;
+                ml.set_pending_embedding(None);
+                log::info!("Cancelled embedding model switch");
+                return Ok(self.progress());
+            }
+
+            db::vectors::ensure_tables(&conn, target)?;
+            settings::set(&conn, settings::PENDING_EMBEDDING_MODEL, target.id)?;
+        }
+        if let Some(previous) = abandoned {
+            ml.unload_embedding_model(previous).await;
+        }
+        ml.set_pending_embedding(Some(target));
+        log::info!("Switching embedding model to {}", target.id);
+
+        self.start(app, pool, ml, queue).await
+    }
+
+    /// Pause after the batch currently being processed.
+    pub fn pause(&self, app: &AppHandle) -> ReindexProgress {
+        if self.progress().state != ReindexState::Running {
+            return self.progress();
+        }
+        self.paused.store(true, Ordering::SeqCst);
+        log::info!("Reindex paused");
+        self.update(app, |p| {
+            p.state = ReindexState::Paused;
+            p.eta_seconds = None;
+        })
+    }
+
+    /// Resume a paused run.
+    pub fn resume(&self, app: &AppHandle) -> ReindexProgress {
+        if self.progress().state != ReindexState::Paused {
+            return self.progress();
+        }
+        self.paused.store(false, Ordering::SeqCst);
+        self.resume.notify_one();
+        log::info!("Reindex resumed");
+        self.update(app, |p| p.state = ReindexState::Running)
+    }
+
+    async fn run(
+        &self,
+        app: AppHandle,
+        pool: DbPool,
+        ml: MlState,
+        queue: JobQueue,
+        items: Vec<ReindexItem>,
+        migrating_to: Option<&'static EmbeddingModelSpec>,
+    ) {
+        if let Some(target) = migrating_to {
+            if let Err(e) = ml.ensure_embedding_downloaded(target).await {
+                log::error!("Failed to download embedding model {}: {}", target.id, e);
+                self.update(&app, |p| {
+                    p.state = ReindexState::Failed;
+                    p.error = Some(e.to_string());
+                });
+                return;
+            }
+        }
+        let embedding_model = migrating_to.unwrap_or_else(|| ml.active_embedding());
+
+        // Time spent paused doesn't count towards throughput
+        let mut active = Duration::ZERO;
+
+        for batch in items.chunks(BATCH_SIZE) {
+            let mut embeddings = 0;
+            let mut emotions = 0;
+            let mut failed = 0;
+
+            while self.paused.load(Ordering::SeqCst) {
+                self.resume.notified().await;
+            }
+
+            let started = Instant::now();
+            let outcomes = queue
+                .run_ml(reindex_batch(&pool, &ml, embedding_model, batch))
+                .await;
+            for (item, outcome) in batch.iter().zip(outcomes) {
+                match outcome {
+                    Ok((embedded, analyzed)) => {
+                        embeddings += embedded as usize;
+                        emotions += analyzed as usize;
+                    }
+                    // Entry deleted since the plan was made
+                    Err(AppError::NotFound(_)) => {}
+                    Err(e) => {
+                        log::warn!("Failed to reindex entry {}: {}", item.entry_id, e);
+                        failed += 1;
+                    }
+                }
+            }
+            active += started.elapsed();
+
+            self.update(&app, |p| {
+                p.processed += batch.len();
+                p.failed += failed;
+                p.embeddings_generated += embeddings;
+                p.emotions_analyzed += emotions;
+                if p.state == ReindexState::Running {
+                    p.eta_seconds = estimate_eta(active, p.processed, p.total);
+                }
+            });
+        }
+
+        let mut error = None;
+        if let Some(target) = migrating_to {
+            if self.progress().failed > 0 {
+                error = Some(format!(
+                    "Some entries could not be embedded with {}; still using the previous model",
+                    target.id
+                ));
+            } else if let Err(e) = finish_migration(&pool, &ml, &queue, target).await {
+                log::error!("Failed to switch embedding model to {}: {}", target.id, e);
+                error = Some(e.to_string());
+            }
+        }
+
+        let progress = self.update(&app, |p| {
+            p.state = match error {
+                Some(_) => ReindexState::Failed,
+                None => ReindexState::Completed,
+            };
+            p.eta_seconds = Some(0);
+            p.error = error;
+        });
+        log::info!(
+            "Reindex completed: {} entries, {} embeddings, {} emotion analyses, {} failed",
+            progress.processed,
+            progress.embeddings_generated,
+            progress.emotions_analyzed,
+            progress.failed
+        );
+    }
+
+    /// Apply a change to the progress and emit it to the frontend.
+    fn update(&self, app: &AppHandle, f: impl FnOnce(&mut ReindexProgress)) -> ReindexProgress {
+        let progress = match self.progress.lock() {
+            Ok(mut p) => {
+                f(&mut p);
+                p.clone()
+            }
+            Err(_) => ReindexProgress::default(),
+        };
+        let _ = app.emit("reindex-progress", &progress);
+        progress
+    }
+}
+
+/// Bring a batch of entries' embeddings and/or emotions up to date. The batch's
+/// embeddings are generated in one batched forward pass, and likewise its emotions.
+/// Returns, per entry and in order, which of the two were regenerated.
+async fn reindex_batch(
+    pool: &DbPool,
+    ml: &MlState,
+    embedding_model: &'static EmbeddingModelSpec,
+    batch: &[ReindexItem],
+) -> Vec<Result<(bool, bool), AppError>> {
+    let mut outcomes: Vec<Result<(bool, bool), AppError>> =
+        batch.iter().map(|_| Ok((false, false))).collect();
+
+    let embed: Vec<usize> = (0..batch.len()).filter(|&i| batch[i].embedding).collect();
+    if !embed.is_empty() {
+        let ids: Vec<&str> = embed.iter().map(|&i| batch[i].entry_id.as_str()).collect();
+        let results = match ml.get_embedding_model_for(embedding_model).await {
+            Ok(model) => handlers::generate_embeddings_with(pool, model.as_ref(), &ids).await,
+            Err(e) => Err(e),
+        };
+        merge_outcomes(&mut outcomes, &embed, results, |o| o.0 = true);
+    }
+
+    // An entry whose embedding failed isn't analyzed either
+    let analyze: Vec<usize> = (0..batch.len())
+        .filter(|&i| batch[i].emotions && outcomes[i].is_ok())
+        .collect();
+    if !analyze.is_empty() {
+        let ids: Vec<&str> = analyze
+            .iter()
+            .map(|&i| batch[i].entry_id.as_str())
+            .collect();
+        let results = handlers::analyze_emotions_batch(pool, ml, &ids).await;
+        merge_outcomes(&mut outcomes, &analyze, results, |o| o.1 = true);
+    }
+
+    outcomes
+}
+
+/// Fold the results of one batched step, run for the entries at `indices`, into the
+/// per-entry outcomes. A failure of the whole step fails each of those entries.
+fn merge_outcomes<T>(
+    outcomes: &mut [Result<(bool, bool), AppError>],
+    indices: &[usize],
+    results: Result<Vec<Result<T, AppError>>, AppError>,
+    mark: impl Fn(&mut (bool, bool)),
+) {
+    match results {
+        Ok(results) => {
+            for (&i, result) in indices.iter().zip(results) {
+                match (result, &mut outcomes[i]) {
+                    (Ok(_), Ok(outcome)) => mark(outcome),
+                    (Err(e), outcome) => *outcome = Err(e),
+                    (Ok(_), Err(_)) => {}
+                }
+            }
+        }
+        Err(e) => {
+            let message = e.to_string();
+            for &i in indices {
+                outcomes[i] = Err(AppError::Ml(message.clone()));
+            }
+        }
+    }
+}
+
+/// Make a fully indexed model the active one and discard the previous model's vectors.
+async fn finish_migration(
+    pool: &DbPool,
+    ml: &MlState,
+    queue: &JobQueue,
+    target: &'static EmbeddingModelSpec,
+) -> Result<(), AppError> {
+    let previous = ml.active_embedding();
+    let catch_up = {
+        let conn = pool.get()?;
+        settings::set(&conn, settings::ACTIVE_EMBEDDING_MODEL, target.id)?;
+        settings::delete(&conn, settings::PENDING_EMBEDDING_MODEL)?;
+        ml.set_active_embedding(target);
+        ml.set_pending_embedding(None);
+
+        db::vectors::drop_model(&conn, previous)?;
+        artifacts::clear_model(&conn, previous.id)?;
+
+        // Entries created or edited while the index was being built
+        plan(&conn, target)?
+    };
+    ml.unload_embedding_model(previous).await;
+
+    for item in catch_up.iter().filter(|i| i.embedding) {
+        queue.enqueue(
+            pool,
+            JobType::Embedding,
+            Some(&item.entry_id),
+            None,
+            PRIORITY_LOW,
+        )?;
+    }
+
+    log::info!(
+        "Switched embedding model from {} to {}",
+        previous.id,
+        target.id
+    );
+    Ok(())
+}
+
+/// Restore the embedding model selection saved in settings.
+/// Called once at startup, before any embedding work runs.
+pub fn load_embedding_settings(conn: &Connection, ml: &MlState) -> Result<(), AppError> {
+    let lookup = |key: &str| -> Result<Option<&'static EmbeddingModelSpec>, AppError> {
+        Ok(settings::get(conn, key)?.and_then(|id| {
+            let spec = crate::ml::models::embedding_model(&id);
+            if spec.is_none() {
+                log::warn!("Ignoring unknown embedding model in settings: {}", id);
+            }
+            spec
+        }))
+    };
+
+    if let Some(active) = lookup(settings::ACTIVE_EMBEDDING_MODEL)? {
+        db::vectors::ensure_tables(conn, active)?;
+        ml.set_active_embedding(active);
+    }
+    if let Some(pending) = lookup(settings::PENDING_EMBEDDING_MODEL)? {
+        db::vectors::ensure_tables(conn, pending)?;
+        ml.set_pending_embedding(Some(pending));
+    }
+    Ok(())
+}
+
+/// Estimate remaining seconds from average time per processed entry.
+fn estimate_eta(active: Duration, processed: usize, total: usize) -> Option<u64> {
+    if processed == 0 {
+        return None;
+    }
+    let remaining = total.saturating_sub(processed) as f64;
+    let per_entry = active.as_secs_f64() / processed as f64;
+    Some((per_entry * remaining).ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::run_migrations;
+    use crate::ml::models::{BGE_SMALL_EMBEDDING, MINILM_EMBEDDING};
+
+    const MODEL: &EmbeddingModelSpec = &MINILM_EMBEDDING;
+
+    fn setup_test_db() -> Connection {
+        #[allow(clippy::missing_transmute_annotations)]
+        unsafe {
+            rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(
+                sqlite_vec::sqlite3_vec_init as *const (),
+            )));
+        }
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO journals (id, content, created_at) VALUES ('old', 'Old entry', '2024-01-01T00:00:00Z');
+             INSERT INTO journals (id, content, created_at) VALUES ('new', 'New entry', '2024-06-01T00:00:00Z');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_plan_finds_missing_and_outdated() {
+        let conn = setup_test_db();
+
+        let items = plan(&conn, MODEL).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].entry_id, "new");
+        assert!(items.iter().all(|i| i.embedding && i.emotions));
+
+        let hash = artifacts::content_hash("Old entry");
+        artifacts::record(&conn, "old", Artifact::Embedding, &hash, MODEL.id).unwrap();
+        artifacts::record(
+            &conn,
+            "old",
+            Artifact::Emotions,
+            &hash,
+            "older-emotion-model",
+        )
+        .unwrap();
+
+        let items = plan(&conn, MODEL).unwrap();
+        let old = items.iter().find(|i| i.entry_id == "old").unwrap();
+        assert!(!old.embedding);
+        assert!(old.emotions);
+    }
+
+    #[test]
+    fn test_plan_includes_embeddings_with_outdated_metadata() {
+        let conn = setup_test_db();
+        let hash = artifacts::content_hash("Old entry");
+        for artifact in [Artifact::Embedding, Artifact::Emotions] {
+            artifacts::record(&conn, "old", artifact, &hash, "current").unwrap();
+        }
+        db::vectors::store_embedding(&conn, MODEL, "old", &[0.1; 384]).unwrap();
+        conn.execute(
+            "UPDATE embedding_metadata SET model_version = 'legacy-model' WHERE journal_id = 'old'",
+            [],
+        )
+        .unwrap();
+
+        let items = plan(&conn, MODEL).unwrap();
+        let old = items.iter().find(|i| i.entry_id == "old").unwrap();
+        assert!(old.embedding);
+    }
+
+    #[test]
+    fn test_plan_is_per_embedding_model() {
+        let conn = setup_test_db();
+        let hash = artifacts::content_hash("Old entry");
+        artifacts::record(&conn, "old", Artifact::Embedding, &hash, MODEL.id).unwrap();
+
+        let bge = &BGE_SMALL_EMBEDDING;
+        let embedded = |model| {
+            plan(&conn, model)
+                .unwrap()
+                .into_iter()
+                .filter(|i| i.embedding)
+                .count()
+        };
+        assert_eq!(embedded(MODEL), 1);
+        assert_eq!(embedded(bge), 2);
+    }
+
+    #[test]
+    fn test_load_embedding_settings() {
+        let conn = setup_test_db();
+        let ml = MlState::new(std::env::temp_dir());
+
+        settings::set(&conn, settings::ACTIVE_EMBEDDING_MODEL, "no-such-model").unwrap();
+        settings::set(
+            &conn,
+            settings::PENDING_EMBEDDING_MODEL,
+            BGE_SMALL_EMBEDDING.id,
+        )
+        .unwrap();
+        load_embedding_settings(&conn, &ml).unwrap();
+
+        assert_eq!(ml.active_embedding().id, MODEL.id);
+        assert_eq!(
+            ml.pending_embedding().map(|m| m.id),
+            Some(BGE_SMALL_EMBEDDING.id)
+        );
+        // The pending model's tables are ready for the background migration
+        db::vectors::store_embedding(&conn, &BGE_SMALL_EMBEDDING, "old", &[0.1; 384]).unwrap();
+    }
+
+    #[test]
+    fn test_estimate_eta() {
+        assert_eq!(estimate_eta(Duration::from_secs(10), 0, 100), None);
+        assert_eq!(estimate_eta(Duration::from_secs(10), 10, 30), Some(20));
+        assert_eq!(estimate_eta(Duration::from_secs(10), 30, 30), Some(0));
+    }
+}
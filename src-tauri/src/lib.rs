@@ -1,23 +1,40 @@
 mod db;
 mod error;
+mod jobs;
 pub mod llm;
 pub mod ml;
 
 use db::chat::{ChatMessage, CreateMessageParams};
+use db::duplicates::DuplicatePair;
+use db::emotions::{AffectPoint, EmotionCorrection, EmotionSeries};
+use db::entities::{EntityDetail, EntitySummary};
 use db::images::{EntryImage, InsertImageParams};
+use db::jobs::{Job, JobType};
 use db::journals::{
-    CreateEntryResponse, DayEmotions, DeleteResponse, Journal, JournalStats, StreakInfo,
+    CreateEntryResponse, DayEmotions, DeleteResponse, EntryType, Journal, JournalStats,
+    LanguageCount, StreakInfo,
 };
-use db::search::HybridSearchResult;
+use db::projection::MapPoint;
+use db::search::{HybridSearchResult, RelatedEntry, SearchFilter};
 use db::templates::{CreateTemplateResponse, DeleteTemplateResponse, Template};
+use db::themes::Theme;
+use db::vectors::VectorStorageStatus;
 use db::DbPool;
 use error::AppError;
 use futures::StreamExt;
+use jobs::reindex::{IndexStatus, ReindexProgress, Reindexer};
+use jobs::JobQueue;
 use llm::safety::SafetyResult;
-use llm::{ChatChunkEvent, ChatErrorEvent, LlmState, OllamaStatus};
-use ml::sentiment::EmotionPrediction;
-use ml::{MlState, ModelStatus};
-use serde::Serialize;
+use llm::{ChatChunkEvent, ChatErrorEvent, LlmState, OllamaStatus, SummaryResponse};
+use ml::anomaly::MoodInsight;
+use ml::calibration::{Calibration, CorrectionAction};
+use ml::import::ImportReport;
+use ml::ner::EntityKind;
+use ml::quantize::VectorStorage;
+use ml::rerank::{RerankSettings, RERANK_CANDIDATES};
+use ml::sentiment::{Aggregation, ChunkEmotions, EmotionPrediction, SentenceEmotions};
+use ml::series::{Granularity, ScoreAggregation, Smoothing};
+use ml::{EmbeddingModelOption, MlState, ModelRepair, ModelStatus, Precision};
 use tauri::{AppHandle, Emitter, Manager, State};
 
 // Re-export for external use
@@ -25,14 +42,6 @@ pub use db::journals;
 pub use db::templates;
 pub use error::AppError as Error;
 
-/// Response from generate_summary command.
-#[derive(Debug, Serialize)]
-pub struct SummaryResponse {
-    pub summary: String,
-    pub period: String,
-    pub entry_count: usize,
-}
-
 /// Create a new journal entry.
 #[tauri::command]
 fn create_entry(
@@ -59,15 +68,20 @@ fn list_entries(
     limit: Option<i64>,
     offset: Option<i64>,
     archived: Option<bool>,
+    language: Option<String>,
 ) -> Result<Vec<Journal>, AppError> {
     let conn = pool.get()?;
-    journals::list(&conn, limit, offset, archived)
+    journals::list(&conn, limit, offset, archived, language.as_deref())
 }
 
 /// Update a journal entry's content, title, entry type, or creation date.
+/// Derived data computed from the old content is refreshed in the background.
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 fn update_entry(
     pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    queue: State<'_, JobQueue>,
     id: String,
     content: Option<String>,
     title: Option<String>,
@@ -79,20 +93,41 @@ fn update_entry(
         id,
         content
             .as_ref()
-            .map(|c| format!("{}...", &c[..c.len().min(20)])),
+            .map(|c| format!("{}...", c.chars().take(20).collect::<String>())),
         title,
         entry_type,
         created_at
     );
-    let conn = pool.get()?;
-    journals::update(
-        &conn,
-        &id,
-        content.as_deref(),
-        title.as_deref(),
-        entry_type.as_deref(),
-        created_at.as_deref(),
-    )
+    let (entry, refresh) = {
+        let conn = pool.get()?;
+        let entry = journals::update(
+            &conn,
+            &id,
+            content.as_deref(),
+            title.as_deref(),
+            entry_type.as_deref(),
+            created_at.as_deref(),
+        )?;
+        let refresh = if content.is_some() {
+            jobs::handlers::refresh_jobs(&conn, ml.active_embedding(), &id)?
+        } else {
+            Vec::new()
+        };
+        (entry, refresh)
+    };
+
+    // Recompute embeddings/emotions derived from the old text in the background
+    for job_type in refresh {
+        queue.enqueue(
+            pool.inner(),
+            job_type,
+            Some(&id),
+            None,
+            db::jobs::PRIORITY_NORMAL,
+        )?;
+    }
+
+    Ok(entry)
 }
 
 /// Delete a journal entry and its associated images.
@@ -100,8 +135,12 @@ fn update_entry(
 fn delete_entry(
     app: AppHandle,
     pool: State<'_, DbPool>,
+    queue: State<'_, JobQueue>,
     id: String,
 ) -> Result<DeleteResponse, AppError> {
+    // Stop any background work for the entry before it disappears
+    queue.cancel_for_entry(pool.inner(), &id)?;
+
     let conn = pool.get()?;
 
     // Get images before deletion (CASCADE will remove DB records)
@@ -147,22 +186,83 @@ fn unarchive_entry(pool: State<'_, DbPool>, id: String) -> Result<Journal, AppEr
     journals::unarchive(&conn, &id)
 }
 
+/// Find pairs of near-identical entries (e.g. from imports or copy-paste), with how
+/// much text they share. Entries must be embedded by the active model to be compared.
+#[tauri::command]
+fn find_duplicate_entries(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    limit: Option<usize>,
+) -> Result<Vec<DuplicatePair>, AppError> {
+    let conn = pool.get()?;
+    db::duplicates::find_duplicates(&conn, ml.active_embedding(), limit.unwrap_or(50))
+}
+
+/// Merge one entry into another: content, images, chat messages and emotions move
+/// to `keep_id`, and `merge_id` is archived rather than deleted.
+#[tauri::command]
+fn merge_entries(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    queue: State<'_, JobQueue>,
+    keep_id: String,
+    merge_id: String,
+) -> Result<Journal, AppError> {
+    queue.cancel_for_entry(pool.inner(), &merge_id)?;
+
+    let (entry, refresh) = {
+        let conn = pool.get()?;
+        let entry = db::duplicates::merge(&conn, &keep_id, &merge_id)?;
+        let refresh = jobs::handlers::refresh_jobs(&conn, ml.active_embedding(), &keep_id)?;
+        (entry, refresh)
+    };
+
+    // Recompute embeddings/emotions if the merged text changed
+    for job_type in refresh {
+        queue.enqueue(
+            pool.inner(),
+            job_type,
+            Some(&keep_id),
+            None,
+            db::jobs::PRIORITY_NORMAL,
+        )?;
+    }
+
+    Ok(entry)
+}
+
 /// Search journal entries using full-text search.
 #[tauri::command]
 fn search_entries(
     pool: State<'_, DbPool>,
     query: String,
     include_archived: Option<bool>,
+    language: Option<String>,
 ) -> Result<Vec<Journal>, AppError> {
     let conn = pool.get()?;
-    journals::search(&conn, &query, include_archived.unwrap_or(false))
+    journals::search(
+        &conn,
+        &query,
+        include_archived.unwrap_or(false),
+        language.as_deref(),
+    )
 }
 
-/// Get journal statistics for the dashboard.
+/// Get journal statistics for the dashboard, optionally for one language.
 #[tauri::command]
-fn get_journal_stats(pool: State<'_, DbPool>) -> Result<JournalStats, AppError> {
+fn get_journal_stats(
+    pool: State<'_, DbPool>,
+    language: Option<String>,
+) -> Result<JournalStats, AppError> {
+    let conn = pool.get()?;
+    journals::get_stats(&conn, language.as_deref())
+}
+
+/// List the languages entries are written in, with entry counts.
+#[tauri::command]
+fn list_entry_languages(pool: State<'_, DbPool>) -> Result<Vec<LanguageCount>, AppError> {
     let conn = pool.get()?;
-    journals::get_stats(&conn)
+    journals::list_languages(&conn)
 }
 
 /// Get extended streak information for the dashboard.
@@ -192,6 +292,228 @@ fn get_emotion_trends(
         .collect())
 }
 
+/// Mood over time: valence/arousal and Ekman emotion scores averaged per "day" or "week".
+#[tauri::command]
+fn get_affect_series(
+    pool: State<'_, DbPool>,
+    start_date: String,
+    end_date: String,
+    granularity: String,
+) -> Result<Vec<AffectPoint>, AppError> {
+    let granularity = Granularity::parse(&granularity)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown granularity: {}", granularity)))?;
+    let conn = pool.get()?;
+    db::emotions::get_affect_series(&conn, &start_date, &end_date, granularity)
+}
+
+/// Per-label emotion scores per "day" or "week", combined by "mean" (default), "max"
+/// or "sum" and optionally smoothed. Periods without analyzed entries are null.
+#[tauri::command]
+fn get_emotion_series(
+    pool: State<'_, DbPool>,
+    start_date: String,
+    end_date: String,
+    labels: Option<Vec<String>>,
+    granularity: Option<String>,
+    aggregation: Option<String>,
+    smoothing: Option<Smoothing>,
+) -> Result<EmotionSeries, AppError> {
+    let granularity = match granularity.as_deref() {
+        None => Granularity::Day,
+        Some(value) => Granularity::parse(value)
+            .ok_or_else(|| AppError::InvalidInput(format!("Unknown granularity: {}", value)))?,
+    };
+    let aggregation = match aggregation.as_deref() {
+        None => ScoreAggregation::default(),
+        Some(value) => ScoreAggregation::parse(value)
+            .ok_or_else(|| AppError::InvalidInput(format!("Unknown aggregation: {}", value)))?,
+    };
+    if let Some(smoothing) = &smoothing {
+        smoothing.validate()?;
+    }
+
+    let conn = pool.get()?;
+    db::emotions::get_emotion_series(
+        &conn,
+        &start_date,
+        &end_date,
+        &labels.unwrap_or_default(),
+        granularity,
+        aggregation,
+        smoothing,
+    )
+}
+
+/// Detect stretches where mood is markedly worse than the user's own baseline in the
+/// two weeks up to `as_of` (YYYY-MM-DD, default today), with the entries behind each.
+#[tauri::command]
+fn get_mood_insights(
+    pool: State<'_, DbPool>,
+    as_of: Option<String>,
+) -> Result<Vec<MoodInsight>, AppError> {
+    let as_of = match as_of {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|_| AppError::InvalidInput(format!("Invalid date: {}", date)))?,
+        None => chrono::Utc::now().date_naive(),
+    };
+    let conn = pool.get()?;
+    db::emotions::get_mood_insights(&conn, as_of)
+}
+
+/// Get recurring themes across the journal, each with its member entries and
+/// monthly frequency. Themes are cached and updated incrementally as entries are
+/// added or edited; `refresh` re-clusters from scratch. With `refine_labels`, themes
+/// still labelled by their top terms are renamed by the local LLM when it's available.
+#[tauri::command]
+async fn get_themes(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    llm: State<'_, LlmState>,
+    refresh: Option<bool>,
+    refine_labels: Option<bool>,
+) -> Result<Vec<Theme>, AppError> {
+    let model = ml.active_embedding();
+    let themes = {
+        let conn = pool.get()?;
+        db::themes::refresh(&conn, model, refresh.unwrap_or(false))?;
+        db::themes::list(&conn, model)?
+    };
+
+    if !refine_labels.unwrap_or(false) || !llm.check_status().await.model_available {
+        return Ok(themes);
+    }
+
+    for theme in themes.iter().filter(|t| !t.label_refined) {
+        let excerpts = {
+            let conn = pool.get()?;
+            theme
+                .entries
+                .iter()
+                .take(ml::themes::LABEL_EXCERPTS)
+                .filter_map(|e| journals::get(&conn, &e.journal_id).ok())
+                .map(|j| j.content.chars().take(ml::themes::EXCERPT_CHARS).collect())
+                .collect::<Vec<String>>()
+        };
+        match llm
+            .ollama
+            .generate_theme_label(&theme.terms, &excerpts)
+            .await
+        {
+            Ok(label) if !label.is_empty() => {
+                let conn = pool.get()?;
+                db::themes::set_label(&conn, theme.id, &label)?;
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to refine label of theme {}: {}", theme.id, e),
+        }
+    }
+
+    let conn = pool.get()?;
+    db::themes::list(&conn, model)
+}
+
+/// Get the 2D semantic map of entries: each embedded entry's position (similar
+/// entries close together), date, dominant emotion and theme. The layout is cached;
+/// new and edited entries are placed next to their neighbours, and `refresh` lays out
+/// everything again.
+#[tauri::command]
+async fn get_entry_map(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    refresh: Option<bool>,
+) -> Result<Vec<MapPoint>, AppError> {
+    let model = ml.active_embedding();
+    let plan = {
+        let conn = pool.get()?;
+        db::themes::refresh(&conn, model, false)?;
+        db::projection::plan(&conn, model, refresh.unwrap_or(false))?
+    };
+    // A full layout can take a few seconds; don't hold the database meanwhile
+    let update = tokio::task::spawn_blocking(move || plan.compute())
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
+
+    let conn = pool.get()?;
+    db::projection::save(&conn, model, &update)?;
+    db::projection::points(&conn, model)
+}
+
+/// Get entries similar to an entry ("more like this"), with similarity scores and the
+/// best-matching passage of each. Uses the stored vectors of the active embedding
+/// model; returns nothing until the entry has been embedded.
+#[tauri::command]
+fn get_related_entries(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    entry_id: String,
+    limit: Option<usize>,
+) -> Result<Vec<RelatedEntry>, AppError> {
+    let limit = limit.unwrap_or(5).min(50);
+    let conn = pool.get()?;
+    journals::get(&conn, &entry_id)?;
+    db::search::related_entries(&conn, ml.active_embedding(), &entry_id, limit)
+}
+
+/// List people mentioned in non-archived entries, most mentioned first.
+#[tauri::command]
+fn list_people(
+    pool: State<'_, DbPool>,
+    limit: Option<usize>,
+) -> Result<Vec<EntitySummary>, AppError> {
+    let conn = pool.get()?;
+    db::entities::list(&conn, EntityKind::Person, limit.unwrap_or(100))
+}
+
+/// List places mentioned in non-archived entries, most mentioned first.
+#[tauri::command]
+fn list_places(
+    pool: State<'_, DbPool>,
+    limit: Option<usize>,
+) -> Result<Vec<EntitySummary>, AppError> {
+    let conn = pool.get()?;
+    db::entities::list(&conn, EntityKind::Place, limit.unwrap_or(100))
+}
+
+/// Get a person or place with the entries mentioning it, a monthly timeline of
+/// mentions, and the emotions of those entries compared to the journal overall.
+#[tauri::command]
+fn get_entity(pool: State<'_, DbPool>, id: i64) -> Result<EntityDetail, AppError> {
+    let conn = pool.get()?;
+    db::entities::get(&conn, id)
+}
+
+/// Merge two people (or two places) found under different names; later mentions
+/// by either name count for `keep_id`.
+#[tauri::command]
+fn merge_entities(
+    pool: State<'_, DbPool>,
+    keep_id: i64,
+    merge_id: i64,
+) -> Result<EntityDetail, AppError> {
+    let conn = pool.get()?;
+    db::entities::merge(&conn, keep_id, merge_id)
+}
+
+/// Queue entity extraction for every entry that hasn't been indexed yet (or was
+/// edited since). Returns the number of entries queued.
+#[tauri::command]
+fn index_entities(pool: State<'_, DbPool>, queue: State<'_, JobQueue>) -> Result<usize, AppError> {
+    let ids = {
+        let conn = pool.get()?;
+        db::entities::list_outdated(&conn)?
+    };
+    for id in &ids {
+        queue.enqueue(
+            pool.inner(),
+            JobType::Entities,
+            Some(id),
+            None,
+            db::jobs::PRIORITY_LOW,
+        )?;
+    }
+    Ok(ids.len())
+}
+
 /// Get entries from the same date in previous years ("On This Day").
 #[tauri::command]
 fn get_on_this_day(pool: State<'_, DbPool>) -> Result<Vec<Journal>, AppError> {
@@ -483,27 +805,59 @@ async fn get_model_status(ml: State<'_, MlState>) -> Result<ModelStatus, AppErro
 }
 
 /// Initialize ML models (download if needed, load into memory).
+/// Emits `model-download-progress` events with per-file byte progress.
 #[tauri::command]
-async fn initialize_models(ml: State<'_, MlState>) -> Result<(), AppError> {
-    ml.initialize(|_progress| {
-        // Progress can be sent via events if needed
+async fn initialize_models(app: AppHandle, ml: State<'_, MlState>) -> Result<(), AppError> {
+    ml.initialize(|progress| {
+        let _ = app.emit("model-download-progress", progress);
+    })
+    .await
+}
+
+/// Install models from a local folder or `.tar`/`.tar.gz` archive instead of downloading.
+/// Each model is validated (checksums, config and tensor shapes) before it replaces
+/// the installed copy.
+#[tauri::command]
+async fn import_models(ml: State<'_, MlState>, path: String) -> Result<ImportReport, AppError> {
+    ml.import_models(std::path::PathBuf::from(path)).await
+}
+
+/// Verify downloaded model files and re-fetch any that are missing or corrupt.
+/// Emits `model-download-progress` events while re-downloading.
+#[tauri::command]
+async fn repair_models(
+    app: AppHandle,
+    ml: State<'_, MlState>,
+) -> Result<Vec<ModelRepair>, AppError> {
+    ml.repair_models(|progress| {
+        let _ = app.emit("model-download-progress", progress);
     })
     .await
 }
 
 /// Get emotions for a journal entry.
-/// If not cached, generates and stores them.
+/// If not cached or the entry changed since analysis, generates and stores them.
 #[tauri::command]
 async fn get_entry_emotions(
     pool: State<'_, DbPool>,
     ml: State<'_, MlState>,
     id: String,
 ) -> Result<Vec<EmotionPrediction>, AppError> {
-    // Check if emotions are already cached
+    // Use cached emotions if they were computed from the current content
     {
         let conn = pool.get()?;
+        let entry = journals::get(&conn, &id)?;
+        let hash = db::artifacts::content_hash(&entry.content);
         let cached = db::emotions::get(&conn, &id)?;
-        if !cached.is_empty() {
+        if !cached.is_empty()
+            && db::artifacts::is_fresh(
+                &conn,
+                &id,
+                db::artifacts::Artifact::Emotions,
+                &hash,
+                db::emotions::model_version_for(entry.language.as_deref()),
+            )?
+        {
             return Ok(cached
                 .into_iter()
                 .map(|(label, score)| EmotionPrediction { label, score })
@@ -511,152 +865,458 @@ async fn get_entry_emotions(
         }
     }
 
-    // Get the journal content
-    let content = {
+    // Generate and cache emotions using ML model
+    jobs::handlers::analyze_emotions(pool.inner(), ml.inner(), &id).await
+}
+
+/// Get the emotions carried by each sentence of an entry, with character offsets.
+/// Cached until the entry's content changes.
+#[tauri::command]
+async fn get_sentence_emotions(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    id: String,
+) -> Result<Vec<SentenceEmotions>, AppError> {
+    {
         let conn = pool.get()?;
         let entry = journals::get(&conn, &id)?;
-        entry.content
-    };
+        if db::artifacts::is_fresh(
+            &conn,
+            &id,
+            db::artifacts::Artifact::SentenceEmotions,
+            &db::artifacts::content_hash(&entry.content),
+            db::emotions::model_version_for(entry.language.as_deref()),
+        )? {
+            return db::emotions::get_sentences(&conn, &id);
+        }
+    }
 
-    // Generate emotions using ML model
-    let model = ml.get_sentiment_model().await?;
-    let predictions = model.predict(&content, 0.1, 5)?;
+    jobs::handlers::analyze_sentence_emotions(pool.inner(), ml.inner(), &id).await
+}
+
+/// Get the user's corrections to an entry's detected emotions.
+#[tauri::command]
+fn get_emotion_corrections(
+    pool: State<'_, DbPool>,
+    id: String,
+) -> Result<Vec<EmotionCorrection>, AppError> {
+    let conn = pool.get()?;
+    db::emotions::get_corrections(&conn, &id)
+}
+
+/// Confirm, remove or add an emotion label on an entry ("confirm" | "remove" | "add").
+/// Corrections are kept apart from model output; trends prefer them, and future
+/// analyses are calibrated by them.
+#[tauri::command]
+fn correct_entry_emotion(
+    pool: State<'_, DbPool>,
+    id: String,
+    label: String,
+    action: String,
+) -> Result<(), AppError> {
+    let action = CorrectionAction::parse(&action)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown correction: {}", action)))?;
+    if !ml::sentiment::EMOTION_LABELS.contains(&label.as_str()) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown emotion label: {}",
+            label
+        )));
+    }
+    let conn = pool.get()?;
+    journals::get(&conn, &id)?;
+    db::emotions::set_correction(&conn, &id, &label, action)
+}
+
+/// Drop the correction for a label, going back to the model's output.
+#[tauri::command]
+fn clear_entry_emotion_correction(
+    pool: State<'_, DbPool>,
+    id: String,
+    label: String,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    db::emotions::clear_correction(&conn, &id, &label)
+}
+
+/// Get the per-label score adjustments learned from corrections.
+#[tauri::command]
+fn get_emotion_calibration(pool: State<'_, DbPool>) -> Result<Calibration, AppError> {
+    let conn = pool.get()?;
+    db::emotions::get_calibration(&conn)
+}
+
+/// Get the per-window emotions stored for a long entry (empty for short entries).
+/// Shows where in the entry each emotion peaks; populated by emotion analysis.
+#[tauri::command]
+fn get_entry_emotion_chunks(
+    pool: State<'_, DbPool>,
+    id: String,
+) -> Result<Vec<ChunkEmotions>, AppError> {
+    let conn = pool.get()?;
+    db::emotions::get_chunks(&conn, &id)
+}
 
-    // Cache the results
+/// Get how per-window emotion scores of long entries are combined.
+#[tauri::command]
+fn get_emotion_aggregation(pool: State<'_, DbPool>) -> Result<Aggregation, AppError> {
+    let conn = pool.get()?;
+    Ok(db::settings::get(&conn, db::settings::EMOTION_AGGREGATION)?
+        .as_deref()
+        .and_then(Aggregation::parse)
+        .unwrap_or_default())
+}
+
+/// Set how per-window emotion scores of long entries are combined ("max" or "mean").
+/// Applies to entries analyzed from now on.
+#[tauri::command]
+fn set_emotion_aggregation(pool: State<'_, DbPool>, aggregation: String) -> Result<(), AppError> {
+    let aggregation = Aggregation::parse(&aggregation).ok_or_else(|| {
+        AppError::InvalidInput(format!("Unknown emotion aggregation: {}", aggregation))
+    })?;
+    let conn = pool.get()?;
+    db::settings::set(
+        &conn,
+        db::settings::EMOTION_AGGREGATION,
+        aggregation.as_str(),
+    )
+}
+
+/// Get the precision models are loaded in.
+#[tauri::command]
+fn get_model_precision(ml: State<'_, MlState>) -> Precision {
+    ml.precision()
+}
+
+/// Set the precision models are loaded in ("f32", "f16" or "bf16").
+/// Loaded models are dropped and reload at the new precision on next use.
+#[tauri::command]
+async fn set_model_precision(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    precision: String,
+) -> Result<(), AppError> {
+    let parsed = Precision::parse(&precision)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown precision: {}", precision)))?;
     {
         let conn = pool.get()?;
-        for pred in &predictions {
-            db::emotions::store(&conn, &id, &pred.label, pred.score)?;
-        }
+        db::settings::set(&conn, db::settings::MODEL_PRECISION, parsed.as_str())?;
     }
+    if parsed != ml.precision() {
+        ml.set_precision(parsed);
+        ml.unload_models().await;
+    }
+    Ok(())
+}
 
-    Ok(predictions)
+/// Get how long a model may sit idle before it is unloaded, in seconds (0 = never).
+#[tauri::command]
+fn get_model_idle_timeout(ml: State<'_, MlState>) -> u64 {
+    ml.idle_timeout().map_or(0, |timeout| timeout.as_secs())
 }
 
-/// Perform hybrid search combining FTS5 and vector similarity.
+/// Set how long a model may sit idle before it is unloaded, in seconds.
+/// 0 keeps models loaded for the whole session.
 #[tauri::command]
+fn set_model_idle_timeout(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    seconds: u64,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    db::settings::set(
+        &conn,
+        db::settings::MODEL_IDLE_TIMEOUT_SECS,
+        &seconds.to_string(),
+    )?;
+    ml.set_idle_timeout(idle_timeout_from_secs(seconds));
+    Ok(())
+}
+
+fn idle_timeout_from_secs(seconds: u64) -> Option<std::time::Duration> {
+    (seconds > 0).then(|| std::time::Duration::from_secs(seconds))
+}
+
+/// Perform hybrid search combining FTS5 and vector similarity, optionally restricted
+/// to a language, entry type and date range (YYYY-MM-DD, inclusive). With reranking
+/// enabled, the top fused candidates are reordered by the cross-encoder.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn hybrid_search(
     pool: State<'_, DbPool>,
     ml: State<'_, MlState>,
     query: String,
     limit: Option<usize>,
     include_archived: Option<bool>,
+    language: Option<String>,
+    entry_type: Option<EntryType>,
+    start_date: Option<String>,
+    end_date: Option<String>,
 ) -> Result<Vec<HybridSearchResult>, AppError> {
     let limit = limit.unwrap_or(20);
-    let include_archived = include_archived.unwrap_or(false);
+    let parse_date = |date: Option<String>| {
+        date.map(|date| {
+            chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                .map_err(|_| AppError::InvalidInput(format!("Invalid date: {}", date)))
+        })
+        .transpose()
+    };
+    let filter = SearchFilter {
+        include_archived: include_archived.unwrap_or(false),
+        language,
+        entry_type,
+        start_date: parse_date(start_date)?,
+        end_date: parse_date(end_date)?,
+    };
 
     // Try to get embedding for semantic search
-    let embedding = if ml.models_ready().await.embedding_downloaded {
+    let embedding = if ml.embedding_available().await {
         match ml.get_embedding_model().await {
-            Ok(model) => model.embed(&query).ok(),
+            Ok(model) => model
+                .embed(&query)
+                .await
+                .ok()
+                .map(|emb| (model.spec(), emb)),
             Err(_) => None,
         }
     } else {
         None
     };
 
-    let conn = pool.get()?;
-
-    if let Some(ref emb) = embedding {
-        db::search::hybrid_search(&conn, &query, Some(emb), limit, include_archived)
+    // Fetch enough fused candidates for the reranker to choose from
+    let candidates = if ml.rerank_settings().enabled {
+        limit.max(RERANK_CANDIDATES)
     } else {
-        // Fall back to FTS-only search
-        db::search::fts_only_search(&conn, &query, limit, include_archived)
+        limit
+    };
+
+    let results = {
+        let conn = pool.get()?;
+        if let Some((model, ref emb)) = embedding {
+            db::search::hybrid_search(&conn, &query, Some((model, emb)), candidates, &filter)?
+        } else {
+            // Fall back to FTS-only search
+            db::search::fts_only_search(&conn, &query, candidates, &filter)?
+        }
+    };
+
+    let passages: Vec<&str> = results
+        .iter()
+        .take(RERANK_CANDIDATES)
+        .map(|r| r.journal.content.as_str())
+        .collect();
+    let scores = ml.rerank(&query, &passages).await;
+    let mut results = db::search::apply_rerank(results, &scores);
+    results.truncate(limit);
+    Ok(results)
+}
+
+/// Get whether search results are reranked by the cross-encoder, and the latency budget.
+#[tauri::command]
+fn get_rerank_settings(ml: State<'_, MlState>) -> RerankSettings {
+    ml.rerank_settings()
+}
+
+/// Turn cross-encoder reranking of search and chat context on or off, optionally
+/// with a new latency budget in milliseconds. Enabling downloads the reranker if needed.
+#[tauri::command]
+async fn set_rerank_settings(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    enabled: bool,
+    budget_ms: Option<u64>,
+) -> Result<RerankSettings, AppError> {
+    if enabled {
+        ml.ensure_reranker_downloaded().await?;
     }
+    let settings = RerankSettings {
+        enabled,
+        budget_ms: budget_ms.unwrap_or(ml.rerank_settings().budget_ms),
+    };
+    {
+        let conn = pool.get()?;
+        db::settings::set(&conn, db::settings::RERANK_ENABLED, &enabled.to_string())?;
+        db::settings::set(
+            &conn,
+            db::settings::RERANK_BUDGET_MS,
+            &settings.budget_ms.to_string(),
+        )?;
+    }
+    ml.set_rerank_settings(settings);
+    Ok(settings)
+}
+
+/// Get how embedding vectors are stored, and how much space they take.
+#[tauri::command]
+fn get_vector_storage(pool: State<'_, DbPool>) -> Result<VectorStorageStatus, AppError> {
+    let conn = pool.get()?;
+    db::vectors::storage_status(&conn)
+}
+
+/// Store embedding vectors as "f32", "int8" or "bit", converting the existing rows.
+/// Quantized storage drops the f32 vectors; top candidates are rescored from int8 vectors.
+#[tauri::command]
+async fn set_vector_storage(
+    pool: State<'_, DbPool>,
+    storage: String,
+) -> Result<VectorStorageStatus, AppError> {
+    let storage = VectorStorage::parse(&storage)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown vector storage: {}", storage)))?;
+    let pool = pool.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        let conn = pool.get()?;
+        db::vectors::set_storage(&conn, storage)
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?
 }
 
 /// Generate embedding for a journal entry in the background.
-/// Returns immediately; embedding is generated asynchronously.
+/// Returns immediately; the embedding job is picked up by the job worker.
 #[tauri::command]
 async fn generate_entry_embedding(
     pool: State<'_, DbPool>,
-    ml: State<'_, MlState>,
+    queue: State<'_, JobQueue>,
     id: String,
 ) -> Result<(), AppError> {
-    // Clone for the background task
-    let pool_clone = pool.inner().clone();
-    let ml_clone = ml.inner().clone();
-
-    // Spawn as non-blocking background task
-    tauri::async_runtime::spawn(async move {
-        if let Err(e) = generate_embedding_inner(&pool_clone, &ml_clone, &id).await {
-            log::error!("Failed to generate embedding for {}: {}", id, e);
-        }
-    });
-
+    queue.enqueue(
+        pool.inner(),
+        JobType::Embedding,
+        Some(&id),
+        None,
+        db::jobs::PRIORITY_HIGH,
+    )?;
     Ok(())
 }
 
-/// Minimum character count to trigger chunking (roughly 100+ words)
-const CHUNK_THRESHOLD_CHARS: usize = 500;
-/// Target chunk size in characters (roughly 100-125 words)
-const CHUNK_SIZE_CHARS: usize = 500;
-/// Overlap between chunks for context continuity
-const CHUNK_OVERLAP_CHARS: usize = 100;
+// Job Queue Commands
 
-async fn generate_embedding_inner(pool: &DbPool, ml: &MlState, id: &str) -> Result<(), AppError> {
-    // Check if embedding already exists
-    {
-        let conn = pool.get()?;
-        if db::vectors::has_embedding(&conn, id)? {
-            return Ok(());
-        }
+/// Enqueue a background job.
+/// Entry-scoped jobs (embedding, emotions, entities, title) need an entry ID; summary jobs take a JSON payload.
+#[tauri::command]
+fn enqueue_job(
+    pool: State<'_, DbPool>,
+    queue: State<'_, JobQueue>,
+    job_type: String,
+    entry_id: Option<String>,
+    payload: Option<String>,
+    priority: Option<i64>,
+) -> Result<Job, AppError> {
+    let job_type: JobType = job_type.parse()?;
+    if job_type != JobType::Summary && entry_id.is_none() {
+        return Err(AppError::InvalidInput(format!(
+            "{} jobs require an entry_id",
+            job_type.as_str()
+        )));
     }
+    queue.enqueue(
+        pool.inner(),
+        job_type,
+        entry_id.as_deref(),
+        payload.as_deref(),
+        priority.unwrap_or(db::jobs::PRIORITY_NORMAL),
+    )
+}
 
-    // Get journal content
-    let content = {
-        let conn = pool.get()?;
-        let entry = journals::get(&conn, id)?;
-        entry.content
-    };
+/// Cancel a pending or running job.
+#[tauri::command]
+fn cancel_job(
+    pool: State<'_, DbPool>,
+    queue: State<'_, JobQueue>,
+    id: String,
+) -> Result<Job, AppError> {
+    queue.cancel(pool.inner(), &id)
+}
+
+/// Get a single job by ID.
+#[tauri::command]
+fn get_job(pool: State<'_, DbPool>, id: String) -> Result<Job, AppError> {
+    let conn = pool.get()?;
+    db::jobs::get(&conn, &id)
+}
 
-    let model = ml.get_embedding_model().await?;
+/// List jobs, optionally filtered by status.
+#[tauri::command]
+fn list_jobs(
+    pool: State<'_, DbPool>,
+    status: Option<String>,
+    limit: Option<i64>,
+) -> Result<Vec<Job>, AppError> {
+    let status = status.map(|s| s.parse()).transpose()?;
+    let conn = pool.get()?;
+    db::jobs::list(&conn, status, limit)
+}
 
-    // Generate full-entry embedding
-    let embedding = model.embed(&content)?;
+/// Embed and analyze every entry with missing or outdated embeddings or emotions.
+/// Progress is reported via "reindex-progress" events. Resumes a paused run.
+#[tauri::command]
+async fn reindex_all(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    queue: State<'_, JobQueue>,
+    reindexer: State<'_, Reindexer>,
+) -> Result<ReindexProgress, AppError> {
+    reindexer
+        .start(
+            app,
+            pool.inner().clone(),
+            ml.inner().clone(),
+            queue.inner().clone(),
+        )
+        .await
+}
 
-    // Store entry-level embedding
-    {
-        let conn = pool.get()?;
-        db::vectors::store_embedding(&conn, id, &embedding)?;
-    }
+/// Pause a running reindex after the current entry.
+#[tauri::command]
+fn pause_reindex(app: AppHandle, reindexer: State<'_, Reindexer>) -> ReindexProgress {
+    reindexer.pause(&app)
+}
 
-    // For longer entries, also generate chunk embeddings for better RAG precision
-    if content.len() > CHUNK_THRESHOLD_CHARS {
-        let chunks = ml::embeddings::chunk_text(&content, CHUNK_SIZE_CHARS, CHUNK_OVERLAP_CHARS);
-
-        if chunks.len() > 1 {
-            let mut chunk_data = Vec::with_capacity(chunks.len());
-
-            for (index, chunk_text) in chunks.into_iter().enumerate() {
-                match model.embed(&chunk_text) {
-                    Ok(chunk_embedding) => {
-                        chunk_data.push(db::vectors::ChunkData {
-                            chunk_index: index,
-                            chunk_text,
-                            embedding: chunk_embedding,
-                        });
-                    }
-                    Err(e) => {
-                        log::warn!("Failed to embed chunk {} for entry {}: {}", index, id, e);
-                    }
-                }
-            }
+/// Resume a paused reindex.
+#[tauri::command]
+fn resume_reindex(app: AppHandle, reindexer: State<'_, Reindexer>) -> ReindexProgress {
+    reindexer.resume(&app)
+}
 
-            if !chunk_data.is_empty() {
-                let conn = pool.get()?;
-                db::vectors::store_chunk_embeddings(&conn, id, &chunk_data)?;
-                log::info!(
-                    "Generated {} chunk embeddings for entry {}",
-                    chunk_data.len(),
-                    id
-                );
-            }
-        }
-    }
+/// Get counts of unindexed entries and the progress of any reindex run.
+#[tauri::command]
+fn get_index_status(
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    reindexer: State<'_, Reindexer>,
+) -> Result<IndexStatus, AppError> {
+    let conn = pool.get()?;
+    reindexer.status(&conn, ml.active_embedding())
+}
 
-    log::info!("Generated embedding for entry {}", id);
-    Ok(())
+/// List supported embedding models and which one is active.
+#[tauri::command]
+fn list_embedding_models(ml: State<'_, MlState>) -> Vec<EmbeddingModelOption> {
+    ml.list_embedding_models()
+}
+
+/// Switch the embedding model. The new model's index is built in the background
+/// (reported via "reindex-progress") and search switches over once it's complete.
+#[tauri::command]
+async fn set_embedding_model(
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+    ml: State<'_, MlState>,
+    queue: State<'_, JobQueue>,
+    reindexer: State<'_, Reindexer>,
+    model_id: String,
+) -> Result<ReindexProgress, AppError> {
+    let target = ml::models::embedding_model(&model_id)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown embedding model: {}", model_id)))?;
+    reindexer
+        .switch_embedding_model(
+            app,
+            pool.inner().clone(),
+            ml.inner().clone(),
+            queue.inner().clone(),
+            target,
+        )
+        .await
 }
 
 // LLM/Chat Commands
@@ -744,7 +1404,7 @@ async fn chat_stream(
     // Get emotions for current entry if available (for enhanced safety check)
     let emotions: Option<Vec<EmotionPrediction>> = if let Some(ref jid) = journal_id {
         let conn = pool.get()?;
-        db::emotions::get(&conn, jid).ok().map(|e| {
+        db::emotions::get_effective(&conn, jid).ok().map(|e| {
             e.into_iter()
                 .map(|(label, score)| EmotionPrediction { label, score })
                 .collect()
@@ -753,10 +1413,21 @@ async fn chat_stream(
         None
     };
 
+    // A sustained low mood across recent entries also calls for more care (noted once per session)
+    let low_mood_trend = {
+        let conn = pool.get()?;
+        db::emotions::get_mood_insights(&conn, chrono::Utc::now().date_naive())
+            .map(|insights| !insights.is_empty())
+            .unwrap_or_else(|e| {
+                log::warn!("Mood insight check failed: {}", e);
+                false
+            })
+    };
+
     // Check safety with emotion context
     let safety_result = llm
         .safety
-        .check_with_emotions(&message, emotions.as_deref());
+        .check_with_mood(&message, emotions.as_deref(), low_mood_trend);
     if !safety_result.safe {
         // Emit the intervention message as a "response"
         if let Some(intervention) = &safety_result.intervention {
@@ -923,112 +1594,7 @@ async fn generate_summary(
     llm: State<'_, LlmState>,
     period: String,
 ) -> Result<SummaryResponse, AppError> {
-    use chrono::{Duration, Local};
-
-    let today = Local::now().date_naive();
-    let (start_date, period_label) = match period.as_str() {
-        "monthly" => {
-            let start = today - Duration::days(30);
-            (start.format("%Y-%m-%d").to_string(), "month")
-        }
-        _ => {
-            let start = today - Duration::days(7);
-            (start.format("%Y-%m-%d").to_string(), "week")
-        }
-    };
-    let end_date = today.format("%Y-%m-%d").to_string();
-
-    // Fetch entries in range
-    let entries = {
-        let conn = pool.get()?;
-        journals::get_entries_in_range(&conn, &start_date, &end_date)?
-    };
-
-    if entries.is_empty() {
-        return Ok(SummaryResponse {
-            summary: format!(
-                "You haven't written any journal entries in the past {}. Start writing to see your reflections here!",
-                period_label
-            ),
-            period: period.clone(),
-            entry_count: 0,
-        });
-    }
-
-    // Fetch emotion trends for the period
-    let emotions = {
-        let conn = pool.get()?;
-        db::emotions::get_daily_emotions(&conn, &start_date, &end_date)?
-    };
-
-    // Aggregate emotions to find top emotions
-    let mut emotion_counts: std::collections::HashMap<String, u32> =
-        std::collections::HashMap::new();
-    for (_, dominant_emotion, _) in &emotions {
-        if let Some(emotion) = dominant_emotion {
-            *emotion_counts.entry(emotion.clone()).or_insert(0) += 1;
-        }
-    }
-    let mut emotion_list: Vec<_> = emotion_counts.into_iter().collect();
-    emotion_list.sort_by(|a, b| b.1.cmp(&a.1));
-    let top_emotions: Vec<String> = emotion_list.into_iter().take(3).map(|(e, _)| e).collect();
-
-    // Format entries for the prompt (limit excerpts to avoid token overflow)
-    let formatted_entries: String = entries
-        .iter()
-        .take(10)
-        .map(|e| {
-            let date = e.created_at.format("%A, %B %d").to_string();
-            let excerpt = if e.content.len() > 300 {
-                format!("{}...", &e.content[..300])
-            } else {
-                e.content.clone()
-            };
-            format!(
-                "- {} ({}): {}",
-                e.title.as_deref().unwrap_or("Untitled"),
-                date,
-                excerpt
-            )
-        })
-        .collect::<Vec<_>>()
-        .join("\n\n");
-
-    let emotion_summary = if top_emotions.is_empty() {
-        "No emotion data available for this period.".to_string()
-    } else {
-        format!("Most frequent emotions: {}", top_emotions.join(", "))
-    };
-
-    // Build the summarization prompt
-    let prompt = format!(
-        r#"You are MindScribe, a thoughtful journaling companion. The user has asked for a reflection on their past {}.
-
-Here are excerpts from their journal entries:
-<entries>
-{}
-</entries>
-
-Their emotional patterns this {}:
-{}
-
-Write a warm, 2-3 paragraph reflection that:
-1. Highlights recurring themes or topics from their writing
-2. Notes emotional patterns without judgment
-3. Offers one gentle observation or question for self-reflection
-
-Do not give advice unless asked. Be supportive and curious. Keep your response focused and meaningful."#,
-        period_label, formatted_entries, period_label, emotion_summary
-    );
-
-    // Generate the summary
-    let summary = llm.ollama.generate_summary(&prompt).await?;
-
-    Ok(SummaryResponse {
-        summary,
-        period,
-        entry_count: entries.len(),
-    })
+    llm::summary::generate(pool.inner(), &llm.ollama, &period).await
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -1059,17 +1625,91 @@ pub fn run() {
             std::fs::create_dir_all(&images_dir)?;
 
             // Initialize ML state
-            let models_dir = app_dir.join("models");
+            let models_dir = ml::models::models_dir(&app_dir);
+            log::info!("Models directory: {}", models_dir.display());
             std::fs::create_dir_all(&models_dir)?;
             let ml_state = MlState::new(models_dir);
+            {
+                let conn = pool.get().map_err(|e| e.to_string())?;
+                if let Err(e) = jobs::reindex::load_embedding_settings(&conn, &ml_state) {
+                    log::error!("Failed to load embedding model settings: {}", e);
+                }
+                match db::settings::get(&conn, db::settings::MODEL_PRECISION) {
+                    Ok(Some(value)) => match Precision::parse(&value) {
+                        Some(precision) => ml_state.set_precision(precision),
+                        None => log::warn!("Ignoring unknown model precision: {}", value),
+                    },
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to load model precision: {}", e),
+                }
+                match db::settings::get(&conn, db::settings::MODEL_IDLE_TIMEOUT_SECS) {
+                    Ok(Some(value)) => match value.parse::<u64>() {
+                        Ok(secs) => ml_state.set_idle_timeout(idle_timeout_from_secs(secs)),
+                        Err(_) => log::warn!("Ignoring invalid model idle timeout: {}", value),
+                    },
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to load model idle timeout: {}", e),
+                }
+                let mut rerank = RerankSettings::default();
+                match db::settings::get(&conn, db::settings::RERANK_ENABLED) {
+                    Ok(Some(value)) => rerank.enabled = value == "true",
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to load rerank setting: {}", e),
+                }
+                match db::settings::get(&conn, db::settings::RERANK_BUDGET_MS) {
+                    Ok(Some(value)) => match value.parse::<u64>() {
+                        Ok(ms) => rerank.budget_ms = ms,
+                        Err(_) => log::warn!("Ignoring invalid rerank budget: {}", value),
+                    },
+                    Ok(None) => {}
+                    Err(e) => log::error!("Failed to load rerank budget: {}", e),
+                }
+                ml_state.set_rerank_settings(rerank);
+            }
+
+            // Warm models up in the background and evict them once idle;
+            // state changes are emitted as `model-status` events
+            {
+                let app_handle = app.handle().clone();
+                ml_state
+                    .lifecycle()
+                    .set_listener(std::sync::Arc::new(move |status| {
+                        let _ = app_handle.emit("model-status", status);
+                    }));
+                let ml = ml_state.clone();
+                tauri::async_runtime::spawn(async move { ml.run_lifecycle().await });
+            }
 
             // Initialize LLM state
             let llm_state = LlmState::new();
 
+            // Start the background job worker (resumes jobs from previous sessions)
+            let job_queue = JobQueue::default();
+            job_queue.start(
+                app.handle().clone(),
+                pool.clone(),
+                ml_state.clone(),
+                llm_state.clone(),
+            );
+
+            // Resume building the index for an unfinished embedding model switch
+            let reindexer = Reindexer::default();
+            if ml_state.pending_embedding().is_some() {
+                let (reindexer, app_handle) = (reindexer.clone(), app.handle().clone());
+                let (pool, ml, queue) = (pool.clone(), ml_state.clone(), job_queue.clone());
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = reindexer.start(app_handle, pool, ml, queue).await {
+                        log::error!("Failed to resume embedding model switch: {}", e);
+                    }
+                });
+            }
+
             // Store in Tauri state
             app.manage(pool);
             app.manage(ml_state);
             app.manage(llm_state);
+            app.manage(job_queue);
+            app.manage(reindexer);
 
             log::info!("MindScribe initialized successfully");
 
@@ -1085,8 +1725,22 @@ pub fn run() {
             unarchive_entry,
             search_entries,
             get_journal_stats,
+            list_entry_languages,
             get_streak_info,
             get_emotion_trends,
+            get_affect_series,
+            get_emotion_series,
+            get_mood_insights,
+            get_themes,
+            get_entry_map,
+            get_related_entries,
+            list_people,
+            list_places,
+            get_entity,
+            merge_entities,
+            index_entities,
+            find_duplicate_entries,
+            merge_entries,
             get_on_this_day,
             create_template,
             get_template,
@@ -1103,9 +1757,37 @@ pub fn run() {
             delete_entry_messages,
             get_model_status,
             initialize_models,
+            repair_models,
+            import_models,
             get_entry_emotions,
+            get_entry_emotion_chunks,
+            get_emotion_corrections,
+            correct_entry_emotion,
+            clear_entry_emotion_correction,
+            get_emotion_calibration,
+            get_sentence_emotions,
+            get_emotion_aggregation,
+            set_emotion_aggregation,
+            get_model_precision,
+            set_model_precision,
+            get_model_idle_timeout,
+            set_model_idle_timeout,
             hybrid_search,
+            get_rerank_settings,
+            set_rerank_settings,
+            get_vector_storage,
+            set_vector_storage,
             generate_entry_embedding,
+            enqueue_job,
+            cancel_job,
+            get_job,
+            list_jobs,
+            reindex_all,
+            pause_reindex,
+            resume_reindex,
+            get_index_status,
+            list_embedding_models,
+            set_embedding_model,
             check_ollama_status,
             check_message_safety,
             generate_title,
@@ -1,9 +1,10 @@
 use serde::Serialize;
 
 use crate::db::chat::ChatMessage as DbChatMessage;
-use crate::db::search::HybridSearchResult;
+use crate::db::search::{HybridSearchResult, SearchFilter};
 use crate::db::DbPool;
 use crate::error::AppError;
+use crate::ml::rerank::RERANK_CANDIDATES;
 use crate::ml::MlState;
 
 use super::ollama::{ChatMessage, OllamaClient};
@@ -203,28 +204,50 @@ pub async fn get_rag_context(
                 score: 1.0, // Highest priority
                 fts_rank: Some(1),
                 vec_rank: Some(1),
+                rerank_score: None,
             });
         }
     }
 
     // Try to get embedding for semantic search
-    let embedding = if ml.models_ready().await.embedding_downloaded {
+    let embedding = if ml.embedding_available().await {
         match ml.get_embedding_model().await {
-            Ok(model) => model.embed(query).ok(),
+            Ok(model) => model.embed(query).await.ok().map(|emb| (model.spec(), emb)),
             Err(_) => None,
         }
     } else {
         None
     };
 
-    let conn = pool.get()?;
+    // Fetch enough fused candidates for the reranker to choose from
+    let candidates = if ml.rerank_settings().enabled {
+        limit.max(RERANK_CANDIDATES)
+    } else {
+        limit
+    };
 
     // Search for related entries (excluding current if already added)
-    let search_results = if let Some(ref emb) = embedding {
-        crate::db::search::hybrid_search(&conn, query, Some(emb), limit, false)?
-    } else {
-        crate::db::search::fts_only_search(&conn, query, limit, false)?
+    let search_results = {
+        let conn = pool.get()?;
+        if let Some((model, ref emb)) = embedding {
+            crate::db::search::hybrid_search(
+                &conn,
+                query,
+                Some((model, emb)),
+                candidates,
+                &SearchFilter::default(),
+            )?
+        } else {
+            crate::db::search::fts_only_search(&conn, query, candidates, &SearchFilter::default())?
+        }
     };
+    let passages: Vec<&str> = search_results
+        .iter()
+        .take(RERANK_CANDIDATES)
+        .map(|r| r.journal.content.as_str())
+        .collect();
+    let scores = ml.rerank(query, &passages).await;
+    let search_results = crate::db::search::apply_rerank(search_results, &scores);
 
     // Add search results, excluding the current entry to avoid duplication
     for result in search_results {
@@ -355,4 +378,106 @@ mod tests {
         assert_eq!(messages[1].role, "user");
         assert_eq!(messages[1].content, "How am I feeling?");
     }
+
+    #[tokio::test]
+    async fn test_rag_context_uses_embedder() {
+        use crate::ml::fake::FakeEmbedder;
+        use crate::ml::models::MINILM_EMBEDDING;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pool = crate::db::init(&dir.path().join("test.db")).unwrap();
+        let ml = MlState::new(dir.path().join("models"));
+        ml.insert_embedding_model(Arc::new(FakeEmbedder::new(&MINILM_EMBEDDING)))
+            .await;
+
+        let (river, work) = {
+            let conn = pool.get().unwrap();
+            let river = crate::db::journals::create(
+                &conn,
+                "A long quiet walk along the river at sunset",
+                None,
+                None,
+            )
+            .unwrap()
+            .id;
+            let work =
+                crate::db::journals::create(&conn, "Deadlines and meetings all day", None, None)
+                    .unwrap()
+                    .id;
+            (river, work)
+        };
+        for id in [&river, &work] {
+            crate::jobs::handlers::generate_embedding(&pool, &ml, id, None)
+                .await
+                .unwrap();
+        }
+
+        let results = get_rag_context(&pool, &ml, "walk by the river", None, 5)
+            .await
+            .unwrap();
+        assert_eq!(results[0].journal.id, river);
+        assert!(results[0].vec_rank.is_some());
+
+        // The current entry always comes first
+        let results = get_rag_context(&pool, &ml, "walk by the river", Some(&work), 5)
+            .await
+            .unwrap();
+        assert_eq!(results[0].journal.id, work);
+        assert_eq!(results.iter().filter(|r| r.journal.id == work).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_rag_context_reranks_candidates() {
+        use crate::ml::fake::{FakeEmbedder, FakeReranker};
+        use crate::ml::models::MINILM_EMBEDDING;
+        use crate::ml::rerank::RerankSettings;
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pool = crate::db::init(&dir.path().join("test.db")).unwrap();
+        let ml = MlState::new(dir.path().join("models"));
+        ml.insert_embedding_model(Arc::new(FakeEmbedder::new(&MINILM_EMBEDDING)))
+            .await;
+        ml.insert_reranker(Arc::new(FakeReranker)).await;
+
+        let ids: Vec<String> = {
+            let conn = pool.get().unwrap();
+            [
+                "Walked to the river, then a long walk home along the river",
+                "River cruise with work colleagues",
+                "Deadlines and meetings all day",
+            ]
+            .iter()
+            .map(|text| {
+                crate::db::journals::create(&conn, text, None, None)
+                    .unwrap()
+                    .id
+            })
+            .collect()
+        };
+        for id in &ids {
+            crate::jobs::handlers::generate_embedding(&pool, &ml, id, None)
+                .await
+                .unwrap();
+        }
+
+        // Disabled: fused order, no reranker scores
+        let results = get_rag_context(&pool, &ml, "river walk", None, 2)
+            .await
+            .unwrap();
+        assert!(results.iter().all(|r| r.rerank_score.is_none()));
+
+        ml.set_rerank_settings(RerankSettings {
+            enabled: true,
+            budget_ms: 5_000,
+        });
+        let results = get_rag_context(&pool, &ml, "river walk", None, 2)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].journal.id, ids[0]);
+        assert_eq!(results[0].rerank_score, Some(4.0));
+        assert_eq!(results[1].rerank_score, Some(1.0));
+    }
 }
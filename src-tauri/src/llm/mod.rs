@@ -1,12 +1,14 @@
 pub mod chat;
 pub mod ollama;
 pub mod safety;
+pub mod summary;
 
 use serde::Serialize;
 
 pub use chat::{ChatService, SourceReference};
 pub use ollama::{OllamaClient, OllamaStatus};
 pub use safety::{SafetyFilter, SafetyResult};
+pub use summary::SummaryResponse;
 
 /// State for managing LLM interactions.
 /// Uses Ollama as the backend for local LLM inference.
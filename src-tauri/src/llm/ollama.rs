@@ -93,6 +93,42 @@ impl OllamaClient {
         Ok(tags.models)
     }
 
+    /// Embed text with an Ollama embedding model via `/api/embeddings`.
+    pub async fn embed(&self, model: &str, prompt: &str) -> Result<Vec<f32>, AppError> {
+        let url = format!("{}/api/embeddings", self.base_url);
+        let request = EmbeddingRequest { model, prompt };
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to request embedding: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Llm(format!(
+                "Ollama returned error {}: {}",
+                status, body
+            )));
+        }
+
+        let resp: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to parse embedding response: {}", e)))?;
+
+        if resp.embedding.is_empty() {
+            return Err(AppError::Llm(format!(
+                "Ollama returned an empty embedding; is '{}' an embedding model?",
+                model
+            )));
+        }
+        Ok(resp.embedding)
+    }
+
     /// Send a chat completion request and stream the response.
     /// Returns an async stream of response chunks.
     pub async fn chat_stream(
@@ -272,6 +308,135 @@ impl OllamaClient {
 
         Ok(title)
     }
+
+    /// List the people and places named in a journal entry, one `person: Name` or
+    /// `place: Name` per line (see `ml::ner::parse_llm_entities`).
+    pub async fn extract_entities(&self, content: &str) -> Result<String, AppError> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let system_prompt = "You are a helpful assistant that finds the people and places named in a journal entry. List each one on its own line as `person: Name` or `place: Name`, spelled exactly as in the entry. Include people referred to by name or by a family role used as a name (e.g. Mum, Grandpa); leave out the writer, pronouns and organisations. If there are none, respond with `none`. Respond with ONLY the list.";
+        let user_prompt = format!("Journal entry:\n\n{}", content);
+
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: user_prompt,
+            },
+        ];
+
+        let request = ChatRequest {
+            model: CHAT_MODEL.to_string(),
+            messages,
+            stream: false,
+            options: Some(ChatOptions {
+                temperature: 0.0,
+                top_p: 0.9,
+                num_predict: 256,
+            }),
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to extract entities: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Llm(format!(
+                "Ollama returned error {}: {}",
+                status, body
+            )));
+        }
+
+        let resp: NonStreamResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to parse entities response: {}", e)))?;
+
+        Ok(resp
+            .message
+            .map(|m| m.content.trim().to_string())
+            .unwrap_or_default())
+    }
+
+    /// Name a recurring theme from its distinctive terms and a few member entries.
+    pub async fn generate_theme_label(
+        &self,
+        terms: &[String],
+        excerpts: &[String],
+    ) -> Result<String, AppError> {
+        let url = format!("{}/api/chat", self.base_url);
+
+        let system_prompt = "You are a helpful assistant that names recurring themes in a personal journal. Given the theme's key terms and excerpts from entries about it, respond with a 2-4 word name for the theme. Respond with ONLY the name, no quotes or extra text.";
+        let user_prompt = format!(
+            "Key terms: {}\n\nExcerpts:\n{}",
+            terms.join(", "),
+            excerpts
+                .iter()
+                .map(|e| format!("- {}", e))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+
+        let messages = vec![
+            ChatMessage {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            },
+            ChatMessage {
+                role: "user".to_string(),
+                content: user_prompt,
+            },
+        ];
+
+        let request = ChatRequest {
+            model: CHAT_MODEL.to_string(),
+            messages,
+            stream: false,
+            options: Some(ChatOptions {
+                temperature: 0.3,
+                top_p: 0.9,
+                num_predict: 16,
+            }),
+        };
+
+        let response = self
+            .client
+            .post(&url)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to generate theme label: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(AppError::Llm(format!(
+                "Ollama returned error {}: {}",
+                status, body
+            )));
+        }
+
+        let resp: NonStreamResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::Llm(format!("Failed to parse theme label response: {}", e)))?;
+
+        let label = resp
+            .message
+            .map(|m| m.content.trim().trim_matches('"').to_string())
+            .unwrap_or_default();
+
+        Ok(label)
+    }
 }
 
 /// Non-streaming response from /api/chat.
@@ -307,6 +472,20 @@ struct TagsResponse {
     models: Vec<OllamaModel>,
 }
 
+/// Request body for /api/embeddings.
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    prompt: &'a str,
+}
+
+/// Response from /api/embeddings.
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    #[serde(default)]
+    embedding: Vec<f32>,
+}
+
 /// Request body for /api/chat.
 #[derive(Debug, Serialize)]
 struct ChatRequest {
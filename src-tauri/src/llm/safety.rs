@@ -1,3 +1,6 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 use regex::RegexSet;
 use serde::Serialize;
 
@@ -34,6 +37,8 @@ const DISTRESS_KEYWORDS: &[&str] = &[
 pub struct SafetyFilter {
     crisis_patterns: RegexSet,
     distress_patterns: RegexSet,
+    /// Whether the low-mood notice was already given this session (shared by clones)
+    mood_trend_acknowledged: Arc<AtomicBool>,
 }
 
 impl SafetyFilter {
@@ -46,6 +51,7 @@ impl SafetyFilter {
         Self {
             crisis_patterns,
             distress_patterns,
+            mood_trend_acknowledged: Arc::new(AtomicBool::new(false)),
         }
     }
 
@@ -61,6 +67,19 @@ impl SafetyFilter {
         &self,
         text: &str,
         emotions: Option<&[EmotionPrediction]>,
+    ) -> SafetyResult {
+        self.check_with_mood(text, emotions, false)
+    }
+
+    /// Check a message for safety concerns, considering emotion predictions and whether
+    /// recent entries show a sustained low mood (see `ml::anomaly`). A low-mood trend
+    /// escalates the first otherwise safe message of the session to distress level;
+    /// once acknowledged, later messages go through unchanged.
+    pub fn check_with_mood(
+        &self,
+        text: &str,
+        emotions: Option<&[EmotionPrediction]>,
+        low_mood_trend: bool,
     ) -> SafetyResult {
         let lower = text.to_lowercase();
 
@@ -101,6 +120,14 @@ impl SafetyFilter {
             }
         }
 
+        if low_mood_trend && !self.mood_trend_acknowledged.swap(true, Ordering::SeqCst) {
+            return SafetyResult {
+                safe: true,
+                level: SafetyLevel::Distress,
+                intervention: Some(MOOD_TREND_MESSAGE.to_string()),
+            };
+        }
+
         SafetyResult {
             safe: true,
             level: SafetyLevel::Safe,
@@ -164,6 +191,10 @@ const DISTRESS_MESSAGE: &str =
 const EMOTION_DISTRESS_MESSAGE: &str =
     "I notice you might be going through a difficult time. Remember, it's okay to feel this way, and you don't have to face it alone.";
 
+/// Message shown when recent entries show a sustained low mood.
+const MOOD_TREND_MESSAGE: &str =
+    "Your recent entries suggest the last couple of weeks have been harder than usual. I'm here to listen, and it's okay to reach out for support.";
+
 /// Support resources appended to responses when distress is detected.
 const SUPPORT_RESOURCES: &str = r#"---
 If you'd like to talk to someone, support is available:
@@ -272,4 +303,32 @@ mod tests {
         assert!(result.safe);
         assert_eq!(result.level, SafetyLevel::Safe);
     }
+
+    #[test]
+    fn test_mood_trend_escalates_once_per_session() {
+        let filter = SafetyFilter::new();
+
+        let result = filter.check_with_mood("Had pasta for dinner", None, false);
+        assert_eq!(result.level, SafetyLevel::Safe);
+
+        // Crisis content takes precedence and doesn't use up the notice
+        let result = filter.check_with_mood("I want to end my life", None, true);
+        assert_eq!(result.level, SafetyLevel::Crisis);
+
+        let result = filter.check_with_mood("Had pasta for dinner", None, true);
+        assert!(result.safe);
+        assert_eq!(result.level, SafetyLevel::Distress);
+        assert_eq!(result.intervention.as_deref(), Some(MOOD_TREND_MESSAGE));
+
+        // Acknowledged for every clone of the filter; ordinary messages pass unchanged
+        let result = filter
+            .clone()
+            .check_with_mood("Had pasta for dinner", None, true);
+        assert_eq!(result.level, SafetyLevel::Safe);
+        assert!(result.intervention.is_none());
+
+        // Distress in the message itself is still flagged
+        let result = filter.check_with_mood("I feel hopeless", None, true);
+        assert_eq!(result.level, SafetyLevel::Distress);
+    }
 }
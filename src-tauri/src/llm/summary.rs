@@ -0,0 +1,129 @@
+use chrono::{Duration, Local};
+use serde::Serialize;
+
+use crate::db::DbPool;
+use crate::error::AppError;
+
+use super::ollama::OllamaClient;
+
+/// Response from generate_summary command.
+#[derive(Debug, Serialize)]
+pub struct SummaryResponse {
+    pub summary: String,
+    pub period: String,
+    pub entry_count: usize,
+}
+
+/// Generate a reflective summary of journal entries for a given period.
+/// Period can be "weekly" (last 7 days) or "monthly" (last 30 days).
+pub async fn generate(
+    pool: &DbPool,
+    ollama: &OllamaClient,
+    period: &str,
+) -> Result<SummaryResponse, AppError> {
+    let today = Local::now().date_naive();
+    let (start_date, period_label) = match period {
+        "monthly" => {
+            let start = today - Duration::days(30);
+            (start.format("%Y-%m-%d").to_string(), "month")
+        }
+        _ => {
+            let start = today - Duration::days(7);
+            (start.format("%Y-%m-%d").to_string(), "week")
+        }
+    };
+    let end_date = today.format("%Y-%m-%d").to_string();
+
+    // Fetch entries in range
+    let entries = {
+        let conn = pool.get()?;
+        crate::db::journals::get_entries_in_range(&conn, &start_date, &end_date)?
+    };
+
+    if entries.is_empty() {
+        return Ok(SummaryResponse {
+            summary: format!(
+                "You haven't written any journal entries in the past {}. Start writing to see your reflections here!",
+                period_label
+            ),
+            period: period.to_string(),
+            entry_count: 0,
+        });
+    }
+
+    // Fetch emotion trends for the period
+    let emotions = {
+        let conn = pool.get()?;
+        crate::db::emotions::get_daily_emotions(&conn, &start_date, &end_date)?
+    };
+
+    // Aggregate emotions to find top emotions
+    let mut emotion_counts: std::collections::HashMap<String, u32> =
+        std::collections::HashMap::new();
+    for (_, dominant_emotion, _) in &emotions {
+        if let Some(emotion) = dominant_emotion {
+            *emotion_counts.entry(emotion.clone()).or_insert(0) += 1;
+        }
+    }
+    let mut emotion_list: Vec<_> = emotion_counts.into_iter().collect();
+    emotion_list.sort_by(|a, b| b.1.cmp(&a.1));
+    let top_emotions: Vec<String> = emotion_list.into_iter().take(3).map(|(e, _)| e).collect();
+
+    // Format entries for the prompt (limit excerpts to avoid token overflow)
+    let formatted_entries: String = entries
+        .iter()
+        .take(10)
+        .map(|e| {
+            let date = e.created_at.format("%A, %B %d").to_string();
+            // Truncated on characters: a byte offset can fall inside a multibyte one
+            let excerpt = if e.content.chars().count() > 300 {
+                format!("{}...", e.content.chars().take(300).collect::<String>())
+            } else {
+                e.content.clone()
+            };
+            format!(
+                "- {} ({}): {}",
+                e.title.as_deref().unwrap_or("Untitled"),
+                date,
+                excerpt
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let emotion_summary = if top_emotions.is_empty() {
+        "No emotion data available for this period.".to_string()
+    } else {
+        format!("Most frequent emotions: {}", top_emotions.join(", "))
+    };
+
+    // Build the summarization prompt
+    let prompt = format!(
+        r#"You are MindScribe, a thoughtful journaling companion. The user has asked for a reflection on their past {}.
+
+Here are excerpts from their journal entries:
+<entries>
+{}
+</entries>
+
+Their emotional patterns this {}:
+{}
+
+Write a warm, 2-3 paragraph reflection that:
+1. Highlights recurring themes or topics from their writing
+2. Notes emotional patterns without judgment
+3. Offers one gentle observation or question for self-reflection
+
+Do not give advice unless asked. Be supportive and curious. Keep your response focused and meaningful."#,
+        period_label, formatted_entries, period_label, emotion_summary
+    );
+
+    // Generate the summary
+    let summary = ollama.generate_summary(&prompt).await?;
+
+    Ok(SummaryResponse {
+        summary,
+        period: period.to_string(),
+        entry_count: entries.len(),
+    })
+}
@@ -0,0 +1,230 @@
+//! Coarser views of GoEmotions scores for charting: Ekman's six basic emotions
+//! and continuous valence/arousal.
+
+use serde::Serialize;
+
+/// Ekman category of each GoEmotions label, following the grouping published with
+/// the GoEmotions dataset. `None` for "neutral".
+pub fn ekman_category(label: &str) -> Option<Ekman> {
+    let category = match label {
+        "anger" | "annoyance" | "disapproval" => Ekman::Anger,
+        "disgust" => Ekman::Disgust,
+        "fear" | "nervousness" => Ekman::Fear,
+        "joy" | "amusement" | "approval" | "excitement" | "gratitude" | "love" | "optimism"
+        | "relief" | "pride" | "admiration" | "desire" | "caring" => Ekman::Joy,
+        "sadness" | "disappointment" | "embarrassment" | "grief" | "remorse" => Ekman::Sadness,
+        "surprise" | "realization" | "confusion" | "curiosity" => Ekman::Surprise,
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Ekman's basic emotions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ekman {
+    Anger,
+    Disgust,
+    Fear,
+    Joy,
+    Sadness,
+    Surprise,
+}
+
+/// Position of each label on the circumplex: valence from -1 (unpleasant) to 1
+/// (pleasant), arousal from 0 (calm) to 1 (activated). Hand-assigned from the
+/// usual placement of these emotions; good for trends, not for absolute claims.
+const AFFECT_COORDINATES: &[(&str, f32, f32)] = &[
+    ("admiration", 0.6, 0.5),
+    ("amusement", 0.7, 0.6),
+    ("anger", -0.7, 0.8),
+    ("annoyance", -0.5, 0.6),
+    ("approval", 0.5, 0.3),
+    ("caring", 0.6, 0.4),
+    ("confusion", -0.2, 0.5),
+    ("curiosity", 0.3, 0.6),
+    ("desire", 0.4, 0.7),
+    ("disappointment", -0.6, 0.4),
+    ("disapproval", -0.5, 0.5),
+    ("disgust", -0.7, 0.6),
+    ("embarrassment", -0.5, 0.6),
+    ("excitement", 0.7, 0.9),
+    ("fear", -0.7, 0.8),
+    ("gratitude", 0.7, 0.4),
+    ("grief", -0.9, 0.4),
+    ("joy", 0.9, 0.7),
+    ("love", 0.9, 0.6),
+    ("nervousness", -0.5, 0.8),
+    ("optimism", 0.7, 0.5),
+    ("pride", 0.6, 0.6),
+    ("realization", 0.1, 0.4),
+    ("relief", 0.5, 0.2),
+    ("remorse", -0.6, 0.4),
+    ("sadness", -0.8, 0.3),
+    ("surprise", 0.2, 0.8),
+    ("neutral", 0.0, 0.2),
+];
+
+/// (valence, arousal) of a label, if it is a GoEmotions label.
+pub fn affect_coordinates(label: &str) -> Option<(f32, f32)> {
+    AFFECT_COORDINATES
+        .iter()
+        .find(|(l, _, _)| *l == label)
+        .map(|&(_, valence, arousal)| (valence, arousal))
+}
+
+/// Per-category scores; each is the sum of its labels' scores, capped at 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct EkmanScores {
+    pub anger: f32,
+    pub disgust: f32,
+    pub fear: f32,
+    pub joy: f32,
+    pub sadness: f32,
+    pub surprise: f32,
+    pub neutral: f32,
+}
+
+impl EkmanScores {
+    fn slot(&mut self, category: Option<Ekman>) -> &mut f32 {
+        match category {
+            Some(Ekman::Anger) => &mut self.anger,
+            Some(Ekman::Disgust) => &mut self.disgust,
+            Some(Ekman::Fear) => &mut self.fear,
+            Some(Ekman::Joy) => &mut self.joy,
+            Some(Ekman::Sadness) => &mut self.sadness,
+            Some(Ekman::Surprise) => &mut self.surprise,
+            None => &mut self.neutral,
+        }
+    }
+
+    fn values(&self) -> [f32; 7] {
+        [
+            self.anger,
+            self.disgust,
+            self.fear,
+            self.joy,
+            self.sadness,
+            self.surprise,
+            self.neutral,
+        ]
+    }
+
+    fn values_mut(&mut self) -> [&mut f32; 7] {
+        [
+            &mut self.anger,
+            &mut self.disgust,
+            &mut self.fear,
+            &mut self.joy,
+            &mut self.sadness,
+            &mut self.surprise,
+            &mut self.neutral,
+        ]
+    }
+}
+
+/// One entry's emotions projected onto the coarse views.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Affect {
+    pub valence: f32,
+    pub arousal: f32,
+    pub ekman: EkmanScores,
+}
+
+/// Project an entry's (label, score) pairs. Valence and arousal are the
+/// score-weighted mean of the labels' coordinates. Returns `None` when no known
+/// label has a positive score.
+pub fn project(emotions: &[(String, f32)]) -> Option<Affect> {
+    let mut ekman = EkmanScores::default();
+    let (mut valence, mut arousal, mut weight) = (0.0, 0.0, 0.0);
+
+    for (label, score) in emotions {
+        let Some((v, a)) = affect_coordinates(label) else {
+            continue;
+        };
+        if *score <= 0.0 {
+            continue;
+        }
+        valence += v * score;
+        arousal += a * score;
+        weight += score;
+        let slot = ekman.slot(ekman_category(label));
+        *slot = (*slot + score).min(1.0);
+    }
+
+    (weight > 0.0).then(|| Affect {
+        valence: valence / weight,
+        arousal: arousal / weight,
+        ekman,
+    })
+}
+
+/// Mean of several entries' projections, or `None` if there are none.
+pub fn mean(affects: &[Affect]) -> Option<Affect> {
+    if affects.is_empty() {
+        return None;
+    }
+    let n = affects.len() as f32;
+    let mut total = Affect {
+        valence: 0.0,
+        arousal: 0.0,
+        ekman: EkmanScores::default(),
+    };
+    for affect in affects {
+        total.valence += affect.valence;
+        total.arousal += affect.arousal;
+        for (sum, value) in total
+            .ekman
+            .values_mut()
+            .into_iter()
+            .zip(affect.ekman.values())
+        {
+            *sum += value;
+        }
+    }
+    total.valence /= n;
+    total.arousal /= n;
+    for value in total.ekman.values_mut() {
+        *value /= n;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml::sentiment::EMOTION_LABELS;
+
+    #[test]
+    fn test_every_label_is_mapped() {
+        for label in EMOTION_LABELS {
+            assert!(affect_coordinates(label).is_some(), "{}", label);
+            assert_eq!(ekman_category(label).is_none(), label == "neutral");
+        }
+    }
+
+    #[test]
+    fn test_project_weights_by_score() {
+        let affect = project(&[("joy".to_string(), 0.9), ("sadness".to_string(), 0.1)]).unwrap();
+        assert!(affect.valence > 0.5);
+        assert_eq!(affect.ekman.joy, 0.9);
+        assert_eq!(affect.ekman.sadness, 0.1);
+
+        // Labels in the same category add up, capped at 1
+        let affect = project(&[("joy".to_string(), 0.7), ("love".to_string(), 0.6)]).unwrap();
+        assert_eq!(affect.ekman.joy, 1.0);
+
+        assert!(project(&[]).is_none());
+        assert!(project(&[("unknown".to_string(), 0.5)]).is_none());
+    }
+
+    #[test]
+    fn test_mean() {
+        let happy = project(&[("joy".to_string(), 1.0)]).unwrap();
+        let sad = project(&[("sadness".to_string(), 1.0)]).unwrap();
+        let mean = mean(&[happy, sad]).unwrap();
+        assert!((mean.valence - 0.05).abs() < 1e-6);
+        assert_eq!(mean.ekman.joy, 0.5);
+        assert_eq!(mean.ekman.sadness, 0.5);
+    }
+}
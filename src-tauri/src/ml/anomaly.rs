@@ -0,0 +1,388 @@
+//! Local detection of stretches where mood is markedly worse than the user's own
+//! baseline. Everything is computed from stored emotion scores; nothing leaves the
+//! device.
+//!
+//! Daily scores (the mean over that day's entries) in a recent window are compared
+//! with the days before it:
+//! - elevated distress: the window's mean negative-emotion score is a z-score of at
+//!   least [`Z_THRESHOLD`] above the baseline (using the standard error of the mean)
+//! - sustained decline: valence falls by [`DECLINE_THRESHOLD`] or more across the
+//!   window (least-squares trend) and ends up below the baseline mean
+
+use std::collections::BTreeMap;
+
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+
+use crate::ml::affect;
+use crate::ml::series::EntryScores;
+
+/// Labels counted as distress, summed per entry (capped at 1).
+pub const NEGATIVE_LABELS: &[&str] = &[
+    "sadness",
+    "grief",
+    "nervousness",
+    "fear",
+    "disappointment",
+    "remorse",
+];
+
+/// Length of the recent window, ending on the evaluated day.
+pub const WINDOW_DAYS: i64 = 14;
+/// Days before the window that form the baseline.
+pub const BASELINE_DAYS: i64 = 90;
+/// Days with entries needed in the window before anything is flagged.
+const MIN_WINDOW_DAYS: usize = 4;
+/// Days with entries needed in the baseline before anything is flagged.
+const MIN_BASELINE_DAYS: usize = 10;
+/// Standard score at which the window counts as anomalous.
+pub const Z_THRESHOLD: f32 = 2.0;
+/// Floor for the baseline's standard deviation, so a very steady history doesn't
+/// turn small changes into huge z-scores.
+const MIN_STD: f32 = 0.05;
+/// Valence drop across the window (on the -1..1 scale) that counts as a decline.
+pub const DECLINE_THRESHOLD: f32 = 0.4;
+/// Evidence entries returned per insight.
+const MAX_EVIDENCE: usize = 5;
+
+/// What a detected pattern is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InsightKind {
+    /// Negative emotions well above the user's baseline
+    ElevatedNegative,
+    /// Valence trending down across the window
+    SustainedDecline,
+}
+
+/// A detected pattern with the numbers and entries behind it.
+#[derive(Debug, Clone, Serialize)]
+pub struct MoodInsight {
+    pub kind: InsightKind,
+    pub window_start: String,
+    pub window_end: String,
+    /// Mean daily score over the baseline (negative score or valence, by kind)
+    pub baseline_mean: f32,
+    /// Mean daily score over the window
+    pub window_mean: f32,
+    /// Standard score of the window mean against the baseline
+    pub z_score: f32,
+    /// Valence change across the window from a least-squares fit (declines only)
+    pub trend: Option<f32>,
+    /// Entries in the window that contributed most
+    pub evidence: Vec<InsightEvidence>,
+}
+
+/// An entry supporting an insight.
+#[derive(Debug, Clone, Serialize)]
+pub struct InsightEvidence {
+    pub journal_id: String,
+    pub date: String,
+    /// Negative-emotion score or valence of the entry, by insight kind
+    pub score: f32,
+    /// The entry's negative labels, strongest first
+    pub labels: Vec<String>,
+}
+
+/// Date range (inclusive, YYYY-MM-DD) of entries needed to evaluate `as_of`.
+pub fn date_range(as_of: NaiveDate) -> (String, String) {
+    let start = window_start(as_of) - Duration::days(BASELINE_DAYS);
+    (
+        start.format("%Y-%m-%d").to_string(),
+        as_of.format("%Y-%m-%d").to_string(),
+    )
+}
+
+fn window_start(as_of: NaiveDate) -> NaiveDate {
+    as_of - Duration::days(WINDOW_DAYS - 1)
+}
+
+/// Sum of an entry's negative-emotion scores, capped at 1.
+pub fn negative_score(emotions: &[(String, f32)]) -> f32 {
+    emotions
+        .iter()
+        .filter(|(label, _)| NEGATIVE_LABELS.contains(&label.as_str()))
+        .map(|(_, score)| score)
+        .sum::<f32>()
+        .min(1.0)
+}
+
+/// Per-entry values used by the detectors.
+struct Scored<'a> {
+    entry: &'a EntryScores,
+    day: NaiveDate,
+    negative: f32,
+    valence: Option<f32>,
+}
+
+/// Evaluate the window ending on `as_of` against the baseline before it.
+/// `entries` should cover [`date_range`]; entries outside it are ignored.
+pub fn detect(entries: &[EntryScores], as_of: NaiveDate) -> Vec<MoodInsight> {
+    let window_start = window_start(as_of);
+    let baseline_start = window_start - Duration::days(BASELINE_DAYS);
+
+    let scored: Vec<Scored> = entries
+        .iter()
+        .filter_map(|entry| {
+            let day = NaiveDate::parse_from_str(&entry.date, "%Y-%m-%d").ok()?;
+            (day >= baseline_start && day <= as_of).then(|| Scored {
+                entry,
+                day,
+                negative: negative_score(&entry.emotions),
+                valence: affect::project(&entry.emotions).map(|a| a.valence),
+            })
+        })
+        .collect();
+    let (window, baseline): (Vec<&Scored>, Vec<&Scored>) =
+        scored.iter().partition(|s| s.day >= window_start);
+
+    let mut insights = Vec::new();
+    let window_bounds = (
+        window_start.format("%Y-%m-%d").to_string(),
+        as_of.format("%Y-%m-%d").to_string(),
+    );
+
+    // Elevated negative emotions
+    let window_days = daily_means(&window, |s| Some(s.negative));
+    let baseline_days = daily_means(&baseline, |s| Some(s.negative));
+    if let Some((baseline_mean, window_mean, z_score)) = compare(&baseline_days, &window_days) {
+        if z_score >= Z_THRESHOLD {
+            let mut evidence: Vec<&&Scored> = window.iter().filter(|s| s.negative > 0.0).collect();
+            evidence.sort_by(|a, b| b.negative.total_cmp(&a.negative));
+            insights.push(MoodInsight {
+                kind: InsightKind::ElevatedNegative,
+                window_start: window_bounds.0.clone(),
+                window_end: window_bounds.1.clone(),
+                baseline_mean,
+                window_mean,
+                z_score,
+                trend: None,
+                evidence: evidence
+                    .into_iter()
+                    .take(MAX_EVIDENCE)
+                    .map(|s| to_evidence(s, s.negative))
+                    .collect(),
+            });
+        }
+    }
+
+    // Sustained decline in valence
+    let window_days = daily_means(&window, |s| s.valence);
+    let baseline_days = daily_means(&baseline, |s| s.valence);
+    if let Some((baseline_mean, window_mean, z_score)) = compare(&baseline_days, &window_days) {
+        let points: Vec<(f32, f32)> = window_days
+            .iter()
+            .map(|(day, value)| ((*day - window_start).num_days() as f32, *value))
+            .collect();
+        let trend = slope(&points).map(|slope| slope * (WINDOW_DAYS - 1) as f32);
+        let last = window_days.values().last().copied().unwrap_or(window_mean);
+        if let Some(trend) = trend.filter(|&t| t <= -DECLINE_THRESHOLD && last < baseline_mean) {
+            let mut evidence: Vec<(&&Scored, f32)> = window
+                .iter()
+                .filter_map(|s| Some((s, s.valence?)))
+                .filter(|(_, valence)| *valence < baseline_mean)
+                .collect();
+            evidence.sort_by(|a, b| a.1.total_cmp(&b.1));
+            insights.push(MoodInsight {
+                kind: InsightKind::SustainedDecline,
+                window_start: window_bounds.0,
+                window_end: window_bounds.1,
+                baseline_mean,
+                window_mean,
+                z_score,
+                trend: Some(trend),
+                evidence: evidence
+                    .into_iter()
+                    .take(MAX_EVIDENCE)
+                    .map(|(s, valence)| to_evidence(s, valence))
+                    .collect(),
+            });
+        }
+    }
+
+    insights
+}
+
+fn to_evidence(scored: &Scored, score: f32) -> InsightEvidence {
+    let mut negative: Vec<&(String, f32)> = scored
+        .entry
+        .emotions
+        .iter()
+        .filter(|(label, _)| NEGATIVE_LABELS.contains(&label.as_str()))
+        .collect();
+    negative.sort_by(|a, b| b.1.total_cmp(&a.1));
+    InsightEvidence {
+        journal_id: scored.entry.journal_id.clone(),
+        date: scored.entry.date.clone(),
+        score,
+        labels: negative
+            .into_iter()
+            .map(|(label, _)| label.clone())
+            .collect(),
+    }
+}
+
+/// Mean of `value` per day, skipping entries without one.
+fn daily_means(
+    scored: &[&Scored],
+    value: impl Fn(&Scored) -> Option<f32>,
+) -> BTreeMap<NaiveDate, f32> {
+    let mut days: BTreeMap<NaiveDate, (f32, usize)> = BTreeMap::new();
+    for s in scored {
+        if let Some(v) = value(s) {
+            let day = days.entry(s.day).or_default();
+            day.0 += v;
+            day.1 += 1;
+        }
+    }
+    days.into_iter()
+        .map(|(day, (sum, count))| (day, sum / count as f32))
+        .collect()
+}
+
+/// (baseline mean, window mean, z-score of the window mean), if both sides have
+/// enough days.
+fn compare(
+    baseline: &BTreeMap<NaiveDate, f32>,
+    window: &BTreeMap<NaiveDate, f32>,
+) -> Option<(f32, f32, f32)> {
+    if baseline.len() < MIN_BASELINE_DAYS || window.len() < MIN_WINDOW_DAYS {
+        return None;
+    }
+    let mean =
+        |values: &BTreeMap<NaiveDate, f32>| values.values().sum::<f32>() / values.len() as f32;
+    let baseline_mean = mean(baseline);
+    let window_mean = mean(window);
+    let variance = baseline
+        .values()
+        .map(|v| (v - baseline_mean).powi(2))
+        .sum::<f32>()
+        / (baseline.len() - 1) as f32;
+    let std_error = variance.sqrt().max(MIN_STD) / (window.len() as f32).sqrt();
+    Some((
+        baseline_mean,
+        window_mean,
+        (window_mean - baseline_mean) / std_error,
+    ))
+}
+
+/// Least-squares slope of (x, y) points; `None` with fewer than two distinct x.
+fn slope(points: &[(f32, f32)]) -> Option<f32> {
+    let n = points.len() as f32;
+    let mean_x = points.iter().map(|p| p.0).sum::<f32>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f32>() / n;
+    let sxx: f32 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f32 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    (sxx > 0.0).then(|| sxy / sxx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, day: NaiveDate, emotions: &[(&str, f32)]) -> EntryScores {
+        EntryScores {
+            journal_id: id.to_string(),
+            date: day.format("%Y-%m-%d").to_string(),
+            emotions: emotions
+                .iter()
+                .map(|(label, score)| (label.to_string(), *score))
+                .collect(),
+        }
+    }
+
+    /// Sixty baseline days alternating between mostly content and a little low.
+    fn baseline(as_of: NaiveDate) -> Vec<EntryScores> {
+        (WINDOW_DAYS..WINDOW_DAYS + 60)
+            .map(|offset| {
+                let day = as_of - Duration::days(offset);
+                if offset % 2 == 0 {
+                    entry(&format!("b{}", offset), day, &[("joy", 0.7)])
+                } else {
+                    entry(
+                        &format!("b{}", offset),
+                        day,
+                        &[("joy", 0.4), ("sadness", 0.2)],
+                    )
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flags_elevated_negative_window() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let mut entries = baseline(as_of);
+        for offset in 0..8 {
+            entries.push(entry(
+                &format!("w{}", offset),
+                as_of - Duration::days(offset),
+                &[("sadness", 0.6), ("grief", 0.3), ("nervousness", 0.2)],
+            ));
+        }
+
+        let insights = detect(&entries, as_of);
+        let elevated = insights
+            .iter()
+            .find(|i| i.kind == InsightKind::ElevatedNegative)
+            .unwrap();
+        assert!(elevated.z_score >= Z_THRESHOLD);
+        assert_eq!(elevated.window_start, "2024-06-17");
+        assert_eq!(elevated.evidence.len(), MAX_EVIDENCE);
+        assert!(elevated.evidence[0].journal_id.starts_with('w'));
+        assert_eq!(elevated.evidence[0].labels[0], "sadness");
+    }
+
+    #[test]
+    fn test_flags_sustained_decline() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        let mut entries = baseline(as_of);
+        // From content to sad over the window
+        let window: [&[(&str, f32)]; 5] = [
+            &[("joy", 0.8)],
+            &[("joy", 0.5), ("sadness", 0.2)],
+            &[("sadness", 0.4), ("joy", 0.2)],
+            &[("sadness", 0.6)],
+            &[("sadness", 0.8), ("grief", 0.3)],
+        ];
+        for (i, emotions) in window.iter().enumerate() {
+            let day = as_of - Duration::days(12 - 3 * i as i64);
+            entries.push(entry(&format!("w{}", i), day, emotions));
+        }
+
+        let insights = detect(&entries, as_of);
+        let decline = insights
+            .iter()
+            .find(|i| i.kind == InsightKind::SustainedDecline)
+            .unwrap();
+        assert!(decline.trend.unwrap() <= -DECLINE_THRESHOLD);
+        assert_eq!(decline.evidence[0].journal_id, "w4");
+    }
+
+    #[test]
+    fn test_needs_enough_history() {
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        // A bad fortnight without a baseline isn't compared against anything
+        let entries: Vec<EntryScores> = (0..10)
+            .map(|offset| {
+                entry(
+                    &format!("w{}", offset),
+                    as_of - Duration::days(offset),
+                    &[("sadness", 0.9)],
+                )
+            })
+            .collect();
+        assert!(detect(&entries, as_of).is_empty());
+
+        // A window that looks like the baseline isn't flagged
+        let mut entries = baseline(as_of);
+        entries.extend((0..8).map(|offset| {
+            entry(
+                &format!("w{}", offset),
+                as_of - Duration::days(offset),
+                &[("joy", 0.5), ("sadness", 0.1)],
+            )
+        }));
+        assert!(detect(&entries, as_of).is_empty());
+    }
+}
@@ -0,0 +1,340 @@
+//! Backend-neutral interfaces for embedding, emotion, entity and reranking models.
+//!
+//! `MlState` and everything above it only see these traits, so a model can run
+//! in-process with candle, behind a local service such as Ollama, or as a fake
+//! in tests.
+
+use async_trait::async_trait;
+
+use crate::error::AppError;
+use crate::llm::ollama::OllamaClient;
+use crate::ml::embeddings::EmbeddingModel;
+use crate::ml::models::{EmbeddingModelSpec, Precision};
+use crate::ml::multilingual::MultilingualEmotionModel;
+use crate::ml::ner::{self, EntityMention, NerModel};
+use crate::ml::rerank::CrossEncoder;
+use crate::ml::sentiment::{Aggregation, ChunkedEmotions, EmotionPrediction, SentimentModel};
+
+/// Turns text into vectors for one registered embedding model.
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Registry entry the vectors belong to; decides their dimension and tables.
+    fn spec(&self) -> &'static EmbeddingModelSpec;
+
+    /// Precision the weights run in (remote backends report F32).
+    fn precision(&self) -> Precision {
+        Precision::F32
+    }
+
+    /// Estimated memory held in this process (0 for remote backends).
+    fn memory_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Generate embeddings for several texts, returned in input order.
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError>;
+
+    /// Generate an embedding for one text.
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_batch(&[text])
+            .await?
+            .pop()
+            .ok_or_else(|| AppError::Ml("No embedding produced".to_string()))
+    }
+}
+
+/// Scores text against the GoEmotions labels.
+pub trait EmotionClassifier: Send + Sync {
+    /// Precision the weights run in.
+    fn precision(&self) -> Precision {
+        Precision::F32
+    }
+
+    /// Estimated memory held in this process.
+    fn memory_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Entry-level predictions plus per-window predictions for long texts.
+    /// Backends that don't window their input return no chunks.
+    fn predict_chunked(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<ChunkedEmotions, AppError>;
+
+    /// `predict_chunked` for several texts, in input order. Backends that can share
+    /// forward passes across texts override this.
+    fn predict_chunked_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<Vec<ChunkedEmotions>, AppError> {
+        texts
+            .iter()
+            .map(|text| self.predict_chunked(text, threshold, max_labels, aggregation))
+            .collect()
+    }
+
+    /// Top emotions above threshold, sorted by confidence.
+    fn predict(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+    ) -> Result<Vec<EmotionPrediction>, AppError> {
+        Ok(self
+            .predict_chunked(text, threshold, max_labels, Aggregation::Max)?
+            .predictions)
+    }
+
+    /// Predictions for several short texts (e.g. sentences), in input order.
+    fn predict_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+    ) -> Result<Vec<Vec<EmotionPrediction>>, AppError> {
+        texts
+            .iter()
+            .map(|text| self.predict(text, threshold, max_labels))
+            .collect()
+    }
+}
+
+/// Scores how relevant passages are to a search query.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Precision the weights run in (remote backends report F32).
+    fn precision(&self) -> Precision {
+        Precision::F32
+    }
+
+    /// Estimated memory held in this process (0 for remote backends).
+    fn memory_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Relevance of each passage to the query, in input order; higher is more
+    /// relevant. Only comparable within one query.
+    async fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, AppError>;
+}
+
+/// Finds the people and places mentioned in text.
+#[async_trait]
+pub trait EntityExtractor: Send + Sync {
+    /// Recorded with the extracted entities, so entries are redone when it changes.
+    fn model_version(&self) -> &'static str;
+
+    /// Precision the weights run in (remote backends report F32).
+    fn precision(&self) -> Precision {
+        Precision::F32
+    }
+
+    /// Estimated memory held in this process (0 for remote backends).
+    fn memory_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Mentions in order of appearance, with character offsets into `text`.
+    async fn extract(&self, text: &str) -> Result<Vec<EntityMention>, AppError>;
+}
+
+#[async_trait]
+impl Embedder for EmbeddingModel {
+    fn spec(&self) -> &'static EmbeddingModelSpec {
+        EmbeddingModel::spec(self)
+    }
+
+    fn precision(&self) -> Precision {
+        EmbeddingModel::precision(self)
+    }
+
+    fn memory_bytes(&self) -> u64 {
+        EmbeddingModel::memory_bytes(self)
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError> {
+        EmbeddingModel::embed_batch(self, texts)
+    }
+}
+
+/// Embeddings from a model served by Ollama. Vectors are L2-normalized so they
+/// compare the same way as the in-process models' vectors.
+pub struct OllamaEmbedder {
+    client: OllamaClient,
+    spec: &'static EmbeddingModelSpec,
+    model: &'static str,
+}
+
+impl OllamaEmbedder {
+    /// Embedder for `spec`, using Ollama's model named `model`.
+    pub fn new(
+        client: OllamaClient,
+        spec: &'static EmbeddingModelSpec,
+        model: &'static str,
+    ) -> Self {
+        Self {
+            client,
+            spec,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OllamaEmbedder {
+    fn spec(&self) -> &'static EmbeddingModelSpec {
+        self.spec
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for text in texts {
+            let embedding = self.client.embed(self.model, text).await?;
+            if embedding.len() != self.spec.dimension {
+                return Err(AppError::Ml(format!(
+                    "Ollama model '{}' returned {} dimensions, expected {}",
+                    self.model,
+                    embedding.len(),
+                    self.spec.dimension
+                )));
+            }
+            embeddings.push(l2_normalized(embedding));
+        }
+        Ok(embeddings)
+    }
+}
+
+/// Scale a vector to unit length (zero vectors are returned unchanged).
+pub(crate) fn l2_normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+impl EmotionClassifier for SentimentModel {
+    fn precision(&self) -> Precision {
+        SentimentModel::precision(self)
+    }
+
+    fn memory_bytes(&self) -> u64 {
+        SentimentModel::memory_bytes(self)
+    }
+
+    fn predict_chunked(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<ChunkedEmotions, AppError> {
+        SentimentModel::predict_chunked(self, text, threshold, max_labels, aggregation)
+    }
+
+    fn predict_chunked_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<Vec<ChunkedEmotions>, AppError> {
+        SentimentModel::predict_chunked_batch(self, texts, threshold, max_labels, aggregation)
+    }
+
+    fn predict_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+    ) -> Result<Vec<Vec<EmotionPrediction>>, AppError> {
+        SentimentModel::predict_batch(self, texts, threshold, max_labels)
+    }
+}
+
+impl EmotionClassifier for MultilingualEmotionModel {
+    fn memory_bytes(&self) -> u64 {
+        MultilingualEmotionModel::memory_bytes(self)
+    }
+
+    /// The model truncates long input instead of windowing it, so there are no chunks.
+    fn predict_chunked(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+        _aggregation: Aggregation,
+    ) -> Result<ChunkedEmotions, AppError> {
+        Ok(ChunkedEmotions {
+            predictions: MultilingualEmotionModel::predict(self, text, threshold, max_labels)?,
+            chunks: Vec::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl EntityExtractor for NerModel {
+    fn model_version(&self) -> &'static str {
+        crate::ml::models::NER_MODEL.local_dir
+    }
+
+    fn precision(&self) -> Precision {
+        NerModel::precision(self)
+    }
+
+    fn memory_bytes(&self) -> u64 {
+        NerModel::memory_bytes(self)
+    }
+
+    async fn extract(&self, text: &str) -> Result<Vec<EntityMention>, AppError> {
+        NerModel::extract(self, text)
+    }
+}
+
+/// Entities named by the local LLM, for languages the NER model doesn't cover.
+/// Names the LLM returns are only kept where they occur in the text.
+pub struct OllamaEntityExtractor {
+    client: OllamaClient,
+}
+
+impl OllamaEntityExtractor {
+    pub fn new(client: OllamaClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl EntityExtractor for OllamaEntityExtractor {
+    fn model_version(&self) -> &'static str {
+        crate::llm::ollama::CHAT_MODEL
+    }
+
+    async fn extract(&self, text: &str) -> Result<Vec<EntityMention>, AppError> {
+        let response = self.client.extract_entities(text).await?;
+        Ok(ner::locate_mentions(
+            text,
+            &ner::parse_llm_entities(&response),
+        ))
+    }
+}
+
+#[async_trait]
+impl Reranker for CrossEncoder {
+    fn precision(&self) -> Precision {
+        CrossEncoder::precision(self)
+    }
+
+    fn memory_bytes(&self) -> u64 {
+        CrossEncoder::memory_bytes(self)
+    }
+
+    async fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, AppError> {
+        CrossEncoder::score(self, query, passages)
+    }
+}
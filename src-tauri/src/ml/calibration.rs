@@ -0,0 +1,182 @@
+//! Personal calibration of emotion predictions, learned from the user's corrections.
+//!
+//! The classifier was trained on Reddit comments and misreads some writing styles
+//! consistently (e.g. sarcasm as "amusement"). Each label gets a score bias that
+//! grows with how often the user removed it versus confirmed or added it.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ml::sentiment::EmotionPrediction;
+
+/// Largest score shift a label can get, in either direction.
+const MAX_BIAS: f32 = 0.3;
+
+/// Pseudo-count of neutral corrections, so a single correction only moves the bias a little.
+const PRIOR_CORRECTIONS: f32 = 4.0;
+
+/// What the user said about one detected (or missing) label on an entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CorrectionAction {
+    /// The label is right
+    Confirm,
+    /// The label doesn't apply
+    Remove,
+    /// The entry carries a label the model missed
+    Add,
+}
+
+impl CorrectionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Confirm => "confirm",
+            Self::Remove => "remove",
+            Self::Add => "add",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "confirm" => Some(Self::Confirm),
+            "remove" => Some(Self::Remove),
+            "add" => Some(Self::Add),
+            _ => None,
+        }
+    }
+}
+
+/// Corrections of one label across all entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CorrectionCounts {
+    /// Confirmed or added
+    pub agreed: u32,
+    /// Removed
+    pub removed: u32,
+}
+
+/// Score bias per label; labels without corrections are left alone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Calibration {
+    biases: BTreeMap<String, f32>,
+}
+
+impl Calibration {
+    /// Learn biases from per-label correction counts.
+    pub fn from_counts<'a>(counts: impl IntoIterator<Item = (&'a str, CorrectionCounts)>) -> Self {
+        let biases = counts
+            .into_iter()
+            .filter_map(|(label, c)| {
+                let total = (c.agreed + c.removed) as f32;
+                if total == 0.0 {
+                    return None;
+                }
+                let net = c.agreed as f32 - c.removed as f32;
+                Some((
+                    label.to_string(),
+                    MAX_BIAS * net / (total + PRIOR_CORRECTIONS),
+                ))
+            })
+            .collect();
+        Self { biases }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.biases.is_empty()
+    }
+
+    /// Score shift for a label (0 when uncorrected).
+    pub fn bias(&self, label: &str) -> f32 {
+        self.biases.get(label).copied().unwrap_or(0.0)
+    }
+
+    /// Shift each prediction by its label's bias, drop those that fall below
+    /// `threshold`, and re-sort by score. Only labels the model already surfaced
+    /// can be boosted.
+    pub fn apply(
+        &self,
+        predictions: Vec<EmotionPrediction>,
+        threshold: f32,
+    ) -> Vec<EmotionPrediction> {
+        if self.is_empty() {
+            return predictions;
+        }
+        let mut calibrated: Vec<EmotionPrediction> = predictions
+            .into_iter()
+            .map(|p| EmotionPrediction {
+                score: (p.score + self.bias(&p.label)).clamp(0.0, 1.0),
+                label: p.label,
+            })
+            .filter(|p| p.score >= threshold)
+            .collect();
+        calibrated.sort_by(|a, b| b.score.total_cmp(&a.score));
+        calibrated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prediction(label: &str, score: f32) -> EmotionPrediction {
+        EmotionPrediction {
+            label: label.to_string(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_bias_grows_with_corrections() {
+        let counts = |agreed, removed| CorrectionCounts { agreed, removed };
+        let calibration = Calibration::from_counts([
+            ("amusement", counts(0, 1)),
+            ("annoyance", counts(0, 12)),
+            ("gratitude", counts(4, 0)),
+            ("joy", counts(2, 2)),
+        ]);
+
+        assert!(calibration.bias("amusement") < 0.0);
+        assert!(calibration.bias("annoyance") < calibration.bias("amusement"));
+        assert!(calibration.bias("annoyance") > -MAX_BIAS);
+        assert!(calibration.bias("gratitude") > 0.0);
+        assert_eq!(calibration.bias("joy"), 0.0);
+        assert_eq!(calibration.bias("fear"), 0.0);
+    }
+
+    #[test]
+    fn test_apply_reorders_and_drops() {
+        let calibration = Calibration::from_counts([
+            (
+                "amusement",
+                CorrectionCounts {
+                    agreed: 0,
+                    removed: 8,
+                },
+            ),
+            (
+                "annoyance",
+                CorrectionCounts {
+                    agreed: 4,
+                    removed: 0,
+                },
+            ),
+        ]);
+        let calibrated = calibration.apply(
+            vec![
+                prediction("amusement", 0.6),
+                prediction("annoyance", 0.3),
+                prediction("sadness", 0.15),
+            ],
+            0.1,
+        );
+
+        let labels: Vec<&str> = calibrated.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, vec!["annoyance", "amusement", "sadness"]);
+        assert!((calibrated[0].score - 0.45).abs() < 1e-6);
+        assert!((calibrated[1].score - 0.4).abs() < 1e-6);
+
+        let calibrated = calibration.apply(vec![prediction("amusement", 0.25)], 0.1);
+        assert!(calibrated.is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Text overlap between entries via word shingles, used to confirm near-duplicates
+//! proposed by embedding similarity.
+
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use serde::Serialize;
+
+/// Words per shingle.
+const SHINGLE_WORDS: usize = 5;
+
+/// Minimum embedding cosine similarity for a pair to be checked for overlap.
+pub const MIN_COSINE: f32 = 0.9;
+
+/// Pairs sharing this share of shingles (Jaccard) are duplicates.
+const MIN_JACCARD: f32 = 0.5;
+
+/// Pairs where one entry's shingles are mostly found in the other (e.g. one was
+/// pasted into the other) are duplicates too.
+const MIN_CONTAINMENT: f32 = 0.8;
+
+/// How much text two entries share.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Overlap {
+    /// Shared shingles over all distinct shingles of both entries
+    pub jaccard: f32,
+    /// Shared shingles over the shingles of the shorter entry
+    pub containment: f32,
+}
+
+impl Overlap {
+    pub fn is_duplicate(&self) -> bool {
+        self.jaccard >= MIN_JACCARD || self.containment >= MIN_CONTAINMENT
+    }
+}
+
+/// Overlap of two texts' word shingles. Case and punctuation are ignored; texts
+/// shorter than a shingle are compared as a single shingle.
+pub fn overlap(a: &str, b: &str) -> Overlap {
+    let (a, b) = (shingles(a), shingles(b));
+    let shared = a.intersection(&b).count() as f32;
+    let union = (a.len() + b.len()) as f32 - shared;
+    let smaller = a.len().min(b.len()) as f32;
+    Overlap {
+        jaccard: if union > 0.0 { shared / union } else { 0.0 },
+        containment: if smaller > 0.0 { shared / smaller } else { 0.0 },
+    }
+}
+
+fn shingles(text: &str) -> HashSet<u64> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if words.is_empty() {
+        return HashSet::new();
+    }
+    words
+        .windows(SHINGLE_WORDS.min(words.len()))
+        .map(|window| {
+            let mut hasher = DefaultHasher::new();
+            window.hash(&mut hasher);
+            hasher.finish()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRY: &str = "Woke up early and walked the dog along the canal. \
+        The fog was still sitting on the water and everything felt quiet. \
+        Afterwards I made coffee and read for an hour before work.";
+
+    #[test]
+    fn test_identical_and_reformatted_text() {
+        let same = overlap(ENTRY, ENTRY);
+        assert_eq!(same.jaccard, 1.0);
+        assert!(same.is_duplicate());
+
+        // Case, punctuation and line breaks don't matter
+        let reformatted = ENTRY.to_uppercase().replace(". ", "\n");
+        assert_eq!(overlap(ENTRY, &reformatted).jaccard, 1.0);
+    }
+
+    #[test]
+    fn test_pasted_into_longer_entry() {
+        let longer = format!(
+            "{} In the evening we cooked dinner with friends and talked until late.",
+            ENTRY
+        );
+        let result = overlap(ENTRY, &longer);
+        assert_eq!(result.containment, 1.0);
+        assert!(result.jaccard < 1.0);
+        assert!(result.is_duplicate());
+    }
+
+    #[test]
+    fn test_similar_topic_is_not_duplicate() {
+        let other = "Walked the dog early again this morning. No fog today, the canal was \
+            busy with rowers and I skipped coffee because I was late for work.";
+        let result = overlap(ENTRY, other);
+        assert!(!result.is_duplicate());
+        assert_eq!(overlap("", ENTRY).jaccard, 0.0);
+    }
+}
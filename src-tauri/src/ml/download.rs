@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use futures::StreamExt;
+use reqwest::{header, Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+use crate::ml::models::ModelInfo;
+
+/// Default Hugging Face Hub endpoint; `HF_ENDPOINT` overrides it, as for the Hub tooling.
+const DEFAULT_HF_ENDPOINT: &str = "https://huggingface.co";
+
+/// Revision files are downloaded from.
+const REVISION: &str = "main";
+
+/// Manifest of verified file hashes written next to each downloaded model.
+const MANIFEST_FILE: &str = "checksums.json";
+
+/// Suffix for files still being downloaded; renamed into place once verified.
+const PARTIAL_SUFFIX: &str = ".part";
+
+/// Minimum bytes between progress callbacks for one file.
+const PROGRESS_INTERVAL_BYTES: u64 = 1024 * 1024;
+
+/// Byte progress for one file of a model download.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileProgress {
+    pub repo_id: &'static str,
+    pub file: &'static str,
+    pub downloaded_bytes: u64,
+    /// None when the server doesn't report a length
+    pub total_bytes: Option<u64>,
+}
+
+/// Size and SHA-256 of a verified model file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChecksum {
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Verified hashes of a model's files, by file name.
+pub type Manifest = HashMap<String, FileChecksum>;
+
+fn manifest_path(models_dir: &Path, model: &ModelInfo) -> PathBuf {
+    model.local_path(models_dir).join(MANIFEST_FILE)
+}
+
+/// Read a model's checksum manifest. Models downloaded before checksums were
+/// recorded have none.
+pub fn read_manifest(models_dir: &Path, model: &ModelInfo) -> Option<Manifest> {
+    read_manifest_at(&model.local_path(models_dir))
+}
+
+/// Read the checksum manifest in a model folder, if it has one.
+pub fn read_manifest_at(dir: &Path) -> Option<Manifest> {
+    let contents = std::fs::read_to_string(dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Write a model's checksum manifest atomically.
+pub fn write_manifest(
+    models_dir: &Path,
+    model: &ModelInfo,
+    manifest: &Manifest,
+) -> Result<(), AppError> {
+    let path = manifest_path(models_dir, model);
+    let contents = serde_json::to_string_pretty(manifest)
+        .map_err(|e| AppError::Storage(format!("Failed to serialize checksums: {}", e)))?;
+    let temp = partial_path(&path);
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, &path)?;
+    Ok(())
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_SUFFIX);
+    path.with_file_name(name)
+}
+
+/// Hex-encoded SHA-256 of a file on disk.
+pub fn sha256_file(path: &Path) -> Result<String, AppError> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Files of a model that are missing or fail verification.
+///
+/// A file is corrupt when its size or SHA-256 differs from the manifest, its hash
+/// differs from a hash pinned in `ModelInfo`, or (for safetensors) its header
+/// doesn't match its length. A file with neither a recorded checksum nor a pinned
+/// hash can't be verified, so it counts as corrupt too.
+pub fn verify_model(models_dir: &Path, model: &ModelInfo) -> Vec<&'static str> {
+    let manifest = read_manifest(models_dir, model).unwrap_or_default();
+    model
+        .files()
+        .into_iter()
+        .filter(|file| {
+            let path = model.local_path(models_dir).join(file);
+            !verify_file(&path, manifest.get(*file), model.pinned_sha256(file))
+        })
+        .collect()
+}
+
+fn verify_file(path: &Path, recorded: Option<&FileChecksum>, pinned: Option<&str>) -> bool {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return false;
+    };
+    if recorded.is_some_and(|checksum| checksum.size != metadata.len()) {
+        return false;
+    }
+    if recorded.is_none() && pinned.is_none() {
+        return false;
+    }
+    let Ok(actual) = sha256_file(path) else {
+        return false;
+    };
+    if recorded.is_some_and(|checksum| checksum.sha256 != actual)
+        || pinned.is_some_and(|expected| expected != actual)
+    {
+        return false;
+    }
+    if path.extension().is_some_and(|ext| ext == "safetensors") {
+        return unsafe { candle_core::safetensors::MmapedSafetensors::new(path) }.is_ok();
+    }
+    true
+}
+
+/// Check a model's files against its manifest without hashing them.
+/// Fast enough for status checks; `verify_model` does the full check.
+///
+/// Files without a recorded checksum (from downloads made before checksums were
+/// recorded) don't match: they are only trusted once `download_model` has checked
+/// them against the Hub's hashes.
+pub fn sizes_match(models_dir: &Path, model: &ModelInfo) -> bool {
+    let Some(manifest) = read_manifest(models_dir, model) else {
+        return false;
+    };
+    model.files().into_iter().all(|file| {
+        let path = model.local_path(models_dir).join(file);
+        match (manifest.get(file), std::fs::metadata(&path)) {
+            (Some(checksum), Ok(metadata)) => checksum.size == metadata.len(),
+            _ => false,
+        }
+    })
+}
+
+/// Download every file of a model that isn't already on disk and verified.
+pub async fn download_model(
+    models_dir: &Path,
+    model: ModelInfo,
+    on_progress: &(dyn Fn(FileProgress) + Send + Sync),
+) -> Result<(), AppError> {
+    // Missing files, plus files without a checksum or whose size no longer matches it
+    let manifest = read_manifest(models_dir, &model).unwrap_or_default();
+    let pending: Vec<&'static str> = model
+        .files()
+        .into_iter()
+        .filter(|file| {
+            let path = model.local_path(models_dir).join(file);
+            match std::fs::metadata(&path) {
+                Ok(metadata) => manifest
+                    .get(*file)
+                    .is_none_or(|checksum| checksum.size != metadata.len()),
+                Err(_) => true,
+            }
+        })
+        .collect();
+    download_files(models_dir, model, &pending, on_progress).await
+}
+
+/// Re-download the files of a model that fail verification.
+/// Returns the files that were replaced.
+pub async fn repair_model(
+    models_dir: &Path,
+    model: ModelInfo,
+    on_progress: &(dyn Fn(FileProgress) + Send + Sync),
+) -> Result<Vec<&'static str>, AppError> {
+    let local_path = model.local_path(models_dir);
+    let corrupt = {
+        let models_dir = models_dir.to_path_buf();
+        tokio::task::spawn_blocking(move || verify_model(&models_dir, &model))
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?
+    };
+    if corrupt.is_empty() {
+        return Ok(corrupt);
+    }
+
+    log::warn!("Repairing {}: {}", model.repo_id, corrupt.join(", "));
+    for file in &corrupt {
+        let path = local_path.join(file);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        // A corrupt partial download can't be resumed either
+        let partial = partial_path(&path);
+        if partial.exists() {
+            std::fs::remove_file(&partial)?;
+        }
+    }
+
+    let mut manifest = read_manifest(models_dir, &model).unwrap_or_default();
+    for file in &corrupt {
+        manifest.remove(*file);
+    }
+    write_manifest(models_dir, &model, &manifest)?;
+
+    download_files(models_dir, model, &corrupt, on_progress).await?;
+    Ok(corrupt)
+}
+
+async fn download_files(
+    models_dir: &Path,
+    model: ModelInfo,
+    files: &[&'static str],
+    on_progress: &(dyn Fn(FileProgress) + Send + Sync),
+) -> Result<(), AppError> {
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    let local_path = model.local_path(models_dir);
+    std::fs::create_dir_all(&local_path)?;
+    log::info!(
+        "Downloading model {} to {}",
+        model.repo_id,
+        local_path.display()
+    );
+
+    let endpoint = std::env::var("HF_ENDPOINT").unwrap_or_else(|_| DEFAULT_HF_ENDPOINT.to_string());
+    let client = Client::new();
+    // The Hub publishes LFS hashes on its redirect response, so metadata is
+    // fetched without following redirects
+    let metadata_client = Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(download_error)?;
+    let mut manifest = read_manifest(models_dir, &model).unwrap_or_default();
+
+    for &file in files {
+        log::info!("Downloading {}...", file);
+        let url = format!(
+            "{}/{}/resolve/{}/{}",
+            endpoint.trim_end_matches('/'),
+            model.repo_id,
+            REVISION,
+            file
+        );
+        let expected_sha256 = match model.pinned_sha256(file) {
+            Some(pinned) => Some(pinned.to_string()),
+            None => {
+                let response = metadata_client
+                    .head(&url)
+                    .send()
+                    .await
+                    .map_err(download_error)?;
+                hub_sha256(response.headers())
+            }
+        };
+        let dest = local_path.join(file);
+
+        // A file kept from before checksums were recorded only needs hashing
+        if let Some(checksum) = matching_file(&dest, expected_sha256.as_deref()).await? {
+            log::info!("{} already matches its published hash", file);
+            manifest.insert(file.to_string(), checksum);
+            write_manifest(models_dir, &model, &manifest)?;
+            continue;
+        }
+
+        let checksum = download_file(
+            &client,
+            &url,
+            &dest,
+            expected_sha256,
+            &|downloaded_bytes, total_bytes| {
+                on_progress(FileProgress {
+                    repo_id: model.repo_id,
+                    file,
+                    downloaded_bytes,
+                    total_bytes,
+                })
+            },
+        )
+        .await?;
+        manifest.insert(file.to_string(), checksum);
+        // Record progress per file so a later failure doesn't lose verified files
+        write_manifest(models_dir, &model, &manifest)?;
+    }
+
+    log::info!("Model download complete");
+    Ok(())
+}
+
+/// Checksum of a file already on disk whose SHA-256 matches `expected`.
+/// Hashed on a blocking thread; `None` when there is no such file or no hash to
+/// compare against.
+async fn matching_file(
+    path: &Path,
+    expected: Option<&str>,
+) -> Result<Option<FileChecksum>, AppError> {
+    let Some(expected) = expected else {
+        return Ok(None);
+    };
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Ok(None);
+    };
+    let path = path.to_path_buf();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&path))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))??;
+    Ok((sha256 == expected).then_some(FileChecksum {
+        size: metadata.len(),
+        sha256,
+    }))
+}
+
+/// Download one file, resuming a previous partial download if there is one.
+///
+/// Bytes go to `<dest>.part`, which is renamed over `dest` only after its SHA-256
+/// matches the expected hash (pinned in `ModelInfo`, or reported by the Hub for
+/// LFS files). Small non-LFS files have no published SHA-256; their hash is
+/// recorded as downloaded.
+async fn download_file(
+    client: &Client,
+    url: &str,
+    dest: &Path,
+    expected_sha256: Option<String>,
+    on_progress: &(dyn Fn(u64, Option<u64>) + Send + Sync),
+) -> Result<FileChecksum, AppError> {
+    let partial = partial_path(dest);
+    let mut existing = std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if existing > 0 {
+        log::info!("Resuming {} from byte {}", url, existing);
+        request = request.header(header::RANGE, format!("bytes={}-", existing));
+    }
+    let response = request.send().await.map_err(download_error)?;
+
+    let status = response.status();
+
+    let total_bytes = if status == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The partial file already holds every byte
+        Some(existing)
+    } else if !status.is_success() {
+        return Err(download_error(format!("{} returned {}", url, status)));
+    } else {
+        let mut file = if status == StatusCode::PARTIAL_CONTENT {
+            std::fs::OpenOptions::new().append(true).open(&partial)?
+        } else {
+            // The server ignored the range; start over
+            existing = 0;
+            std::fs::File::create(&partial)?
+        };
+        let total_bytes = response.content_length().map(|len| len + existing);
+
+        let mut downloaded = existing;
+        let mut reported = downloaded;
+        on_progress(downloaded, total_bytes);
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.map_err(download_error)?;
+            file.write_all(&chunk)?;
+            downloaded += chunk.len() as u64;
+            if downloaded - reported >= PROGRESS_INTERVAL_BYTES {
+                on_progress(downloaded, total_bytes);
+                reported = downloaded;
+            }
+        }
+        file.sync_all()?;
+        on_progress(downloaded, total_bytes);
+        total_bytes
+    };
+
+    let size = std::fs::metadata(&partial)?.len();
+    if total_bytes.is_some_and(|total| total != size) {
+        // Keep the partial file; the next attempt resumes from it
+        return Err(download_error(format!(
+            "Incomplete download of {}: {} of {} bytes",
+            url,
+            size,
+            total_bytes.unwrap_or_default()
+        )));
+    }
+
+    let partial_for_hash = partial.clone();
+    let sha256 = tokio::task::spawn_blocking(move || sha256_file(&partial_for_hash))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))??;
+    if let Some(expected) = expected_sha256 {
+        if expected != sha256 {
+            std::fs::remove_file(&partial)?;
+            return Err(download_error(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                url, expected, sha256
+            )));
+        }
+    }
+
+    std::fs::rename(&partial, dest)?;
+    Ok(FileChecksum { size, sha256 })
+}
+
+/// SHA-256 the Hub reports for LFS files (`X-Linked-ETag`). The plain ETag of
+/// non-LFS files is a git SHA-1, so only 64-character hex values are accepted.
+fn hub_sha256(headers: &header::HeaderMap) -> Option<String> {
+    let etag = headers.get("x-linked-etag")?.to_str().ok()?;
+    let etag = etag.trim_start_matches("W/").trim_matches('"');
+    (etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit())).then(|| etag.to_lowercase())
+}
+
+fn download_error(e: impl std::fmt::Display) -> AppError {
+    AppError::Io(std::io::Error::other(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MODEL: ModelInfo = ModelInfo {
+        repo_id: "test/model",
+        model_file: "model.safetensors",
+        tokenizer_file: "tokenizer.json",
+        config_file: "config.json",
+        local_dir: "test-model",
+        extra_files: &[],
+        pinned_sha256: &[("config.json", EMPTY_SHA256)],
+    };
+
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn write_model(models_dir: &Path) {
+        let dir = TEST_MODEL.local_path(models_dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let weights = [(
+            "w".to_string(),
+            candle_core::Tensor::zeros(16, candle_core::DType::F32, &candle_core::Device::Cpu)
+                .unwrap(),
+        )];
+        candle_core::safetensors::save(
+            &weights.into_iter().collect(),
+            dir.join("model.safetensors"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("tokenizer.json"), "{}").unwrap();
+        std::fs::write(dir.join("config.json"), "").unwrap();
+    }
+
+    /// Record the files as they are on disk, as a verified download would.
+    fn record_manifest(models_dir: &Path) {
+        let mut manifest = Manifest::new();
+        for file in TEST_MODEL.files() {
+            let path = TEST_MODEL.local_path(models_dir).join(file);
+            let checksum = FileChecksum {
+                size: std::fs::metadata(&path).unwrap().len(),
+                sha256: sha256_file(&path).unwrap(),
+            };
+            manifest.insert(file.to_string(), checksum);
+        }
+        write_manifest(models_dir, &TEST_MODEL, &manifest).unwrap();
+    }
+
+    #[test]
+    fn test_verify_detects_truncated_and_modified_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_model(dir.path());
+        record_manifest(dir.path());
+        assert!(verify_model(dir.path(), &TEST_MODEL).is_empty());
+        assert!(sizes_match(dir.path(), &TEST_MODEL));
+
+        // Same length, different content
+        let local = TEST_MODEL.local_path(dir.path());
+        std::fs::write(local.join("tokenizer.json"), "[]").unwrap();
+        assert_eq!(
+            verify_model(dir.path(), &TEST_MODEL),
+            vec!["tokenizer.json"]
+        );
+
+        // Truncated weights fail on size alone
+        let weights = local.join("model.safetensors");
+        let bytes = std::fs::read(&weights).unwrap();
+        std::fs::write(&weights, &bytes[..bytes.len() - 8]).unwrap();
+        assert!(!sizes_match(dir.path(), &TEST_MODEL));
+        assert!(verify_model(dir.path(), &TEST_MODEL).contains(&"model.safetensors"));
+    }
+
+    #[test]
+    fn test_verify_without_manifest_only_trusts_pinned_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_model(dir.path());
+        let local = TEST_MODEL.local_path(dir.path());
+
+        // Only config.json has a pin to check against
+        assert_eq!(
+            verify_model(dir.path(), &TEST_MODEL),
+            vec!["model.safetensors", "tokenizer.json"]
+        );
+        std::fs::write(local.join("config.json"), "{}").unwrap();
+        assert_eq!(verify_model(dir.path(), &TEST_MODEL).len(), 3);
+    }
+
+    #[test]
+    fn test_sizes_match_requires_recorded_checksums() {
+        let dir = tempfile::tempdir().unwrap();
+        write_model(dir.path());
+        let local = TEST_MODEL.local_path(dir.path());
+
+        // Status checks neither trust nor record files without a manifest
+        assert!(!sizes_match(dir.path(), &TEST_MODEL));
+        assert!(read_manifest(dir.path(), &TEST_MODEL).is_none());
+
+        record_manifest(dir.path());
+        assert!(sizes_match(dir.path(), &TEST_MODEL));
+
+        let mut manifest = read_manifest(dir.path(), &TEST_MODEL).unwrap();
+        manifest.remove("tokenizer.json");
+        write_manifest(dir.path(), &TEST_MODEL, &manifest).unwrap();
+        assert!(!sizes_match(dir.path(), &TEST_MODEL));
+        assert!(local.join("tokenizer.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_matching_file_checks_the_expected_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        assert_eq!(
+            matching_file(&path, Some(EMPTY_SHA256)).await.unwrap(),
+            None
+        );
+
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(matching_file(&path, None).await.unwrap(), None);
+        assert_eq!(
+            matching_file(&path, Some(EMPTY_SHA256)).await.unwrap(),
+            Some(FileChecksum {
+                size: 0,
+                sha256: EMPTY_SHA256.to_string(),
+            })
+        );
+
+        std::fs::write(&path, "{}").unwrap();
+        assert_eq!(
+            matching_file(&path, Some(EMPTY_SHA256)).await.unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_hub_sha256_only_accepts_lfs_hashes() {
+        let mut headers = header::HeaderMap::new();
+        assert_eq!(hub_sha256(&headers), None);
+
+        headers.insert(
+            "x-linked-etag",
+            format!("\"{}\"", EMPTY_SHA256.to_uppercase())
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(hub_sha256(&headers), Some(EMPTY_SHA256.to_string()));
+
+        headers.insert(
+            "x-linked-etag",
+            "\"da39a3ee5e6b4b0d3255bfef95601890afd80709\""
+                .parse()
+                .unwrap(),
+        );
+        assert_eq!(hub_sha256(&headers), None);
+    }
+}
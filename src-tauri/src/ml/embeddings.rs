@@ -1,13 +1,54 @@
 use std::path::Path;
+use std::sync::OnceLock;
 
 use candle_core::{DType, Device, Tensor};
 use candle_nn::VarBuilder;
-use candle_transformers::models::bert::{BertModel, Config};
+use candle_transformers::models::bert::Config;
 use regex::Regex;
-use tokenizers::Tokenizer;
+use tokenizers::{Encoding, Tokenizer};
 
 use crate::error::AppError;
-use crate::ml::models::{get_device, EMBEDDING_MODEL};
+use crate::ml::encoders::Bert;
+use crate::ml::models::{
+    estimate_weight_bytes, get_device, EmbeddingModelSpec, Pooling, Precision,
+};
+
+/// Maximum texts run through the model in one forward pass.
+/// Larger batches stop paying off on CPU once padding and memory bandwidth dominate.
+const MAX_BATCH_SIZE: usize = 16;
+
+/// Split text into sentences at sentence boundaries (terminal punctuation followed by
+/// whitespace, or a blank line). Returns trimmed (start, end) byte ranges; terminal
+/// punctuation stays with its sentence.
+pub fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    static SENTENCE_RE: OnceLock<Regex> = OnceLock::new();
+    let sentence_re =
+        SENTENCE_RE.get_or_init(|| Regex::new(r"(?:[.!?]\s+|\n\n+)").expect("Invalid regex"));
+
+    let mut spans = Vec::new();
+    let mut push_trimmed = |start: usize, end: usize| {
+        let raw = &text[start..end];
+        let leading = raw.len() - raw.trim_start().len();
+        let trimmed = raw.trim();
+        if !trimmed.is_empty() {
+            spans.push((start + leading, start + leading + trimmed.len()));
+        }
+    };
+
+    let mut start = 0;
+    for boundary in sentence_re.find_iter(text) {
+        let end = if boundary.as_str().starts_with(['.', '!', '?']) {
+            boundary.start() + 1
+        } else {
+            boundary.start()
+        };
+        push_trimmed(start, end);
+        start = boundary.end();
+    }
+    push_trimmed(start, text.len());
+
+    spans
+}
 
 /// Chunk text into smaller segments for better embedding quality.
 /// Uses sentence boundaries with overlap for context preservation.
@@ -17,21 +58,16 @@ pub fn chunk_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<Str
         return vec![text.to_string()];
     }
 
-    // Split by sentence boundaries (period followed by space, or double newline)
-    let sentence_re = Regex::new(r"(?:[.!?]\s+|\n\n+)").expect("Invalid regex");
-    let sentences: Vec<&str> = sentence_re.split(text).collect();
+    let sentences = sentence_spans(text)
+        .into_iter()
+        .map(|(start, end)| &text[start..end]);
 
     let mut chunks = Vec::new();
     let mut current_chunk = String::new();
     let mut overlap_buffer = String::new();
 
     for sentence in sentences {
-        let sentence = sentence.trim();
-        if sentence.is_empty() {
-            continue;
-        }
-
-        // Add sentence ending back (simplified - just use period)
+        // Sentences cut at a blank line have no terminal punctuation; add a period
         let sentence_with_punct = if sentence.ends_with(['.', '!', '?']) {
             sentence.to_string()
         } else {
@@ -50,11 +86,7 @@ pub fn chunk_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<Str
             chunks.push(current_chunk.clone());
 
             // Start new chunk with overlap from previous
-            current_chunk = if overlap_buffer.len() > overlap_chars {
-                overlap_buffer[overlap_buffer.len() - overlap_chars..].to_string()
-            } else {
-                overlap_buffer.clone()
-            };
+            current_chunk = last_chars(&overlap_buffer, overlap_chars).to_string();
 
             if !current_chunk.is_empty() {
                 current_chunk.push(' ');
@@ -86,19 +118,46 @@ pub fn chunk_text(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<Str
     chunks
 }
 
-/// Embedding model wrapper using all-MiniLM-L6-v2.
+/// The last `count` characters of `text` (all of it if shorter). Cut on a character
+/// boundary, since a byte offset can fall inside a multibyte character.
+fn last_chars(text: &str, count: usize) -> &str {
+    if count == 0 {
+        return "";
+    }
+    match text.char_indices().rev().nth(count - 1) {
+        Some((start, _)) => &text[start..],
+        None => text,
+    }
+}
+
+/// Embedding model wrapper for BERT-style sentence embedding models.
 pub struct EmbeddingModel {
-    model: BertModel,
+    model: Bert,
     tokenizer: Tokenizer,
+    /// Token used to pad shorter sequences in a batch ([PAD] for BERT, <pad> for XLM-R vocabularies)
+    pad_token_id: u32,
     device: Device,
+    spec: &'static EmbeddingModelSpec,
+    precision: Precision,
+    memory_bytes: u64,
 }
 
 impl EmbeddingModel {
-    /// Load the embedding model from disk.
-    pub fn load(models_dir: &Path) -> Result<Self, AppError> {
-        let model_path = EMBEDDING_MODEL.model_path(models_dir);
-        let tokenizer_path = EMBEDDING_MODEL.tokenizer_path(models_dir);
-        let config_path = EMBEDDING_MODEL.config_path(models_dir);
+    /// Load an embedding model from disk with weights in the given precision.
+    pub fn load(
+        models_dir: &Path,
+        spec: &'static EmbeddingModelSpec,
+        precision: Precision,
+    ) -> Result<Self, AppError> {
+        let info = spec.local_info().ok_or_else(|| {
+            AppError::Ml(format!(
+                "Embedding model {} doesn't run in-process",
+                spec.id
+            ))
+        })?;
+        let model_path = info.model_path(models_dir);
+        let tokenizer_path = info.tokenizer_path(models_dir);
+        let config_path = info.config_path(models_dir);
 
         log::info!("Loading embedding model from: {}", model_path.display());
 
@@ -113,69 +172,136 @@ impl EmbeddingModel {
 
         // Load model weights
         let device = get_device();
+        let precision = precision.supported_on(&device);
+        let memory_bytes = estimate_weight_bytes(&model_path, precision.dtype());
         let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&[model_path], DType::F32, &device)
+            VarBuilder::from_mmaped_safetensors(&[model_path], precision.dtype(), &device)
                 .map_err(|e| AppError::Ml(format!("Failed to load weights: {}", e)))?
         };
 
-        let model = BertModel::load(vb, &config)
+        let model = Bert::load(vb, &config)
             .map_err(|e| AppError::Ml(format!("Failed to load model: {}", e)))?;
 
+        let pad_token_id = tokenizer
+            .get_padding()
+            .map(|padding| padding.pad_id)
+            .or_else(|| tokenizer.token_to_id("[PAD]"))
+            .or_else(|| tokenizer.token_to_id("<pad>"))
+            .unwrap_or(0);
+
         Ok(Self {
             model,
             tokenizer,
+            pad_token_id,
             device,
+            spec,
+            precision,
+            memory_bytes,
         })
     }
 
+    /// The registry entry this model was loaded from.
+    pub fn spec(&self) -> &'static EmbeddingModelSpec {
+        self.spec
+    }
+
+    /// Precision the weights were loaded in.
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Estimated memory held by the loaded weights.
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory_bytes
+    }
+
     /// Generate an embedding for the given text.
     pub fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
-        // Tokenize the input
-        let encoding = self
-            .tokenizer
-            .encode(text, true)
-            .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))?;
+        self.embed_batch(&[text])?
+            .pop()
+            .ok_or_else(|| AppError::Ml("No embedding produced".to_string()))
+    }
+
+    /// Generate embeddings for several texts, returned in input order.
+    /// Texts are grouped by token length and each group runs as one padded forward pass.
+    pub fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError> {
+        let encodings = texts
+            .iter()
+            .map(|text| {
+                self.tokenizer
+                    .encode(*text, true)
+                    .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Batch similar lengths together to minimise padding
+        let mut order: Vec<usize> = (0..encodings.len()).collect();
+        order.sort_by_key(|&i| encodings[i].get_ids().len());
+
+        let mut embeddings = vec![Vec::new(); encodings.len()];
+        for batch in order.chunks(MAX_BATCH_SIZE) {
+            let batch_encodings: Vec<&Encoding> = batch.iter().map(|&i| &encodings[i]).collect();
+            let batch_embeddings = self.forward_batch(&batch_encodings)?;
+            for (&i, embedding) in batch.iter().zip(batch_embeddings) {
+                embeddings[i] = embedding;
+            }
+        }
 
-        let input_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
-        let token_type_ids = encoding.get_type_ids();
+        Ok(embeddings)
+    }
+
+    /// Run one forward pass over encodings padded to the longest one.
+    fn forward_batch(&self, encodings: &[&Encoding]) -> Result<Vec<Vec<f32>>, AppError> {
+        let batch_size = encodings.len();
+        let seq_len = encodings
+            .iter()
+            .map(|e| e.get_ids().len())
+            .max()
+            .unwrap_or(0);
+
+        let mut input_ids = Vec::with_capacity(batch_size * seq_len);
+        let mut attention_mask = Vec::with_capacity(batch_size * seq_len);
+        let mut token_type_ids = Vec::with_capacity(batch_size * seq_len);
+        for encoding in encodings {
+            let padding = seq_len - encoding.get_ids().len();
+            input_ids.extend_from_slice(encoding.get_ids());
+            input_ids.extend(std::iter::repeat_n(self.pad_token_id, padding));
+            // Padding positions are excluded from attention and mean pooling
+            attention_mask.extend_from_slice(encoding.get_attention_mask());
+            attention_mask.extend(std::iter::repeat_n(0u32, padding));
+            token_type_ids.extend_from_slice(encoding.get_type_ids());
+            token_type_ids.extend(std::iter::repeat_n(0u32, padding));
+        }
 
         // Convert to tensors
-        let input_ids = Tensor::new(input_ids, &self.device)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .unsqueeze(0)
+        let shape = (batch_size, seq_len);
+        let input_ids = Tensor::from_vec(input_ids, shape, &self.device)
             .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        let attention_mask = Tensor::new(attention_mask, &self.device)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .unsqueeze(0)
+        let attention_mask = Tensor::from_vec(attention_mask, shape, &self.device)
             .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        let token_type_ids = Tensor::new(token_type_ids, &self.device)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .unsqueeze(0)
+        let token_type_ids = Tensor::from_vec(token_type_ids, shape, &self.device)
             .map_err(|e| AppError::Ml(e.to_string()))?;
 
         // Run inference
         let output = self
             .model
-            .forward(&input_ids, &token_type_ids, Some(&attention_mask))
+            .forward(&input_ids, &token_type_ids, &attention_mask)
+            .and_then(|output| output.to_dtype(DType::F32))
             .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
 
-        // Mean pooling over sequence dimension (considering attention mask)
-        let embedding = mean_pooling(&output, &attention_mask)?;
+        let embeddings = match self.spec.pooling {
+            // Mean pooling over sequence dimension (considering attention mask)
+            Pooling::Mean => mean_pooling(&output, &attention_mask)?,
+            Pooling::Cls => cls_pooling(&output)?,
+        };
 
         // L2 normalize
-        let embedding = l2_normalize(&embedding)?;
-
-        // Convert to Vec<f32>
-        let embedding: Vec<f32> = embedding
-            .squeeze(0)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .to_vec1()
-            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let embeddings = l2_normalize(&embeddings)?;
 
-        Ok(embedding)
+        // Convert to Vec<Vec<f32>>
+        embeddings
+            .to_vec2()
+            .map_err(|e| AppError::Ml(e.to_string()))
     }
 }
 
@@ -211,6 +337,16 @@ fn mean_pooling(embeddings: &Tensor, attention_mask: &Tensor) -> Result<Tensor,
         .map_err(|e| AppError::Ml(e.to_string()))
 }
 
+/// CLS pooling: use the hidden state of the first ([CLS]) token.
+fn cls_pooling(embeddings: &Tensor) -> Result<Tensor, AppError> {
+    // embeddings: [batch, seq_len, hidden_size] -> [batch, hidden_size]
+    embeddings
+        .narrow(1, 0, 1)
+        .map_err(|e| AppError::Ml(e.to_string()))?
+        .squeeze(1)
+        .map_err(|e| AppError::Ml(e.to_string()))
+}
+
 /// L2 normalize an embedding tensor.
 fn l2_normalize(embedding: &Tensor) -> Result<Tensor, AppError> {
     let norm = embedding
@@ -231,7 +367,7 @@ fn l2_normalize(embedding: &Tensor) -> Result<Tensor, AppError> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::db::vectors::EMBEDDING_DIM;
+    use crate::ml::models::{MINILM_EMBEDDING, PRECISION_FIXTURES};
 
     #[test]
     fn test_chunk_text_short() {
@@ -257,6 +393,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_chunk_text_non_ascii() {
+        let hindi = "आज सुबह मैं नदी के किनारे टहलने गया. ".repeat(20);
+        let german = "Heute früh bin ich am Fluss spazieren gegangen, schön ruhig. ".repeat(20);
+        for text in [hindi.as_str(), german.as_str()] {
+            // Every overlap length, so the cut lands at every offset within a character
+            for overlap in 0..12 {
+                let chunks = chunk_text(text, 120, overlap);
+                assert!(chunks.len() > 1);
+            }
+        }
+        assert_eq!(last_chars("früh", 3), "rüh");
+        assert_eq!(last_chars("नदी", 10), "नदी");
+        assert_eq!(last_chars("नदी", 0), "");
+    }
+
     #[test]
     fn test_chunk_text_preserves_content() {
         let text = "Sentence one. Sentence two. Sentence three.";
@@ -267,6 +419,20 @@ mod tests {
         assert!(chunks.iter().any(|c| c.contains("three")));
     }
 
+    #[test]
+    fn test_sentence_spans() {
+        let text = "  I woke up early!  The sun was out.\n\nThen it rained  ";
+        let sentences: Vec<&str> = sentence_spans(text)
+            .into_iter()
+            .map(|(start, end)| &text[start..end])
+            .collect();
+        assert_eq!(
+            sentences,
+            vec!["I woke up early!", "The sun was out.", "Then it rained"]
+        );
+        assert!(sentence_spans("   ").is_empty());
+    }
+
     #[test]
     fn test_chunk_text_empty() {
         let text = "";
@@ -279,16 +445,16 @@ mod tests {
     #[ignore = "Requires model download"]
     fn test_embedding_dimension() {
         let models_dir = std::path::PathBuf::from("../models");
-        let model = EmbeddingModel::load(&models_dir).unwrap();
+        let model = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
         let embedding = model.embed("Hello, world!").unwrap();
-        assert_eq!(embedding.len(), EMBEDDING_DIM);
+        assert_eq!(embedding.len(), MINILM_EMBEDDING.dimension);
     }
 
     #[test]
     #[ignore = "Requires model download"]
     fn test_similar_texts_have_similar_embeddings() {
         let models_dir = std::path::PathBuf::from("../models");
-        let model = EmbeddingModel::load(&models_dir).unwrap();
+        let model = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
 
         let e1 = model.embed("I am happy today").unwrap();
         let e2 = model.embed("I feel joyful today").unwrap();
@@ -301,6 +467,44 @@ mod tests {
         assert!(sim_12 > sim_13);
     }
 
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_embed_batch_matches_single() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
+
+        // Different lengths so the shorter texts are padded
+        let texts = [
+            "Short one.",
+            "A considerably longer sentence about a walk along the river after work.",
+            "Medium length text here.",
+        ];
+        let batch = model.embed_batch(&texts).unwrap();
+        assert_eq!(batch.len(), texts.len());
+
+        for (text, batched) in texts.iter().zip(&batch) {
+            let single = model.embed(text).unwrap();
+            assert!(cosine_similarity(&single, batched) > 0.9999);
+        }
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_reduced_precision_matches_f32() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let full = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, Precision::F32).unwrap();
+        let reference = full.embed_batch(PRECISION_FIXTURES).unwrap();
+
+        for precision in [Precision::F16, Precision::Bf16] {
+            let model = EmbeddingModel::load(&models_dir, &MINILM_EMBEDDING, precision).unwrap();
+            assert_eq!(model.memory_bytes() * 2, full.memory_bytes());
+            let embeddings = model.embed_batch(PRECISION_FIXTURES).unwrap();
+            for (expected, actual) in reference.iter().zip(&embeddings) {
+                assert!(cosine_similarity(expected, actual) > 0.99);
+            }
+        }
+    }
+
     fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
         let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
         let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
@@ -0,0 +1,348 @@
+use candle_core::{DType, Result, Tensor};
+use candle_nn::{embedding, layer_norm, linear, Embedding, LayerNorm, Linear, Module, VarBuilder};
+use candle_transformers::models::bert::{BertEncoder, Config as BertConfig};
+use serde::Deserialize;
+
+// candle's BertModel and DistilBertModel build their attention masks in F32, which
+// can't be added to F16/BF16 attention scores. These wrappers build the mask in the
+// weights' dtype so the same encoders run at any precision.
+
+/// Additive attention mask from a padding mask (1 = attend, 0 = padding):
+/// 0 where attended and the dtype's most negative value where padded.
+/// Shape [batch, seq_len] -> [batch, 1, 1, seq_len], broadcast over heads and queries.
+fn additive_mask(attention_mask: &Tensor, dtype: DType) -> Result<Tensor> {
+    let min = match dtype {
+        DType::F16 => -65504.0,
+        DType::BF16 => -3.38e38,
+        _ => f32::MIN as f64,
+    };
+    let mask = attention_mask.unsqueeze(1)?.unsqueeze(1)?.to_dtype(dtype)?;
+    (mask.ones_like()? - mask)?.affine(min, 0.0)
+}
+
+/// BERT encoder (embeddings + transformer layers) for any float dtype.
+pub struct Bert {
+    word_embeddings: Embedding,
+    position_embeddings: Embedding,
+    token_type_embeddings: Embedding,
+    layer_norm: LayerNorm,
+    encoder: BertEncoder,
+    dtype: DType,
+}
+
+impl Bert {
+    /// Load weights, trying the bare layout first and then the `<model_type>.` prefix.
+    pub fn load(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        match Self::load_at(vb.clone(), config) {
+            Ok(model) => Ok(model),
+            Err(err) => match &config.model_type {
+                Some(model_type) => Self::load_at(vb.pp(model_type), config).map_err(|_| err),
+                None => Err(err),
+            },
+        }
+    }
+
+    fn load_at(vb: VarBuilder, config: &BertConfig) -> Result<Self> {
+        let embeddings = vb.pp("embeddings");
+        Ok(Self {
+            word_embeddings: embedding(
+                config.vocab_size,
+                config.hidden_size,
+                embeddings.pp("word_embeddings"),
+            )?,
+            position_embeddings: embedding(
+                config.max_position_embeddings,
+                config.hidden_size,
+                embeddings.pp("position_embeddings"),
+            )?,
+            token_type_embeddings: embedding(
+                config.type_vocab_size,
+                config.hidden_size,
+                embeddings.pp("token_type_embeddings"),
+            )?,
+            layer_norm: layer_norm(
+                config.hidden_size,
+                config.layer_norm_eps,
+                embeddings.pp("LayerNorm"),
+            )?,
+            encoder: BertEncoder::load(vb.pp("encoder"), config)?,
+            dtype: vb.dtype(),
+        })
+    }
+
+    /// Hidden states [batch, seq_len, hidden] in the weights' dtype.
+    /// The attention mask uses 1 for tokens to attend to and 0 for padding.
+    pub fn forward(
+        &self,
+        input_ids: &Tensor,
+        token_type_ids: &Tensor,
+        attention_mask: &Tensor,
+    ) -> Result<Tensor> {
+        let seq_len = input_ids.dim(1)?;
+        let position_ids = Tensor::arange(0u32, seq_len as u32, input_ids.device())?;
+        let embeddings = (self.word_embeddings.forward(input_ids)?
+            + self.token_type_embeddings.forward(token_type_ids)?)?
+        .broadcast_add(&self.position_embeddings.forward(&position_ids)?)?;
+        let hidden_states = self.layer_norm.forward(&embeddings)?;
+
+        let mask = additive_mask(attention_mask, self.dtype)?;
+        self.encoder.forward(&hidden_states, &mask)
+    }
+}
+
+/// Activation used in DistilBERT's feed-forward layers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistilBertActivation {
+    Gelu,
+    Relu,
+}
+
+/// The parts of a DistilBERT config.json needed to build the encoder.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DistilBertConfig {
+    pub vocab_size: usize,
+    pub dim: usize,
+    pub n_layers: usize,
+    pub n_heads: usize,
+    pub hidden_dim: usize,
+    pub activation: DistilBertActivation,
+    pub max_position_embeddings: usize,
+}
+
+/// DistilBERT layer norms use a fixed epsilon rather than a config value.
+const DISTILBERT_LAYER_NORM_EPS: f64 = 1e-12;
+
+struct DistilBertLayer {
+    q_lin: Linear,
+    k_lin: Linear,
+    v_lin: Linear,
+    out_lin: Linear,
+    sa_layer_norm: LayerNorm,
+    lin1: Linear,
+    lin2: Linear,
+    output_layer_norm: LayerNorm,
+    n_heads: usize,
+    activation: DistilBertActivation,
+}
+
+impl DistilBertLayer {
+    fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let dim = config.dim;
+        let attention = vb.pp("attention");
+        let ffn = vb.pp("ffn");
+        Ok(Self {
+            q_lin: linear(dim, dim, attention.pp("q_lin"))?,
+            k_lin: linear(dim, dim, attention.pp("k_lin"))?,
+            v_lin: linear(dim, dim, attention.pp("v_lin"))?,
+            out_lin: linear(dim, dim, attention.pp("out_lin"))?,
+            sa_layer_norm: layer_norm(dim, DISTILBERT_LAYER_NORM_EPS, vb.pp("sa_layer_norm"))?,
+            lin1: linear(dim, config.hidden_dim, ffn.pp("lin1"))?,
+            lin2: linear(config.hidden_dim, dim, ffn.pp("lin2"))?,
+            output_layer_norm: layer_norm(
+                dim,
+                DISTILBERT_LAYER_NORM_EPS,
+                vb.pp("output_layer_norm"),
+            )?,
+            n_heads: config.n_heads,
+            activation: config.activation,
+        })
+    }
+
+    fn forward(&self, hidden_states: &Tensor, mask: &Tensor) -> Result<Tensor> {
+        let (batch, seq_len, dim) = hidden_states.dims3()?;
+        let head_dim = dim / self.n_heads;
+        let split_heads = |xs: Tensor| -> Result<Tensor> {
+            xs.reshape((batch, seq_len, self.n_heads, head_dim))?
+                .transpose(1, 2)?
+                .contiguous()
+        };
+
+        let q = split_heads(self.q_lin.forward(hidden_states)?)?;
+        let k = split_heads(self.k_lin.forward(hidden_states)?)?;
+        let v = split_heads(self.v_lin.forward(hidden_states)?)?;
+
+        let q = (q / (head_dim as f64).sqrt())?;
+        let scores = q
+            .matmul(&k.transpose(2, 3)?.contiguous()?)?
+            .broadcast_add(mask)?;
+        // Softmax in F32 so half-precision scores don't overflow
+        let weights = candle_nn::ops::softmax_last_dim(&scores.to_dtype(DType::F32)?)?
+            .to_dtype(hidden_states.dtype())?;
+        let context = weights
+            .matmul(&v)?
+            .transpose(1, 2)?
+            .reshape((batch, seq_len, dim))?;
+
+        let sa_output = (self.out_lin.forward(&context)? + hidden_states)?;
+        let sa_output = self.sa_layer_norm.forward(&sa_output)?;
+
+        let ffn_hidden = self.lin1.forward(&sa_output)?;
+        let ffn_hidden = match self.activation {
+            DistilBertActivation::Gelu => ffn_hidden.gelu()?,
+            DistilBertActivation::Relu => ffn_hidden.relu()?,
+        };
+        let ffn_output = (self.lin2.forward(&ffn_hidden)? + sa_output)?;
+        self.output_layer_norm.forward(&ffn_output)
+    }
+}
+
+/// DistilBERT encoder (embeddings + transformer layers) for any float dtype.
+pub struct DistilBert {
+    word_embeddings: Embedding,
+    position_embeddings: Embedding,
+    layer_norm: LayerNorm,
+    layers: Vec<DistilBertLayer>,
+    dtype: DType,
+}
+
+impl DistilBert {
+    pub fn load(vb: VarBuilder, config: &DistilBertConfig) -> Result<Self> {
+        let embeddings = vb.pp("embeddings");
+        let layers = (0..config.n_layers)
+            .map(|index| DistilBertLayer::load(vb.pp(format!("transformer.layer.{index}")), config))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            word_embeddings: embedding(
+                config.vocab_size,
+                config.dim,
+                embeddings.pp("word_embeddings"),
+            )?,
+            position_embeddings: embedding(
+                config.max_position_embeddings,
+                config.dim,
+                embeddings.pp("position_embeddings"),
+            )?,
+            layer_norm: layer_norm(
+                config.dim,
+                DISTILBERT_LAYER_NORM_EPS,
+                embeddings.pp("LayerNorm"),
+            )?,
+            layers,
+            dtype: vb.dtype(),
+        })
+    }
+
+    /// Hidden states [batch, seq_len, dim] in the weights' dtype.
+    /// The attention mask uses 1 for tokens to attend to and 0 for padding.
+    pub fn forward(&self, input_ids: &Tensor, attention_mask: &Tensor) -> Result<Tensor> {
+        let seq_len = input_ids.dim(1)?;
+        let position_ids = Tensor::arange(0u32, seq_len as u32, input_ids.device())?;
+        let embeddings = self
+            .word_embeddings
+            .forward(input_ids)?
+            .broadcast_add(&self.position_embeddings.forward(&position_ids)?)?;
+        let mut hidden_states = self.layer_norm.forward(&embeddings)?;
+
+        let mask = additive_mask(attention_mask, self.dtype)?;
+        for layer in &self.layers {
+            hidden_states = layer.forward(&hidden_states, &mask)?;
+        }
+        Ok(hidden_states)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::Device;
+    use candle_nn::VarMap;
+    use candle_transformers::models::{bert::BertModel, distilbert::DistilBertModel};
+    use std::collections::HashMap;
+
+    const BERT_CONFIG: &str = r#"{
+        "vocab_size": 50, "hidden_size": 32, "num_hidden_layers": 2,
+        "num_attention_heads": 4, "intermediate_size": 64, "hidden_act": "gelu",
+        "hidden_dropout_prob": 0.0, "max_position_embeddings": 16, "type_vocab_size": 2,
+        "initializer_range": 0.02, "layer_norm_eps": 1e-12, "pad_token_id": 0
+    }"#;
+
+    const DISTILBERT_CONFIG: &str = r#"{
+        "vocab_size": 50, "dim": 32, "n_layers": 2, "n_heads": 4, "hidden_dim": 64,
+        "activation": "gelu", "max_position_embeddings": 16, "initializer_range": 0.02,
+        "pad_token_id": 0
+    }"#;
+
+    /// Two sequences, the second padded after three tokens.
+    fn inputs() -> (Tensor, Tensor) {
+        let ids = Tensor::new(&[[2u32, 7, 9, 4, 3], [2, 11, 3, 0, 0]], &Device::Cpu).unwrap();
+        let mask = Tensor::new(&[[1u32, 1, 1, 1, 1], [1, 1, 1, 0, 0]], &Device::Cpu).unwrap();
+        (ids, mask)
+    }
+
+    fn weights(varmap: &VarMap) -> HashMap<String, Tensor> {
+        varmap
+            .data()
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(name, var)| (name.clone(), var.as_tensor().clone()))
+            .collect()
+    }
+
+    /// Largest absolute difference over the unpadded first sequence.
+    fn max_diff(a: &Tensor, b: &Tensor) -> f32 {
+        let a = a.get(0).unwrap().to_dtype(DType::F32).unwrap();
+        let b = b.get(0).unwrap().to_dtype(DType::F32).unwrap();
+        (a - b)
+            .unwrap()
+            .abs()
+            .unwrap()
+            .flatten_all()
+            .unwrap()
+            .max(0)
+            .unwrap()
+            .to_scalar::<f32>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bert_matches_candle_and_runs_in_f16() {
+        let config: BertConfig = serde_json::from_str(BERT_CONFIG).unwrap();
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let reference = BertModel::load(vb.clone(), &config).unwrap();
+        let model = Bert::load(vb, &config).unwrap();
+
+        let (ids, mask) = inputs();
+        let type_ids = ids.zeros_like().unwrap();
+        let expected = reference.forward(&ids, &type_ids, Some(&mask)).unwrap();
+        let output = model.forward(&ids, &type_ids, &mask).unwrap();
+        assert!(max_diff(&expected, &output) < 1e-4);
+
+        let half = VarBuilder::from_tensors(weights(&varmap), DType::F16, &Device::Cpu);
+        let half = Bert::load(half, &config).unwrap();
+        let output = half.forward(&ids, &type_ids, &mask).unwrap();
+        assert_eq!(output.dtype(), DType::F16);
+        assert!(max_diff(&expected, &output) < 0.05);
+    }
+
+    #[test]
+    fn test_distilbert_matches_candle_and_runs_in_f16() {
+        let config: DistilBertConfig = serde_json::from_str(DISTILBERT_CONFIG).unwrap();
+        let candle_config = serde_json::from_str(DISTILBERT_CONFIG).unwrap();
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let reference = DistilBertModel::load(vb.clone(), &candle_config).unwrap();
+        let model = DistilBert::load(vb, &config).unwrap();
+
+        let (ids, mask) = inputs();
+        // candle's DistilBERT takes an inverted mask (1 = padding) that must already
+        // broadcast over heads and queries
+        let inverted = (mask.ones_like().unwrap() - &mask)
+            .unwrap()
+            .to_dtype(DType::U8)
+            .unwrap()
+            .reshape((2, 1, 1, 5))
+            .unwrap();
+        let expected = reference.forward(&ids, &inverted).unwrap();
+        let output = model.forward(&ids, &mask).unwrap();
+        assert!(max_diff(&expected, &output) < 1e-4);
+
+        let half = VarBuilder::from_tensors(weights(&varmap), DType::F16, &Device::Cpu);
+        let half = DistilBert::load(half, &config).unwrap();
+        let output = half.forward(&ids, &mask).unwrap();
+        assert_eq!(output.dtype(), DType::F16);
+        assert!(max_diff(&expected, &output) < 0.05);
+    }
+}
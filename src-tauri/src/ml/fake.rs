@@ -0,0 +1,262 @@
+//! Deterministic stand-ins for the ML backends, so code above `MlState` can be
+//! tested without model weights.
+
+use async_trait::async_trait;
+
+use crate::error::AppError;
+use crate::llm::ollama::CHAT_MODEL;
+use crate::ml::backend::{l2_normalized, Embedder, EmotionClassifier, EntityExtractor, Reranker};
+use crate::ml::models::{EmbeddingModelSpec, NER_MODEL};
+use crate::ml::ner::{self, EntityKind, EntityMention};
+use crate::ml::sentiment::{top_predictions, Aggregation, ChunkedEmotions, EMOTION_LABELS};
+
+/// Hashes words into buckets ("bag of words"), so texts sharing words get similar
+/// vectors and identical texts get identical ones.
+pub struct FakeEmbedder {
+    spec: &'static EmbeddingModelSpec,
+}
+
+impl FakeEmbedder {
+    /// Fake producing vectors of `spec.dimension`, stored under `spec`'s tables.
+    pub fn new(spec: &'static EmbeddingModelSpec) -> Self {
+        Self { spec }
+    }
+
+    fn vector(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.spec.dimension];
+        for word in words(text) {
+            vector[(fnv1a(&word) % self.spec.dimension as u64) as usize] += 1.0;
+        }
+        l2_normalized(vector)
+    }
+}
+
+#[async_trait]
+impl Embedder for FakeEmbedder {
+    fn spec(&self) -> &'static EmbeddingModelSpec {
+        self.spec
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, AppError> {
+        Ok(texts.iter().map(|text| self.vector(text)).collect())
+    }
+}
+
+/// Keywords the fake classifier recognises, with the label each one scores.
+const FAKE_LEXICON: &[(&str, &str)] = &[
+    ("happy", "joy"),
+    ("glad", "joy"),
+    ("joy", "joy"),
+    ("sad", "sadness"),
+    ("lonely", "sadness"),
+    ("cried", "sadness"),
+    ("angry", "anger"),
+    ("furious", "anger"),
+    ("afraid", "fear"),
+    ("scared", "fear"),
+    ("anxious", "nervousness"),
+    ("nervous", "nervousness"),
+    ("grateful", "gratitude"),
+    ("thankful", "gratitude"),
+    ("love", "love"),
+    ("proud", "pride"),
+];
+
+/// Scores each label 0.4 per matching keyword (capped at 0.99); text without
+/// keywords is neutral.
+#[derive(Default)]
+pub struct FakeEmotionClassifier;
+
+impl FakeEmotionClassifier {
+    fn scores(text: &str) -> Vec<f32> {
+        let mut scores = vec![0.0f32; EMOTION_LABELS.len()];
+        for word in words(text) {
+            for (keyword, label) in FAKE_LEXICON {
+                if word == *keyword {
+                    if let Some(idx) = EMOTION_LABELS.iter().position(|l| l == label) {
+                        scores[idx] = (scores[idx] + 0.4).min(0.99);
+                    }
+                }
+            }
+        }
+        scores
+    }
+}
+
+impl EmotionClassifier for FakeEmotionClassifier {
+    fn predict_chunked(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+        _aggregation: Aggregation,
+    ) -> Result<ChunkedEmotions, AppError> {
+        Ok(ChunkedEmotions {
+            predictions: top_predictions(&Self::scores(text), threshold, max_labels),
+            chunks: Vec::new(),
+        })
+    }
+}
+
+/// Names the fake extractor treats as places; other names are people.
+const FAKE_PLACES: &[&str] = &["Berlin", "Lisbon", "London", "Paris"];
+
+/// Treats runs of capitalized words that don't start a sentence as names, then
+/// reports every occurrence of those names (including sentence-initial ones).
+pub struct FakeEntityExtractor {
+    model_version: &'static str,
+}
+
+impl FakeEntityExtractor {
+    /// Fake recording its results under the NER model's version.
+    pub fn ner() -> Self {
+        Self {
+            model_version: NER_MODEL.local_dir,
+        }
+    }
+
+    /// Fake recording its results under the local LLM's version, like the fallback.
+    pub fn llm() -> Self {
+        Self {
+            model_version: CHAT_MODEL,
+        }
+    }
+
+    fn names(text: &str) -> Vec<(EntityKind, String)> {
+        let mut names: Vec<(EntityKind, String)> = Vec::new();
+        let mut run: Vec<&str> = Vec::new();
+        let mut flush = |run: &mut Vec<&str>| {
+            let name = ner::normalize_name(&run.join(" "));
+            run.clear();
+            if name.is_empty() || names.iter().any(|(_, n)| *n == name) {
+                return;
+            }
+            let kind = if FAKE_PLACES.contains(&name.as_str()) {
+                EntityKind::Place
+            } else {
+                EntityKind::Person
+            };
+            names.push((kind, name));
+        };
+
+        let mut sentence_start = true;
+        for token in text.split_whitespace() {
+            let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+            let capitalized = word.chars().next().is_some_and(char::is_uppercase);
+            if capitalized && !sentence_start && word != "I" {
+                run.push(word);
+                if token.ends_with(|c: char| !c.is_alphanumeric()) {
+                    flush(&mut run);
+                }
+            } else {
+                flush(&mut run);
+            }
+            sentence_start = token.ends_with(['.', '!', '?']);
+        }
+        flush(&mut run);
+        names
+    }
+}
+
+#[async_trait]
+impl EntityExtractor for FakeEntityExtractor {
+    fn model_version(&self) -> &'static str {
+        self.model_version
+    }
+
+    async fn extract(&self, text: &str) -> Result<Vec<EntityMention>, AppError> {
+        Ok(ner::locate_mentions(text, &Self::names(text)))
+    }
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is stable across Rust releases.
+fn fnv1a(word: &str) -> u64 {
+    word.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Scores a passage by how many of its words start with a query word.
+#[derive(Default)]
+pub struct FakeReranker;
+
+#[async_trait]
+impl Reranker for FakeReranker {
+    async fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, AppError> {
+        let words = |text: &str| -> Vec<String> {
+            text.split(|c: char| !c.is_alphanumeric())
+                .filter(|w| !w.is_empty())
+                .map(str::to_lowercase)
+                .collect()
+        };
+        let query = words(query);
+        Ok(passages
+            .iter()
+            .map(|passage| {
+                words(passage)
+                    .iter()
+                    .filter(|word| query.iter().any(|q| word.starts_with(q.as_str())))
+                    .count() as f32
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml::models::MINILM_EMBEDDING;
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[tokio::test]
+    async fn test_fake_embedder_is_deterministic() {
+        let embedder = FakeEmbedder::new(&MINILM_EMBEDDING);
+        let a = embedder.embed("A quiet walk by the river").await.unwrap();
+        let b = embedder.embed("a quiet walk by the river").await.unwrap();
+        let c = embedder.embed("Deadlines at work again").await.unwrap();
+
+        assert_eq!(a.len(), MINILM_EMBEDDING.dimension);
+        assert_eq!(a, b);
+        assert!(cosine(&a, &b) > cosine(&a, &c));
+    }
+
+    #[test]
+    fn test_fake_classifier_uses_lexicon() {
+        let classifier = FakeEmotionClassifier;
+        let predictions = classifier
+            .predict("So happy and grateful, really happy", 0.3, 3)
+            .unwrap();
+        assert_eq!(predictions[0].label, "joy");
+        assert_eq!(predictions[1].label, "gratitude");
+
+        let neutral = classifier.predict("Went to the shop", 0.3, 3).unwrap();
+        assert_eq!(neutral[0].label, "neutral");
+    }
+
+    #[tokio::test]
+    async fn test_fake_entity_extractor() {
+        let mentions = FakeEntityExtractor::ner()
+            .extract("Met Sam Rivera in Lisbon. Later Sam's sister came too, and I was glad.")
+            .await
+            .unwrap();
+        let found: Vec<(EntityKind, &str)> =
+            mentions.iter().map(|m| (m.kind, m.name.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (EntityKind::Person, "Sam Rivera"),
+                (EntityKind::Place, "Lisbon"),
+                (EntityKind::Person, "Sam"),
+            ]
+        );
+    }
+}
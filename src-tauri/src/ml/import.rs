@@ -0,0 +1,504 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::ml::download::{self, FileChecksum};
+use crate::ml::embeddings::EmbeddingModel;
+use crate::ml::models::{self, EmbeddingModelSpec, ModelInfo, Precision};
+use crate::ml::multilingual::MultilingualEmotionModel;
+use crate::ml::ner::NerModel;
+use crate::ml::rerank::CrossEncoder;
+use crate::ml::sentiment::SentimentModel;
+
+/// Directories deeper than this below the import source aren't searched.
+const MAX_SEARCH_DEPTH: usize = 5;
+
+/// Largest single file accepted from an archive; the biggest model weights are
+/// around 1 GB.
+const MAX_ARCHIVE_ENTRY_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Largest total size unpacked from one archive.
+const MAX_ARCHIVE_BYTES: u64 = 16 * 1024 * 1024 * 1024;
+
+/// A model that can be sideloaded, and how to check that its files load.
+#[derive(Clone, Copy)]
+enum Importable {
+    /// An in-process embedding model and its download info
+    Embedding(&'static EmbeddingModelSpec, ModelInfo),
+    Sentiment,
+    MultilingualSentiment,
+    Ner,
+    Reranker,
+}
+
+impl Importable {
+    fn all() -> impl Iterator<Item = Importable> {
+        models::EMBEDDING_MODELS
+            .iter()
+            .filter_map(|spec| Some(Importable::Embedding(spec, spec.local_info()?)))
+            .chain([
+                Importable::Sentiment,
+                Importable::MultilingualSentiment,
+                Importable::Ner,
+                Importable::Reranker,
+            ])
+    }
+
+    fn info(&self) -> ModelInfo {
+        match self {
+            Importable::Embedding(_, info) => *info,
+            Importable::Sentiment => models::SENTIMENT_MODEL,
+            Importable::MultilingualSentiment => models::MULTILINGUAL_SENTIMENT_MODEL,
+            Importable::Ner => models::NER_MODEL,
+            Importable::Reranker => models::RERANKER_MODEL,
+        }
+    }
+
+    /// Load the model from `models_dir`, which parses its config and checks every
+    /// tensor's shape against it.
+    fn validate(&self, models_dir: &Path) -> Result<(), AppError> {
+        match self {
+            Importable::Embedding(spec, _) => {
+                EmbeddingModel::load(models_dir, spec, Precision::F32).map(drop)
+            }
+            Importable::Sentiment => SentimentModel::load(models_dir, Precision::F32).map(drop),
+            Importable::MultilingualSentiment => {
+                MultilingualEmotionModel::load(models_dir).map(drop)
+            }
+            Importable::Ner => NerModel::load(models_dir, Precision::F32).map(drop),
+            Importable::Reranker => CrossEncoder::load(models_dir, Precision::F32).map(drop),
+        }
+    }
+}
+
+/// Outcome of importing models from a folder or archive.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    /// Models installed into the models directory
+    pub imported: Vec<&'static str>,
+    /// Models found in the source that failed validation
+    pub rejected: Vec<RejectedModel>,
+}
+
+/// A model whose files were found but couldn't be installed.
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedModel {
+    pub model: &'static str,
+    pub reason: String,
+}
+
+/// Install models from a directory or a `.tar`/`.tar.gz` archive into `models_dir`.
+///
+/// Each model is found by its folder name (the local directory, the repository
+/// name, or a Hugging Face cache `models--org--name` folder). Its files are
+/// copied to a staging folder, checked against pinned hashes and any
+/// `checksums.json` shipped alongside, and loaded once to validate the config and
+/// tensor shapes. Only models that pass replace the installed copy; a file with
+/// no pinned hash and no shipped checksum to compare against fails.
+pub fn import_models(source: &Path, models_dir: &Path) -> Result<ImportReport, AppError> {
+    std::fs::create_dir_all(models_dir)?;
+    let staging = models_dir.join(format!(".import-{}", uuid::Uuid::new_v4()));
+    let result = import_into(source, models_dir, &staging);
+    if staging.exists() {
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+    result
+}
+
+fn import_into(source: &Path, models_dir: &Path, staging: &Path) -> Result<ImportReport, AppError> {
+    let root = if source.is_dir() {
+        source.to_path_buf()
+    } else if source.is_file() {
+        let unpacked = staging.join("archive");
+        unpack_archive(source, &unpacked)?;
+        unpacked
+    } else {
+        return Err(AppError::InvalidInput(format!(
+            "Import source not found: {}",
+            source.display()
+        )));
+    };
+
+    let staged_models = staging.join("models");
+    let mut report = ImportReport::default();
+    for model in Importable::all() {
+        let info = model.info();
+        let Some(found) = find_model_dir(&root, &info) else {
+            continue;
+        };
+        log::info!("Importing {} from {}", info.repo_id, found.display());
+
+        match stage_model(&found, &staged_models, model) {
+            Ok(()) => {
+                install(&staged_models, models_dir, &info)?;
+                report.imported.push(info.local_dir);
+            }
+            Err(e) => {
+                log::warn!("Rejected imported model {}: {}", info.repo_id, e);
+                report.rejected.push(RejectedModel {
+                    model: info.local_dir,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    if report.imported.is_empty() && report.rejected.is_empty() {
+        return Err(AppError::InvalidInput(format!(
+            "No model files found in {}",
+            source.display()
+        )));
+    }
+    Ok(report)
+}
+
+/// Copy a model's files into the staging area, verify checksums and load it.
+fn stage_model(found: &Path, staged_models: &Path, model: Importable) -> Result<(), AppError> {
+    let info = model.info();
+    let shipped = download::read_manifest_at(found);
+    let target = info.local_path(staged_models);
+    std::fs::create_dir_all(&target)?;
+
+    let mut manifest = download::Manifest::new();
+    for file in info.files() {
+        let src = found.join(file);
+        if !src.is_file() {
+            return Err(AppError::InvalidInput(format!("Missing file: {}", file)));
+        }
+        let dest = target.join(file);
+        std::fs::copy(&src, &dest)?;
+
+        let checksum = FileChecksum {
+            size: std::fs::metadata(&dest)?.len(),
+            sha256: download::sha256_file(&dest)?,
+        };
+        let expected = info.pinned_sha256(file).map(str::to_string).or_else(|| {
+            shipped
+                .as_ref()
+                .and_then(|m| m.get(file))
+                .map(|c| c.sha256.clone())
+        });
+        match expected {
+            Some(expected) if expected == checksum.sha256 => {}
+            Some(_) => {
+                return Err(AppError::InvalidInput(format!(
+                    "Checksum mismatch for {}",
+                    file
+                )));
+            }
+            None => {
+                return Err(AppError::InvalidInput(format!(
+                    "No pinned hash or checksums.json entry to verify {} against",
+                    file
+                )));
+            }
+        }
+        manifest.insert(file.to_string(), checksum);
+    }
+
+    model.validate(staged_models)?;
+    download::write_manifest(staged_models, &info, &manifest)
+}
+
+/// Move a staged model into `models_dir`, replacing any installed copy.
+fn install(staged_models: &Path, models_dir: &Path, info: &ModelInfo) -> Result<(), AppError> {
+    let staged = info.local_path(staged_models);
+    let target = info.local_path(models_dir);
+    if target.exists() {
+        let backup = models_dir.join(format!(".replaced-{}", uuid::Uuid::new_v4()));
+        std::fs::rename(&target, &backup)?;
+        if let Err(e) = std::fs::rename(&staged, &target) {
+            // Put the previous install back rather than leave nothing
+            std::fs::rename(&backup, &target)?;
+            return Err(e.into());
+        }
+        std::fs::remove_dir_all(&backup)?;
+    } else {
+        std::fs::rename(&staged, &target)?;
+    }
+    Ok(())
+}
+
+/// Find the folder holding a model's weights below `root`.
+fn find_model_dir(root: &Path, info: &ModelInfo) -> Option<PathBuf> {
+    let repo_name = info.repo_id.rsplit('/').next().unwrap_or(info.repo_id);
+    let cache_name = format!("models--{}", info.repo_id.replace('/', "--"));
+    let holds_weights = |dir: &Path| dir.join(info.model_file).is_file();
+
+    let mut level = vec![root.to_path_buf()];
+    for _ in 0..=MAX_SEARCH_DEPTH {
+        let mut next = Vec::new();
+        for dir in level {
+            let name = dir.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if (name == info.local_dir || name == repo_name) && holds_weights(&dir) {
+                return Some(dir);
+            }
+            if name == cache_name {
+                // Hugging Face cache layout: models--org--name/snapshots/<revision>/
+                let snapshot = subdirs(&dir.join("snapshots"))
+                    .into_iter()
+                    .find(|snapshot| holds_weights(snapshot));
+                if snapshot.is_some() {
+                    return snapshot;
+                }
+            }
+            next.extend(subdirs(&dir));
+        }
+        level = next;
+    }
+    None
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+/// Unpack a `.tar`, `.tar.gz` or `.tgz` archive into `dest`.
+fn unpack_archive(archive: &Path, dest: &Path) -> Result<(), AppError> {
+    let name = archive
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let file = File::open(archive)?;
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        unpack_tar(flate2::read::GzDecoder::new(file), dest)
+    } else if name.ends_with(".tar") {
+        unpack_tar(file, dest)
+    } else {
+        Err(AppError::InvalidInput(format!(
+            "Unsupported archive (expected .tar, .tar.gz or .tgz): {}",
+            archive.display()
+        )))
+    }
+}
+
+/// Extract the regular files of a tar stream into `dest`. Directories are created
+/// on demand; links and devices are skipped. Entries whose path has anything but
+/// plain names (after an optional leading `./`) are rejected, as are archives over
+/// the size limits.
+fn unpack_tar(reader: impl Read, dest: &Path) -> Result<(), AppError> {
+    unpack_tar_limited(reader, dest, MAX_ARCHIVE_ENTRY_BYTES, MAX_ARCHIVE_BYTES)
+}
+
+fn unpack_tar_limited(
+    reader: impl Read,
+    dest: &Path,
+    max_entry_bytes: u64,
+    max_total_bytes: u64,
+) -> Result<(), AppError> {
+    let mut archive = tar::Archive::new(reader);
+    let mut total_bytes = 0u64;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let relative = safe_relative_path(&path)?;
+
+        let size = entry.size();
+        total_bytes = total_bytes.saturating_add(size);
+        if size > max_entry_bytes || total_bytes > max_total_bytes {
+            return Err(AppError::InvalidInput(format!(
+                "Archive too large: {} is {} bytes",
+                path.display(),
+                size
+            )));
+        }
+
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // The entry reader ends at the size in its header
+        std::io::copy(&mut entry, &mut File::create(&target)?)?;
+    }
+    Ok(())
+}
+
+fn safe_relative_path(path: &Path) -> Result<&Path, AppError> {
+    let relative = path.strip_prefix(".").unwrap_or(path);
+    let safe = relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if safe {
+        Ok(relative)
+    } else {
+        Err(AppError::InvalidInput(format!(
+            "Unsafe path in archive: {}",
+            path.display()
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a tar archive with the given regular files. Names are written into the
+    /// header as-is, so unsafe paths can be tested.
+    fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, data) in files {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_unpack_tar() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = tar(&[
+            ("models/all-MiniLM-L6-v2/config.json", b"{}"),
+            ("./models/all-MiniLM-L6-v2/tokenizer.json", &[7u8; 700]),
+        ]);
+        unpack_tar(archive.as_slice(), dir.path()).unwrap();
+
+        let model_dir = dir.path().join("models/all-MiniLM-L6-v2");
+        assert_eq!(std::fs::read(model_dir.join("config.json")).unwrap(), b"{}");
+        assert_eq!(
+            std::fs::read(model_dir.join("tokenizer.json")).unwrap(),
+            vec![7u8; 700]
+        );
+    }
+
+    #[test]
+    fn test_unpack_tar_rejects_unsafe_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "../escape.txt",
+            "models/../../escape.txt",
+            "/tmp/escape.txt",
+        ] {
+            let archive = tar(&[(name, b"x")]);
+            assert!(unpack_tar(archive.as_slice(), dir.path()).is_err());
+        }
+        assert!(!dir.path().parent().unwrap().join("escape.txt").exists());
+    }
+
+    #[test]
+    fn test_unpack_tar_enforces_size_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = tar(&[("big.bin", &[0u8; 200])]);
+        assert!(unpack_tar_limited(archive.as_slice(), dir.path(), 100, 1000).is_err());
+        assert!(!dir.path().join("big.bin").exists());
+
+        let archive = tar(&[("a.bin", &[0u8; 100]), ("b.bin", &[0u8; 100])]);
+        assert!(unpack_tar_limited(archive.as_slice(), dir.path(), 100, 150).is_err());
+        assert!(!dir.path().join("b.bin").exists());
+        unpack_tar_limited(archive.as_slice(), dir.path(), 100, 200).unwrap();
+        assert_eq!(std::fs::read(dir.path().join("b.bin")).unwrap().len(), 100);
+    }
+
+    #[test]
+    fn test_find_model_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = models::EMBEDDING_MODEL;
+
+        // A folder with the right name but no weights is ignored
+        std::fs::create_dir_all(dir.path().join("a").join(info.local_dir)).unwrap();
+        assert_eq!(find_model_dir(dir.path(), &info), None);
+
+        // Hugging Face cache layout
+        let snapshot = dir
+            .path()
+            .join("hub/models--sentence-transformers--all-MiniLM-L6-v2/snapshots/abc123");
+        std::fs::create_dir_all(&snapshot).unwrap();
+        std::fs::write(snapshot.join(info.model_file), b"").unwrap();
+        assert_eq!(find_model_dir(dir.path(), &info), Some(snapshot));
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_model_and_keeps_install() {
+        let source = tempfile::tempdir().unwrap();
+        let models_dir = tempfile::tempdir().unwrap();
+        let info = models::EMBEDDING_MODEL;
+
+        let found = source.path().join(info.local_dir);
+        std::fs::create_dir_all(&found).unwrap();
+        for file in info.files() {
+            std::fs::write(found.join(file), b"not a model").unwrap();
+        }
+        ship_checksums(source.path(), &info);
+        let installed = info.local_path(models_dir.path());
+        std::fs::create_dir_all(&installed).unwrap();
+        std::fs::write(installed.join("config.json"), b"{}").unwrap();
+
+        let report = import_models(source.path(), models_dir.path()).unwrap();
+        assert!(report.imported.is_empty());
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].model, info.local_dir);
+        assert!(!report.rejected[0].reason.contains("checksum"));
+
+        // The existing install is untouched and staging is cleaned up
+        assert_eq!(std::fs::read(installed.join("config.json")).unwrap(), b"{}");
+        let leftovers = std::fs::read_dir(models_dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with(".import-")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    /// Ship a `checksums.json` matching the files in the model's source folder.
+    fn ship_checksums(source: &Path, info: &ModelInfo) {
+        let mut manifest = download::Manifest::new();
+        for file in info.files() {
+            let path = info.local_path(source).join(file);
+            let checksum = FileChecksum {
+                size: std::fs::metadata(&path).unwrap().len(),
+                sha256: download::sha256_file(&path).unwrap(),
+            };
+            manifest.insert(file.to_string(), checksum);
+        }
+        download::write_manifest(source, info, &manifest).unwrap();
+    }
+
+    #[test]
+    fn test_import_rejects_files_without_known_hashes() {
+        let source = tempfile::tempdir().unwrap();
+        let models_dir = tempfile::tempdir().unwrap();
+        let info = models::EMBEDDING_MODEL;
+
+        let found = source.path().join(info.local_dir);
+        std::fs::create_dir_all(&found).unwrap();
+        for file in info.files() {
+            std::fs::write(found.join(file), b"not a model").unwrap();
+        }
+
+        let report = import_models(source.path(), models_dir.path()).unwrap();
+        assert!(report.imported.is_empty());
+        assert!(report.rejected[0].reason.contains("No pinned hash"));
+        assert!(!info.local_path(models_dir.path()).exists());
+    }
+
+    #[test]
+    fn test_import_requires_model_files() {
+        let source = tempfile::tempdir().unwrap();
+        let models_dir = tempfile::tempdir().unwrap();
+        assert!(import_models(source.path(), models_dir.path()).is_err());
+    }
+}
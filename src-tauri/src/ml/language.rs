@@ -0,0 +1,77 @@
+use whatlang::Lang;
+
+/// ISO 639-3 code for English, the language the default models are trained on.
+pub const ENGLISH: &str = "eng";
+
+/// Entries shorter than this don't carry enough signal to detect a language.
+const MIN_DETECT_CHARS: usize = 20;
+
+/// Minimum detector confidence for storing a language.
+/// whatlang's own `is_reliable` rejects most single-sentence entries, so this is looser.
+const MIN_CONFIDENCE: f64 = 0.25;
+
+/// Detect the language of an entry.
+/// Returns an ISO 639-3 code (e.g. "eng", "spa", "deu", "hin"), or None if the
+/// text is too short or the detection is too uncertain to act on.
+pub fn detect(text: &str) -> Option<&'static str> {
+    let text = text.trim();
+    if text.chars().count() < MIN_DETECT_CHARS {
+        return None;
+    }
+
+    let info = whatlang::detect(text)?;
+    if info.is_reliable() || info.confidence() >= MIN_CONFIDENCE {
+        Some(info.lang().code())
+    } else {
+        None
+    }
+}
+
+/// Whether an entry should be handled by the English models.
+/// Entries with no detected language fall back to English.
+pub fn is_english(language: Option<&str>) -> bool {
+    language.is_none_or(|code| code == ENGLISH)
+}
+
+/// Human-readable English name for a language code, if it is known.
+pub fn display_name(code: &str) -> Option<&'static str> {
+    Lang::from_code(code).map(|lang| lang.eng_name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_languages() {
+        assert_eq!(
+            detect("Today I felt tired after work but happy to see my family."),
+            Some("eng")
+        );
+        assert_eq!(
+            detect(
+                "Hoy me siento muy cansado después del trabajo, pero feliz de ver a mi familia."
+            ),
+            Some("spa")
+        );
+        assert_eq!(
+            detect("Heute war ein anstrengender Tag, aber ich bin froh, dass ich meine Freunde getroffen habe."),
+            Some("deu")
+        );
+        assert_eq!(detect("आज मैं बहुत खुश हूँ क्योंकि मेरे दोस्त आए थे।"), Some("hin"));
+    }
+
+    #[test]
+    fn test_short_text_is_undetected() {
+        assert_eq!(detect("ok fine"), None);
+        assert_eq!(detect("   "), None);
+    }
+
+    #[test]
+    fn test_is_english() {
+        assert!(is_english(None));
+        assert!(is_english(Some("eng")));
+        assert!(!is_english(Some("spa")));
+        assert_eq!(display_name("deu"), Some("German"));
+    }
+}
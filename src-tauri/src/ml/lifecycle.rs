@@ -0,0 +1,238 @@
+//! Per-model load state and idle tracking.
+//!
+//! `MlState` records every load, use and eviction here so the UI can show which
+//! models are resident, and so the eviction loop can find models that have sat
+//! idle past the configured timeout.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+
+/// How long a model may go unused before it is evicted, unless configured.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// How often the eviction loop looks for idle models.
+pub const EVICTION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether a model is in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadState {
+    Unloaded,
+    Loading,
+    Loaded,
+}
+
+/// Load state of one model, as reported to the UI.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelLifecycleStatus {
+    pub model: &'static str,
+    pub state: LoadState,
+    /// Last time the model served a request in this session
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Callback invoked whenever a model changes state.
+pub type LifecycleListener = Arc<dyn Fn(&ModelLifecycleStatus) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    state: LoadState,
+    last_used: Option<Instant>,
+    last_used_at: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    fn status(&self, model: &'static str) -> ModelLifecycleStatus {
+        ModelLifecycleStatus {
+            model,
+            state: self.state,
+            last_used_at: self.last_used_at,
+        }
+    }
+}
+
+/// Shared load-state table for all models.
+#[derive(Clone)]
+pub struct Lifecycle {
+    entries: Arc<Mutex<HashMap<&'static str, Entry>>>,
+    listener: Arc<RwLock<Option<LifecycleListener>>>,
+    /// `None` keeps models loaded for the whole session
+    idle_timeout: Arc<RwLock<Option<Duration>>>,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            listener: Arc::new(RwLock::new(None)),
+            idle_timeout: Arc::new(RwLock::new(Some(DEFAULT_IDLE_TIMEOUT))),
+        }
+    }
+}
+
+impl Lifecycle {
+    /// Register the callback notified on every state change.
+    pub fn set_listener(&self, listener: LifecycleListener) {
+        if let Ok(mut current) = self.listener.write() {
+            *current = Some(listener);
+        }
+    }
+
+    /// Idle time after which a loaded model is evicted, or `None` if never.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+            .read()
+            .map(|timeout| *timeout)
+            .unwrap_or(Some(DEFAULT_IDLE_TIMEOUT))
+    }
+
+    /// Set the idle timeout; `None` disables eviction.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        if let Ok(mut current) = self.idle_timeout.write() {
+            *current = timeout;
+        }
+    }
+
+    /// Record that a model served a request.
+    pub fn touch(&self, model: &'static str) {
+        if let Ok(mut entries) = self.entries.lock() {
+            let entry = entries.entry(model).or_insert(Entry {
+                state: LoadState::Loaded,
+                last_used: None,
+                last_used_at: None,
+            });
+            entry.last_used = Some(Instant::now());
+            entry.last_used_at = Some(Utc::now());
+        }
+    }
+
+    /// Record a state change and notify the listener.
+    pub fn set_state(&self, model: &'static str, state: LoadState) {
+        let status = match self.entries.lock() {
+            Ok(mut entries) => {
+                let entry = entries.entry(model).or_insert(Entry {
+                    state: LoadState::Unloaded,
+                    last_used: None,
+                    last_used_at: None,
+                });
+                if entry.state == state {
+                    return;
+                }
+                entry.state = state;
+                entry.status(model)
+            }
+            Err(_) => return,
+        };
+
+        let listener = self.listener.read().ok().and_then(|l| l.clone());
+        if let Some(listener) = listener {
+            listener(&status);
+        }
+    }
+
+    /// Loaded models whose last use is older than the idle timeout at `now`.
+    pub fn idle_models(&self, now: Instant) -> Vec<&'static str> {
+        let Some(timeout) = self.idle_timeout() else {
+            return Vec::new();
+        };
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        let mut idle: Vec<&'static str> = entries
+            .iter()
+            .filter(|(_, entry)| entry.state == LoadState::Loaded)
+            .filter(|(_, entry)| {
+                entry
+                    .last_used
+                    .is_none_or(|used| now.saturating_duration_since(used) >= timeout)
+            })
+            .map(|(model, _)| *model)
+            .collect();
+        idle.sort_unstable();
+        idle
+    }
+
+    /// State of every tracked model, plus `expected` models not seen yet
+    /// (reported as unloaded).
+    pub fn statuses(&self, expected: &[&'static str]) -> Vec<ModelLifecycleStatus> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+        let mut statuses: Vec<ModelLifecycleStatus> = entries
+            .iter()
+            .map(|(model, entry)| entry.status(model))
+            .collect();
+        for model in expected {
+            if !entries.contains_key(model) {
+                statuses.push(ModelLifecycleStatus {
+                    model,
+                    state: LoadState::Unloaded,
+                    last_used_at: None,
+                });
+            }
+        }
+        statuses.sort_by_key(|status| status.model);
+        statuses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_models_respects_timeout() {
+        let lifecycle = Lifecycle::default();
+        lifecycle.set_idle_timeout(Some(Duration::from_secs(60)));
+        lifecycle.set_state("a", LoadState::Loaded);
+        lifecycle.touch("a");
+        lifecycle.set_state("b", LoadState::Loading);
+
+        let now = Instant::now();
+        assert!(lifecycle.idle_models(now).is_empty());
+        // A model still loading is never evicted
+        assert_eq!(
+            lifecycle.idle_models(now + Duration::from_secs(61)),
+            vec!["a"]
+        );
+
+        lifecycle.set_idle_timeout(None);
+        assert!(lifecycle
+            .idle_models(now + Duration::from_secs(3600))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_state_changes_notify_listener() {
+        let lifecycle = Lifecycle::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        lifecycle.set_listener(Arc::new(move |status| {
+            sink.lock().unwrap().push((status.model, status.state));
+        }));
+
+        lifecycle.set_state("a", LoadState::Unloaded);
+        lifecycle.set_state("a", LoadState::Loading);
+        lifecycle.set_state("a", LoadState::Loaded);
+        lifecycle.set_state("a", LoadState::Loaded);
+        lifecycle.touch("a");
+        lifecycle.set_state("a", LoadState::Unloaded);
+
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                ("a", LoadState::Loading),
+                ("a", LoadState::Loaded),
+                ("a", LoadState::Unloaded),
+            ]
+        );
+
+        let statuses = lifecycle.statuses(&["a", "b"]);
+        assert_eq!(statuses.len(), 2);
+        assert!(statuses[0].last_used_at.is_some());
+        assert_eq!(statuses[1].state, LoadState::Unloaded);
+    }
+}
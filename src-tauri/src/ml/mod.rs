@@ -1,24 +1,73 @@
+pub mod affect;
+pub mod anomaly;
+pub mod backend;
+pub mod calibration;
+pub mod dedup;
+pub mod download;
 pub mod embeddings;
+pub mod encoders;
+#[cfg(test)]
+pub mod fake;
+pub mod import;
+pub mod language;
+pub mod lifecycle;
 pub mod models;
+pub mod multilingual;
+pub mod ner;
+pub mod projection;
+pub mod quantize;
+pub mod rerank;
 pub mod sentiment;
+pub mod series;
+pub mod themes;
 
-pub use models::{ModelInfo, EMBEDDING_MODEL, SENTIMENT_MODEL};
+pub use backend::{Embedder, EmotionClassifier, EntityExtractor, Reranker};
+pub use models::{EmbeddingModelSpec, ModelInfo, Precision, EMBEDDING_MODEL, SENTIMENT_MODEL};
 
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
 use crate::error::AppError;
+use crate::llm::ollama::OllamaClient;
+use backend::OllamaEmbedder;
+use download::FileProgress;
 use embeddings::EmbeddingModel;
+use import::ImportReport;
+use lifecycle::{LoadState, ModelLifecycleStatus};
+use models::EmbeddingSource;
+use multilingual::MultilingualEmotionModel;
+use ner::NerModel;
+use rerank::{CrossEncoder, RerankSettings};
 use sentiment::SentimentModel;
 
 /// ML state wrapper with lazy model loading.
-/// Models are loaded on first use and cached for subsequent calls.
+/// Models are loaded on first use (or warmed up after startup), cached for
+/// subsequent calls, and evicted again once they sit idle.
 #[derive(Clone)]
 pub struct MlState {
     models_dir: PathBuf,
-    embedding_model: Arc<RwLock<Option<Arc<EmbeddingModel>>>>,
-    sentiment_model: Arc<RwLock<Option<Arc<SentimentModel>>>>,
+    /// Loaded embedding models by registry ID (the active model, plus the
+    /// pending one while the index is being migrated)
+    embedding_models: Arc<RwLock<HashMap<&'static str, Arc<dyn Embedder>>>>,
+    sentiment_model: Arc<RwLock<Option<Arc<dyn EmotionClassifier>>>>,
+    /// Emotion model for non-English entries, downloaded on first use
+    multilingual_emotion_model: Arc<RwLock<Option<Arc<dyn EmotionClassifier>>>>,
+    /// Named-entity model for English entries, downloaded on first use
+    ner_model: Arc<RwLock<Option<Arc<dyn EntityExtractor>>>>,
+    /// Cross-encoder reranking search results, loaded when reranking is enabled
+    reranker: Arc<RwLock<Option<Arc<dyn Reranker>>>>,
+    rerank_settings: Arc<std::sync::RwLock<RerankSettings>>,
+    /// Model used for search and new embeddings
+    active_embedding: Arc<std::sync::RwLock<&'static EmbeddingModelSpec>>,
+    /// Model being switched to; its index is built in the background
+    pending_embedding: Arc<std::sync::RwLock<Option<&'static EmbeddingModelSpec>>>,
+    /// Precision the embedding and sentiment models are loaded in
+    precision: Arc<std::sync::RwLock<Precision>>,
+    /// Load state and last use of each model
+    lifecycle: lifecycle::Lifecycle,
 }
 
 impl MlState {
@@ -26,109 +75,507 @@ impl MlState {
     pub fn new(models_dir: PathBuf) -> Self {
         Self {
             models_dir,
-            embedding_model: Arc::new(RwLock::new(None)),
+            embedding_models: Arc::new(RwLock::new(HashMap::new())),
             sentiment_model: Arc::new(RwLock::new(None)),
+            multilingual_emotion_model: Arc::new(RwLock::new(None)),
+            ner_model: Arc::new(RwLock::new(None)),
+            reranker: Arc::new(RwLock::new(None)),
+            rerank_settings: Arc::new(std::sync::RwLock::new(RerankSettings::default())),
+            active_embedding: Arc::new(std::sync::RwLock::new(&models::MINILM_EMBEDDING)),
+            pending_embedding: Arc::new(std::sync::RwLock::new(None)),
+            precision: Arc::new(std::sync::RwLock::new(Precision::default())),
+            lifecycle: lifecycle::Lifecycle::default(),
         }
     }
 
+    /// Precision requested for the embedding and sentiment models.
+    pub fn precision(&self) -> Precision {
+        self.precision
+            .read()
+            .map(|precision| *precision)
+            .unwrap_or_default()
+    }
+
+    /// Set the precision models are loaded in. Takes effect the next time a model
+    /// is loaded; call `unload_models` to apply it to models already in memory.
+    pub fn set_precision(&self, precision: Precision) {
+        if let Ok(mut current) = self.precision.write() {
+            *current = precision;
+        }
+    }
+
+    /// Drop every loaded model from memory; they reload on next use.
+    pub async fn unload_models(&self) {
+        let embedding_ids: Vec<&'static str> = {
+            let mut guard = self.embedding_models.write().await;
+            let ids = guard.keys().copied().collect();
+            guard.clear();
+            ids
+        };
+        for id in embedding_ids {
+            self.lifecycle.set_state(id, LoadState::Unloaded);
+        }
+        if self.sentiment_model.write().await.take().is_some() {
+            self.lifecycle
+                .set_state(models::SENTIMENT_MODEL.local_dir, LoadState::Unloaded);
+        }
+        if self
+            .multilingual_emotion_model
+            .write()
+            .await
+            .take()
+            .is_some()
+        {
+            self.lifecycle.set_state(
+                models::MULTILINGUAL_SENTIMENT_MODEL.local_dir,
+                LoadState::Unloaded,
+            );
+        }
+        if self.ner_model.write().await.take().is_some() {
+            self.lifecycle
+                .set_state(models::NER_MODEL.local_dir, LoadState::Unloaded);
+        }
+        if self.reranker.write().await.take().is_some() {
+            self.lifecycle
+                .set_state(models::RERANKER_MODEL.local_dir, LoadState::Unloaded);
+        }
+    }
+
+    /// Model load state tracking, e.g. to register a state change listener.
+    pub fn lifecycle(&self) -> &lifecycle::Lifecycle {
+        &self.lifecycle
+    }
+
+    /// Idle time after which a loaded model is evicted, or `None` if never.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        self.lifecycle.idle_timeout()
+    }
+
+    /// Set the idle timeout; `None` keeps models loaded for the whole session.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        self.lifecycle.set_idle_timeout(timeout);
+    }
+
+    /// Load the active embedding model and the sentiment model in the background
+    /// so the first search doesn't stall. Models that aren't downloaded are skipped.
+    pub async fn warm_up(&self) {
+        let embedding = self.active_embedding();
+        if self.is_embedding_downloaded(embedding) {
+            if let Err(e) = self.get_embedding_model_for(embedding).await {
+                log::warn!("Failed to warm up embedding model: {}", e);
+            }
+        }
+        if models::is_model_downloaded(&self.models_dir, models::SENTIMENT_MODEL) {
+            if let Err(e) = self.get_sentiment_model().await {
+                log::warn!("Failed to warm up sentiment model: {}", e);
+            }
+        }
+    }
+
+    /// Drop models that haven't been used within the idle timeout.
+    /// Models still held by an in-flight request are left for the next check.
+    pub async fn evict_idle(&self) -> Vec<&'static str> {
+        let mut evicted = Vec::new();
+        for id in self.lifecycle.idle_models(Instant::now()) {
+            let removed = if id == models::SENTIMENT_MODEL.local_dir {
+                take_if_unused(&mut *self.sentiment_model.write().await)
+            } else if id == models::MULTILINGUAL_SENTIMENT_MODEL.local_dir {
+                take_if_unused(&mut *self.multilingual_emotion_model.write().await)
+            } else if id == models::NER_MODEL.local_dir {
+                take_if_unused(&mut *self.ner_model.write().await)
+            } else if id == models::RERANKER_MODEL.local_dir {
+                take_if_unused(&mut *self.reranker.write().await)
+            } else {
+                let mut guard = self.embedding_models.write().await;
+                match guard.get(id) {
+                    Some(model) if Arc::strong_count(model) == 1 => guard.remove(id).is_some(),
+                    Some(_) => false,
+                    None => true,
+                }
+            };
+            if removed {
+                log::info!("Evicted idle model {}", id);
+                self.lifecycle.set_state(id, LoadState::Unloaded);
+                evicted.push(id);
+            }
+        }
+        evicted
+    }
+
+    /// Warm up models, then evict idle ones periodically for the rest of the session.
+    /// Spawned once at startup; never returns.
+    pub async fn run_lifecycle(&self) {
+        self.warm_up().await;
+        let mut interval = tokio::time::interval(lifecycle::EVICTION_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.evict_idle().await;
+        }
+    }
+
+    /// Load state and last use of every known model.
+    pub fn model_states(&self) -> Vec<ModelLifecycleStatus> {
+        let active = self.active_embedding();
+        let mut expected = vec![active.id, models::SENTIMENT_MODEL.local_dir];
+        if let Some(pending) = self.pending_embedding() {
+            expected.push(pending.id);
+        }
+        self.lifecycle.statuses(&expected)
+    }
+
+    /// Models currently in memory with their precision and estimated weight memory.
+    pub async fn loaded_models(&self) -> Vec<LoadedModel> {
+        let mut loaded: Vec<LoadedModel> = self
+            .embedding_models
+            .read()
+            .await
+            .values()
+            .map(|model| LoadedModel {
+                id: model.spec().id,
+                precision: model.precision(),
+                memory_bytes: model.memory_bytes(),
+            })
+            .collect();
+        loaded.sort_by_key(|model| model.id);
+
+        if let Some(model) = self.sentiment_model.read().await.as_ref() {
+            loaded.push(LoadedModel {
+                id: models::SENTIMENT_MODEL.local_dir,
+                precision: model.precision(),
+                memory_bytes: model.memory_bytes(),
+            });
+        }
+        if let Some(model) = self.multilingual_emotion_model.read().await.as_ref() {
+            loaded.push(LoadedModel {
+                id: models::MULTILINGUAL_SENTIMENT_MODEL.local_dir,
+                precision: model.precision(),
+                memory_bytes: model.memory_bytes(),
+            });
+        }
+        if let Some(model) = self.ner_model.read().await.as_ref() {
+            loaded.push(LoadedModel {
+                id: models::NER_MODEL.local_dir,
+                precision: model.precision(),
+                memory_bytes: model.memory_bytes(),
+            });
+        }
+        if let Some(model) = self.reranker.read().await.as_ref() {
+            loaded.push(LoadedModel {
+                id: models::RERANKER_MODEL.local_dir,
+                precision: model.precision(),
+                memory_bytes: model.memory_bytes(),
+            });
+        }
+        loaded
+    }
+
+    /// The embedding model used for search and new embeddings.
+    pub fn active_embedding(&self) -> &'static EmbeddingModelSpec {
+        self.active_embedding
+            .read()
+            .map(|spec| *spec)
+            .unwrap_or(&models::MINILM_EMBEDDING)
+    }
+
+    /// Switch the embedding model used for search and new embeddings.
+    pub fn set_active_embedding(&self, spec: &'static EmbeddingModelSpec) {
+        if let Ok(mut active) = self.active_embedding.write() {
+            *active = spec;
+        }
+    }
+
+    /// Drop a loaded embedding model from memory (e.g. after switching away from it).
+    pub async fn unload_embedding_model(&self, spec: &'static EmbeddingModelSpec) {
+        if self
+            .embedding_models
+            .write()
+            .await
+            .remove(spec.id)
+            .is_some()
+        {
+            self.lifecycle.set_state(spec.id, LoadState::Unloaded);
+        }
+    }
+
+    /// Supported embedding models with their download and selection state.
+    pub fn list_embedding_models(&self) -> Vec<EmbeddingModelOption> {
+        let active = self.active_embedding().id;
+        let pending = self.pending_embedding().map(|spec| spec.id);
+        models::EMBEDDING_MODELS
+            .iter()
+            .map(|spec| EmbeddingModelOption {
+                id: spec.id,
+                name: spec.name,
+                dimension: spec.dimension,
+                pooling: spec.pooling,
+                multilingual: spec.multilingual,
+                downloaded: self.is_embedding_downloaded(spec),
+                active: spec.id == active,
+                pending: Some(spec.id) == pending,
+            })
+            .collect()
+    }
+
+    /// The embedding model being migrated to, if any.
+    pub fn pending_embedding(&self) -> Option<&'static EmbeddingModelSpec> {
+        self.pending_embedding.read().ok().and_then(|spec| *spec)
+    }
+
+    /// Set or clear the embedding model being migrated to.
+    pub fn set_pending_embedding(&self, spec: Option<&'static EmbeddingModelSpec>) {
+        if let Ok(mut pending) = self.pending_embedding.write() {
+            *pending = spec;
+        }
+    }
+
+    /// Check whether an embedding model's files are on disk.
+    /// Models served by another process have nothing to download.
+    pub fn is_embedding_downloaded(&self, spec: &EmbeddingModelSpec) -> bool {
+        spec.local_info()
+            .is_none_or(|info| models::is_model_downloaded(&self.models_dir, info))
+    }
+
+    /// Whether the active embedding model can be used without downloading anything.
+    pub async fn embedding_available(&self) -> bool {
+        let active = self.active_embedding();
+        self.embedding_models.read().await.contains_key(active.id)
+            || self.is_embedding_downloaded(active)
+    }
+
+    /// Download an embedding model if it isn't on disk yet.
+    pub async fn ensure_embedding_downloaded(
+        &self,
+        spec: &'static EmbeddingModelSpec,
+    ) -> Result<(), AppError> {
+        if let Some(info) = spec.local_info() {
+            if !self.is_embedding_downloaded(spec) {
+                log::info!("Downloading embedding model {}...", spec.id);
+                download::download_model(&self.models_dir, info, &|_| {}).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check whether the multilingual emotion model's files are on disk.
+    pub fn is_multilingual_emotion_downloaded(&self) -> bool {
+        models::is_model_downloaded(&self.models_dir, models::MULTILINGUAL_SENTIMENT_MODEL)
+    }
+
+    /// Download the multilingual emotion model if it isn't on disk yet.
+    pub async fn ensure_multilingual_emotion_downloaded(&self) -> Result<(), AppError> {
+        if !self.is_multilingual_emotion_downloaded() {
+            log::info!("Downloading multilingual emotion model...");
+            download::download_model(
+                &self.models_dir,
+                models::MULTILINGUAL_SENTIMENT_MODEL,
+                &|_| {},
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the named-entity model's files are on disk.
+    pub fn is_ner_downloaded(&self) -> bool {
+        models::is_model_downloaded(&self.models_dir, models::NER_MODEL)
+    }
+
+    /// Download the named-entity model if it isn't on disk yet.
+    pub async fn ensure_ner_downloaded(&self) -> Result<(), AppError> {
+        if !self.is_ner_downloaded() {
+            log::info!("Downloading NER model...");
+            download::download_model(&self.models_dir, models::NER_MODEL, &|_| {}).await?;
+        }
+        Ok(())
+    }
+
+    /// Check whether the search reranker's files are on disk.
+    pub fn is_reranker_downloaded(&self) -> bool {
+        models::is_model_downloaded(&self.models_dir, models::RERANKER_MODEL)
+    }
+
+    /// Download the search reranker if it isn't on disk yet.
+    pub async fn ensure_reranker_downloaded(&self) -> Result<(), AppError> {
+        if !self.is_reranker_downloaded() {
+            log::info!("Downloading reranker...");
+            download::download_model(&self.models_dir, models::RERANKER_MODEL, &|_| {}).await?;
+        }
+        Ok(())
+    }
+
     /// Check if models are downloaded and ready.
     pub async fn models_ready(&self) -> ModelStatus {
-        let embedding_ready =
-            models::is_model_downloaded(&self.models_dir, models::EMBEDDING_MODEL);
+        let active = self.active_embedding();
+        let embedding_ready = self.is_embedding_downloaded(active);
         let sentiment_ready =
             models::is_model_downloaded(&self.models_dir, models::SENTIMENT_MODEL);
+        let loaded_models = self.loaded_models().await;
 
         ModelStatus {
             embedding_downloaded: embedding_ready,
             sentiment_downloaded: sentiment_ready,
+            multilingual_sentiment_downloaded: self.is_multilingual_emotion_downloaded(),
+            ner_downloaded: self.is_ner_downloaded(),
+            reranker_downloaded: self.is_reranker_downloaded(),
             models_dir: self.models_dir.clone(),
+            embedding_model: active.id,
+            pending_embedding_model: self.pending_embedding().map(|spec| spec.id),
+            precision: self.precision(),
+            memory_bytes: loaded_models.iter().map(|model| model.memory_bytes).sum(),
+            loaded_models,
+            models: self.model_states(),
+            idle_timeout_secs: self.idle_timeout().map(|timeout| timeout.as_secs()),
         }
     }
 
     /// Initialize models (download if needed, load into memory).
     /// This is typically called during app startup or on user request.
-    pub async fn initialize(&self, on_progress: impl Fn(DownloadProgress)) -> Result<(), AppError> {
+    pub async fn initialize(
+        &self,
+        on_progress: impl Fn(DownloadProgress) + Send + Sync,
+    ) -> Result<(), AppError> {
         log::info!("Initializing ML models at: {}", self.models_dir.display());
 
         // Download embedding model if needed
-        if !models::is_model_downloaded(&self.models_dir, models::EMBEDDING_MODEL) {
-            log::info!("Downloading embedding model...");
-            on_progress(DownloadProgress {
-                model: "embedding".to_string(),
-                stage: "downloading".to_string(),
-                progress: 0.0,
-            });
-            models::download_model(&self.models_dir, models::EMBEDDING_MODEL).await?;
+        let embedding = self.active_embedding();
+        if let Some(info) = embedding.local_info() {
+            if !self.is_embedding_downloaded(embedding) {
+                log::info!("Downloading embedding model...");
+                download::download_model(&self.models_dir, info, &|file| {
+                    on_progress(DownloadProgress::downloading("embedding", file))
+                })
+                .await?;
+            }
         }
 
         // Download sentiment model if needed
         if !models::is_model_downloaded(&self.models_dir, models::SENTIMENT_MODEL) {
             log::info!("Downloading sentiment model...");
-            on_progress(DownloadProgress {
-                model: "sentiment".to_string(),
-                stage: "downloading".to_string(),
-                progress: 0.0,
-            });
-            models::download_model(&self.models_dir, models::SENTIMENT_MODEL).await?;
+            download::download_model(&self.models_dir, models::SENTIMENT_MODEL, &|file| {
+                on_progress(DownloadProgress::downloading("sentiment", file))
+            })
+            .await?;
         }
 
         // Pre-load models
-        on_progress(DownloadProgress {
-            model: "embedding".to_string(),
-            stage: "loading".to_string(),
-            progress: 0.5,
-        });
+        on_progress(DownloadProgress::stage("embedding", "loading", 0.5));
         self.get_embedding_model().await?;
 
-        on_progress(DownloadProgress {
-            model: "sentiment".to_string(),
-            stage: "loading".to_string(),
-            progress: 0.5,
-        });
+        on_progress(DownloadProgress::stage("sentiment", "loading", 0.5));
         self.get_sentiment_model().await?;
 
-        on_progress(DownloadProgress {
-            model: "all".to_string(),
-            stage: "complete".to_string(),
-            progress: 1.0,
-        });
+        on_progress(DownloadProgress::stage("all", "complete", 1.0));
 
         log::info!("ML models initialized successfully");
         Ok(())
     }
 
-    /// Get or load the embedding model.
-    pub async fn get_embedding_model(&self) -> Result<Arc<EmbeddingModel>, AppError> {
+    /// Install models from a local folder or archive (for machines without network access).
+    /// Loaded models are dropped so the imported files are used from now on.
+    pub async fn import_models(&self, source: PathBuf) -> Result<ImportReport, AppError> {
+        let models_dir = self.models_dir.clone();
+        let report =
+            tokio::task::spawn_blocking(move || import::import_models(&source, &models_dir))
+                .await
+                .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))??;
+        if !report.imported.is_empty() {
+            self.unload_models().await;
+        }
+        Ok(report)
+    }
+
+    /// Verify every model on disk and re-download files that are missing or corrupt.
+    /// Models that weren't downloaded are skipped.
+    pub async fn repair_models(
+        &self,
+        on_progress: impl Fn(DownloadProgress) + Send + Sync,
+    ) -> Result<Vec<ModelRepair>, AppError> {
+        let installed = models::EMBEDDING_MODELS
+            .iter()
+            .filter_map(|spec| spec.local_info())
+            .chain([
+                models::SENTIMENT_MODEL,
+                models::MULTILINGUAL_SENTIMENT_MODEL,
+                models::NER_MODEL,
+                models::RERANKER_MODEL,
+            ])
+            .filter(|info| info.local_path(&self.models_dir).exists());
+
+        let mut repairs = Vec::new();
+        for info in installed {
+            let files = download::repair_model(&self.models_dir, info, &|file| {
+                on_progress(DownloadProgress::downloading(info.local_dir, file))
+            })
+            .await?;
+            if !files.is_empty() {
+                repairs.push(ModelRepair {
+                    model: info.local_dir,
+                    files,
+                });
+            }
+        }
+
+        if !repairs.is_empty() {
+            // Loaded weights may have come from the corrupt files
+            self.unload_models().await;
+        }
+        on_progress(DownloadProgress::stage("all", "complete", 1.0));
+        Ok(repairs)
+    }
+
+    /// Get or load the active embedding model.
+    pub async fn get_embedding_model(&self) -> Result<Arc<dyn Embedder>, AppError> {
+        self.get_embedding_model_for(self.active_embedding()).await
+    }
+
+    /// Get or load a specific embedding model.
+    pub async fn get_embedding_model_for(
+        &self,
+        spec: &'static EmbeddingModelSpec,
+    ) -> Result<Arc<dyn Embedder>, AppError> {
         // Fast path: check if already loaded
         {
-            let guard = self.embedding_model.read().await;
-            if let Some(model) = guard.as_ref() {
+            let guard = self.embedding_models.read().await;
+            if let Some(model) = guard.get(spec.id) {
+                self.lifecycle.touch(spec.id);
                 return Ok(Arc::clone(model));
             }
         }
 
         // Slow path: acquire write lock and load
-        let mut guard = self.embedding_model.write().await;
+        let mut guard = self.embedding_models.write().await;
 
         // Double-check after acquiring write lock
-        if let Some(model) = guard.as_ref() {
+        if let Some(model) = guard.get(spec.id) {
+            self.lifecycle.touch(spec.id);
             return Ok(Arc::clone(model));
         }
 
-        log::info!("Loading embedding model...");
-        let model = Arc::new(EmbeddingModel::load(&self.models_dir)?);
-        *guard = Some(Arc::clone(&model));
+        log::info!("Loading embedding model {}...", spec.id);
+        self.lifecycle.set_state(spec.id, LoadState::Loading);
+        let model = match self.load_embedder(spec) {
+            Ok(model) => model,
+            Err(e) => {
+                self.lifecycle.set_state(spec.id, LoadState::Unloaded);
+                return Err(e);
+            }
+        };
+        guard.insert(spec.id, Arc::clone(&model));
+        self.lifecycle.touch(spec.id);
+        self.lifecycle.set_state(spec.id, LoadState::Loaded);
         log::info!("Embedding model loaded");
 
         Ok(model)
     }
 
     /// Get or load the sentiment model.
-    pub async fn get_sentiment_model(&self) -> Result<Arc<SentimentModel>, AppError> {
+    pub async fn get_sentiment_model(&self) -> Result<Arc<dyn EmotionClassifier>, AppError> {
+        let id = models::SENTIMENT_MODEL.local_dir;
         // Fast path: check if already loaded
         {
             let guard = self.sentiment_model.read().await;
             if let Some(model) = guard.as_ref() {
+                self.lifecycle.touch(id);
                 return Ok(Arc::clone(model));
             }
         }
@@ -138,16 +585,236 @@ impl MlState {
 
         // Double-check after acquiring write lock
         if let Some(model) = guard.as_ref() {
+            self.lifecycle.touch(id);
             return Ok(Arc::clone(model));
         }
 
         log::info!("Loading sentiment model...");
-        let model = Arc::new(SentimentModel::load(&self.models_dir)?);
+        self.lifecycle.set_state(id, LoadState::Loading);
+        let model = match SentimentModel::load(&self.models_dir, self.precision()) {
+            Ok(model) => Arc::new(model) as Arc<dyn EmotionClassifier>,
+            Err(e) => {
+                self.lifecycle.set_state(id, LoadState::Unloaded);
+                return Err(e);
+            }
+        };
         *guard = Some(Arc::clone(&model));
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
         log::info!("Sentiment model loaded");
 
         Ok(model)
     }
+
+    /// Get or load the multilingual emotion model, downloading it on first use.
+    pub async fn get_multilingual_emotion_model(
+        &self,
+    ) -> Result<Arc<dyn EmotionClassifier>, AppError> {
+        let id = models::MULTILINGUAL_SENTIMENT_MODEL.local_dir;
+        {
+            let guard = self.multilingual_emotion_model.read().await;
+            if let Some(model) = guard.as_ref() {
+                self.lifecycle.touch(id);
+                return Ok(Arc::clone(model));
+            }
+        }
+
+        self.ensure_multilingual_emotion_downloaded().await?;
+
+        let mut guard = self.multilingual_emotion_model.write().await;
+        if let Some(model) = guard.as_ref() {
+            self.lifecycle.touch(id);
+            return Ok(Arc::clone(model));
+        }
+
+        log::info!("Loading multilingual emotion model...");
+        self.lifecycle.set_state(id, LoadState::Loading);
+        let model = match MultilingualEmotionModel::load(&self.models_dir) {
+            Ok(model) => Arc::new(model) as Arc<dyn EmotionClassifier>,
+            Err(e) => {
+                self.lifecycle.set_state(id, LoadState::Unloaded);
+                return Err(e);
+            }
+        };
+        *guard = Some(Arc::clone(&model));
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+        log::info!("Multilingual emotion model loaded");
+
+        Ok(model)
+    }
+
+    /// Get or load the named-entity model, downloading it on first use.
+    pub async fn get_ner_model(&self) -> Result<Arc<dyn EntityExtractor>, AppError> {
+        let id = models::NER_MODEL.local_dir;
+        {
+            let guard = self.ner_model.read().await;
+            if let Some(model) = guard.as_ref() {
+                self.lifecycle.touch(id);
+                return Ok(Arc::clone(model));
+            }
+        }
+
+        self.ensure_ner_downloaded().await?;
+
+        let mut guard = self.ner_model.write().await;
+        if let Some(model) = guard.as_ref() {
+            self.lifecycle.touch(id);
+            return Ok(Arc::clone(model));
+        }
+
+        log::info!("Loading NER model...");
+        self.lifecycle.set_state(id, LoadState::Loading);
+        let model = match NerModel::load(&self.models_dir, self.precision()) {
+            Ok(model) => Arc::new(model) as Arc<dyn EntityExtractor>,
+            Err(e) => {
+                self.lifecycle.set_state(id, LoadState::Unloaded);
+                return Err(e);
+            }
+        };
+        *guard = Some(Arc::clone(&model));
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+        log::info!("NER model loaded");
+
+        Ok(model)
+    }
+
+    /// Get or load the search reranker. Unlike the other models it isn't downloaded
+    /// here, so a search never waits on a download; enabling reranking fetches it.
+    pub async fn get_reranker(&self) -> Result<Arc<dyn Reranker>, AppError> {
+        let id = models::RERANKER_MODEL.local_dir;
+        {
+            let guard = self.reranker.read().await;
+            if let Some(model) = guard.as_ref() {
+                self.lifecycle.touch(id);
+                return Ok(Arc::clone(model));
+            }
+        }
+
+        if !self.is_reranker_downloaded() {
+            return Err(AppError::ModelNotReady(
+                "Reranker is not downloaded".to_string(),
+            ));
+        }
+
+        let mut guard = self.reranker.write().await;
+        if let Some(model) = guard.as_ref() {
+            self.lifecycle.touch(id);
+            return Ok(Arc::clone(model));
+        }
+
+        log::info!("Loading reranker...");
+        self.lifecycle.set_state(id, LoadState::Loading);
+        let model = match CrossEncoder::load(&self.models_dir, self.precision()) {
+            Ok(model) => Arc::new(model) as Arc<dyn Reranker>,
+            Err(e) => {
+                self.lifecycle.set_state(id, LoadState::Unloaded);
+                return Err(e);
+            }
+        };
+        *guard = Some(Arc::clone(&model));
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+        log::info!("Reranker loaded");
+
+        Ok(model)
+    }
+
+    /// Whether search results are reranked, and the time reranking may take.
+    pub fn rerank_settings(&self) -> RerankSettings {
+        self.rerank_settings
+            .read()
+            .map(|settings| *settings)
+            .unwrap_or_default()
+    }
+
+    /// Set whether search results are reranked and the time reranking may take.
+    pub fn set_rerank_settings(&self, settings: RerankSettings) {
+        if let Ok(mut current) = self.rerank_settings.write() {
+            *current = settings;
+        }
+    }
+
+    /// Reranker scores for the leading `passages` (fused search candidates, best
+    /// first) within the latency budget. Empty when reranking is disabled or the
+    /// reranker isn't available, so callers keep the fused order.
+    pub async fn rerank(&self, query: &str, passages: &[&str]) -> Vec<f32> {
+        let settings = self.rerank_settings();
+        if !settings.enabled || passages.is_empty() {
+            return Vec::new();
+        }
+        let started = Instant::now();
+        let reranker = match self.get_reranker().await {
+            Ok(reranker) => reranker,
+            Err(e) => {
+                log::warn!("Reranker unavailable: {}", e);
+                return Vec::new();
+            }
+        };
+        // Loading the model counts against the budget
+        let budget = Duration::from_millis(settings.budget_ms).saturating_sub(started.elapsed());
+        rerank::score_within(reranker.as_ref(), query, passages, budget).await
+    }
+
+    /// Create the backend for an embedding model: candle for downloaded weights,
+    /// or a client for models served by Ollama.
+    fn load_embedder(
+        &self,
+        spec: &'static EmbeddingModelSpec,
+    ) -> Result<Arc<dyn Embedder>, AppError> {
+        Ok(match spec.source {
+            EmbeddingSource::Local(_) => Arc::new(EmbeddingModel::load(
+                &self.models_dir,
+                spec,
+                self.precision(),
+            )?),
+            EmbeddingSource::Ollama { model } => {
+                Arc::new(OllamaEmbedder::new(OllamaClient::new(), spec, model))
+            }
+        })
+    }
+
+    /// Use a specific embedder for its model instead of loading one (e.g. a fake
+    /// in tests). It is dropped like a loaded model on unload or eviction.
+    pub async fn insert_embedding_model(&self, model: Arc<dyn Embedder>) {
+        let id = model.spec().id;
+        self.embedding_models.write().await.insert(id, model);
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+    }
+
+    /// Use a specific classifier for English entries instead of loading one.
+    pub async fn insert_sentiment_model(&self, model: Arc<dyn EmotionClassifier>) {
+        let id = models::SENTIMENT_MODEL.local_dir;
+        *self.sentiment_model.write().await = Some(model);
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+    }
+
+    /// Use a specific classifier for non-English entries instead of loading one.
+    pub async fn insert_multilingual_emotion_model(&self, model: Arc<dyn EmotionClassifier>) {
+        let id = models::MULTILINGUAL_SENTIMENT_MODEL.local_dir;
+        *self.multilingual_emotion_model.write().await = Some(model);
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+    }
+
+    /// Use a specific entity extractor for English entries instead of loading one.
+    pub async fn insert_ner_model(&self, model: Arc<dyn EntityExtractor>) {
+        let id = models::NER_MODEL.local_dir;
+        *self.ner_model.write().await = Some(model);
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+    }
+
+    /// Use a specific search reranker instead of loading one.
+    pub async fn insert_reranker(&self, model: Arc<dyn Reranker>) {
+        let id = models::RERANKER_MODEL.local_dir;
+        *self.reranker.write().await = Some(model);
+        self.lifecycle.touch(id);
+        self.lifecycle.set_state(id, LoadState::Loaded);
+    }
 }
 
 /// Status of ML model availability.
@@ -155,7 +822,57 @@ impl MlState {
 pub struct ModelStatus {
     pub embedding_downloaded: bool,
     pub sentiment_downloaded: bool,
+    pub multilingual_sentiment_downloaded: bool,
+    pub ner_downloaded: bool,
+    pub reranker_downloaded: bool,
     pub models_dir: PathBuf,
+    pub embedding_model: &'static str,
+    pub pending_embedding_model: Option<&'static str>,
+    /// Requested precision; a loaded model may differ if its device doesn't support it
+    pub precision: Precision,
+    pub loaded_models: Vec<LoadedModel>,
+    /// Estimated memory held by all loaded model weights
+    pub memory_bytes: u64,
+    /// Load state and last use of each model
+    pub models: Vec<ModelLifecycleStatus>,
+    /// Idle time before a model is evicted; `None` if models stay loaded
+    pub idle_timeout_secs: Option<u64>,
+}
+
+/// A model currently held in memory.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LoadedModel {
+    pub id: &'static str,
+    pub precision: Precision,
+    pub memory_bytes: u64,
+}
+
+/// Take a cached model out of its slot unless a request is still using it.
+/// Returns true if the slot is now empty.
+fn take_if_unused<T: ?Sized>(slot: &mut Option<Arc<T>>) -> bool {
+    match slot {
+        Some(model) if Arc::strong_count(model) > 1 => false,
+        _ => {
+            slot.take();
+            true
+        }
+    }
+}
+
+/// A supported embedding model as shown in settings.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct EmbeddingModelOption {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub dimension: usize,
+    pub pooling: models::Pooling,
+    /// Suitable for entries that aren't in English
+    pub multilingual: bool,
+    pub downloaded: bool,
+    /// Used for search and new embeddings
+    pub active: bool,
+    /// Being switched to; its index is still being built
+    pub pending: bool,
 }
 
 /// Progress information during model download/loading.
@@ -164,4 +881,41 @@ pub struct DownloadProgress {
     pub model: String,
     pub stage: String,
     pub progress: f32,
+    /// File being downloaded, with its byte progress
+    pub file: Option<String>,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+impl DownloadProgress {
+    fn stage(model: &str, stage: &str, progress: f32) -> Self {
+        Self {
+            model: model.to_string(),
+            stage: stage.to_string(),
+            progress,
+            file: None,
+            downloaded_bytes: 0,
+            total_bytes: None,
+        }
+    }
+
+    fn downloading(model: &str, file: FileProgress) -> Self {
+        let progress = match file.total_bytes {
+            Some(total) if total > 0 => file.downloaded_bytes as f32 / total as f32,
+            _ => 0.0,
+        };
+        Self {
+            file: Some(file.file.to_string()),
+            downloaded_bytes: file.downloaded_bytes,
+            total_bytes: file.total_bytes,
+            ..Self::stage(model, "downloading", progress)
+        }
+    }
+}
+
+/// Files re-downloaded for one model by `repair_models`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelRepair {
+    pub model: &'static str,
+    pub files: Vec<&'static str>,
 }
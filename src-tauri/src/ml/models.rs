@@ -1,8 +1,17 @@
 use std::path::{Path, PathBuf};
 
-use hf_hub::api::sync::Api;
+/// Environment variable that overrides where models are stored, so they can be
+/// provisioned ahead of time (e.g. on machines without network access).
+pub const MODELS_DIR_ENV: &str = "MINDSCRIBE_MODELS_DIR";
 
-use crate::error::AppError;
+/// Directory models are stored in: `MINDSCRIBE_MODELS_DIR` if set, otherwise
+/// `models` under the app data directory.
+pub fn models_dir(app_dir: &Path) -> PathBuf {
+    std::env::var_os(MODELS_DIR_ENV)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| app_dir.join("models"))
+}
 
 /// Embedding model: all-MiniLM-L6-v2 (384-dim)
 pub const EMBEDDING_MODEL: ModelInfo = ModelInfo {
@@ -12,8 +21,151 @@ pub const EMBEDDING_MODEL: ModelInfo = ModelInfo {
     config_file: "config.json",
     local_dir: "all-MiniLM-L6-v2",
     extra_files: &[],
+    pinned_sha256: &[],
+};
+
+/// Default embedding model. Uses the original vector table names.
+pub const MINILM_EMBEDDING: EmbeddingModelSpec = EmbeddingModelSpec {
+    id: "all-MiniLM-L6-v2",
+    name: "MiniLM L6 (English, fast)",
+    source: EmbeddingSource::Local(EMBEDDING_MODEL),
+    dimension: 384,
+    pooling: Pooling::Mean,
+    multilingual: false,
+    table_suffix: None,
 };
 
+/// BGE small English v1.5 (384-dim, CLS pooling). Stronger retrieval quality than MiniLM.
+pub const BGE_SMALL_EMBEDDING: EmbeddingModelSpec = EmbeddingModelSpec {
+    id: "bge-small-en-v1.5",
+    name: "BGE Small (English)",
+    source: EmbeddingSource::Local(ModelInfo {
+        repo_id: "BAAI/bge-small-en-v1.5",
+        model_file: "model.safetensors",
+        tokenizer_file: "tokenizer.json",
+        config_file: "config.json",
+        local_dir: "bge-small-en-v1.5",
+        extra_files: &[],
+        pinned_sha256: &[],
+    }),
+    dimension: 384,
+    pooling: Pooling::Cls,
+    multilingual: false,
+    table_suffix: Some("bge_small_en_v1_5"),
+};
+
+/// Multilingual paraphrase MiniLM L12 (384-dim) for journals not written in English.
+pub const MULTILINGUAL_MINILM_EMBEDDING: EmbeddingModelSpec = EmbeddingModelSpec {
+    id: "paraphrase-multilingual-MiniLM-L12-v2",
+    name: "MiniLM L12 (Multilingual)",
+    source: EmbeddingSource::Local(ModelInfo {
+        repo_id: "sentence-transformers/paraphrase-multilingual-MiniLM-L12-v2",
+        model_file: "model.safetensors",
+        tokenizer_file: "tokenizer.json",
+        config_file: "config.json",
+        local_dir: "paraphrase-multilingual-MiniLM-L12-v2",
+        extra_files: &[],
+        pinned_sha256: &[],
+    }),
+    dimension: 384,
+    pooling: Pooling::Mean,
+    multilingual: true,
+    table_suffix: Some("multilingual_minilm_l12_v2"),
+};
+
+/// nomic-embed-text served by a local Ollama instance (768-dim). Nothing is downloaded
+/// by the app; the model must be pulled in Ollama.
+pub const OLLAMA_NOMIC_EMBEDDING: EmbeddingModelSpec = EmbeddingModelSpec {
+    id: "ollama-nomic-embed-text",
+    name: "Nomic Embed (Ollama)",
+    source: EmbeddingSource::Ollama {
+        model: "nomic-embed-text",
+    },
+    dimension: 768,
+    pooling: Pooling::Mean,
+    multilingual: false,
+    table_suffix: Some("ollama_nomic_embed_text"),
+};
+
+/// All supported embedding models.
+pub const EMBEDDING_MODELS: &[EmbeddingModelSpec] = &[
+    MINILM_EMBEDDING,
+    BGE_SMALL_EMBEDDING,
+    MULTILINGUAL_MINILM_EMBEDDING,
+    OLLAMA_NOMIC_EMBEDDING,
+];
+
+/// Look up a supported embedding model by ID.
+pub fn embedding_model(id: &str) -> Option<&'static EmbeddingModelSpec> {
+    EMBEDDING_MODELS.iter().find(|m| m.id == id)
+}
+
+/// How token embeddings are reduced to a single sentence embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pooling {
+    /// Attention-masked mean over all tokens (sentence-transformers models)
+    Mean,
+    /// Hidden state of the [CLS] token (BGE models)
+    Cls,
+}
+
+/// A supported embedding model and how its vectors are stored.
+#[derive(Debug, Clone, Copy)]
+pub struct EmbeddingModelSpec {
+    /// Stable identifier, also stored as the model version of derived embeddings
+    pub id: &'static str,
+    pub name: &'static str,
+    pub source: EmbeddingSource,
+    pub dimension: usize,
+    /// Pooling applied by the local backend; remote backends pool on their side
+    pub pooling: Pooling,
+    /// Trained on many languages; recommended when entries aren't in English
+    pub multilingual: bool,
+    /// Suffix for this model's vec0 tables. None uses the original table names.
+    table_suffix: Option<&'static str>,
+}
+
+/// Where an embedding model runs.
+#[derive(Debug, Clone, Copy)]
+pub enum EmbeddingSource {
+    /// Weights downloaded from the Hub and run in-process with candle
+    Local(ModelInfo),
+    /// Served by Ollama's `/api/embeddings` endpoint under this model name
+    Ollama { model: &'static str },
+}
+
+impl EmbeddingModelSpec {
+    /// Download info for models run in-process; `None` for remote backends.
+    pub fn local_info(&self) -> Option<ModelInfo> {
+        match self.source {
+            EmbeddingSource::Local(info) => Some(info),
+            EmbeddingSource::Ollama { .. } => None,
+        }
+    }
+
+    /// vec0 table holding entry-level embeddings for this model.
+    pub fn entry_table(&self) -> String {
+        match self.table_suffix {
+            Some(suffix) => format!("journal_embeddings_{}", suffix),
+            None => "journal_embeddings".to_string(),
+        }
+    }
+
+    /// Whether this model uses the original (pre-registry) vector tables.
+    pub fn uses_default_tables(&self) -> bool {
+        self.table_suffix.is_none()
+    }
+
+    /// vec0 table holding chunk embeddings for this model.
+    pub fn chunk_table(&self) -> String {
+        match self.table_suffix {
+            Some(suffix) => format!("chunk_embeddings_{}", suffix),
+            None => "chunk_embeddings".to_string(),
+        }
+    }
+}
+
 /// Sentiment model: DistilBERT GoEmotions (27 emotions + neutral)
 /// Uses vocab-based tokenizer (vocab.txt) instead of tokenizer.json
 pub const SENTIMENT_MODEL: ModelInfo = ModelInfo {
@@ -23,6 +175,43 @@ pub const SENTIMENT_MODEL: ModelInfo = ModelInfo {
     config_file: "config.json",
     local_dir: "distilbert-go-emotions",
     extra_files: &["tokenizer_config.json", "special_tokens_map.json"],
+    pinned_sha256: &[],
+};
+
+/// Multilingual emotion model: XLM-RoBERTa fine-tuned on emotion data in 19 languages.
+/// Predicts anger, fear, joy and sadness; labels are mapped onto the GoEmotions taxonomy.
+pub const MULTILINGUAL_SENTIMENT_MODEL: ModelInfo = ModelInfo {
+    repo_id: "MilaNLProc/xlm-emo-t",
+    model_file: "pytorch_model.bin",
+    tokenizer_file: "tokenizer.json",
+    config_file: "config.json",
+    local_dir: "xlm-emo-t",
+    extra_files: &[],
+    pinned_sha256: &[],
+};
+
+/// Named-entity model: cased BERT fine-tuned on CoNLL-2003 (people, places,
+/// organisations, misc). English only; uses a vocab-based tokenizer like the sentiment model.
+pub const NER_MODEL: ModelInfo = ModelInfo {
+    repo_id: "dslim/bert-base-NER",
+    model_file: "model.safetensors",
+    tokenizer_file: "vocab.txt",
+    config_file: "config.json",
+    local_dir: "bert-base-ner",
+    extra_files: &["tokenizer_config.json", "special_tokens_map.json"],
+    pinned_sha256: &[],
+};
+
+/// Search reranker: MiniLM cross-encoder trained on MS MARCO passage ranking. Scores a
+/// query and passage read together; uncased, with a vocab-based tokenizer.
+pub const RERANKER_MODEL: ModelInfo = ModelInfo {
+    repo_id: "cross-encoder/ms-marco-MiniLM-L-6-v2",
+    model_file: "model.safetensors",
+    tokenizer_file: "vocab.txt",
+    config_file: "config.json",
+    local_dir: "ms-marco-minilm-l6",
+    extra_files: &["tokenizer_config.json", "special_tokens_map.json"],
+    pinned_sha256: &[],
 };
 
 /// Information about a model to download.
@@ -35,6 +224,10 @@ pub struct ModelInfo {
     pub local_dir: &'static str,
     /// Additional files needed (e.g., tokenizer_config.json for vocab-based tokenizers)
     pub extra_files: &'static [&'static str],
+    /// SHA-256 of files whose expected content is known in advance, by file name.
+    /// Other files are checked against the hash the Hub reports at download time.
+    /// `cargo run --example pin_model_hashes` prints these for every model.
+    pub pinned_sha256: &'static [(&'static str, &'static str)],
 }
 
 impl ModelInfo {
@@ -57,90 +250,165 @@ impl ModelInfo {
     pub fn config_path(&self, models_dir: &Path) -> PathBuf {
         self.local_path(models_dir).join(self.config_file)
     }
-}
 
-/// Check if a model is already downloaded.
-pub fn is_model_downloaded(models_dir: &Path, model: ModelInfo) -> bool {
-    let model_path = model.model_path(models_dir);
-    let tokenizer_path = model.tokenizer_path(models_dir);
-    let config_path = model.config_path(models_dir);
+    /// Every file the model needs, weights first.
+    pub fn files(&self) -> Vec<&'static str> {
+        [self.model_file, self.tokenizer_file, self.config_file]
+            .into_iter()
+            .chain(self.extra_files.iter().copied())
+            .collect()
+    }
 
-    let base_files_exist = model_path.exists() && tokenizer_path.exists() && config_path.exists();
+    /// Pinned SHA-256 for one of the model's files, if any.
+    pub fn pinned_sha256(&self, file: &str) -> Option<&'static str> {
+        self.pinned_sha256
+            .iter()
+            .find(|(name, _)| *name == file)
+            .map(|(_, sha256)| *sha256)
+    }
+}
 
-    // Also check extra files
-    let extra_files_exist = model
-        .extra_files
+/// Check if a model is already downloaded: every file is on disk and has the size
+/// recorded in its checksum manifest. Files are never hashed here; without a
+/// recorded checksum a model counts as not downloaded until `download_model` has
+/// verified it.
+pub fn is_model_downloaded(models_dir: &Path, model: ModelInfo) -> bool {
+    let all_exist = model
+        .files()
         .iter()
         .all(|file| model.local_path(models_dir).join(file).exists());
 
-    base_files_exist && extra_files_exist
+    all_exist && super::download::sizes_match(models_dir, &model)
 }
 
-/// Download a model from HuggingFace Hub.
-pub async fn download_model(models_dir: &Path, model: ModelInfo) -> Result<(), AppError> {
-    let local_path = model.local_path(models_dir);
-    std::fs::create_dir_all(&local_path)?;
-
-    log::info!(
-        "Downloading model {} to {}",
-        model.repo_id,
-        local_path.display()
-    );
-
-    // Use HuggingFace Hub API to download files
-    // Run in blocking task since hf-hub uses sync I/O
-    let repo_id = model.repo_id.to_string();
-    let model_file = model.model_file.to_string();
-    let tokenizer_file = model.tokenizer_file.to_string();
-    let config_file = model.config_file.to_string();
-    let extra_files: Vec<String> = model.extra_files.iter().map(|s| s.to_string()).collect();
-    let local_path_clone = local_path.clone();
-
-    tokio::task::spawn_blocking(move || {
-        let api = Api::new().map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
-        let repo = api.model(repo_id);
-
-        // Download model weights
-        log::info!("Downloading model weights...");
-        let model_src = repo
-            .get(&model_file)
-            .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
-        std::fs::copy(&model_src, local_path_clone.join(&model_file))?;
-
-        // Download tokenizer
-        log::info!("Downloading tokenizer...");
-        let tokenizer_src = repo
-            .get(&tokenizer_file)
-            .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
-        std::fs::copy(&tokenizer_src, local_path_clone.join(&tokenizer_file))?;
-
-        // Download config
-        log::info!("Downloading config...");
-        let config_src = repo
-            .get(&config_file)
-            .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
-        std::fs::copy(&config_src, local_path_clone.join(&config_file))?;
-
-        // Download extra files (e.g., tokenizer_config.json for vocab-based tokenizers)
-        for file in &extra_files {
-            log::info!("Downloading {}...", file);
-            let src = repo
-                .get(file)
-                .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))?;
-            std::fs::copy(&src, local_path_clone.join(file))?;
+/// Get the device for ML inference.
+pub fn get_device() -> candle_core::Device {
+    log::info!("Using CPU for inference");
+    candle_core::Device::Cpu
+}
+
+/// Floating-point precision model weights are loaded and run in.
+///
+/// int8 is not offered: candle's quantized kernels only cover its GGUF decoder
+/// models, and there are no quantized BERT or DistilBERT implementations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Full precision; the reference outputs were produced with it
+    #[default]
+    F32,
+    /// Half precision; halves weight memory
+    F16,
+    /// bfloat16; halves weight memory with F32's range but fewer mantissa bits
+    Bf16,
+}
+
+impl Precision {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Precision::F32 => "f32",
+            Precision::F16 => "f16",
+            Precision::Bf16 => "bf16",
         }
+    }
 
-        log::info!("Model download complete");
-        Ok::<_, AppError>(())
-    })
-    .await
-    .map_err(|e| AppError::Io(std::io::Error::other(e.to_string())))??;
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "f32" => Some(Precision::F32),
+            "f16" => Some(Precision::F16),
+            "bf16" => Some(Precision::Bf16),
+            _ => None,
+        }
+    }
+
+    /// The precision that can actually run on a device.
+    /// candle has no BF16 matmul on CPU, so BF16 falls back to F16 there.
+    pub fn supported_on(self, device: &candle_core::Device) -> Self {
+        match self {
+            Precision::Bf16 if device.is_cpu() => {
+                log::warn!("BF16 is not supported on CPU, using F16 instead");
+                Precision::F16
+            }
+            precision => precision,
+        }
+    }
 
-    Ok(())
+    pub fn dtype(&self) -> candle_core::DType {
+        match self {
+            Precision::F32 => candle_core::DType::F32,
+            Precision::F16 => candle_core::DType::F16,
+            Precision::Bf16 => candle_core::DType::BF16,
+        }
+    }
 }
 
-/// Get the device for ML inference.
-pub fn get_device() -> candle_core::Device {
-    log::info!("Using CPU for inference");
-    candle_core::Device::Cpu
+/// Journal-style texts used to check reduced-precision outputs against F32.
+#[cfg(test)]
+pub(crate) const PRECISION_FIXTURES: &[&str] = &[
+    "Today was a good day. I finally finished the project and celebrated with friends.",
+    "I couldn't sleep again. Everything about the move feels overwhelming.",
+    "Grateful for the long walk by the river this morning.",
+    "I am furious that the meeting was cancelled without any notice.",
+    "Not sure how I feel about the news. Mostly confused, a little hopeful.",
+    "Missing grandma today. Found her old recipe book in the attic and cried.",
+    "Ok.",
+    "The train was late, the coffee was cold, and then it started raining. Still, the \
+     conversation with Sam on the platform made me laugh harder than I have in weeks, \
+     and by the time I got home I had almost forgotten how annoyed I was.",
+];
+
+/// Estimate the memory held by a model's weights once loaded in the given dtype.
+/// Counts parameters from the safetensors header; other formats fall back to file size.
+pub fn estimate_weight_bytes(model_path: &Path, dtype: candle_core::DType) -> u64 {
+    let is_safetensors = model_path
+        .extension()
+        .is_some_and(|ext| ext == "safetensors");
+    if is_safetensors {
+        if let Ok(weights) = unsafe { candle_core::safetensors::MmapedSafetensors::new(model_path) }
+        {
+            let parameters: usize = weights
+                .tensors()
+                .iter()
+                .map(|(_, view)| view.shape().iter().product::<usize>())
+                .sum();
+            return (parameters * dtype.size_in_bytes()) as u64;
+        }
+    }
+    std::fs::metadata(model_path).map(|m| m.len()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::{DType, Device, Tensor};
+
+    #[test]
+    fn test_precision_parse() {
+        for precision in [Precision::F32, Precision::F16, Precision::Bf16] {
+            assert_eq!(Precision::parse(precision.as_str()), Some(precision));
+        }
+        assert_eq!(Precision::parse("int8"), None);
+        assert_eq!(Precision::Bf16.supported_on(&Device::Cpu), Precision::F16);
+        assert_eq!(Precision::F16.supported_on(&Device::Cpu), Precision::F16);
+    }
+
+    #[test]
+    fn test_estimate_weight_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.safetensors");
+        let weights = [
+            (
+                "a".to_string(),
+                Tensor::zeros((4, 8), DType::F32, &Device::Cpu).unwrap(),
+            ),
+            (
+                "b".to_string(),
+                Tensor::zeros(8, DType::F32, &Device::Cpu).unwrap(),
+            ),
+        ];
+        candle_core::safetensors::save(&weights.into_iter().collect(), &path).unwrap();
+
+        assert_eq!(estimate_weight_bytes(&path, DType::F32), 40 * 4);
+        assert_eq!(estimate_weight_bytes(&path, DType::F16), 40 * 2);
+    }
 }
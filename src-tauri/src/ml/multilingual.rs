@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::xlm_roberta::{Config, XLMRobertaForSequenceClassification};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use crate::error::AppError;
+use crate::ml::models::{estimate_weight_bytes, get_device, MULTILINGUAL_SENTIMENT_MODEL};
+use crate::ml::sentiment::{EmotionPrediction, EMOTION_LABELS};
+
+/// XLM-RoBERTa supports 514 positions, two of which are reserved for padding offsets.
+const MAX_SEQUENCE_TOKENS: usize = 512;
+
+/// Classifier labels from the model config, in output order.
+#[derive(Debug, serde::Deserialize)]
+struct LabelConfig {
+    id2label: HashMap<String, String>,
+}
+
+/// Map a label from another emotion taxonomy onto `EMOTION_LABELS`.
+/// Returns None for labels with no GoEmotions counterpart.
+pub fn map_to_goemotions(label: &str) -> Option<&'static str> {
+    let label = label.trim().to_lowercase();
+    if let Some(known) = EMOTION_LABELS.iter().find(|l| **l == label) {
+        return Some(known);
+    }
+
+    let mapped = match label.as_str() {
+        "happiness" | "happy" | "positive" => "joy",
+        "sad" | "negative" => "sadness",
+        "angry" | "rage" => "anger",
+        "fearful" | "scared" | "anxiety" => "fear",
+        "surprised" => "surprise",
+        "disgusted" => "disgust",
+        "trust" => "approval",
+        "anticipation" => "optimism",
+        "others" | "other" | "none" | "no emotion" => "neutral",
+        _ => return None,
+    };
+    Some(mapped)
+}
+
+/// Emotion model for entries that aren't written in English.
+/// Uses XLM-RoBERTa sequence classification with softmax over its own label set,
+/// then maps the labels onto the GoEmotions taxonomy used everywhere else.
+/// Always runs in F32: candle's XLM-RoBERTa builds its attention mask in F32.
+pub struct MultilingualEmotionModel {
+    model: XLMRobertaForSequenceClassification,
+    tokenizer: Tokenizer,
+    /// GoEmotions label for each classifier output (None when it has no counterpart)
+    labels: Vec<Option<&'static str>>,
+    device: Device,
+    memory_bytes: u64,
+}
+
+impl MultilingualEmotionModel {
+    /// Load the multilingual emotion model from disk.
+    pub fn load(models_dir: &Path) -> Result<Self, AppError> {
+        let model_path = MULTILINGUAL_SENTIMENT_MODEL.model_path(models_dir);
+        let tokenizer_path = MULTILINGUAL_SENTIMENT_MODEL.tokenizer_path(models_dir);
+        let config_path = MULTILINGUAL_SENTIMENT_MODEL.config_path(models_dir);
+
+        log::info!(
+            "Loading multilingual emotion model from: {}",
+            model_path.display()
+        );
+
+        let config_str = std::fs::read_to_string(&config_path)?;
+        let config: Config = serde_json::from_str(&config_str)
+            .map_err(|e| AppError::Ml(format!("Failed to parse config: {}", e)))?;
+        let label_config: LabelConfig = serde_json::from_str(&config_str)
+            .map_err(|e| AppError::Ml(format!("Failed to parse labels: {}", e)))?;
+        let labels = output_labels(&label_config.id2label)?;
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| AppError::Ml(format!("Failed to load tokenizer: {}", e)))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_SEQUENCE_TOKENS,
+                ..Default::default()
+            }))
+            .map_err(|e| AppError::Ml(format!("Failed to configure tokenizer: {}", e)))?;
+
+        let device = get_device();
+        let memory_bytes = estimate_weight_bytes(&model_path, DType::F32);
+        let is_safetensors = model_path
+            .extension()
+            .is_some_and(|ext| ext == "safetensors");
+        let vb = if is_safetensors {
+            unsafe { VarBuilder::from_mmaped_safetensors(&[&model_path], DType::F32, &device) }
+        } else {
+            VarBuilder::from_pth(&model_path, DType::F32, &device)
+        }
+        .map_err(|e| AppError::Ml(format!("Failed to load weights: {}", e)))?;
+
+        let model = XLMRobertaForSequenceClassification::new(labels.len(), &config, vb)
+            .map_err(|e| AppError::Ml(format!("Failed to load model: {}", e)))?;
+
+        Ok(Self {
+            model,
+            tokenizer,
+            labels,
+            device,
+            memory_bytes,
+        })
+    }
+
+    /// Estimated memory held by the loaded weights.
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory_bytes
+    }
+
+    /// Predict emotions for the given text.
+    /// Returns top GoEmotions labels above threshold, sorted by confidence.
+    pub fn predict(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+    ) -> Result<Vec<EmotionPrediction>, AppError> {
+        let encoding = self
+            .tokenizer
+            .encode(text, true)
+            .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))?;
+
+        let input_ids = Tensor::new(encoding.get_ids(), &self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let attention_mask = Tensor::new(encoding.get_attention_mask(), &self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let token_type_ids = input_ids
+            .zeros_like()
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+
+        let logits = self
+            .model
+            .forward(&input_ids, &attention_mask, &token_type_ids)
+            .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
+
+        // Single-label classifier: softmax across its classes
+        let probs: Vec<f32> = candle_nn::ops::softmax(&logits, D::Minus1)
+            .and_then(|p| p.squeeze(0))
+            .and_then(|p| p.to_vec1())
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+
+        Ok(collect_predictions(
+            &self.labels,
+            &probs,
+            threshold,
+            max_labels,
+        ))
+    }
+}
+
+/// Order id2label by class index and map each label onto GoEmotions.
+fn output_labels(
+    id2label: &HashMap<String, String>,
+) -> Result<Vec<Option<&'static str>>, AppError> {
+    let mut indexed = id2label
+        .iter()
+        .map(|(id, label)| {
+            id.parse::<usize>()
+                .map(|idx| (idx, label.as_str()))
+                .map_err(|_| AppError::Ml(format!("Invalid label id in config: {}", id)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    indexed.sort_by_key(|(idx, _)| *idx);
+
+    Ok(indexed
+        .into_iter()
+        .map(|(_, label)| {
+            let mapped = map_to_goemotions(label);
+            if mapped.is_none() {
+                log::warn!("Dropping emotion label with no GoEmotions match: {}", label);
+            }
+            mapped
+        })
+        .collect())
+}
+
+/// Turn class probabilities into GoEmotions predictions.
+/// Classes that map to the same label are merged by keeping the higher score.
+fn collect_predictions(
+    labels: &[Option<&'static str>],
+    probs: &[f32],
+    threshold: f32,
+    max_labels: usize,
+) -> Vec<EmotionPrediction> {
+    let mut best: HashMap<&'static str, f32> = HashMap::new();
+    for (label, &score) in labels.iter().zip(probs) {
+        if let Some(label) = label {
+            let entry = best.entry(label).or_insert(score);
+            *entry = entry.max(score);
+        }
+    }
+
+    let mut predictions: Vec<EmotionPrediction> = best
+        .iter()
+        .filter(|(_, &score)| score >= threshold)
+        .map(|(label, &score)| EmotionPrediction {
+            label: label.to_string(),
+            score,
+        })
+        .collect();
+
+    predictions.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    predictions.truncate(max_labels);
+
+    if predictions.is_empty() {
+        predictions.push(EmotionPrediction {
+            label: "neutral".to_string(),
+            score: best.get("neutral").copied().unwrap_or(0.0),
+        });
+    }
+
+    predictions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_to_goemotions() {
+        assert_eq!(map_to_goemotions("joy"), Some("joy"));
+        assert_eq!(map_to_goemotions("Anger"), Some("anger"));
+        assert_eq!(map_to_goemotions("happiness"), Some("joy"));
+        assert_eq!(map_to_goemotions("others"), Some("neutral"));
+        assert_eq!(map_to_goemotions("LABEL_0"), None);
+    }
+
+    #[test]
+    fn test_output_labels_follow_class_index() {
+        let id2label: HashMap<String, String> = [
+            ("1", "joy"),
+            ("0", "anger"),
+            ("3", "sadness"),
+            ("2", "fear"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let labels = output_labels(&id2label).unwrap();
+        assert_eq!(
+            labels,
+            vec![Some("anger"), Some("joy"), Some("fear"), Some("sadness")]
+        );
+    }
+
+    #[test]
+    fn test_collect_predictions_maps_and_merges() {
+        let labels = vec![Some("joy"), Some("joy"), None, Some("sadness")];
+        let predictions = collect_predictions(&labels, &[0.2, 0.5, 0.9, 0.05], 0.1, 5);
+
+        assert_eq!(predictions.len(), 1);
+        assert_eq!(predictions[0].label, "joy");
+        assert!((predictions[0].score - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_collect_predictions_falls_back_to_neutral() {
+        let labels = vec![Some("joy"), Some("sadness")];
+        let predictions = collect_predictions(&labels, &[0.05, 0.02], 0.1, 5);
+
+        assert_eq!(predictions.len(), 1);
+        assert_eq!(predictions[0].label, "neutral");
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_predict_spanish() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = MultilingualEmotionModel::load(&models_dir).unwrap();
+
+        let predictions = model
+            .predict("¡Hoy estoy muy feliz con mi familia!", 0.1, 3)
+            .unwrap();
+        assert_eq!(predictions[0].label, "joy");
+    }
+}
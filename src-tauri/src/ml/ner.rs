@@ -0,0 +1,503 @@
+//! Named-entity extraction: the people and places an entry mentions.
+//!
+//! English entries go through a BERT token classifier; other languages (or machines
+//! without the model) ask the local LLM for names, which are then located in the text.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use candle_core::{DType, Device, Module, Tensor, D};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::Config;
+use serde::{Deserialize, Serialize};
+use tokenizers::{
+    models::wordpiece::WordPiece, normalizers::BertNormalizer,
+    pre_tokenizers::bert::BertPreTokenizer, processors::bert::BertProcessing, Tokenizer,
+};
+
+use crate::error::AppError;
+use crate::ml::encoders::Bert;
+use crate::ml::models::{estimate_weight_bytes, get_device, Precision, NER_MODEL};
+use crate::ml::sentiment::{char_offset, token_windows, WINDOW_OVERLAP_TOKENS};
+
+/// Spans the model is less sure of than this (mean over their words) are dropped.
+const MIN_SCORE: f32 = 0.6;
+
+/// Kind of entity tracked in the people and places index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Person,
+    Place,
+}
+
+impl EntityKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Person => "person",
+            EntityKind::Place => "place",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "person" => Some(EntityKind::Person),
+            "place" => Some(EntityKind::Place),
+            _ => None,
+        }
+    }
+
+    /// Kind for a CoNLL entity type; organisations and misc aren't tracked.
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "PER" => Some(EntityKind::Person),
+            "LOC" => Some(EntityKind::Place),
+            _ => None,
+        }
+    }
+}
+
+/// One mention of a person or place in an entry.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EntityMention {
+    pub kind: EntityKind,
+    /// Name as written, without a trailing possessive
+    pub name: String,
+    /// Character offsets of the mention within the entry
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub score: f32,
+}
+
+/// Classifier labels from the model config, in output order.
+#[derive(Debug, Deserialize)]
+struct LabelConfig {
+    id2label: HashMap<String, String>,
+}
+
+/// BERT token classifier tagging words with BIO labels (B-PER, I-LOC, O, ...).
+pub struct NerModel {
+    model: Bert,
+    classifier: candle_nn::Linear,
+    tokenizer: Tokenizer,
+    /// BIO label of each classifier output
+    labels: Vec<String>,
+    cls_token_id: u32,
+    sep_token_id: u32,
+    device: Device,
+    precision: Precision,
+    memory_bytes: u64,
+}
+
+impl NerModel {
+    /// Load the NER model from disk with weights in the given precision.
+    pub fn load(models_dir: &Path, precision: Precision) -> Result<Self, AppError> {
+        let model_path = NER_MODEL.model_path(models_dir);
+        let tokenizer_path = NER_MODEL.tokenizer_path(models_dir);
+        let config_path = NER_MODEL.config_path(models_dir);
+
+        log::info!("Loading NER model from: {}", model_path.display());
+
+        let config_str = std::fs::read_to_string(&config_path)?;
+        let config: Config = serde_json::from_str(&config_str)
+            .map_err(|e| AppError::Ml(format!("Failed to parse config: {}", e)))?;
+        let label_config: LabelConfig = serde_json::from_str(&config_str)
+            .map_err(|e| AppError::Ml(format!("Failed to parse labels: {}", e)))?;
+        let labels = ordered_labels(&label_config.id2label)?;
+
+        // The model is cased, so the vocab-based tokenizer must not lowercase
+        let vocab_path_str = tokenizer_path.to_string_lossy().to_string();
+        let wordpiece = WordPiece::from_file(&vocab_path_str)
+            .unk_token("[UNK]".to_string())
+            .build()
+            .map_err(|e| AppError::Ml(format!("Failed to build WordPiece: {}", e)))?;
+        let mut tokenizer = Tokenizer::new(wordpiece);
+        let cls_token_id = tokenizer.token_to_id("[CLS]").unwrap_or(101);
+        let sep_token_id = tokenizer.token_to_id("[SEP]").unwrap_or(102);
+        tokenizer.with_normalizer(Some(BertNormalizer::new(true, true, Some(false), false)));
+        tokenizer.with_pre_tokenizer(Some(BertPreTokenizer));
+        tokenizer.with_post_processor(Some(BertProcessing::new(
+            ("[SEP]".to_string(), sep_token_id),
+            ("[CLS]".to_string(), cls_token_id),
+        )));
+
+        let device = get_device();
+        let precision = precision.supported_on(&device);
+        let memory_bytes = estimate_weight_bytes(&model_path, precision.dtype());
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[model_path], precision.dtype(), &device)
+                .map_err(|e| AppError::Ml(format!("Failed to load weights: {}", e)))?
+        };
+
+        let model = Bert::load(vb.clone(), &config)
+            .map_err(|e| AppError::Ml(format!("Failed to load model: {}", e)))?;
+        let classifier = candle_nn::linear(config.hidden_size, labels.len(), vb.pp("classifier"))
+            .map_err(|e| AppError::Ml(format!("Failed to load classifier: {}", e)))?;
+
+        Ok(Self {
+            model,
+            classifier,
+            tokenizer,
+            labels,
+            cls_token_id,
+            sep_token_id,
+            device,
+            precision,
+            memory_bytes,
+        })
+    }
+
+    /// Precision the weights were loaded in.
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Estimated memory held by the loaded weights.
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory_bytes
+    }
+
+    /// People and places mentioned in the text, in order of appearance.
+    /// Long texts are tagged in overlapping windows; each token keeps the tag from
+    /// the window where it sits furthest from an edge.
+    pub fn extract(&self, text: &str) -> Result<Vec<EntityMention>, AppError> {
+        // Special tokens are added per window, so encode without them
+        let encoding = self
+            .tokenizer
+            .encode(text, false)
+            .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))?;
+        let ids = encoding.get_ids();
+        let offsets = encoding.get_offsets();
+        let word_ids = encoding.get_word_ids();
+
+        let mut tags: Vec<(usize, f32)> = vec![(0, 0.0); ids.len()];
+        for (i, &(start, end)) in token_windows(ids.len()).iter().enumerate() {
+            let skip = if i == 0 { 0 } else { WINDOW_OVERLAP_TOKENS / 2 };
+            for (offset, tag) in self.tag_window(&ids[start..end])?.into_iter().enumerate() {
+                if offset >= skip {
+                    tags[start + offset] = tag;
+                }
+            }
+        }
+
+        // Words are tagged by their first sub-token
+        let mut words: Vec<WordTag> = Vec::new();
+        for (i, &(label, score)) in tags.iter().enumerate() {
+            let (start, end) = offsets[i];
+            let continues_word = i > 0 && word_ids[i].is_some() && word_ids[i] == word_ids[i - 1];
+            match words.last_mut() {
+                Some(word) if continues_word => word.end = end,
+                _ => words.push(WordTag {
+                    tag: &self.labels[label],
+                    score,
+                    start,
+                    end,
+                }),
+            }
+        }
+
+        Ok(decode_words(&words)
+            .into_iter()
+            .filter(|span| span.score >= MIN_SCORE)
+            .filter_map(|span| span.mention(text))
+            .collect())
+    }
+
+    /// Most likely label and its probability for each token of a window
+    /// (token IDs without special tokens).
+    fn tag_window(&self, ids: &[u32]) -> Result<Vec<(usize, f32)>, AppError> {
+        let mut input_ids = Vec::with_capacity(ids.len() + 2);
+        input_ids.push(self.cls_token_id);
+        input_ids.extend_from_slice(ids);
+        input_ids.push(self.sep_token_id);
+        let seq_len = input_ids.len();
+
+        let input_ids = Tensor::from_vec(input_ids, (1, seq_len), &self.device)
+            .and_then(|t| t.to_dtype(DType::I64))
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let token_type_ids = input_ids
+            .zeros_like()
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let attention_mask = Tensor::ones((1, seq_len), DType::U8, &self.device)
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, &attention_mask)
+            .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
+        let probs: Vec<Vec<f32>> = self
+            .classifier
+            .forward(&hidden)
+            .and_then(|logits| logits.to_dtype(DType::F32))
+            .and_then(|logits| candle_nn::ops::softmax(&logits, D::Minus1))
+            .and_then(|probs| probs.squeeze(0))
+            .and_then(|probs| probs.to_vec2())
+            .map_err(|e| AppError::Ml(format!("Classifier failed: {}", e)))?;
+
+        // Drop [CLS] and [SEP]
+        Ok(probs[1..seq_len - 1]
+            .iter()
+            .map(|scores| {
+                scores
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap_or((0, 0.0))
+            })
+            .collect())
+    }
+}
+
+/// Order id2label by class index.
+fn ordered_labels(id2label: &HashMap<String, String>) -> Result<Vec<String>, AppError> {
+    let mut indexed = id2label
+        .iter()
+        .map(|(id, label)| {
+            id.parse::<usize>()
+                .map(|idx| (idx, label.clone()))
+                .map_err(|_| AppError::Ml(format!("Invalid label id in config: {}", id)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    indexed.sort_by_key(|(idx, _)| *idx);
+    Ok(indexed.into_iter().map(|(_, label)| label).collect())
+}
+
+/// A word with the BIO tag of its first sub-token. Offsets are byte offsets.
+#[derive(Debug, Clone)]
+struct WordTag<'a> {
+    tag: &'a str,
+    score: f32,
+    start: usize,
+    end: usize,
+}
+
+/// A run of words tagged as one person or place. Offsets are byte offsets.
+#[derive(Debug, Clone, PartialEq)]
+struct Span {
+    kind: EntityKind,
+    start: usize,
+    end: usize,
+    /// Mean tag probability over the span's words
+    score: f32,
+}
+
+impl Span {
+    fn mention(&self, text: &str) -> Option<EntityMention> {
+        let name = normalize_name(text.get(self.start..self.end)?);
+        if name.is_empty() {
+            return None;
+        }
+        Some(EntityMention {
+            kind: self.kind,
+            name,
+            start_offset: char_offset(text, self.start),
+            end_offset: char_offset(text, self.end),
+            score: self.score,
+        })
+    }
+}
+
+/// Group BIO-tagged words into entity spans. An I- tag continues an open span of
+/// the same type and otherwise starts a new one, as B- does. Spans of untracked
+/// types (organisations, misc) are decoded but dropped.
+fn decode_words(words: &[WordTag]) -> Vec<Span> {
+    // (entity type, start, end, summed score, word count)
+    let mut open: Option<(&str, usize, usize, f32, usize)> = None;
+    let mut spans = Vec::new();
+
+    let mut close = |open: &mut Option<(&str, usize, usize, f32, usize)>| {
+        if let Some((tag, start, end, total, count)) = open.take() {
+            if let Some(kind) = EntityKind::from_tag(tag) {
+                spans.push(Span {
+                    kind,
+                    start,
+                    end,
+                    score: total / count as f32,
+                });
+            }
+        }
+    };
+
+    for word in words {
+        let Some((prefix, tag)) = word.tag.split_once('-') else {
+            close(&mut open);
+            continue;
+        };
+        match &mut open {
+            Some((open_tag, _, end, total, count)) if prefix == "I" && *open_tag == tag => {
+                *end = word.end;
+                *total += word.score;
+                *count += 1;
+            }
+            _ => {
+                close(&mut open);
+                open = Some((tag, word.start, word.end, word.score, 1));
+            }
+        }
+    }
+    close(&mut open);
+    spans
+}
+
+/// Clean up a name as written: surrounding punctuation and a trailing possessive
+/// are removed and whitespace is collapsed ("Sam's" -> "Sam").
+pub fn normalize_name(name: &str) -> String {
+    let collapsed = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    let trimmed = collapsed.trim_matches(|c: char| !c.is_alphanumeric());
+    let without_possessive = ["'s", "’s", "'S", "’S"]
+        .iter()
+        .find_map(|suffix| trimmed.strip_suffix(suffix))
+        .unwrap_or(trimmed);
+    without_possessive
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_string()
+}
+
+/// Key aliases are matched on: the normalized name, lowercased.
+pub fn alias_key(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+/// Parse the local LLM's answer to `OllamaClient::extract_entities`: one
+/// `person: Name` or `place: Name` per line. Other lines are ignored.
+pub fn parse_llm_entities(response: &str) -> Vec<(EntityKind, String)> {
+    let mut names: Vec<(EntityKind, String)> = Vec::new();
+    for line in response.lines() {
+        let line = line.trim().trim_start_matches(['-', '*', ' ']);
+        let Some((kind, name)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(kind) = EntityKind::parse(&kind.trim().to_lowercase()) else {
+            continue;
+        };
+        let name = normalize_name(name);
+        if !name.is_empty() && !names.iter().any(|(k, n)| *k == kind && *n == name) {
+            names.push((kind, name));
+        }
+    }
+    names
+}
+
+/// Find every whole-word occurrence of the given names in the text. Names that
+/// don't occur (e.g. made up by the LLM) produce no mentions.
+pub fn locate_mentions(text: &str, names: &[(EntityKind, String)]) -> Vec<EntityMention> {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric());
+    let mut mentions: Vec<EntityMention> = Vec::new();
+
+    // Longest names first, so "Sam Rivera" claims its span before "Sam"
+    let mut names: Vec<&(EntityKind, String)> = names.iter().collect();
+    names.sort_by_key(|(_, name)| std::cmp::Reverse(name.len()));
+
+    let mut claimed: Vec<(usize, usize)> = Vec::new();
+    for (kind, name) in names {
+        for (start, _) in text.match_indices(name.as_str()) {
+            let end = start + name.len();
+            let overlaps = claimed.iter().any(|&(s, e)| start < e && s < end);
+            if overlaps
+                || !is_boundary(text[..start].chars().next_back())
+                || !is_boundary(text[end..].chars().next())
+            {
+                continue;
+            }
+            claimed.push((start, end));
+            mentions.push(EntityMention {
+                kind: *kind,
+                name: name.clone(),
+                start_offset: char_offset(text, start),
+                end_offset: char_offset(text, end),
+                score: 1.0,
+            });
+        }
+    }
+    mentions.sort_by_key(|m| m.start_offset);
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word<'a>(tag: &'a str, start: usize, end: usize) -> WordTag<'a> {
+        WordTag {
+            tag,
+            score: 0.9,
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_decode_words() {
+        // "Sam Rivera met Alex at Acme in Lisbon"
+        let words = [
+            word("B-PER", 0, 3),
+            word("I-PER", 4, 10),
+            word("O", 11, 14),
+            word("B-PER", 15, 19),
+            word("O", 20, 22),
+            word("B-ORG", 23, 27),
+            word("O", 28, 30),
+            word("I-LOC", 31, 37),
+        ];
+        let spans = decode_words(&words);
+        let found: Vec<(EntityKind, usize, usize)> =
+            spans.iter().map(|s| (s.kind, s.start, s.end)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (EntityKind::Person, 0, 10),
+                (EntityKind::Person, 15, 19),
+                (EntityKind::Place, 31, 37),
+            ]
+        );
+
+        // An I- tag of another type starts a new span
+        let spans = decode_words(&[word("B-LOC", 0, 5), word("I-PER", 6, 9)]);
+        assert_eq!(spans.len(), 2);
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("Sam's"), "Sam");
+        assert_eq!(normalize_name(" Sam  Rivera’s, "), "Sam Rivera");
+        assert_eq!(normalize_name("\"Lisbon\""), "Lisbon");
+        assert_eq!(alias_key("SAM's"), "sam");
+        assert_eq!(normalize_name(" ,. "), "");
+    }
+
+    #[test]
+    fn test_llm_entities_are_located() {
+        let response =
+            "person: Sam Rivera\n- place: Lisbon\nperson: Sam\nNone of the others\nperson: Morgan";
+        let names = parse_llm_entities(response);
+        assert_eq!(names.len(), 4);
+
+        let text = "Sam Rivera flew to Lisbon. Later Sam called; Samantha didn't.";
+        let mentions = locate_mentions(text, &names);
+        let found: Vec<(&str, usize)> = mentions
+            .iter()
+            .map(|m| (m.name.as_str(), m.start_offset))
+            .collect();
+        // "Morgan" isn't in the text and "Samantha" isn't a whole-word match
+        assert_eq!(found, vec![("Sam Rivera", 0), ("Lisbon", 19), ("Sam", 33)]);
+        assert_eq!(mentions[1].kind, EntityKind::Place);
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_extract_people_and_places() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = NerModel::load(&models_dir, Precision::F32).unwrap();
+
+        let mentions = model
+            .extract("Had lunch with Sam Rivera in Lisbon, then called Grandma.")
+            .unwrap();
+        assert!(mentions
+            .iter()
+            .any(|m| m.kind == EntityKind::Person && m.name == "Sam Rivera"));
+        assert!(mentions
+            .iter()
+            .any(|m| m.kind == EntityKind::Place && m.name == "Lisbon"));
+    }
+}
@@ -0,0 +1,411 @@
+//! 2D map of entry embeddings: PCA gives a starting layout which exact t-SNE
+//! refines so that similar entries end up close together. Entries added later are
+//! placed among their nearest already-mapped neighbours instead of re-running t-SNE.
+
+/// t-SNE is quadratic in the number of entries, so larger journals lay out an even
+/// sample and place the remaining entries next to their neighbours.
+pub const MAX_TSNE_ENTRIES: usize = 2000;
+
+/// Below this many entries the PCA layout is used as is.
+const MIN_TSNE_ENTRIES: usize = 8;
+
+const PERPLEXITY: f32 = 30.0;
+const ITERATIONS: usize = 750;
+const EXAGGERATION_ITERATIONS: usize = 250;
+const EARLY_EXAGGERATION: f32 = 12.0;
+const POWER_ITERATIONS: usize = 100;
+
+/// Neighbours averaged when placing an entry into an existing layout.
+const PLACEMENT_NEIGHBORS: usize = 5;
+
+/// Lay out `vectors` in 2D, with coordinates centred and scaled into [-1, 1].
+/// Deterministic: the same vectors always give the same layout.
+pub fn layout(vectors: &[Vec<f32>]) -> Vec<[f32; 2]> {
+    if vectors.len() > MAX_TSNE_ENTRIES {
+        let step = vectors.len() as f32 / MAX_TSNE_ENTRIES as f32;
+        let sample: Vec<usize> = (0..MAX_TSNE_ENTRIES)
+            .map(|i| (i as f32 * step) as usize)
+            .collect();
+        let sampled: Vec<Vec<f32>> = sample.iter().map(|&i| vectors[i].clone()).collect();
+        let positions = layout(&sampled);
+
+        let mut result: Vec<Option<[f32; 2]>> = vec![None; vectors.len()];
+        for (&i, &position) in sample.iter().zip(&positions) {
+            result[i] = Some(position);
+        }
+        let anchors: Vec<(&[f32], [f32; 2])> = sampled
+            .iter()
+            .map(Vec::as_slice)
+            .zip(positions.iter().copied())
+            .collect();
+        return result
+            .into_iter()
+            .enumerate()
+            .map(|(i, position)| position.unwrap_or_else(|| place(&vectors[i], &anchors)))
+            .collect();
+    }
+
+    let init: Vec<[f32; 2]> = pca(vectors, 2)
+        .into_iter()
+        .map(|c| [c[0], c.get(1).copied().unwrap_or(0.0)])
+        .collect();
+    let positions = if vectors.len() < MIN_TSNE_ENTRIES {
+        init
+    } else {
+        tsne(vectors, init)
+    };
+    normalized(positions)
+}
+
+/// Position for a new entry in an existing layout: the similarity-weighted average
+/// of its nearest `anchors` (mapped entries' vectors and positions).
+pub fn place(vector: &[f32], anchors: &[(&[f32], [f32; 2])]) -> [f32; 2] {
+    let mut scored: Vec<(f32, [f32; 2])> = anchors
+        .iter()
+        .map(|(anchor, position)| (cosine(vector, anchor), *position))
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.truncate(PLACEMENT_NEIGHBORS);
+    if scored.is_empty() {
+        return [0.0, 0.0];
+    }
+
+    let weights: Vec<f32> = scored.iter().map(|(s, _)| s.max(0.0).powi(2)).collect();
+    let total: f32 = weights.iter().sum();
+    let (weights, total) = if total > 0.0 {
+        (weights, total)
+    } else {
+        (vec![1.0; scored.len()], scored.len() as f32)
+    };
+    let mut position = [0.0, 0.0];
+    for ((_, p), w) in scored.iter().zip(&weights) {
+        position[0] += p[0] * w / total;
+        position[1] += p[1] * w / total;
+    }
+    position
+}
+
+/// Project `vectors` onto their first `components` principal axes, found by power
+/// iteration with deflation against the axes already found.
+pub fn pca(vectors: &[Vec<f32>], components: usize) -> Vec<Vec<f32>> {
+    let Some(dim) = vectors.first().map(Vec::len) else {
+        return Vec::new();
+    };
+    let mut mean = vec![0.0f32; dim];
+    for v in vectors {
+        for (m, x) in mean.iter_mut().zip(v) {
+            *m += x / vectors.len() as f32;
+        }
+    }
+    let centered: Vec<Vec<f32>> = vectors
+        .iter()
+        .map(|v| v.iter().zip(&mean).map(|(x, m)| x - m).collect())
+        .collect();
+
+    let mut axes: Vec<Vec<f32>> = Vec::new();
+    for c in 0..components.min(dim) {
+        // Fixed start that isn't orthogonal to typical data
+        let mut axis: Vec<f32> = (0..dim).map(|i| 1.0 + (i % (c + 2)) as f32).collect();
+        orthogonalize(&mut axis, &axes);
+        for _ in 0..POWER_ITERATIONS {
+            let mut next = vec![0.0f32; dim];
+            for row in &centered {
+                let score = dot(row, &axis);
+                for (n, x) in next.iter_mut().zip(row) {
+                    *n += score * x;
+                }
+            }
+            orthogonalize(&mut next, &axes);
+            // No variance left along any remaining direction
+            if dot(&next, &next) < 1e-12 {
+                break;
+            }
+            axis = next;
+        }
+        axes.push(axis);
+    }
+
+    centered
+        .iter()
+        .map(|row| axes.iter().map(|axis| dot(row, axis)).collect())
+        .collect()
+}
+
+/// Exact t-SNE (van der Maaten & Hinton, 2008) starting from `init`, with early
+/// exaggeration, momentum and per-coordinate gains.
+fn tsne(vectors: &[Vec<f32>], init: Vec<[f32; 2]>) -> Vec<[f32; 2]> {
+    let n = vectors.len();
+    let perplexity = PERPLEXITY.min((n - 1) as f32 / 3.0);
+    let p = joint_probabilities(vectors, perplexity);
+
+    // Start small so early exaggeration can form clusters
+    let spread = (init.iter().map(|y| y[0] * y[0]).sum::<f32>() / n as f32).sqrt();
+    let scale = if spread > 0.0 { 1e-4 / spread } else { 0.0 };
+    let mut y: Vec<[f32; 2]> = init.iter().map(|y| [y[0] * scale, y[1] * scale]).collect();
+
+    let learning_rate = (n as f32 / EARLY_EXAGGERATION / 4.0).max(50.0);
+    let mut update = vec![[0.0f32; 2]; n];
+    let mut gains = vec![[1.0f32; 2]; n];
+    let mut q = vec![0.0f32; n * n];
+    let mut gradient = vec![[0.0f32; 2]; n];
+
+    for iteration in 0..ITERATIONS {
+        let (exaggeration, momentum) = if iteration < EXAGGERATION_ITERATIONS {
+            (EARLY_EXAGGERATION, 0.5)
+        } else {
+            (1.0, 0.8)
+        };
+
+        // Student-t similarities in the layout
+        let mut sum = 0.0f32;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (dx, dy) = (y[i][0] - y[j][0], y[i][1] - y[j][1]);
+                let kernel = 1.0 / (1.0 + dx * dx + dy * dy);
+                q[i * n + j] = kernel;
+                q[j * n + i] = kernel;
+                sum += 2.0 * kernel;
+            }
+        }
+
+        for i in 0..n {
+            let mut g = [0.0f32; 2];
+            for j in (0..n).filter(|&j| j != i) {
+                let kernel = q[i * n + j];
+                let force = (exaggeration * p[i * n + j] - kernel / sum) * kernel;
+                g[0] += 4.0 * force * (y[i][0] - y[j][0]);
+                g[1] += 4.0 * force * (y[i][1] - y[j][1]);
+            }
+            gradient[i] = g;
+        }
+
+        for i in 0..n {
+            for d in 0..2 {
+                gains[i][d] = if (gradient[i][d] > 0.0) != (update[i][d] > 0.0) {
+                    gains[i][d] + 0.2
+                } else {
+                    (gains[i][d] * 0.8).max(0.01)
+                };
+                update[i][d] =
+                    momentum * update[i][d] - learning_rate * gains[i][d] * gradient[i][d];
+                y[i][d] += update[i][d];
+            }
+        }
+    }
+    y
+}
+
+/// Symmetric input similarities: each entry's Gaussian over cosine distances, with
+/// its bandwidth found by binary search to match `perplexity`.
+fn joint_probabilities(vectors: &[Vec<f32>], perplexity: f32) -> Vec<f32> {
+    let n = vectors.len();
+    let units: Vec<Vec<f32>> = vectors.iter().map(|v| unit(v)).collect();
+    let mut distances = vec![0.0f32; n * n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let d = (2.0 - 2.0 * dot(&units[i], &units[j])).max(0.0);
+            distances[i * n + j] = d;
+            distances[j * n + i] = d;
+        }
+    }
+
+    let target = perplexity.ln();
+    let mut conditional = vec![0.0f32; n * n];
+    let mut row = vec![0.0f32; n];
+    for i in 0..n {
+        let others = (0..n).filter(|&j| j != i);
+        // Shifting by the nearest distance keeps exp() from underflowing
+        let nearest = others
+            .clone()
+            .map(|j| distances[i * n + j])
+            .fold(f32::INFINITY, f32::min);
+        let (mut beta, mut low, mut high) = (1.0f32, 0.0f32, f32::INFINITY);
+        for _ in 0..50 {
+            let mut sum = 0.0f32;
+            let mut weighted = 0.0f32;
+            for j in others.clone() {
+                let d = distances[i * n + j] - nearest;
+                row[j] = (-d * beta).exp();
+                sum += row[j];
+                weighted += d * row[j];
+            }
+            let entropy = sum.ln() + beta * weighted / sum;
+            if (entropy - target).abs() < 1e-5 {
+                break;
+            }
+            if entropy > target {
+                low = beta;
+                beta = if high.is_finite() {
+                    (beta + high) / 2.0
+                } else {
+                    beta * 2.0
+                };
+            } else {
+                high = beta;
+                beta = (beta + low) / 2.0;
+            }
+        }
+        let sum: f32 = others.clone().map(|j| row[j]).sum();
+        for j in others {
+            conditional[i * n + j] = row[j] / sum;
+        }
+    }
+
+    let mut joint = vec![0.0f32; n * n];
+    for i in 0..n {
+        for j in (0..n).filter(|&j| j != i) {
+            joint[i * n + j] =
+                ((conditional[i * n + j] + conditional[j * n + i]) / (2.0 * n as f32)).max(1e-12);
+        }
+    }
+    joint
+}
+
+/// Centre the layout on the origin and scale it into [-1, 1].
+fn normalized(mut positions: Vec<[f32; 2]>) -> Vec<[f32; 2]> {
+    if positions.is_empty() {
+        return positions;
+    }
+    let n = positions.len() as f32;
+    let mean = positions
+        .iter()
+        .fold([0.0, 0.0], |acc, p| [acc[0] + p[0] / n, acc[1] + p[1] / n]);
+    let extent = positions
+        .iter()
+        .map(|p| (p[0] - mean[0]).abs().max((p[1] - mean[1]).abs()))
+        .fold(0.0f32, f32::max);
+    let scale = if extent > 0.0 { 1.0 / extent } else { 0.0 };
+    for p in &mut positions {
+        *p = [(p[0] - mean[0]) * scale, (p[1] - mean[1]) * scale];
+    }
+    positions
+}
+
+/// Remove the components along `axes` (orthonormal) and normalise what's left.
+fn orthogonalize(v: &mut [f32], axes: &[Vec<f32>]) {
+    for axis in axes {
+        let projection = dot(v, axis);
+        for (x, a) in v.iter_mut().zip(axis) {
+            *x -= projection * a;
+        }
+    }
+    let norm = dot(v, v).sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+fn unit(v: &[f32]) -> Vec<f32> {
+    let norm = dot(v, v).sqrt();
+    if norm > 0.0 {
+        v.iter().map(|x| x / norm).collect()
+    } else {
+        v.to_vec()
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let norm = (dot(a, a) * dot(b, b)).sqrt();
+    if norm > 0.0 {
+        dot(a, b) / norm
+    } else {
+        0.0
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `per_group` noisy vectors around each of `groups` orthogonal directions.
+    fn grouped(groups: usize, per_group: usize) -> Vec<Vec<f32>> {
+        let mut vectors = Vec::new();
+        for g in 0..groups {
+            for k in 0..per_group {
+                let mut v = vec![0.0f32; 16];
+                v[g] = 1.0;
+                v[8 + k % 8] = 0.15;
+                v[(g + 1) % 8] += 0.02 * k as f32;
+                vectors.push(v);
+            }
+        }
+        vectors
+    }
+
+    fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+        ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+    }
+
+    #[test]
+    fn test_pca_finds_main_axis() {
+        // Points spread along (1, 1, 0) with a little spread along z
+        let vectors: Vec<Vec<f32>> = (0..10)
+            .map(|i| {
+                let t = i as f32;
+                vec![t, t, 0.1 * (i % 2) as f32]
+            })
+            .collect();
+        let projected = pca(&vectors, 2);
+        assert_eq!(projected.len(), 10);
+        let first: Vec<f32> = projected.iter().map(|c| c[0]).collect();
+        let increasing = first.windows(2).all(|w| w[1] > w[0]);
+        let decreasing = first.windows(2).all(|w| w[1] < w[0]);
+        assert!(increasing || decreasing);
+        assert!(projected.iter().all(|c| c[1].abs() < 0.1));
+    }
+
+    #[test]
+    fn test_layout_separates_groups() {
+        let vectors = grouped(3, 10);
+        let positions = layout(&vectors);
+        assert_eq!(positions.len(), 30);
+        assert!(positions
+            .iter()
+            .all(|p| p[0].abs() <= 1.0 + 1e-5 && p[1].abs() <= 1.0 + 1e-5));
+        assert_eq!(layout(&vectors), positions);
+
+        let centre = |g: usize| {
+            let members = &positions[g * 10..(g + 1) * 10];
+            members.iter().fold([0.0, 0.0], |acc, p| {
+                [acc[0] + p[0] / 10.0, acc[1] + p[1] / 10.0]
+            })
+        };
+        let centres: Vec<[f32; 2]> = (0..3).map(centre).collect();
+        for (i, p) in positions.iter().enumerate() {
+            let own = distance(*p, centres[i / 10]);
+            for (g, c) in centres.iter().enumerate().filter(|(g, _)| *g != i / 10) {
+                assert!(
+                    own < distance(*p, *c),
+                    "entry {} is closer to group {}",
+                    i,
+                    g
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_layouts() {
+        assert!(layout(&[]).is_empty());
+        assert_eq!(layout(&[vec![1.0, 0.0]]), vec![[0.0, 0.0]]);
+        let pair = layout(&[vec![1.0, 0.0], vec![0.0, 1.0]]);
+        assert!((distance(pair[0], pair[1]) - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_place_next_to_neighbours() {
+        let a = [1.0, 0.0, 0.0];
+        let b = [0.0, 1.0, 0.0];
+        let anchors: Vec<(&[f32], [f32; 2])> = vec![(&a, [-1.0, 0.0]), (&b, [1.0, 0.5])];
+        assert_eq!(place(&[2.0, 0.0, 0.0], &anchors), [-1.0, 0.0]);
+        let between = place(&[1.0, 1.0, 0.0], &anchors);
+        assert!((between[0] - 0.0).abs() < 1e-5 && (between[1] - 0.25).abs() < 1e-5);
+        // Nothing similar: plain average
+        assert_eq!(place(&[0.0, 0.0, 1.0], &anchors), [0.0, 0.25]);
+        assert_eq!(place(&a, &[]), [0.0, 0.0]);
+    }
+}
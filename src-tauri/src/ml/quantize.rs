@@ -0,0 +1,179 @@
+//! Compact indexes for embedding vectors. sqlite-vec indexes int8 and bit vectors as
+//! well as f32: int8 keeps each component in a byte (4x smaller), bit keeps only its
+//! sign (32x smaller), so KNN searches scan far less data. The f32 vectors aren't
+//! kept: quantized searches fetch extra candidates and rescore them against the
+//! full-precision query using int8 vectors, read from the index itself for int8 and
+//! from an int8 copy kept beside bit indexes (so bit storage takes about 28% of f32).
+//! Both keep rankings close to f32 (see the recall comparison in `db::vectors` tests).
+
+/// How embedding vectors are stored in the vec0 tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorStorage {
+    /// Full precision, 4 bytes per dimension
+    #[default]
+    F32,
+    /// Components scaled to -127..=127, 1 byte per dimension
+    Int8,
+    /// Sign of each component, 1 bit per dimension; searched by Hamming distance
+    Bit,
+}
+
+impl VectorStorage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VectorStorage::F32 => "f32",
+            VectorStorage::Int8 => "int8",
+            VectorStorage::Bit => "bit",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "f32" => Some(VectorStorage::F32),
+            "int8" => Some(VectorStorage::Int8),
+            "bit" => Some(VectorStorage::Bit),
+            _ => None,
+        }
+    }
+
+    /// The storage a vec0 table was created with, from its `CREATE` statement.
+    pub fn of_table_sql(sql: &str) -> Self {
+        let sql = sql.to_lowercase();
+        if sql.contains("int8[") {
+            VectorStorage::Int8
+        } else if sql.contains("bit[") {
+            VectorStorage::Bit
+        } else {
+            VectorStorage::F32
+        }
+    }
+
+    /// vec0 column type for vectors of `dimension` components.
+    pub fn column_type(&self, dimension: usize) -> String {
+        let element = match self {
+            VectorStorage::F32 => "float",
+            VectorStorage::Int8 => "int8",
+            VectorStorage::Bit => "bit",
+        };
+        format!("{}[{}]", element, dimension)
+    }
+
+    /// SQL wrapping a bound blob parameter so vec0 reads it as this storage type.
+    pub fn sql_value(&self, param: &str) -> String {
+        match self {
+            VectorStorage::F32 => param.to_string(),
+            VectorStorage::Int8 => format!("vec_int8({})", param),
+            VectorStorage::Bit => format!("vec_bit({})", param),
+        }
+    }
+
+    /// Bytes stored per vector of `dimension` components.
+    pub fn bytes_per_vector(&self, dimension: usize) -> usize {
+        match self {
+            VectorStorage::F32 => dimension * 4,
+            VectorStorage::Int8 => dimension,
+            VectorStorage::Bit => dimension.div_ceil(8),
+        }
+    }
+
+    /// Candidates fetched from the index per result wanted, before rescoring.
+    pub fn oversample(&self) -> usize {
+        match self {
+            VectorStorage::F32 => 1,
+            VectorStorage::Int8 => 2,
+            VectorStorage::Bit => 8,
+        }
+    }
+
+    /// Encode a unit-length vector as a blob of this storage type.
+    pub fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        match self {
+            VectorStorage::F32 => vector.iter().flat_map(|f| f.to_le_bytes()).collect(),
+            VectorStorage::Int8 => vector
+                .iter()
+                .map(|f| (f.clamp(-1.0, 1.0) * 127.0).round() as i8 as u8)
+                .collect(),
+            VectorStorage::Bit => {
+                let mut bits = vec![0u8; vector.len().div_ceil(8)];
+                for (i, f) in vector.iter().enumerate() {
+                    if *f > 0.0 {
+                        bits[i / 8] |= 1 << (i % 8);
+                    }
+                }
+                bits
+            }
+        }
+    }
+
+    /// Decode a stored blob back to floats. Int8 components are rescaled; bit vectors
+    /// become ±1/√n, the unit vector with the stored signs.
+    pub fn decode(&self, blob: &[u8]) -> Vec<f32> {
+        match self {
+            VectorStorage::F32 => blob
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            VectorStorage::Int8 => blob.iter().map(|b| *b as i8 as f32 / 127.0).collect(),
+            VectorStorage::Bit => {
+                let magnitude = 1.0 / ((blob.len() * 8) as f32).sqrt();
+                (0..blob.len() * 8)
+                    .map(|i| {
+                        if blob[i / 8] & (1 << (i % 8)) != 0 {
+                            magnitude
+                        } else {
+                            -magnitude
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let vector =
+            crate::ml::backend::l2_normalized(vec![0.5, -0.25, 0.0, 1.0, -1.0, 0.1, 0.2, -0.3]);
+
+        let f32_blob = VectorStorage::F32.encode(&vector);
+        assert_eq!(f32_blob.len(), VectorStorage::F32.bytes_per_vector(8));
+        assert_eq!(VectorStorage::F32.decode(&f32_blob), vector);
+
+        let int8_blob = VectorStorage::Int8.encode(&vector);
+        assert_eq!(int8_blob.len(), 8);
+        for (decoded, original) in VectorStorage::Int8.decode(&int8_blob).iter().zip(&vector) {
+            assert!((decoded - original).abs() <= 0.5 / 127.0);
+        }
+
+        let bit_blob = VectorStorage::Bit.encode(&vector);
+        assert_eq!(bit_blob, vec![0b0110_1001]);
+        let signs: Vec<bool> = VectorStorage::Bit
+            .decode(&bit_blob)
+            .iter()
+            .map(|f| *f > 0.0)
+            .collect();
+        assert_eq!(
+            signs,
+            vec![true, false, false, true, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_storage_names() {
+        for storage in [VectorStorage::F32, VectorStorage::Int8, VectorStorage::Bit] {
+            assert_eq!(VectorStorage::parse(storage.as_str()), Some(storage));
+            let sql = format!(
+                "CREATE VIRTUAL TABLE t USING vec0(id TEXT PRIMARY KEY, embedding {})",
+                storage.column_type(384)
+            );
+            assert_eq!(VectorStorage::of_table_sql(&sql), storage);
+        }
+        assert_eq!(VectorStorage::F32.column_type(384), "float[384]");
+        assert_eq!(VectorStorage::parse("f16"), None);
+    }
+}
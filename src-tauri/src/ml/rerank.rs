@@ -0,0 +1,314 @@
+//! Cross-encoder reranking of search candidates: a MiniLM model trained on MS MARCO
+//! reads the query and each passage together and scores how well the passage answers
+//! the query, which is more precise than fusing ranks from keyword and vector search.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use candle_core::{DType, Device, Module, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::Config;
+use serde::Serialize;
+use tokenizers::{
+    models::wordpiece::WordPiece, normalizers::BertNormalizer,
+    pre_tokenizers::bert::BertPreTokenizer, Tokenizer,
+};
+
+use crate::error::AppError;
+use crate::ml::backend::Reranker;
+use crate::ml::encoders::Bert;
+use crate::ml::models::{estimate_weight_bytes, get_device, Precision, RERANKER_MODEL};
+
+/// Fused candidates reranked per search.
+pub const RERANK_CANDIDATES: usize = 20;
+
+/// Candidates scored per forward pass; the latency budget is checked between batches.
+pub const BATCH_SIZE: usize = 4;
+
+/// Query tokens kept; the passage gets the rest of the pair's tokens.
+const MAX_QUERY_TOKENS: usize = 64;
+
+/// Tokens per query-passage pair, including special tokens. Entries are truncated
+/// to fit, which keeps latency predictable; the opening of an entry carries most
+/// of its topic.
+const MAX_PAIR_TOKENS: usize = 256;
+
+/// Default time reranking may add to a search, in milliseconds.
+pub const DEFAULT_BUDGET_MS: u64 = 500;
+
+const PAD_TOKEN_ID: u32 = 0;
+
+/// Whether search results are reranked, and how long reranking may take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RerankSettings {
+    pub enabled: bool,
+    /// Candidates not scored within this many milliseconds keep their fused order
+    pub budget_ms: u64,
+}
+
+impl Default for RerankSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            budget_ms: DEFAULT_BUDGET_MS,
+        }
+    }
+}
+
+/// BERT cross-encoder with a single relevance logit (BertForSequenceClassification).
+pub struct CrossEncoder {
+    model: Bert,
+    pooler: candle_nn::Linear,
+    classifier: candle_nn::Linear,
+    tokenizer: Tokenizer,
+    cls_token_id: u32,
+    sep_token_id: u32,
+    device: Device,
+    precision: Precision,
+    memory_bytes: u64,
+}
+
+impl CrossEncoder {
+    /// Load the reranker from disk with weights in the given precision.
+    pub fn load(models_dir: &Path, precision: Precision) -> Result<Self, AppError> {
+        let model_path = RERANKER_MODEL.model_path(models_dir);
+        let tokenizer_path = RERANKER_MODEL.tokenizer_path(models_dir);
+        let config_path = RERANKER_MODEL.config_path(models_dir);
+
+        log::info!("Loading reranker from: {}", model_path.display());
+
+        let config_str = std::fs::read_to_string(&config_path)?;
+        let config: Config = serde_json::from_str(&config_str)
+            .map_err(|e| AppError::Ml(format!("Failed to parse config: {}", e)))?;
+
+        // Special tokens are added per pair, so the tokenizer has no post-processor
+        let vocab_path_str = tokenizer_path.to_string_lossy().to_string();
+        let wordpiece = WordPiece::from_file(&vocab_path_str)
+            .unk_token("[UNK]".to_string())
+            .build()
+            .map_err(|e| AppError::Ml(format!("Failed to build WordPiece: {}", e)))?;
+        let mut tokenizer = Tokenizer::new(wordpiece);
+        let cls_token_id = tokenizer.token_to_id("[CLS]").unwrap_or(101);
+        let sep_token_id = tokenizer.token_to_id("[SEP]").unwrap_or(102);
+        tokenizer.with_normalizer(Some(BertNormalizer::default()));
+        tokenizer.with_pre_tokenizer(Some(BertPreTokenizer));
+
+        let device = get_device();
+        let precision = precision.supported_on(&device);
+        let memory_bytes = estimate_weight_bytes(&model_path, precision.dtype());
+        let vb = unsafe {
+            VarBuilder::from_mmaped_safetensors(&[model_path], precision.dtype(), &device)
+                .map_err(|e| AppError::Ml(format!("Failed to load weights: {}", e)))?
+        };
+
+        let model = Bert::load(vb.clone(), &config)
+            .map_err(|e| AppError::Ml(format!("Failed to load model: {}", e)))?;
+        let pooler = candle_nn::linear(
+            config.hidden_size,
+            config.hidden_size,
+            vb.pp("bert.pooler.dense"),
+        )
+        .map_err(|e| AppError::Ml(format!("Failed to load pooler: {}", e)))?;
+        let classifier = candle_nn::linear(config.hidden_size, 1, vb.pp("classifier"))
+            .map_err(|e| AppError::Ml(format!("Failed to load classifier: {}", e)))?;
+
+        Ok(Self {
+            model,
+            pooler,
+            classifier,
+            tokenizer,
+            cls_token_id,
+            sep_token_id,
+            device,
+            precision,
+            memory_bytes,
+        })
+    }
+
+    /// Precision the weights were loaded in.
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Estimated memory held by the loaded weights.
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory_bytes
+    }
+
+    /// Relevance logit of each passage for the query, in input order; higher is
+    /// more relevant.
+    pub fn score(&self, query: &str, passages: &[&str]) -> Result<Vec<f32>, AppError> {
+        if passages.is_empty() {
+            return Ok(Vec::new());
+        }
+        let encode = |text: &str| {
+            self.tokenizer
+                .encode(text, false)
+                .map(|encoding| encoding.get_ids().to_vec())
+                .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))
+        };
+        let query_ids = encode(query)?;
+        let pairs: Vec<(Vec<u32>, Vec<u32>)> = passages
+            .iter()
+            .map(|passage| {
+                Ok(pair_inputs(
+                    &query_ids,
+                    &encode(passage)?,
+                    self.cls_token_id,
+                    self.sep_token_id,
+                ))
+            })
+            .collect::<Result<_, AppError>>()?;
+
+        let seq_len = pairs.iter().map(|(ids, _)| ids.len()).max().unwrap_or(0);
+        let mut input_ids = Vec::with_capacity(pairs.len() * seq_len);
+        let mut token_type_ids = Vec::with_capacity(pairs.len() * seq_len);
+        // 1 = attend, 0 = padding
+        let mut attention_mask = Vec::with_capacity(pairs.len() * seq_len);
+        for (ids, types) in &pairs {
+            let padding = seq_len - ids.len();
+            input_ids.extend_from_slice(ids);
+            input_ids.extend(std::iter::repeat_n(PAD_TOKEN_ID, padding));
+            token_type_ids.extend_from_slice(types);
+            token_type_ids.extend(std::iter::repeat_n(0u32, padding));
+            attention_mask.extend(std::iter::repeat_n(1u8, ids.len()));
+            attention_mask.extend(std::iter::repeat_n(0u8, padding));
+        }
+
+        let shape = (pairs.len(), seq_len);
+        let input_ids = Tensor::from_vec(input_ids, shape, &self.device)
+            .and_then(|t| t.to_dtype(DType::I64))
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let token_type_ids = Tensor::from_vec(token_type_ids, shape, &self.device)
+            .and_then(|t| t.to_dtype(DType::I64))
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+        let attention_mask = Tensor::from_vec(attention_mask, shape, &self.device)
+            .map_err(|e| AppError::Ml(e.to_string()))?;
+
+        let hidden = self
+            .model
+            .forward(&input_ids, &token_type_ids, &attention_mask)
+            .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
+
+        // [CLS] -> pooler (dense + tanh) -> relevance logit
+        hidden
+            .narrow(1, 0, 1)
+            .and_then(|cls| cls.squeeze(1))
+            .and_then(|cls| self.pooler.forward(&cls))
+            .and_then(|pooled| pooled.tanh())
+            .and_then(|pooled| self.classifier.forward(&pooled))
+            .and_then(|logits| logits.to_dtype(DType::F32))
+            .and_then(|logits| logits.squeeze(1))
+            .and_then(|logits| logits.to_vec1())
+            .map_err(|e| AppError::Ml(format!("Classifier failed: {}", e)))
+    }
+}
+
+/// Token IDs and token type IDs of `[CLS] query [SEP] passage [SEP]`, with the
+/// query and then the passage truncated to fit `MAX_PAIR_TOKENS`.
+fn pair_inputs(query: &[u32], passage: &[u32], cls: u32, sep: u32) -> (Vec<u32>, Vec<u32>) {
+    let query = &query[..query.len().min(MAX_QUERY_TOKENS)];
+    let passage_room = MAX_PAIR_TOKENS - 3 - query.len();
+    let passage = &passage[..passage.len().min(passage_room)];
+
+    let mut ids = Vec::with_capacity(query.len() + passage.len() + 3);
+    ids.push(cls);
+    ids.extend_from_slice(query);
+    ids.push(sep);
+    let first_segment = ids.len();
+    ids.extend_from_slice(passage);
+    ids.push(sep);
+
+    let mut types = vec![0u32; first_segment];
+    types.resize(ids.len(), 1);
+    (ids, types)
+}
+
+/// Score `passages` against `query` in batches until `budget` runs out. Returns
+/// scores for a prefix of the passages: all of them if time allowed, fewer (or none)
+/// if not, or if the reranker fails.
+pub async fn score_within(
+    reranker: &dyn Reranker,
+    query: &str,
+    passages: &[&str],
+    budget: Duration,
+) -> Vec<f32> {
+    let started = Instant::now();
+    let mut scores = Vec::with_capacity(passages.len());
+    for batch in passages.chunks(BATCH_SIZE) {
+        if started.elapsed() >= budget {
+            log::info!(
+                "Reranking stopped after {} of {} candidates ({} ms budget)",
+                scores.len(),
+                passages.len(),
+                budget.as_millis()
+            );
+            break;
+        }
+        match reranker.score(query, batch).await {
+            Ok(batch_scores) => scores.extend(batch_scores),
+            Err(e) => {
+                log::warn!("Reranking failed: {}", e);
+                break;
+            }
+        }
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ml::fake::FakeReranker;
+
+    #[test]
+    fn test_pair_inputs() {
+        let (ids, types) = pair_inputs(&[7, 8], &[20, 21, 22], 101, 102);
+        assert_eq!(ids, vec![101, 7, 8, 102, 20, 21, 22, 102]);
+        assert_eq!(types, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+
+        // Long passages are cut so the pair fits
+        let passage: Vec<u32> = (0..1000).collect();
+        let (ids, types) = pair_inputs(&[7], &passage, 101, 102);
+        assert_eq!(ids.len(), MAX_PAIR_TOKENS);
+        assert_eq!(ids.last(), Some(&102));
+        assert_eq!(types.iter().filter(|&&t| t == 0).count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_score_within_budget() {
+        let passages = [
+            "walked the dog by the canal",
+            "taxes are due",
+            "the dog slept",
+            "meeting notes",
+            "a long walk",
+        ];
+        let scores =
+            score_within(&FakeReranker, "dog walk", &passages, Duration::from_secs(5)).await;
+        assert_eq!(scores.len(), passages.len());
+        assert!(scores[0] > scores[2] && scores[2] > scores[1]);
+
+        // No time left: nothing is scored and the fused order stands
+        let scores = score_within(&FakeReranker, "dog walk", &passages, Duration::ZERO).await;
+        assert!(scores.is_empty());
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_cross_encoder_prefers_relevant_passage() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = CrossEncoder::load(&models_dir, Precision::F32).unwrap();
+
+        let scores = model
+            .score(
+                "Why was I anxious before the interview?",
+                &[
+                    "Made pasta for dinner and watched a film.",
+                    "Couldn't sleep, kept rehearsing answers for tomorrow's job interview.",
+                ],
+            )
+            .unwrap();
+        assert!(scores[1] > scores[0]);
+    }
+}
@@ -2,18 +2,31 @@ use std::path::Path;
 
 use candle_core::{DType, Device, Module, Tensor};
 use candle_nn::VarBuilder;
-use candle_transformers::models::distilbert::{Config, DistilBertModel};
 use tokenizers::{
     models::wordpiece::WordPiece, normalizers::BertNormalizer,
     pre_tokenizers::bert::BertPreTokenizer, processors::bert::BertProcessing, Tokenizer,
 };
 
 use crate::error::AppError;
-use crate::ml::models::{get_device, SENTIMENT_MODEL};
+use crate::ml::encoders::{DistilBert, DistilBertConfig};
+use crate::ml::models::{estimate_weight_bytes, get_device, Precision, SENTIMENT_MODEL};
 
 /// Hidden dimension for DistilBERT base models
 const DISTILBERT_HIDDEN_DIM: usize = 768;
 
+/// [CLS] and [SEP] token IDs in the BERT uncased vocabulary
+const CLS_TOKEN_ID: u32 = 101;
+const SEP_TOKEN_ID: u32 = 102;
+const PAD_TOKEN_ID: u32 = 0;
+
+/// Maximum windows or sentences run through the model in one forward pass
+const MAX_BATCH_SIZE: usize = 16;
+
+/// Content tokens per window: DistilBERT's 512 positions minus [CLS] and [SEP]
+const WINDOW_TOKENS: usize = 510;
+/// Tokens shared by consecutive windows so emotions spanning a boundary aren't split
+pub(crate) const WINDOW_OVERLAP_TOKENS: usize = 64;
+
 /// GoEmotions taxonomy: 27 emotion labels + neutral
 pub const EMOTION_LABELS: [&str; 28] = [
     "admiration",
@@ -53,18 +66,75 @@ pub struct EmotionPrediction {
     pub score: f32,
 }
 
+/// How per-window scores are combined into entry-level scores.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggregation {
+    /// Strongest score in any window; a single intense passage dominates
+    #[default]
+    Max,
+    /// Average across windows; reflects the entry's overall tone
+    Mean,
+}
+
+impl Aggregation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Aggregation::Max => "max",
+            Aggregation::Mean => "mean",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "max" => Some(Aggregation::Max),
+            "mean" => Some(Aggregation::Mean),
+            _ => None,
+        }
+    }
+}
+
+/// Emotions predicted for one window of an entry.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChunkEmotions {
+    pub chunk_index: usize,
+    /// Character offsets of the window within the entry
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub predictions: Vec<EmotionPrediction>,
+}
+
+/// Emotions predicted for one sentence of an entry.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SentenceEmotions {
+    pub sentence_index: usize,
+    /// Character offsets of the sentence within the entry
+    pub start_offset: usize,
+    pub end_offset: usize,
+    pub predictions: Vec<EmotionPrediction>,
+}
+
+/// Entry-level emotions with the per-window results they were aggregated from.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChunkedEmotions {
+    pub predictions: Vec<EmotionPrediction>,
+    pub chunks: Vec<ChunkEmotions>,
+}
+
 /// Sentiment analysis model using DistilBERT fine-tuned on GoEmotions.
 pub struct SentimentModel {
-    model: DistilBertModel,
+    model: DistilBert,
     pre_classifier: candle_nn::Linear,
     classifier: candle_nn::Linear,
     tokenizer: Tokenizer,
     device: Device,
+    precision: Precision,
+    memory_bytes: u64,
 }
 
 impl SentimentModel {
-    /// Load the sentiment model from disk.
-    pub fn load(models_dir: &Path) -> Result<Self, AppError> {
+    /// Load the sentiment model from disk with weights in the given precision.
+    pub fn load(models_dir: &Path, precision: Precision) -> Result<Self, AppError> {
         let model_path = SENTIMENT_MODEL.model_path(models_dir);
         let tokenizer_path = SENTIMENT_MODEL.tokenizer_path(models_dir);
         let config_path = SENTIMENT_MODEL.config_path(models_dir);
@@ -73,7 +143,7 @@ impl SentimentModel {
 
         // Load config
         let config_str = std::fs::read_to_string(&config_path)?;
-        let config: Config = serde_json::from_str(&config_str)
+        let config: DistilBertConfig = serde_json::from_str(&config_str)
             .map_err(|e| AppError::Ml(format!("Failed to parse config: {}", e)))?;
 
         // Build tokenizer from vocab.txt using WordPiece
@@ -88,18 +158,20 @@ impl SentimentModel {
         tokenizer.with_normalizer(Some(BertNormalizer::default()));
         tokenizer.with_pre_tokenizer(Some(BertPreTokenizer));
         tokenizer.with_post_processor(Some(BertProcessing::new(
-            ("[SEP]".to_string(), 102),
-            ("[CLS]".to_string(), 101),
+            ("[SEP]".to_string(), SEP_TOKEN_ID),
+            ("[CLS]".to_string(), CLS_TOKEN_ID),
         )));
 
         // Load model weights
         let device = get_device();
+        let precision = precision.supported_on(&device);
+        let memory_bytes = estimate_weight_bytes(&model_path, precision.dtype());
         let vb = unsafe {
-            VarBuilder::from_mmaped_safetensors(&[model_path], DType::F32, &device)
+            VarBuilder::from_mmaped_safetensors(&[model_path], precision.dtype(), &device)
                 .map_err(|e| AppError::Ml(format!("Failed to load weights: {}", e)))?
         };
 
-        let model = DistilBertModel::load(vb.pp("distilbert"), &config)
+        let model = DistilBert::load(vb.pp("distilbert"), &config)
             .map_err(|e| AppError::Ml(format!("Failed to load model: {}", e)))?;
 
         // Load pre_classifier (768 -> 768) and classifier (768 -> 28) heads
@@ -123,115 +195,323 @@ impl SentimentModel {
             classifier,
             tokenizer,
             device,
+            precision,
+            memory_bytes,
         })
     }
 
+    /// Precision the weights were loaded in.
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// Estimated memory held by the loaded weights.
+    pub fn memory_bytes(&self) -> u64 {
+        self.memory_bytes
+    }
+
     /// Predict emotions for the given text.
     /// Returns top emotions above threshold, sorted by confidence.
+    /// Long texts are split into windows whose scores are combined by their maximum.
     pub fn predict(
         &self,
         text: &str,
         threshold: f32,
         max_labels: usize,
     ) -> Result<Vec<EmotionPrediction>, AppError> {
-        // Tokenize the input
-        let encoding = self
-            .tokenizer
-            .encode(text, true)
-            .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))?;
-
-        let input_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
-
-        // Convert to tensors
-        // Convert to I64 - candle requires 64-bit integers for embedding lookups
-        let input_ids = Tensor::new(input_ids, &self.device)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .unsqueeze(0)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .to_dtype(DType::I64)
-            .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        // IMPORTANT: Candle's DistilBert uses INVERTED mask logic (1 = mask out, 0 = attend)
-        // See: https://github.com/huggingface/candle/issues/2721
-        let attention_mask = Tensor::new(attention_mask, &self.device)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .unsqueeze(0)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .eq(0u32)
-            .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        // Run inference
-        let output = self
-            .model
-            .forward(&input_ids, &attention_mask)
-            .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
-
-        // Get CLS token representation (first token), keeping batch dimension
-        // output shape: [batch, seq_len, hidden] -> [batch, hidden]
-        let cls_output = output
-            .narrow(1, 0, 1)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .squeeze(1)
-            .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        // Apply pre_classifier -> ReLU -> classifier
-        let hidden = self
-            .pre_classifier
-            .forward(&cls_output)
-            .map_err(|e| AppError::Ml(format!("Pre-classifier failed: {}", e)))?;
-
-        let hidden = hidden
-            .relu()
-            .map_err(|e| AppError::Ml(format!("ReLU failed: {}", e)))?;
-
-        let logits = self
-            .classifier
-            .forward(&hidden)
-            .map_err(|e| AppError::Ml(format!("Classifier failed: {}", e)))?;
-
-        // Apply sigmoid for multi-label classification
-        let probs = sigmoid(&logits)?;
-
-        // Convert to predictions (squeeze batch dim first)
-        let probs_vec: Vec<f32> = probs
-            .squeeze(0)
-            .map_err(|e| AppError::Ml(e.to_string()))?
-            .to_vec1()
-            .map_err(|e| AppError::Ml(e.to_string()))?;
-
-        let mut predictions: Vec<EmotionPrediction> = probs_vec
+        Ok(self
+            .predict_chunked(text, threshold, max_labels, Aggregation::Max)?
+            .predictions)
+    }
+
+    /// Predict emotions over overlapping token windows that fit DistilBERT's 512 positions.
+    /// Per-window sigmoid scores are aggregated into the entry-level predictions, and the
+    /// per-window predictions are returned so callers can show where emotions peak.
+    pub fn predict_chunked(
+        &self,
+        text: &str,
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<ChunkedEmotions, AppError> {
+        self.predict_chunked_batch(&[text], threshold, max_labels, aggregation)?
+            .pop()
+            .ok_or_else(|| AppError::Ml("No emotions produced".to_string()))
+    }
+
+    /// `predict_chunked` for several texts, with every text's windows run through the
+    /// model in shared batches. Results are in input order.
+    pub fn predict_chunked_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+        aggregation: Aggregation,
+    ) -> Result<Vec<ChunkedEmotions>, AppError> {
+        // Special tokens are added per window, so encode without them
+        let encodings = texts
             .iter()
-            .enumerate()
-            .filter(|(_, &score)| score >= threshold)
-            .map(|(idx, &score)| EmotionPrediction {
-                label: EMOTION_LABELS[idx].to_string(),
-                score,
+            .map(|text| {
+                self.tokenizer
+                    .encode(*text, false)
+                    .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))
             })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let windows: Vec<Vec<(usize, usize)>> = encodings
+            .iter()
+            .map(|encoding| token_windows(encoding.get_ids().len()))
             .collect();
+        let window_ids: Vec<&[u32]> = encodings
+            .iter()
+            .zip(&windows)
+            .flat_map(|(encoding, windows)| {
+                let ids = encoding.get_ids();
+                windows.iter().map(move |&(start, end)| &ids[start..end])
+            })
+            .collect();
+        let mut all_scores = self.batch_scores(&window_ids)?.into_iter();
+
+        let mut results = Vec::with_capacity(texts.len());
+        for ((text, encoding), windows) in texts.iter().zip(&encodings).zip(&windows) {
+            let offsets = encoding.get_offsets();
+            let window_scores: Vec<Vec<f32>> = all_scores.by_ref().take(windows.len()).collect();
+
+            let chunks = windows
+                .iter()
+                .zip(&window_scores)
+                .enumerate()
+                .map(|(chunk_index, (&(start, end), scores))| {
+                    // Token offsets are byte offsets into the original text; expose char offsets
+                    let (start_offset, end_offset) = if start < end {
+                        (
+                            char_offset(text, offsets[start].0),
+                            char_offset(text, offsets[end - 1].1),
+                        )
+                    } else {
+                        (0, text.chars().count())
+                    };
+
+                    ChunkEmotions {
+                        chunk_index,
+                        start_offset,
+                        end_offset,
+                        predictions: top_predictions(scores, threshold, max_labels),
+                    }
+                })
+                .collect();
+
+            let scores = aggregate_scores(&window_scores, aggregation);
+            results.push(ChunkedEmotions {
+                predictions: top_predictions(&scores, threshold, max_labels),
+                chunks,
+            });
+        }
 
-        // Sort by score descending (NaN values sort to the end)
-        predictions.sort_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
+        Ok(results)
+    }
+
+    /// Predict emotions for several short texts (e.g. sentences) in batched forward passes.
+    /// Texts longer than one window are truncated.
+    pub fn predict_batch(
+        &self,
+        texts: &[&str],
+        threshold: f32,
+        max_labels: usize,
+    ) -> Result<Vec<Vec<EmotionPrediction>>, AppError> {
+        let encodings = texts
+            .iter()
+            .map(|text| {
+                self.tokenizer
+                    .encode(*text, false)
+                    .map_err(|e| AppError::Ml(format!("Tokenization failed: {}", e)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Limit to max_labels
-        predictions.truncate(max_labels);
+        let ids: Vec<&[u32]> = encodings
+            .iter()
+            .map(|encoding| {
+                let ids = encoding.get_ids();
+                &ids[..ids.len().min(WINDOW_TOKENS)]
+            })
+            .collect();
 
-        // If no emotions above threshold, return "neutral"
-        // "neutral" is at index 27 in EMOTION_LABELS (last element)
-        const NEUTRAL_IDX: usize = 27;
-        if predictions.is_empty() {
-            predictions.push(EmotionPrediction {
-                label: EMOTION_LABELS[NEUTRAL_IDX].to_string(),
-                score: probs_vec[NEUTRAL_IDX],
-            });
+        Ok(self
+            .batch_scores(&ids)?
+            .iter()
+            .map(|scores| top_predictions(scores, threshold, max_labels))
+            .collect())
+    }
+
+    /// Sigmoid scores for every label on each window of token IDs (without special tokens),
+    /// returned in input order. Windows of similar length are padded to a common length
+    /// and run through the model in batches.
+    fn batch_scores(&self, windows: &[&[u32]]) -> Result<Vec<Vec<f32>>, AppError> {
+        // Batch similar lengths together to minimise padding
+        let mut order: Vec<usize> = (0..windows.len()).collect();
+        order.sort_by_key(|&i| windows[i].len());
+
+        let mut results = vec![Vec::new(); windows.len()];
+        for batch_order in order.chunks(MAX_BATCH_SIZE) {
+            let batch: Vec<&[u32]> = batch_order.iter().map(|&i| windows[i]).collect();
+            let seq_len = batch.iter().map(|ids| ids.len()).max().unwrap_or(0) + 2;
+
+            let mut input_ids = Vec::with_capacity(batch.len() * seq_len);
+            // 1 = attend, 0 = padding
+            let mut attention_mask = Vec::with_capacity(batch.len() * seq_len);
+            for ids in &batch {
+                input_ids.push(CLS_TOKEN_ID);
+                input_ids.extend_from_slice(ids);
+                input_ids.push(SEP_TOKEN_ID);
+                attention_mask.extend(std::iter::repeat_n(1u8, ids.len() + 2));
+
+                let padding = seq_len - ids.len() - 2;
+                input_ids.extend(std::iter::repeat_n(PAD_TOKEN_ID, padding));
+                attention_mask.extend(std::iter::repeat_n(0u8, padding));
+            }
+
+            // Convert to I64 - candle requires 64-bit integers for embedding lookups
+            let input_ids = Tensor::from_vec(input_ids, (batch.len(), seq_len), &self.device)
+                .map_err(|e| AppError::Ml(e.to_string()))?
+                .to_dtype(DType::I64)
+                .map_err(|e| AppError::Ml(e.to_string()))?;
+            let attention_mask =
+                Tensor::from_vec(attention_mask, (batch.len(), seq_len), &self.device)
+                    .map_err(|e| AppError::Ml(e.to_string()))?;
+
+            // Run inference
+            let output = self
+                .model
+                .forward(&input_ids, &attention_mask)
+                .map_err(|e| AppError::Ml(format!("Inference failed: {}", e)))?;
+
+            // Get CLS token representation (first token), keeping batch dimension
+            // output shape: [batch, seq_len, hidden] -> [batch, hidden]
+            let cls_output = output
+                .narrow(1, 0, 1)
+                .map_err(|e| AppError::Ml(e.to_string()))?
+                .squeeze(1)
+                .map_err(|e| AppError::Ml(e.to_string()))?;
+
+            // Apply pre_classifier -> ReLU -> classifier
+            let hidden = self
+                .pre_classifier
+                .forward(&cls_output)
+                .map_err(|e| AppError::Ml(format!("Pre-classifier failed: {}", e)))?;
+
+            let hidden = hidden
+                .relu()
+                .map_err(|e| AppError::Ml(format!("ReLU failed: {}", e)))?;
+
+            let logits = self
+                .classifier
+                .forward(&hidden)
+                .and_then(|logits| logits.to_dtype(DType::F32))
+                .map_err(|e| AppError::Ml(format!("Classifier failed: {}", e)))?;
+
+            // Apply sigmoid for multi-label classification
+            let probs: Vec<Vec<f32>> = sigmoid(&logits)?
+                .to_vec2()
+                .map_err(|e| AppError::Ml(e.to_string()))?;
+            for (&i, scores) in batch_order.iter().zip(probs) {
+                results[i] = scores;
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// Split a token sequence into overlapping windows that fit the model with [CLS] and [SEP].
+/// Returns (start, end) token ranges; an empty sequence still gets one (empty) window.
+pub(crate) fn token_windows(token_count: usize) -> Vec<(usize, usize)> {
+    if token_count <= WINDOW_TOKENS {
+        return vec![(0, token_count)];
+    }
+
+    let stride = WINDOW_TOKENS - WINDOW_OVERLAP_TOKENS;
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + WINDOW_TOKENS).min(token_count);
+        windows.push((start, end));
+        if end == token_count {
+            break;
+        }
+        start += stride;
+    }
+    windows
+}
+
+/// Convert a byte offset into a character offset.
+pub(crate) fn char_offset(text: &str, byte_offset: usize) -> usize {
+    text.get(..byte_offset)
+        .map(|prefix| prefix.chars().count())
+        .unwrap_or_else(|| text.chars().count())
+}
+
+/// Combine per-window label scores into one score per label.
+fn aggregate_scores(window_scores: &[Vec<f32>], aggregation: Aggregation) -> Vec<f32> {
+    let mut combined = vec![0.0f32; EMOTION_LABELS.len()];
+    if window_scores.is_empty() {
+        return combined;
+    }
+
+    for scores in window_scores {
+        for (total, &score) in combined.iter_mut().zip(scores) {
+            *total = match aggregation {
+                Aggregation::Max => total.max(score),
+                Aggregation::Mean => *total + score,
+            };
         }
+    }
+
+    if aggregation == Aggregation::Mean {
+        let count = window_scores.len() as f32;
+        combined.iter_mut().for_each(|total| *total /= count);
+    }
+
+    combined
+}
 
-        Ok(predictions)
+/// Top labels above threshold, sorted by confidence.
+/// Falls back to "neutral" when nothing passes the threshold.
+pub(crate) fn top_predictions(
+    scores: &[f32],
+    threshold: f32,
+    max_labels: usize,
+) -> Vec<EmotionPrediction> {
+    let mut predictions: Vec<EmotionPrediction> = scores
+        .iter()
+        .enumerate()
+        .filter(|(_, &score)| score >= threshold)
+        .map(|(idx, &score)| EmotionPrediction {
+            label: EMOTION_LABELS[idx].to_string(),
+            score,
+        })
+        .collect();
+
+    // Sort by score descending (NaN values sort to the end)
+    predictions.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    // Limit to max_labels
+    predictions.truncate(max_labels);
+
+    // If no emotions above threshold, return "neutral"
+    // "neutral" is at index 27 in EMOTION_LABELS (last element)
+    const NEUTRAL_IDX: usize = 27;
+    if predictions.is_empty() {
+        predictions.push(EmotionPrediction {
+            label: EMOTION_LABELS[NEUTRAL_IDX].to_string(),
+            score: scores.get(NEUTRAL_IDX).copied().unwrap_or(0.0),
+        });
     }
+
+    predictions
 }
 
 /// Sigmoid activation function.
@@ -255,6 +535,7 @@ fn sigmoid(x: &Tensor) -> Result<Tensor, AppError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ml::models::PRECISION_FIXTURES;
 
     #[test]
     fn test_emotion_labels_count() {
@@ -269,11 +550,99 @@ mod tests {
         assert!(EMOTION_LABELS.contains(&"neutral"));
     }
 
+    #[test]
+    fn test_token_windows() {
+        assert_eq!(token_windows(0), vec![(0, 0)]);
+        assert_eq!(token_windows(510), vec![(0, 510)]);
+
+        let windows = token_windows(1200);
+        assert_eq!(windows, vec![(0, 510), (446, 956), (892, 1200)]);
+        // Consecutive windows overlap
+        for pair in windows.windows(2) {
+            assert_eq!(pair[0].1 - pair[1].0, WINDOW_OVERLAP_TOKENS);
+        }
+    }
+
+    #[test]
+    fn test_aggregate_scores() {
+        let mut first = vec![0.0; EMOTION_LABELS.len()];
+        let mut second = vec![0.0; EMOTION_LABELS.len()];
+        first[17] = 0.8; // joy
+        second[17] = 0.2;
+        second[25] = 0.6; // sadness
+
+        let max = aggregate_scores(&[first.clone(), second.clone()], Aggregation::Max);
+        assert!((max[17] - 0.8).abs() < 1e-6);
+        assert!((max[25] - 0.6).abs() < 1e-6);
+
+        let mean = aggregate_scores(&[first, second], Aggregation::Mean);
+        assert!((mean[17] - 0.5).abs() < 1e-6);
+        assert!((mean[25] - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_top_predictions_falls_back_to_neutral() {
+        let mut scores = vec![0.01; EMOTION_LABELS.len()];
+        scores[27] = 0.05;
+        let predictions = top_predictions(&scores, 0.1, 3);
+        assert_eq!(predictions.len(), 1);
+        assert_eq!(predictions[0].label, "neutral");
+
+        scores[17] = 0.9;
+        scores[0] = 0.4;
+        let predictions = top_predictions(&scores, 0.1, 3);
+        assert_eq!(predictions[0].label, "joy");
+        assert_eq!(predictions[1].label, "admiration");
+    }
+
+    #[test]
+    fn test_char_offset() {
+        assert_eq!(char_offset("héllo", 3), 2);
+        assert_eq!(char_offset("héllo", 100), 5);
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_predict_long_entry() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = SentimentModel::load(&models_dir, Precision::F32).unwrap();
+
+        let text = "I had an ordinary day at the office. ".repeat(80)
+            + "Then I got the news and I am so happy and excited!";
+        let result = model
+            .predict_chunked(&text, 0.1, 3, Aggregation::Max)
+            .unwrap();
+        assert!(result.chunks.len() > 1);
+        assert_eq!(
+            result.chunks.last().unwrap().end_offset,
+            text.chars().count()
+        );
+    }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_predict_batch_matches_single() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let model = SentimentModel::load(&models_dir, Precision::F32).unwrap();
+
+        let texts = [
+            "I am so happy today!",
+            "I miss her terribly and can't stop crying.",
+        ];
+        let batch = model.predict_batch(&texts, 0.1, 3).unwrap();
+
+        for (text, batched) in texts.iter().zip(&batch) {
+            let single = model.predict(text, 0.1, 3).unwrap();
+            assert_eq!(single[0].label, batched[0].label);
+            assert!((single[0].score - batched[0].score).abs() < 1e-4);
+        }
+    }
+
     #[test]
     #[ignore = "Requires model download"]
     fn test_predict_emotions() {
         let models_dir = std::path::PathBuf::from("../models");
-        let model = SentimentModel::load(&models_dir).unwrap();
+        let model = SentimentModel::load(&models_dir, Precision::F32).unwrap();
 
         let predictions = model.predict("I am so happy today!", 0.1, 3).unwrap();
         assert!(!predictions.is_empty());
@@ -285,4 +654,22 @@ mod tests {
                 || labels.contains(&"optimism")
         );
     }
+
+    #[test]
+    #[ignore = "Requires model download"]
+    fn test_reduced_precision_matches_f32() {
+        let models_dir = std::path::PathBuf::from("../models");
+        let full = SentimentModel::load(&models_dir, Precision::F32).unwrap();
+        let reference = full.predict_batch(PRECISION_FIXTURES, 0.0, 3).unwrap();
+
+        for precision in [Precision::F16, Precision::Bf16] {
+            let model = SentimentModel::load(&models_dir, precision).unwrap();
+            assert_eq!(model.memory_bytes() * 2, full.memory_bytes());
+            let predictions = model.predict_batch(PRECISION_FIXTURES, 0.0, 3).unwrap();
+            for (expected, actual) in reference.iter().zip(&predictions) {
+                assert_eq!(expected[0].label, actual[0].label);
+                assert!((expected[0].score - actual[0].score).abs() < 0.02);
+            }
+        }
+    }
 }
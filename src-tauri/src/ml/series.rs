@@ -0,0 +1,209 @@
+//! Bucketing, aggregation and smoothing for per-entry scores charted over time.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Deserialize;
+
+use crate::error::AppError;
+
+/// One analyzed entry's emotion scores, as read for time series.
+#[derive(Debug, Clone)]
+pub struct EntryScores {
+    pub journal_id: String,
+    /// Creation date (YYYY-MM-DD)
+    pub date: String,
+    /// (label, score) pairs
+    pub emotions: Vec<(String, f32)>,
+}
+
+/// Period that series are bucketed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    Day,
+    /// ISO weeks, starting on Monday
+    Week,
+}
+
+impl Granularity {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "day" => Some(Self::Day),
+            "week" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    /// First day of the period containing `date` (YYYY-MM-DD).
+    pub fn period_start(&self, date: &str) -> String {
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(day) => self.start_of(day).format("%Y-%m-%d").to_string(),
+            Err(_) => date.to_string(),
+        }
+    }
+
+    fn start_of(&self, day: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => day,
+            Self::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    /// Start of every period overlapping `start_date..=end_date`, in order.
+    pub fn periods(&self, start_date: &str, end_date: &str) -> Result<Vec<String>, AppError> {
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| AppError::InvalidInput(format!("Invalid date: {}", date)))
+        };
+        let (start, end) = (parse(start_date)?, parse(end_date)?);
+        let step = match self {
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::days(7),
+        };
+
+        let mut periods = Vec::new();
+        let mut period = self.start_of(start);
+        while period <= end {
+            periods.push(period.format("%Y-%m-%d").to_string());
+            period += step;
+        }
+        Ok(periods)
+    }
+}
+
+/// How the scores of entries in one period are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScoreAggregation {
+    #[default]
+    Mean,
+    Max,
+    Sum,
+}
+
+impl ScoreAggregation {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "mean" => Some(Self::Mean),
+            "max" => Some(Self::Max),
+            "sum" => Some(Self::Sum),
+            _ => None,
+        }
+    }
+
+    /// Combine one score per entry; `None` when there are no entries.
+    pub fn apply(&self, scores: &[f32]) -> Option<f32> {
+        if scores.is_empty() {
+            return None;
+        }
+        let sum: f32 = scores.iter().sum();
+        Some(match self {
+            Self::Mean => sum / scores.len() as f32,
+            Self::Max => scores.iter().copied().fold(f32::MIN, f32::max),
+            Self::Sum => sum,
+        })
+    }
+}
+
+/// Optional smoothing of an aggregated series.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum Smoothing {
+    /// Mean of the values in the trailing `window` periods
+    Rolling { window: usize },
+    /// Exponentially weighted moving average; higher `alpha` follows recent values closer
+    Ewma { alpha: f32 },
+}
+
+impl Smoothing {
+    pub fn validate(&self) -> Result<(), AppError> {
+        match *self {
+            Self::Rolling { window: 0 } => Err(AppError::InvalidInput(
+                "Rolling window must be at least 1".to_string(),
+            )),
+            Self::Ewma { alpha } if !(alpha > 0.0 && alpha <= 1.0) => Err(AppError::InvalidInput(
+                "EWMA alpha must be in (0, 1]".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Smooth a series with gaps. Gaps stay gaps: they neither get a value nor
+    /// count as zero, so a rolling window averages only the periods it has data for
+    /// and the EWMA carries its state across them.
+    pub fn apply(&self, values: &[Option<f32>]) -> Vec<Option<f32>> {
+        match *self {
+            Self::Rolling { window } => (0..values.len())
+                .map(|i| {
+                    values[i]?;
+                    let start = (i + 1).saturating_sub(window);
+                    let present: Vec<f32> = values[start..=i].iter().flatten().copied().collect();
+                    Some(present.iter().sum::<f32>() / present.len() as f32)
+                })
+                .collect(),
+            Self::Ewma { alpha } => {
+                let mut state: Option<f32> = None;
+                values
+                    .iter()
+                    .map(|value| {
+                        let value = (*value)?;
+                        let next = match state {
+                            Some(previous) => alpha * value + (1.0 - alpha) * previous,
+                            None => value,
+                        };
+                        state = Some(next);
+                        Some(next)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_periods() {
+        assert_eq!(
+            Granularity::Day
+                .periods("2024-02-28", "2024-03-01")
+                .unwrap(),
+            vec!["2024-02-28", "2024-02-29", "2024-03-01"]
+        );
+        // Weeks start on the Monday on or before the start date
+        assert_eq!(
+            Granularity::Week
+                .periods("2024-01-03", "2024-01-15")
+                .unwrap(),
+            vec!["2024-01-01", "2024-01-08", "2024-01-15"]
+        );
+        assert!(Granularity::Day
+            .periods("2024-01-02", "2024-01-01")
+            .unwrap()
+            .is_empty());
+        assert!(Granularity::Day.periods("yesterday", "2024-01-01").is_err());
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let scores = [0.2, 0.6, 0.4];
+        assert!((ScoreAggregation::Mean.apply(&scores).unwrap() - 0.4).abs() < 1e-6);
+        assert_eq!(ScoreAggregation::Max.apply(&scores), Some(0.6));
+        assert!((ScoreAggregation::Sum.apply(&scores).unwrap() - 1.2).abs() < 1e-6);
+        assert_eq!(ScoreAggregation::Mean.apply(&[]), None);
+    }
+
+    #[test]
+    fn test_smoothing_keeps_gaps() {
+        let values = [Some(1.0), None, Some(0.0), Some(0.5)];
+
+        let rolling = Smoothing::Rolling { window: 2 }.apply(&values);
+        assert_eq!(rolling, vec![Some(1.0), None, Some(0.0), Some(0.25)]);
+
+        let ewma = Smoothing::Ewma { alpha: 0.5 }.apply(&values);
+        assert_eq!(ewma, vec![Some(1.0), None, Some(0.5), Some(0.5)]);
+
+        assert!(Smoothing::Rolling { window: 0 }.validate().is_err());
+        assert!(Smoothing::Ewma { alpha: 0.0 }.validate().is_err());
+        assert!(Smoothing::Ewma { alpha: 1.0 }.validate().is_ok());
+    }
+}
@@ -0,0 +1,368 @@
+//! Recurring themes: spherical k-means over entry embeddings, with the number of
+//! clusters chosen by silhouette score and labels taken from each cluster's most
+//! distinctive terms (class-based TF-IDF).
+
+use std::collections::{HashMap, HashSet};
+
+/// Fewer embedded entries than this don't make meaningful themes.
+pub const MIN_ENTRIES: usize = 6;
+
+/// Upper bound on the number of themes.
+pub const MAX_THEMES: usize = 12;
+
+/// Smallest average cluster size considered when picking the cluster count.
+const MIN_AVERAGE_SIZE: usize = 3;
+
+/// Terms kept per theme.
+pub const THEME_TERMS: usize = 8;
+
+/// Terms joined into a theme's default label.
+const LABEL_TERMS: usize = 3;
+
+/// Entries shown to the local LLM when it names a theme, and characters of each.
+pub const LABEL_EXCERPTS: usize = 3;
+pub const EXCERPT_CHARS: usize = 300;
+
+const MAX_ITERATIONS: usize = 50;
+
+/// Silhouette is quadratic in the number of points, so larger journals are scored on a sample.
+const SILHOUETTE_SAMPLE: usize = 400;
+
+/// Fixed seed so the same journal always yields the same themes.
+const SEED: u64 = 0x7e3a_11c5_d00d_f00d;
+
+/// Common English words that never make useful theme terms.
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "again", "all", "also", "and", "any", "are", "back", "because", "been",
+    "before", "being", "but", "can", "could", "day", "did", "does", "doing", "don", "down", "even",
+    "feel", "felt", "for", "from", "get", "got", "had", "has", "have", "her", "here", "him", "his",
+    "how", "into", "its", "just", "know", "like", "made", "make", "more", "much", "need", "not",
+    "now", "off", "one", "only", "other", "our", "out", "over", "really", "she", "should", "some",
+    "still", "than", "that", "the", "their", "them", "then", "there", "these", "they", "thing",
+    "things", "think", "this", "time", "today", "too", "very", "want", "was", "way", "were",
+    "what", "when", "where", "which", "while", "who", "why", "will", "with", "would", "you",
+    "your",
+];
+
+/// Cluster assignment of each input vector, and the unit-length cluster centroids.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clustering {
+    pub centroids: Vec<Vec<f32>>,
+    pub assignments: Vec<usize>,
+}
+
+/// Cluster unit-length vectors, trying every cluster count from 2 up to
+/// `MAX_THEMES` (bounded by the number of vectors) and keeping the one with the
+/// best silhouette. Returns `None` with fewer than `MIN_ENTRIES` vectors.
+pub fn cluster(vectors: &[Vec<f32>]) -> Option<Clustering> {
+    if vectors.len() < MIN_ENTRIES {
+        return None;
+    }
+    let max_k = (vectors.len() / MIN_AVERAGE_SIZE).clamp(2, MAX_THEMES);
+
+    (2..=max_k)
+        .map(|k| {
+            let clustering = kmeans(vectors, k);
+            let score = silhouette(vectors, &clustering.assignments);
+            (clustering, score)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(clustering, _)| clustering)
+}
+
+/// Spherical k-means (cosine similarity) with k-means++ seeding. Clusters that end
+/// up empty are dropped, so fewer than `k` centroids may be returned.
+pub fn kmeans(vectors: &[Vec<f32>], k: usize) -> Clustering {
+    let k = k.min(vectors.len()).max(1);
+    let mut rng = SplitMix64(SEED);
+    let mut centroids = seed_centroids(vectors, k, &mut rng);
+    let mut assignments = vec![usize::MAX; vectors.len()];
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (assignment, vector) in assignments.iter_mut().zip(vectors) {
+            let nearest = nearest(&centroids, vector);
+            if *assignment != nearest {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        centroids = (0..centroids.len())
+            .map(|c| {
+                centroid(
+                    vectors
+                        .iter()
+                        .zip(&assignments)
+                        .filter(|(_, &a)| a == c)
+                        .map(|(v, _)| v.as_slice()),
+                )
+                .unwrap_or_else(|| centroids[c].clone())
+            })
+            .collect();
+    }
+
+    compact(centroids, assignments)
+}
+
+/// Index of the centroid most similar to `vector`.
+pub fn nearest(centroids: &[Vec<f32>], vector: &[f32]) -> usize {
+    centroids
+        .iter()
+        .map(|c| dot(c, vector))
+        .enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Unit-length mean of some vectors, or `None` if there are none.
+pub fn centroid<'a>(vectors: impl Iterator<Item = &'a [f32]>) -> Option<Vec<f32>> {
+    let mut sum: Option<Vec<f32>> = None;
+    for vector in vectors {
+        match sum.as_mut() {
+            Some(sum) => sum.iter_mut().zip(vector).for_each(|(s, v)| *s += v),
+            None => sum = Some(vector.to_vec()),
+        }
+    }
+    sum.map(crate::ml::backend::l2_normalized)
+}
+
+/// Mean silhouette (cosine distance) over a deterministic sample of points.
+/// Ranges from -1 to 1; higher means tighter, better separated clusters.
+pub fn silhouette(vectors: &[Vec<f32>], assignments: &[usize]) -> f32 {
+    let k = assignments.iter().max().map_or(0, |m| m + 1);
+    if k < 2 {
+        return -1.0;
+    }
+    let step = vectors.len().div_ceil(SILHOUETTE_SAMPLE);
+    let sample: Vec<usize> = (0..vectors.len()).step_by(step).collect();
+
+    let mut total = 0.0;
+    for &i in &sample {
+        let mut sums = vec![0.0f32; k];
+        let mut counts = vec![0usize; k];
+        for &j in &sample {
+            if i != j {
+                sums[assignments[j]] += 1.0 - dot(&vectors[i], &vectors[j]);
+                counts[assignments[j]] += 1;
+            }
+        }
+        let own = assignments[i];
+        if counts[own] == 0 {
+            // Singleton clusters score 0 by convention
+            continue;
+        }
+        let a = sums[own] / counts[own] as f32;
+        let b = (0..k)
+            .filter(|&c| c != own && counts[c] > 0)
+            .map(|c| sums[c] / counts[c] as f32)
+            .fold(f32::MAX, f32::min);
+        if b < f32::MAX {
+            total += (b - a) / a.max(b).max(f32::EPSILON);
+        }
+    }
+    total / sample.len() as f32
+}
+
+/// The `THEME_TERMS` most distinctive terms of each cluster, by class-based TF-IDF:
+/// a term scores high when it is frequent in the cluster but rare in the others.
+/// Terms must appear in at least two of a cluster's entries (when it has two).
+pub fn distinctive_terms(texts: &[&str], assignments: &[usize], k: usize) -> Vec<Vec<String>> {
+    let mut term_counts: Vec<HashMap<String, usize>> = vec![HashMap::new(); k];
+    let mut doc_counts: Vec<HashMap<String, usize>> = vec![HashMap::new(); k];
+    let mut sizes = vec![0usize; k];
+    for (text, &cluster) in texts.iter().zip(assignments) {
+        sizes[cluster] += 1;
+        let mut seen = HashSet::new();
+        for term in terms(text) {
+            *term_counts[cluster].entry(term.clone()).or_default() += 1;
+            if seen.insert(term.clone()) {
+                *doc_counts[cluster].entry(term).or_default() += 1;
+            }
+        }
+    }
+
+    let mut corpus_counts: HashMap<&str, usize> = HashMap::new();
+    for counts in &term_counts {
+        for (term, count) in counts {
+            *corpus_counts.entry(term).or_default() += count;
+        }
+    }
+    let total_terms: usize = corpus_counts.values().sum();
+    let average_terms = total_terms as f32 / k.max(1) as f32;
+
+    (0..k)
+        .map(|c| {
+            let cluster_terms: usize = term_counts[c].values().sum();
+            let min_docs = sizes[c].min(2);
+            let mut scored: Vec<(&String, f32)> = term_counts[c]
+                .iter()
+                .filter(|(term, _)| doc_counts[c][*term] >= min_docs)
+                .map(|(term, &count)| {
+                    let tf = count as f32 / cluster_terms as f32;
+                    let idf = (1.0 + average_terms / corpus_counts[term.as_str()] as f32).ln();
+                    (term, tf * idf)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            scored
+                .into_iter()
+                .take(THEME_TERMS)
+                .map(|(term, _)| term.clone())
+                .collect()
+        })
+        .collect()
+}
+
+/// Default label for a theme: its top terms, e.g. "work, deadline, manager".
+pub fn label(terms: &[String]) -> String {
+    if terms.is_empty() {
+        return "Miscellaneous".to_string();
+    }
+    terms[..terms.len().min(LABEL_TERMS)].join(", ")
+}
+
+/// Lowercased words of at least three letters, without stop words.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphabetic() && c != '\'')
+        .map(|word| word.trim_matches('\'').to_lowercase())
+        .filter(|word| word.chars().count() >= 3 && !word.contains('\''))
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// k-means++: each further seed is drawn with probability proportional to its
+/// distance from the nearest seed so far.
+fn seed_centroids(vectors: &[Vec<f32>], k: usize, rng: &mut SplitMix64) -> Vec<Vec<f32>> {
+    let mut centroids = vec![vectors[rng.below(vectors.len())].clone()];
+    while centroids.len() < k {
+        let distances: Vec<f32> = vectors
+            .iter()
+            .map(|v| (1.0 - dot(&centroids[nearest(&centroids, v)], v)).max(0.0))
+            .collect();
+        let total: f32 = distances.iter().sum();
+        if total <= 0.0 {
+            // Fewer distinct points than clusters
+            break;
+        }
+        let mut target = rng.unit() * total;
+        let mut chosen = distances.len() - 1;
+        for (i, d) in distances.iter().enumerate() {
+            if target < *d {
+                chosen = i;
+                break;
+            }
+            target -= d;
+        }
+        centroids.push(vectors[chosen].clone());
+    }
+    centroids
+}
+
+/// Drop empty clusters and renumber assignments to match.
+fn compact(centroids: Vec<Vec<f32>>, assignments: Vec<usize>) -> Clustering {
+    let used: HashSet<usize> = assignments.iter().copied().collect();
+    let mut renumber = vec![usize::MAX; centroids.len()];
+    let mut kept = Vec::new();
+    for (i, centroid) in centroids.into_iter().enumerate() {
+        if used.contains(&i) {
+            renumber[i] = kept.len();
+            kept.push(centroid);
+        }
+    }
+    Clustering {
+        centroids: kept,
+        assignments: assignments.into_iter().map(|a| renumber[a]).collect(),
+    }
+}
+
+/// Small deterministic PRNG (SplitMix64), enough for seeding k-means.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1).
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unit vectors scattered slightly around `groups` orthogonal directions.
+    fn grouped_vectors(groups: usize, per_group: usize) -> Vec<Vec<f32>> {
+        let mut rng = SplitMix64(1);
+        (0..groups * per_group)
+            .map(|i| {
+                let mut v: Vec<f32> = (0..8).map(|_| rng.unit() * 0.2).collect();
+                v[i % groups] += 1.0;
+                crate::ml::backend::l2_normalized(v)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cluster_picks_natural_count() {
+        let vectors = grouped_vectors(3, 6);
+        let clustering = cluster(&vectors).unwrap();
+        assert_eq!(clustering.centroids.len(), 3);
+        // Members of a group share a cluster
+        for i in 0..vectors.len() {
+            assert_eq!(clustering.assignments[i], clustering.assignments[i % 3]);
+        }
+        assert_eq!(cluster(&vectors), Some(clustering));
+
+        assert!(cluster(&vectors[..MIN_ENTRIES - 1]).is_none());
+    }
+
+    #[test]
+    fn test_nearest_and_centroid() {
+        let vectors = grouped_vectors(2, 4);
+        let clustering = kmeans(&vectors, 2);
+        let own = clustering.assignments[0];
+        assert_eq!(nearest(&clustering.centroids, &vectors[0]), own);
+
+        let c = centroid(vectors.iter().map(|v| v.as_slice())).unwrap();
+        assert!((dot(&c, &c) - 1.0).abs() < 1e-5);
+        assert!(centroid(std::iter::empty()).is_none());
+    }
+
+    #[test]
+    fn test_distinctive_terms() {
+        let texts = [
+            "Deadline at work, my manager moved the deadline again",
+            "Long meeting at work with the manager about the deadline",
+            "Went running by the river, legs sore from running",
+            "Morning run along the river before breakfast, running felt easy",
+        ];
+        let terms = distinctive_terms(&texts, &[0, 0, 1, 1], 2);
+        assert_eq!(terms[0][..3], ["deadline", "manager", "work"]);
+        assert_eq!(terms[1][..2], ["running", "river"]);
+        assert!(terms
+            .iter()
+            .flatten()
+            .all(|t| !STOP_WORDS.contains(&t.as_str())));
+
+        assert_eq!(label(&terms[0]), "deadline, manager, work");
+        assert_eq!(label(&[]), "Miscellaneous");
+    }
+}
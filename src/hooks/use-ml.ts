@@ -72,6 +72,9 @@ export function useHybridSearch(
         query: debouncedQuery,
         limit: params.limit ?? 20,
         includeArchived: params.includeArchived ?? false,
+        entryType: params.entryType,
+        startDate: params.startDate,
+        endDate: params.endDate,
       });
     },
     enabled: debouncedQuery.trim().length > 0,
@@ -1,4 +1,4 @@
-import type { JournalEntry } from "./journal";
+import type { EntryType, JournalEntry } from "./journal";
 
 export interface EmotionPrediction {
   label: string;
@@ -16,12 +16,17 @@ export interface HybridSearchResult {
   score: number;
   fts_rank: number | null;
   vec_rank: number | null;
+  rerank_score: number | null;
 }
 
 export interface HybridSearchParams {
   query: string;
   limit?: number;
   includeArchived?: boolean;
+  entryType?: EntryType;
+  /** Inclusive YYYY-MM-DD bounds on the entry's creation date */
+  startDate?: string;
+  endDate?: string;
 }
 
 // Muted emotion display - all emotions use the same subtle grey styling with dark mode support
@@ -6,6 +6,7 @@ export interface JournalEntry {
   title: string | null;
   entry_type: EntryType;
   is_archived: boolean;
+  language: string | null;
   created_at: string;
   updated_at: string;
 }